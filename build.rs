@@ -18,6 +18,13 @@ fn main() {
         println!("cargo:rustc-env=SIMD_ENABLED=SIMD JSON parsing unavailable");
     }
 
+    // Export the field the constraints are evaluated in
+    if env::var("CARGO_FEATURE_BLS12_381").is_ok() {
+        println!("cargo:rustc-env=FIELD=over BLS12-381");
+    } else {
+        println!("cargo:rustc-env=FIELD=over BLS12-377");
+    }
+
     // Generate C FFI bindings
     let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
     let package_name = env::var("CARGO_PKG_NAME").unwrap();
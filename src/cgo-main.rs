@@ -2,6 +2,9 @@
 #[macro_use]
 extern crate pest_derive;
 
+#[macro_use]
+mod output;
+
 mod cgo;
 mod check;
 mod column;
@@ -10,8 +13,11 @@ mod compute;
 mod constants;
 mod dag;
 mod errors;
+mod field;
 mod import;
 mod pretty;
+mod progress;
+mod storage;
 mod structs;
 mod transformer;
 mod utils;
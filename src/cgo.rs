@@ -1,7 +1,7 @@
+use crate::utils::parallel::*;
 use anyhow::*;
 use compiler::ConstraintSet;
 use log::*;
-use rayon::prelude::*;
 use transformer::{AutoConstraint, ExpansionLevel};
 
 use crate::{
@@ -215,6 +215,7 @@ pub fn make_corset(mut constraints: ConstraintSet) -> Result<Corset> {
     Ok(constraints)
 }
 
+#[cfg(not(feature = "wasm"))]
 pub fn corset_from_file(filename: &str) -> Result<Corset> {
     info!("Loading `{}`", &filename);
     // Read the constraint-set bin file
@@ -222,9 +223,10 @@ pub fn corset_from_file(filename: &str) -> Result<Corset> {
         .with_context(|| anyhow!("while reading `{}`", filename))?;
     //
     let constraints = if RON_BINFILE {
-        ron::from_str(binfile).with_context(|| anyhow!("while parsing `{}` (RON)", filename))?
+        ConstraintSet::from_ron(binfile)
+            .with_context(|| anyhow!("while parsing `{}` (RON)", filename))?
     } else {
-        serde_json::from_str(binfile)
+        ConstraintSet::from_json(binfile)
             .with_context(|| anyhow!("while parsing `{}` (JSON)", filename))?
     };
     make_corset(constraints)
@@ -232,13 +234,15 @@ pub fn corset_from_file(filename: &str) -> Result<Corset> {
 
 pub fn corset_from_str(binfile: &str) -> Result<Corset> {
     let constraints = if RON_BINFILE {
-        ron::from_str(binfile).with_context(|| anyhow!("while parsing bin file (RON)"))?
+        ConstraintSet::from_ron(binfile).with_context(|| anyhow!("while parsing bin file (RON)"))?
     } else {
-        serde_json::from_str(binfile).with_context(|| anyhow!("while parsing bin file (JSON)"))?
+        ConstraintSet::from_json(binfile)
+            .with_context(|| anyhow!("while parsing bin file (JSON)"))?
     };
     make_corset(constraints)
 }
 
+#[cfg(not(feature = "wasm"))]
 pub fn compute_trace_from_file(
     constraints: &mut Corset,
     tracefile: &str,
@@ -1,16 +1,24 @@
+use crate::field::Fr;
+use crate::utils::parallel::*;
 use crate::{
     column::{ColumnSet, Value},
-    compiler::{Constraint, ConstraintSet, Domain, EvalSettings, Expression, Node},
+    compiler::{
+        generator::SubexpressionMemo, ColumnRef, Constraint, ConstraintSet, Domain, EvalCache,
+        EvalSettings, Expression, Node, ShiftMode,
+    },
     pretty::*,
     structs::Handle,
 };
 use anyhow::*;
-use cached::SizedCache;
+use ark_ff::UniformRand;
+use either::Either;
 use itertools::Itertools;
 use log::*;
 use owo_colors::OwoColorize;
-use rayon::prelude::*;
-use std::collections::HashSet;
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -18,11 +26,48 @@ enum CheckingError {
     #[error("columns for {} not found in trace file", .0.pretty())]
     NoColumnsFound(Handle),
     #[error("")]
-    FailingConstraint(Handle, String),
+    FailingConstraint(Handle, isize, String),
+    #[error("{1}")]
+    FailingRow(isize, String),
     #[error("")]
     MismatchingLengths(Error),
 }
 
+/// A constraint that did not hold, along with the first row where it failed
+/// when it is known
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedConstraint {
+    pub name: String,
+    pub row: Option<isize>,
+}
+
+/// The error returned by [`check`] when some constraints do not hold
+#[derive(Error, Debug, Clone, Serialize, Deserialize)]
+pub struct CheckFailure {
+    pub failed: Vec<FailedConstraint>,
+}
+impl std::fmt::Display for CheckFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "constraints failed: {}",
+            self.failed
+                .iter()
+                .map(|x| x.name.bold().red().to_string())
+                .join(", ")
+        )
+    }
+}
+
+/// Returns the row where a constraint failed, if the error carries it
+fn failing_row(err: &Error) -> Option<isize> {
+    match err.downcast_ref::<CheckingError>() {
+        Some(CheckingError::FailingConstraint(_, row, _))
+        | Some(CheckingError::FailingRow(row, _)) => Some(*row),
+        _ => None,
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct DebugSettings {
     /// whether to skip reporting s-exps reducing to 0
@@ -31,6 +76,8 @@ pub struct DebugSettings {
     dim: bool,
     /// whether to stop reporting a constraint on the first failure
     continue_on_error: bool,
+    /// when continuing on errors, how many failing rows to report per constraint
+    max_failures: usize,
     /// whether to report computation details on failing constraints
     report: bool,
     /// how many lines to show left of the failure point
@@ -41,6 +88,28 @@ pub struct DebugSettings {
     full_trace: bool,
     /// whether to display the original source code along the compiled form
     src: bool,
+    /// if set, only check this many randomly chosen rows of each constraint
+    sample: Option<usize>,
+    /// the seed used to choose the sampled rows
+    seed: u64,
+    /// how shifted columns are read outside of the trace
+    shift_mode: ShiftMode,
+    /// whether to check that permuted columns are a permutation of their sources
+    check_permutations: bool,
+    /// whether to memoize the values of subexpressions shared by the constraints
+    memoize: bool,
+    /// whether to only check the rows appended by the latest trace appended
+    /// to the columns, as well as the rows reaching them through shifts
+    appended_only: bool,
+    /// whether to warn about suspicious traces, e.g. constant boolean columns
+    pedantic: bool,
+    /// whether the pedantic warnings make the check fail
+    deny_warnings: bool,
+    /// whether to report field elements as is, rather than showing the ones
+    /// larger than half the modulus as negative numbers
+    raw_values: bool,
+    /// whether to display a progress bar while checking
+    progress: bool,
 }
 impl DebugSettings {
     pub fn new() -> Self {
@@ -48,11 +117,22 @@ impl DebugSettings {
             unclutter: false,
             dim: false,
             continue_on_error: false,
+            max_failures: 10,
             report: false,
             context_span_before: 2,
             context_span_after: 2,
             full_trace: false,
             src: false,
+            sample: None,
+            seed: 0,
+            shift_mode: ShiftMode::Error,
+            check_permutations: false,
+            memoize: false,
+            appended_only: false,
+            pedantic: false,
+            deny_warnings: false,
+            raw_values: false,
+            progress: false,
         }
     }
     pub fn dim(self, x: bool) -> Self {
@@ -73,6 +153,12 @@ impl DebugSettings {
             ..self
         }
     }
+    pub fn max_failures(self, x: usize) -> Self {
+        Self {
+            max_failures: x,
+            ..self
+        }
+    }
     pub fn report(self, x: bool) -> Self {
         Self { report: x, ..self }
     }
@@ -109,6 +195,136 @@ impl DebugSettings {
             ..self
         }
     }
+    pub fn and_sample(self, x: Option<usize>) -> Self {
+        Self { sample: x, ..self }
+    }
+    pub fn seed(self, x: u64) -> Self {
+        Self { seed: x, ..self }
+    }
+    pub fn shift_mode(self, x: ShiftMode) -> Self {
+        Self {
+            shift_mode: x,
+            ..self
+        }
+    }
+    pub fn check_permutations(self, x: bool) -> Self {
+        Self {
+            check_permutations: x,
+            ..self
+        }
+    }
+    pub fn memoize(self, x: bool) -> Self {
+        Self { memoize: x, ..self }
+    }
+    // This is only used by the lib
+    #[allow(dead_code)]
+    pub fn appended_only(self, x: bool) -> Self {
+        Self {
+            appended_only: x,
+            ..self
+        }
+    }
+    pub fn pedantic(self, x: bool) -> Self {
+        Self {
+            pedantic: x,
+            ..self
+        }
+    }
+    pub fn deny_warnings(self, x: bool) -> Self {
+        Self {
+            deny_warnings: x,
+            ..self
+        }
+    }
+    pub fn raw_values(self, x: bool) -> Self {
+        Self {
+            raw_values: x,
+            ..self
+        }
+    }
+    pub fn progress(self, x: bool) -> Self {
+        Self {
+            progress: x,
+            ..self
+        }
+    }
+}
+
+/// The boolean columns of `cs` that hold the same value on all the rows of
+/// the imported trace, along with this value; such columns have usually been
+/// forgotten by the trace producer.
+pub fn constant_boolean_columns(cs: &ConstraintSet) -> Vec<(Handle, Value)> {
+    cs.columns
+        .iter()
+        .filter(|(_, c)| c.t.is_boolean())
+        .filter_map(|(r, c)| {
+            let len = cs.columns.len(&r)? as isize;
+            let mut values = (cs.padding_rows_for_column(&r)..len)
+                .map(|i| cs.columns.get(&r, i, false).unwrap_or_default());
+            let first = values.next()?;
+            values
+                .all(|x| x == first)
+                .then(|| (c.handle.clone(), first))
+        })
+        .sorted_by(|a, b| a.0.cmp(&b.0))
+        .collect()
+}
+
+/// Randomly choose `count` distinct rows among `0..nrows`, in increasing order.
+/// The same seed always yields the same rows.
+pub fn sample_rows(nrows: usize, count: usize, seed: u64) -> Vec<isize> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    rand::seq::index::sample(&mut rng, nrows, count.min(nrows))
+        .into_iter()
+        .map(|i| i as isize)
+        .sorted()
+        .collect()
+}
+
+/// The rows to check among `from..nrows`, taking sampling into account
+fn rows_to_check(
+    from: isize,
+    nrows: isize,
+    settings: &DebugSettings,
+) -> Either<std::ops::Range<isize>, std::vec::IntoIter<isize>> {
+    let from = from.clamp(0, nrows.max(0));
+    match settings.sample {
+        Some(count) => Either::Right(
+            sample_rows((nrows - from) as usize, count, settings.seed)
+                .into_iter()
+                .map(|i| i + from)
+                .collect::<Vec<_>>()
+                .into_iter(),
+        ),
+        None => Either::Left(from..nrows),
+    }
+}
+
+/// The first row of `expr` to check when only checking the appended rows.
+///
+/// Rows before the appended ones are only affected by the append if they read
+/// them through a forward shift; conversely, appended rows reading the former
+/// ones through a backward shift are checked anyway. Hence, it is sound to
+/// start the check `expr.future_spill()` rows before the first appended one.
+/// Note that this does not hold for constraints spanning the whole trace, such
+/// as lookups, permutations or constraints on a given domain, which are thus
+/// always fully checked. As `expr` may span several modules, e.g. through
+/// interleaved columns, the check starts from the earliest row appended to any
+/// of them.
+fn first_appended_row(cs: &ConstraintSet, expr: &Node) -> isize {
+    expr.dependencies()
+        .iter()
+        .map(|r| {
+            cs.columns
+                .appended_from
+                .get(&cs.handle(r).module)
+                .map(|from| from * cs.length_multiplier(r) as isize)
+                .unwrap_or(0)
+        })
+        .min()
+        .map(|from| from - expr.future_spill())
+        .unwrap_or(0)
+        .max(0)
 }
 
 /// Pretty print an expresion and all its intermediate value for debugging (or
@@ -149,32 +365,72 @@ fn fail(
             .sorted_by_key(|h| cs.handle(h).name.clone())
             .collect::<Vec<_>>()
     };
+
+    bail!(
+        trace_span(cs, &handles, i, wrap, settings)
+            + &expr.debug(
+                &|n| n.eval(
+                    i,
+                    |handle, i, wrap| cs.columns.get(handle, i, wrap),
+                    &mut None,
+                    &Default::default(),
+                ),
+                settings.unclutter,
+                settings.dim,
+                settings.src,
+                settings.raw_values,
+            )
+    )
+}
+
+/// Render the values of the given columns around the row `i` as a table,
+/// annotated with the declared type of the columns, and marking the cells
+/// that are padding rather than trace data
+pub(crate) fn trace_span(
+    cs: &ConstraintSet,
+    handles: &[ColumnRef],
+    i: isize,
+    wrap: bool,
+    settings: DebugSettings,
+) -> String {
     // Determine max perspective length
     let mut max_perspective_len = 0;
-    for h in &handles {
+    for h in handles {
         if let Some(p) = &cs.handle(h).perspective {
             max_perspective_len = max_perspective_len.max(p.len());
         }
     }
-    //
-    let mut m_columns = vec![vec![String::new()]
-        .into_iter()
-        .chain(
-            handles
-                .iter()
-                .map(|h| to_column_name(cs.handle(h), max_perspective_len)),
-        )
-        .collect::<Vec<_>>()];
+
+    // The column names, followed by their declared type
+    let mut m_columns = vec![
+        vec![String::new()]
+            .into_iter()
+            .chain(
+                handles
+                    .iter()
+                    .map(|h| to_column_name(cs.handle(h), max_perspective_len)),
+            )
+            .collect::<Vec<_>>(),
+        vec![String::new()]
+            .into_iter()
+            .chain(
+                handles
+                    .iter()
+                    .map(|h| cs.columns.column(h).unwrap().t.to_string()),
+            )
+            .collect::<Vec<_>>(),
+    ];
+    let mut shows_padding = false;
     let (eval_columns_range, idx_highlight) = if wrap {
         (
             (i - settings.context_span_before)..=i + settings.context_span_after,
-            // - 1 to account for the title column
-            (i - settings.context_span_before) - 1,
+            // - 2 to account for the title columns
+            (i - settings.context_span_before) - 2,
         )
     } else {
         (
             (i - settings.context_span_before).max(0)..=i + settings.context_span_after,
-            (i - settings.context_span_before).max(0) - 1,
+            (i - settings.context_span_before).max(0) - 2,
         )
     };
     for j in eval_columns_range {
@@ -185,8 +441,19 @@ fn fail(
                     cs.columns
                         .get(handle, j, true)
                         .map(|x| {
-                            x.pretty_with_base(cs.columns.column(handle).unwrap().base)
-                                .to_string()
+                            let base = cs.columns.column(handle).unwrap().base;
+                            let x = if settings.raw_values {
+                                x.pretty_with_base(base)
+                            } else {
+                                x.pretty_signed(base)
+                            };
+                            // padding rows are not part of the imported trace
+                            if (0..cs.padding_rows_for_column(handle)).contains(&j) {
+                                shows_padding = true;
+                                format!("[{}]", x)
+                            } else {
+                                x
+                            }
                         })
                         .unwrap_or_else(|| "nil".into())
                 }))
@@ -197,7 +464,7 @@ fn fail(
     let mut trace = String::new();
     for ii in 0..m_columns[0].len() {
         for (j, col) in m_columns.iter().enumerate() {
-            let padding = col.iter().map(String::len).max().unwrap() + 2;
+            let padding = col.iter().map(|x| x.chars().count()).max().unwrap() + 2;
             if j as isize + idx_highlight == i {
                 trace.push_str(&format!(
                     "{:width$}",
@@ -217,40 +484,52 @@ fn fail(
         }
         trace.push('\n');
     }
+    if shows_padding {
+        trace.push_str(&format!("{}\n", "[x]: padding".dimmed()));
+    }
     trace.push('\n');
-
-    bail!(
-        trace
-            + &expr.debug(
-                &|n| n.eval(
-                    i,
-                    |handle, i, wrap| cs.columns.get(handle, i, wrap),
-                    &mut None,
-                    &Default::default(),
-                ),
-                settings.unclutter,
-                settings.dim,
-                settings.src,
-            )
-    )
+    trace
 }
 
+/// How many subexpression values are memoized during a check, in the same
+/// ballpark as the other evaluation caches.
+const MEMOIZED_VALUES: usize = 200_000;
+
 fn check_constraint_at(
     cs: &ConstraintSet,
     expr: &Node,
     i: isize,
-    wrap: bool,
+    shift: ShiftMode,
     fail_on_oob: bool,
-    cache: &mut Option<SizedCache<Value, Value>>,
+    cache: &mut Option<EvalCache>,
     settings: DebugSettings,
 ) -> Result<()> {
+    // the first read before the spilling of its module, if any
+    let beyond_spilling = std::cell::Cell::new(None);
     let r = expr.eval(
         i,
-        |handle, i, wrap| cs.columns.get_raw(handle, i, wrap),
+        |handle, j, wrap| {
+            if shift == ShiftMode::Error && j < 0 && beyond_spilling.get().is_none() {
+                let spilling = cs.spilling_of(&cs.handle(handle).module).unwrap_or(0);
+                if j < -spilling {
+                    beyond_spilling.set(Some((j, spilling)));
+                }
+            }
+            cs.columns.get_raw(handle, j, wrap)
+        },
         cache,
-        &EvalSettings::new().wrap(wrap),
+        &EvalSettings::new().shift(shift),
     );
+    if let Some((j, spilling)) = beyond_spilling.get() {
+        bail!(
+            "row {} reads row {}, beyond the {} spilling rows of the module",
+            i,
+            j,
+            spilling
+        );
+    }
 
+    let wrap = shift == ShiftMode::Wrap;
     if let Some(r) = r {
         if !r.is_zero() {
             return fail(cs, expr, i, wrap, settings);
@@ -261,25 +540,42 @@ fn check_constraint_at(
     Ok(())
 }
 
-fn check_inrange(expr: &Node, cs: &ConstraintSet, max: &Value) -> Result<()> {
+fn check_inrange(
+    module: &str,
+    expr: &Node,
+    cs: &ConstraintSet,
+    max: &Value,
+    settings: &DebugSettings,
+) -> Result<()> {
+    let max_bi = max.to_bi();
     let l = cs.dependencies_len(expr, false)?;
     if let Some(l) = l {
-        for i in 0..l as isize {
-            let r = expr
-                .eval(
+        for i in rows_to_check(0, l as isize, settings) {
+            let r = expr.eval(
+                i,
+                |handle, i, wrap| cs.columns.get_raw(handle, i, wrap),
+                &mut None,
+                &Default::default(),
+            );
+            // Values are compared through their canonical integer
+            // representation in the field, so that e.g. -1 is out of range
+            let Some(r) = r else {
+                bail!(CheckingError::FailingRow(
                     i,
-                    |handle, i, wrap| cs.columns.get_raw(handle, i, wrap),
-                    &mut None,
-                    &Default::default(),
-                )
-                .unwrap();
-            if r.ge(max) {
-                bail!(
-                    "{} = {} > {}",
-                    expr.to_string().white().bold(),
-                    r.pretty().red().bold(),
-                    max.pretty().blue()
-                )
+                    format!("row {}: {} can not be evaluated", i, expr.pretty_in(module))
+                ))
+            };
+            if r.to_field_bi().map(|x| x >= max_bi).unwrap_or(true) {
+                bail!(CheckingError::FailingRow(
+                    i,
+                    format!(
+                        "row {}: {} = {} ≥ {}",
+                        i,
+                        expr.pretty_in(module),
+                        r.pretty().red().bold(),
+                        max.pretty().blue()
+                    )
+                ))
             }
         }
         Ok(())
@@ -293,13 +589,30 @@ fn check_constraint(
     expr: &Node,
     domain: &Option<Domain<isize>>,
     name: &Handle,
+    cache: &mut Option<EvalCache>,
     settings: DebugSettings,
 ) -> Result<()> {
-    let mut cache = Some(cached::SizedCache::with_size(200000)); // ~1.60MB cache
     match domain {
         Some(is) => {
+            // Negative indices are counted from the end of the trace, so that
+            // e.g. {-1} targets the last row
+            let nrows = cs
+                .dependencies_len(expr, true)
+                .map_err(CheckingError::MismatchingLengths)?
+                .unwrap_or(1) as isize;
             for i in is.iter() {
-                check_constraint_at(cs, expr, i, true, true, &mut cache, settings)?;
+                let row = if i < 0 { nrows + i } else { i };
+                if row < 0 {
+                    bail!(CheckingError::FailingConstraint(
+                        name.clone(),
+                        i,
+                        format!("row {} is out of a trace of {} rows", i, nrows)
+                    ));
+                }
+                check_constraint_at(cs, expr, row, ShiftMode::Wrap, true, cache, settings)
+                    .map_err(|e| {
+                        CheckingError::FailingConstraint(name.clone(), row, e.to_string())
+                    })?;
             }
         }
         None => {
@@ -309,19 +622,46 @@ fn check_constraint(
                 .map_err(CheckingError::MismatchingLengths)?;
             // Determine number of rows for checking
             let nrows = if let Some(l) = l { l as isize } else { 1 };
-            // Check all the rows
-            for i in 0..nrows as isize {
-                let err = check_constraint_at(cs, expr, i, false, false, &mut cache, settings)
-                    .map_err(|e| CheckingError::FailingConstraint(name.clone(), e.to_string()));
-
-                if err.is_err() {
-                    if settings.continue_on_error {
-                        eprintln!("{:?}", err);
-                    } else {
-                        bail!(err.err().unwrap());
+            // Check all the rows, or a sample of them
+            let from = if settings.appended_only {
+                first_appended_row(cs, expr)
+            } else {
+                0
+            };
+            let mut failures = 0;
+            for i in rows_to_check(from, nrows, &settings) {
+                if let Err(e) =
+                    check_constraint_at(cs, expr, i, settings.shift_mode, false, cache, settings)
+                {
+                    if !settings.continue_on_error {
+                        bail!(CheckingError::FailingConstraint(
+                            name.clone(),
+                            i,
+                            e.to_string()
+                        ));
                     }
+                    if failures < settings.max_failures {
+                        eprintln!("{} failed:\n{}\n", name.to_string().red().bold(), e);
+                    }
+                    failures += 1;
                 }
             }
+            if failures > settings.max_failures {
+                eprintln!(
+                    "{} failed on {} rows (showing first {})",
+                    name.to_string().red().bold(),
+                    failures,
+                    settings.max_failures
+                );
+                return Ok(());
+            } else if failures > 0 {
+                eprintln!(
+                    "{} failed on {} rows",
+                    name.to_string().red().bold(),
+                    failures
+                );
+                return Ok(());
+            }
         }
     };
     info!("{} validated", name.pretty());
@@ -334,14 +674,12 @@ fn check_lookup(
     parents: &[Node],
     children: &[Node],
 ) -> Result<()> {
-    // Compute the LC \sum_k (k+1) × x_k[i]
-    fn pseudo_rlc(exps: &[Node], i: usize, cs: &ColumnSet) -> Value {
-        let mut ax = Value::zero();
-
-        for (j, exp) in exps.iter().enumerate() {
-            let mut x = Value::from(j + 2);
-            let col_value = exp
-                .eval(
+    // The tuple formed by the values of `exps` at row i; they are compared as
+    // a whole, as any linear combination of them could collide
+    fn tuple(exps: &[Node], i: usize, cs: &ColumnSet) -> Vec<Value> {
+        exps.iter()
+            .map(|exp| {
+                exp.eval(
                     i as isize,
                     |handle, j, _| {
                         cs.get(handle, j, false)
@@ -350,12 +688,9 @@ fn check_lookup(
                     &mut None,
                     &EvalSettings::default(),
                 )
-                .unwrap_or_default();
-
-            x.mul_assign(&col_value);
-            ax.add_assign(&x);
-        }
-        ax
+                .unwrap_or_default()
+            })
+            .collect()
     }
 
     // Check that we have the same number of columns; should be guaranteed by the com
@@ -392,32 +727,16 @@ fn check_lookup(
     let child_module = cs.module_of_exprs(children).unwrap();
     let child_len = cs.iter_len(&child_module);
 
-    let parent_hashes: HashSet<_> = (0..parent_len)
-        .map(|i| pseudo_rlc(parents, i, &cs.columns))
+    let parent_tuples: HashSet<_> = (0..parent_len)
+        .map(|i| tuple(parents, i, &cs.columns))
         .collect();
 
     for i in 0..child_len {
-        if !parent_hashes.contains(&pseudo_rlc(children, i, &cs.columns)) {
+        let child_tuple = tuple(children, i, &cs.columns);
+        if !parent_tuples.contains(&child_tuple) {
             let pretty_expected_matches = parents
                 .iter()
-                .zip(children.iter().zip(children.iter().map(|e| {
-                    e.eval(
-                        i as isize,
-                        |handle, j, _| {
-                            cs.columns.get(handle, j, false).or_else(|| {
-                                cs.columns
-                                    .column(handle)
-                                    .unwrap()
-                                    .padding_value
-                                    .as_ref()
-                                    .cloned()
-                            })
-                        },
-                        &mut None,
-                        &EvalSettings::default(),
-                    )
-                    .unwrap_or_default()
-                })))
+                .zip(children.iter().zip(child_tuple.iter()))
                 .map(|(parent, (child, value))| {
                     format!(
                         "{} - {}: {}",
@@ -427,13 +746,182 @@ fn check_lookup(
                     )
                 })
                 .join("\n");
-            bail!("mismatch line {}:\n{}", i, pretty_expected_matches);
+            bail!(CheckingError::FailingRow(
+                i as isize,
+                format!("mismatch line {}:\n{}", i, pretty_expected_matches)
+            ));
         }
     }
 
     Ok(())
 }
 
+/// Check that the rows of the `to` columns are a permutation of the rows of the
+/// `from` columns, i.e. that both are equal as multisets of tuples. The first
+/// row of `to` not found among the rows of `from` is reported.
+fn check_permutation(cs: &ConstraintSet, from: &[ColumnRef], to: &[ColumnRef]) -> Result<()> {
+    let len = |cols: &[ColumnRef]| -> Result<usize> {
+        cols.iter()
+            .map(|c| {
+                cs.columns
+                    .len(c)
+                    .ok_or_else(|| anyhow!(CheckingError::NoColumnsFound(cs.handle(c).clone())))
+            })
+            .try_fold(0, |ax, l| l.map(|l| ax.max(l)))
+    };
+    let row = |cols: &[ColumnRef], i: usize| -> Vec<Value> {
+        cols.iter()
+            .map(|c| cs.columns.get(c, i as isize, false).unwrap_or_default())
+            .collect()
+    };
+
+    let (from_len, to_len) = (len(from)?, len(to)?);
+    if from_len != to_len {
+        bail!(CheckingError::MismatchingLengths(anyhow!(
+            "{} rows in the source columns, but {} in the permuted ones",
+            from_len,
+            to_len
+        )))
+    }
+
+    let mut sources: HashMap<Vec<Value>, usize> = HashMap::new();
+    for i in 0..from_len {
+        *sources.entry(row(from, i)).or_default() += 1;
+    }
+    for i in 0..to_len {
+        let tuple = row(to, i);
+        match sources.get_mut(&tuple) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => bail!(CheckingError::FailingRow(
+                i as isize,
+                format!(
+                    "({}) at line {} is not a row of ({})",
+                    to.iter()
+                        .zip(tuple.iter())
+                        .map(|(c, x)| format!("{}: {}", cs.handle(c).pretty(), x.pretty()))
+                        .join(", "),
+                    i,
+                    from.iter().map(|c| cs.handle(c).pretty()).join(", ")
+                )
+            )),
+        }
+    }
+
+    Ok(())
+}
+
+/// The error returned by [`run_with_timeout`] when the job did not complete in
+/// time
+// This is only used by the lib
+#[allow(dead_code)]
+#[derive(Error, Debug, Clone, Copy)]
+#[error("timed out after {0:?}")]
+pub struct TimedOut(pub std::time::Duration);
+
+/// Run `job` on a worker thread and wait at most `timeout` for its result.
+///
+/// On timeout, the worker thread is detached rather than killed: it, and any
+/// rayon task it spawned, may keep running briefly in the background until it
+/// completes, but its result will be discarded.
+// This is only used by the lib
+#[allow(dead_code)]
+pub fn run_with_timeout<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(
+    timeout: Option<std::time::Duration>,
+    job: F,
+) -> Result<T> {
+    let Some(timeout) = timeout else {
+        return Ok(job());
+    };
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        // the receiver may be gone if we timed out
+        let _ = tx.send(job());
+    });
+    match rx.recv_timeout(timeout) {
+        Result::Ok(r) => Ok(r),
+        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => bail!(TimedOut(timeout)),
+        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => bail!("worker thread panicked"),
+    }
+}
+
+/// The constraints of `cs` selected by the `only` and `skip` filters
+fn selected_constraints<'a>(
+    cs: &'a ConstraintSet,
+    only: &Option<Vec<String>>,
+    skip: &[String],
+) -> Vec<&'a Constraint> {
+    cs.constraints
+        .iter()
+        .chain(cs.assertions.iter())
+        .filter(|c| {
+            only.as_ref()
+                .map(|o| o.iter().any(|p| c.is_selected_by(p)))
+                .unwrap_or(true)
+        })
+        .filter(|c| !skip.iter().any(|p| c.is_selected_by(p)))
+        .collect()
+}
+
+/// A uniformly random field element
+fn random_value(rng: &mut StdRng) -> Value {
+    Value::try_from(Value::from(Fr::rand(rng)).to_bi()).unwrap()
+}
+
+/// Evaluate the vanishing constraints selected by `only` and `skip` on
+/// `probes` assignments of random field elements to the cells they read, and
+/// return the ones that did not vanish on all of them.
+///
+/// By the Schwartz-Zippel lemma, these constraints are, with an overwhelming
+/// probability, not identically null as polynomials over their columns; if
+/// they hold on a trace, it is thanks to its values rather than by
+/// construction.
+pub fn random_probe(
+    cs: &ConstraintSet,
+    only: &Option<Vec<String>>,
+    skip: &[String],
+    probes: usize,
+    seed: u64,
+) -> Vec<Handle> {
+    let rng = std::cell::RefCell::new(StdRng::seed_from_u64(seed));
+    selected_constraints(cs, only, skip)
+        .into_iter()
+        .filter_map(|c| match c {
+            Constraint::Vanishes { handle, expr, .. } => {
+                let exprs = match expr.e() {
+                    Expression::List(es) => es.iter().collect::<Vec<_>>(),
+                    _ => vec![expr.as_ref()],
+                };
+                (0..probes)
+                    .any(|_| {
+                        // a cell must read the same value wherever it appears
+                        let cells = std::cell::RefCell::new(HashMap::new());
+                        exprs.iter().any(|e| {
+                            e.eval(
+                                0,
+                                |h, i, _| {
+                                    Some(
+                                        cells
+                                            .borrow_mut()
+                                            .entry((h.clone(), i))
+                                            .or_insert_with(|| random_value(&mut rng.borrow_mut()))
+                                            .clone(),
+                                    )
+                                },
+                                &mut None,
+                                &Default::default(),
+                            )
+                            .map(|x| !x.is_zero())
+                            .unwrap_or(false)
+                        })
+                    })
+                    .then(|| handle.clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
 pub fn check(
     cs: &ConstraintSet,
     only: &Option<Vec<String>>,
@@ -445,40 +933,78 @@ pub fn check(
         return Ok(());
     }
 
-    let todo = cs
-        .constraints
-        .iter()
-        .filter(|c| only.as_ref().map(|o| o.contains(&c.name())).unwrap_or(true))
-        .filter(|c| !skip.contains(&c.name()))
-        .collect::<Vec<_>>();
+    let todo = selected_constraints(cs, only, skip);
     if todo.is_empty() {
         bail!("refusing to check an empty constraint set")
     }
 
+    let suspicious = if settings.pedantic {
+        constant_boolean_columns(cs)
+    } else {
+        Vec::new()
+    };
+    for (handle, x) in suspicious.iter() {
+        warn!(
+            "boolean column {} is constant ({}) over the whole trace",
+            handle.pretty(),
+            x.pretty()
+        );
+    }
+
+    // A single memo is shared by all the checked constraints, so that they can
+    // reuse each other's subexpressions
+    let memo = settings.memoize.then(|| {
+        Arc::new(SubexpressionMemo::new(
+            &todo
+                .iter()
+                .filter_map(|c| match c {
+                    Constraint::Vanishes { expr, .. } => Some(expr.as_ref()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+            MEMOIZED_VALUES,
+        ))
+    });
+
+    let progress = crate::progress::bar(settings.progress, todo.len(), "checking");
     let failed = todo
         .par_iter()
+        .inspect(|_| progress.inc(1))
         .filter_map(|c| {
             match c {
                 Constraint::Vanishes {
                     handle: name,
                     domain,
                     expr,
+                    ..
                 } => {
                     if matches!(expr.e(), Expression::Void) {
                         return None;
                     }
 
+                    let mut cache = Some(if let Some(memo) = memo.as_ref() {
+                        EvalCache::new().memoize(memo.clone())
+                    } else {
+                        EvalCache::new()
+                    });
+
                     match expr.as_ref().e() {
                         Expression::List(es) => {
                             for e in es {
-                                if let Err(err) = check_constraint(cs, e, domain, name, settings) {
+                                if let Err(err) =
+                                    check_constraint(cs, e, domain, name, &mut cache, settings)
+                                {
                                     match err.downcast_ref::<CheckingError>() {
                                         Some(err) => match err {
                                             CheckingError::NoColumnsFound(_) => {
                                                 warn!("{}", err);
                                                 break;
                                             }
-                                            CheckingError::FailingConstraint(handle, trace) => {
+                                            CheckingError::FailingConstraint(
+                                                handle,
+                                                row,
+                                                trace,
+                                            ) => {
                                                 if settings.report {
                                                     println!(
                                                         "{} failed:\n{}\n",
@@ -486,12 +1012,19 @@ pub fn check(
                                                         trace
                                                     );
                                                 }
-                                                return Some(name.to_owned());
+                                                return Some(FailedConstraint {
+                                                    name: name.to_string(),
+                                                    row: Some(*row),
+                                                });
                                             }
                                             CheckingError::MismatchingLengths(err) => {
                                                 error!("{err}");
-                                                return Some(name.to_owned());
+                                                return Some(FailedConstraint {
+                                                    name: name.to_string(),
+                                                    row: None,
+                                                });
                                             }
+                                            CheckingError::FailingRow(..) => unreachable!(),
                                         },
                                         None => {
                                             warn!("{}", err);
@@ -503,13 +1036,15 @@ pub fn check(
                             None
                         }
                         _ => {
-                            if let Err(err) = check_constraint(cs, expr, domain, name, settings) {
+                            if let Err(err) =
+                                check_constraint(cs, expr, domain, name, &mut cache, settings)
+                            {
                                 match err.downcast_ref::<CheckingError>() {
                                     Some(CheckingError::NoColumnsFound(_)) => {
                                         warn!("{}", err);
                                         None
                                     }
-                                    Some(CheckingError::FailingConstraint(handle, trace)) => {
+                                    Some(CheckingError::FailingConstraint(handle, row, trace)) => {
                                         if settings.report {
                                             println!(
                                                 "{} failed:\n{}\n",
@@ -517,12 +1052,19 @@ pub fn check(
                                                 trace
                                             );
                                         }
-                                        Some(name.to_owned())
+                                        Some(FailedConstraint {
+                                            name: name.to_string(),
+                                            row: Some(*row),
+                                        })
                                     }
                                     Some(CheckingError::MismatchingLengths(err)) => {
                                         error!("{err}");
-                                        return Some(name.to_owned());
+                                        return Some(FailedConstraint {
+                                            name: name.to_string(),
+                                            row: None,
+                                        });
                                     }
+                                    Some(CheckingError::FailingRow(..)) => unreachable!(),
                                     None => {
                                         warn!("{}", err);
                                         None
@@ -543,26 +1085,39 @@ pub fn check(
                         if settings.report {
                             println!("{} failed:\n{:?}\n", handle, trace);
                         }
-                        Some(handle.to_owned())
+                        Some(FailedConstraint {
+                            name: handle.to_string(),
+                            row: failing_row(&trace),
+                        })
                     } else {
                         None
                     }
                 }
-                Constraint::Permutation {
-                    handle: _name,
-                    from: _from,
-                    to: _to,
-                    ..
-                } => {
-                    // warn!("Permutation validation not yet implemented");
-                    None
+                Constraint::Permutation { handle, from, to } => {
+                    if !settings.check_permutations {
+                        return None;
+                    }
+                    if let Err(trace) = check_permutation(cs, from, to) {
+                        if settings.report {
+                            println!("{} failed:\n{:?}\n", handle, trace);
+                        }
+                        Some(FailedConstraint {
+                            name: handle.to_string(),
+                            row: failing_row(&trace),
+                        })
+                    } else {
+                        None
+                    }
                 }
                 Constraint::InRange { handle, exp, max } => {
-                    if let Err(trace) = check_inrange(exp, &cs, max) {
+                    if let Err(trace) = check_inrange(&handle.module, exp, &cs, max, &settings) {
                         if settings.report {
                             println!("{} failed:\n{:?}\n", handle, trace);
                         }
-                        Some(handle.to_owned())
+                        Some(FailedConstraint {
+                            name: handle.to_string(),
+                            row: failing_row(&trace),
+                        })
                     } else {
                         None
                     }
@@ -573,19 +1128,28 @@ pub fn check(
                 }
             }
         })
-        .collect::<HashSet<_>>();
+        .collect::<Vec<_>>();
+    drop(progress);
     if failed.is_empty() {
-        info!("Validation successful");
+        if settings.deny_warnings && !suspicious.is_empty() {
+            bail!(crate::errors::Failure::Trace(format!(
+                "constant boolean columns: {}",
+                suspicious.iter().map(|(h, _)| h.pretty()).join(", ")
+            )))
+        }
+        if let Some(count) = settings.sample {
+            info!("No violations found in {} sampled rows", count);
+        } else {
+            info!("Validation successful");
+        }
         Ok(())
     } else {
-        bail!(
-            "constraints failed: {}",
-            failed
+        bail!(CheckFailure {
+            failed: failed
                 .into_iter()
-                .map(|x| x.to_string().bold().red().to_string())
-                .collect::<Vec<_>>()
-                .join(", ")
-        )
+                .sorted_by(|a, b| a.name.cmp(&b.name))
+                .collect()
+        })
     }
 }
 
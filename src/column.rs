@@ -1,20 +1,22 @@
+use crate::field::Fr;
 use crate::{
     compiler::{ColumnRef, EvalSettings, Intrinsic, Kind, Magma, Node},
     constants, errors,
     pretty::{opcodes, Base, Pretty},
+    storage::{ColumnStorage, MmapSettings, MmapStorage},
     structs::Handle,
 };
 use anyhow::*;
-use ark_bls12_377::fr::Fr;
 use ark_ff::{fields::Field, BigInteger, PrimeField};
 use itertools::Itertools;
+use log::*;
 use num_bigint::{BigInt, Sign};
 use num_traits::{Euclid, FromPrimitive, Num, One, ToPrimitive, Zero};
 use owo_colors::OwoColorize;
 use serde::{Deserialize, Serialize};
 use std::ops::{AddAssign, MulAssign, SubAssign};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     str::FromStr,
     sync::OnceLock,
 };
@@ -23,6 +25,13 @@ pub type RegisterID = usize;
 pub type ColumnID = usize;
 
 static POW_2_256: OnceLock<BigInt> = OnceLock::new();
+static FIELD_MODULUS: OnceLock<BigInt> = OnceLock::new();
+
+/// The modulus of the scalar field
+pub fn field_modulus() -> &'static BigInt {
+    FIELD_MODULUS.get_or_init(|| BigInt::from_bytes_le(Sign::Plus, &Fr::MODULUS.to_bytes_le()))
+}
+
 fn clamp_bi(bi: &mut BigInt) {
     // TODO: adapt to field size
     *bi = bi.rem_euclid(POW_2_256.get_or_init(|| {
@@ -140,6 +149,42 @@ impl Value {
         }
     }
 
+    /// Raise `self` to the power `exp` by squaring, staying within the
+    /// field: an integer is reduced modulo the field, but keeps its sign so
+    /// that the result is exact as long as it is smaller than the modulus.
+    pub(crate) fn pow(&self, exp: usize) -> Value {
+        match self {
+            Value::BigInt(i) => {
+                let r = i
+                    .magnitude()
+                    .modpow(&exp.into(), field_modulus().magnitude());
+                Value::BigInt(BigInt::from_biguint(
+                    if i.sign() == Sign::Minus && exp % 2 == 1 {
+                        Sign::Minus
+                    } else {
+                        Sign::Plus
+                    },
+                    r,
+                ))
+            }
+            Value::Native(f) => Value::Native(f.pow([exp as u64])),
+            Value::ExoNative(_) => {
+                let mut ax = Value::one().same_as(self);
+                let mut base = self.clone();
+                let mut exp = exp;
+                while exp > 0 {
+                    if exp % 2 == 1 {
+                        ax.mul_assign(&base);
+                    }
+                    let square = base.clone();
+                    base.mul_assign(&square);
+                    exp /= 2;
+                }
+                ax
+            }
+        }
+    }
+
     pub(crate) fn negate(&mut self) {
         let mut ax = Value::zero().same_as(self);
         ax.sub_assign(self);
@@ -234,11 +279,16 @@ impl Value {
 
     pub(crate) fn to_native(&mut self) {
         if let Value::BigInt(i) = self {
+            if i.sign() == Sign::Minus {
+                // a negative integer -x stands for the field element p - x
+                let m = field_modulus();
+                *i = (m - (-&*i) % m) % m;
+            }
             clamp_bi(i);
             *self = if i.bits() as usize > crate::constants::FIELD_BITSIZE {
                 let bs = i.to_bytes_le();
                 let mut r = Vec::new();
-                for bytes in &bs.1.iter().chunks(crate::constants::FIELD_BITSIZE / 8) {
+                for bytes in &bs.1.iter().chunks(crate::constants::FIELD_CHUNK_BYTES) {
                     let bb = bytes.cloned().collect_vec();
                     let small_big_int = BigInt::from_bytes_le(Sign::Plus, &bb);
                     r.push(Fr::from_str(&small_big_int.to_string()).unwrap());
@@ -266,6 +316,40 @@ impl Value {
     //     }
     // }
 
+    /// Returns the canonical integer representing this value in the field, if
+    /// it fits in a single field element.
+    pub(crate) fn to_field_bi(&self) -> Option<BigInt> {
+        match self {
+            Value::BigInt(i) => {
+                let f = Fr::from(i.magnitude().clone());
+                Some(Value::Native(if i.sign() == Sign::Minus { -f } else { f }).to_bi())
+            }
+            Value::Native(_) => Some(self.to_bi()),
+            Value::ExoNative(_) => None,
+        }
+    }
+
+    /// Pretty-print this value in `base`, showing the field elements larger
+    /// than half the modulus as their negative representative, e.g. `-3`
+    /// rather than `p - 3`; only the numeric bases are affected.
+    pub(crate) fn pretty_signed(&self, base: Base) -> String {
+        if !matches!(base, Base::Dec | Base::Hex) {
+            return self.pretty_with_base(base);
+        }
+        match self.to_field_bi() {
+            Some(i) if i > field_modulus() / 2 => {
+                // negate in the same representation, so that the base is
+                // rendered the same way as for the other values
+                let negated = match self {
+                    Value::Native(f) => Value::Native(-*f),
+                    _ => Value::BigInt(field_modulus() - i),
+                };
+                format!("-{}", negated.pretty_with_base(base))
+            }
+            _ => self.pretty_with_base(base),
+        }
+    }
+
     pub(crate) fn to_bi_variant(&self) -> Value {
         match self {
             Value::BigInt(_) => self.clone(),
@@ -277,11 +361,16 @@ impl Value {
     pub(crate) fn into_native(self) -> Value {
         match self {
             Value::BigInt(mut i) => {
+                if i.sign() == Sign::Minus {
+                    // a negative integer -x stands for the field element p - x
+                    let m = field_modulus();
+                    i = (m - (-i) % m) % m;
+                }
                 clamp_bi(&mut i);
                 if i.bits() as usize > crate::constants::FIELD_BITSIZE {
                     let bs = i.to_bytes_le();
                     let mut r = Vec::new();
-                    for bytes in &bs.1.iter().chunks(crate::constants::FIELD_BITSIZE / 8) {
+                    for bytes in &bs.1.iter().chunks(crate::constants::FIELD_CHUNK_BYTES) {
                         let bb = bytes.cloned().collect_vec();
                         let small_big_int = BigInt::from_bytes_le(Sign::Plus, &bb);
                         r.push(Fr::from_str(&small_big_int.to_string()).unwrap());
@@ -296,6 +385,14 @@ impl Value {
         }
     }
 
+    /// Returns this value as a single field element, if it fits in one.
+    pub fn to_fr(&self) -> Option<Fr> {
+        match self.clone().into_native() {
+            Value::Native(f) => Some(f),
+            _ => None,
+        }
+    }
+
     pub(crate) fn same_as(self, other: &Value) -> Value {
         match other {
             Value::BigInt(_) => self.to_bi_variant(),
@@ -402,6 +499,15 @@ impl From<isize> for Value {
         }
     }
 }
+impl From<i64> for Value {
+    fn from(x: i64) -> Self {
+        if *crate::IS_NATIVE.read().unwrap() {
+            Value::Native(Fr::from(x))
+        } else {
+            Value::BigInt(BigInt::from_i64(x).unwrap())
+        }
+    }
+}
 impl From<u64> for Value {
     fn from(x: u64) -> Self {
         if *crate::IS_NATIVE.read().unwrap() {
@@ -420,12 +526,33 @@ impl From<i32> for Value {
         }
     }
 }
-impl From<&str> for Value {
-    fn from(x: &str) -> Self {
-        if *crate::IS_NATIVE.read().unwrap() {
-            Value::Native(Fr::from_str(x).unwrap())
+impl TryFrom<&str> for Value {
+    type Error = anyhow::Error;
+
+    fn try_from(x: &str) -> Result<Self> {
+        if let Some(magnitude) = x.strip_prefix('-') {
+            let i = -Value::try_from(magnitude)?.to_bi();
+            Result::Ok(if *crate::IS_NATIVE.read().unwrap() {
+                Value::BigInt(i).into_native()
+            } else {
+                Value::BigInt(i)
+            })
+        } else if let Some(hex) = x.strip_prefix("0x") {
+            let i = BigInt::from_str_radix(hex, 16)
+                .map_err(|_| anyhow!("invalid hexadecimal value `{}`", x))?;
+            Result::Ok(if *crate::IS_NATIVE.read().unwrap() {
+                Value::BigInt(i).into_native()
+            } else {
+                Value::BigInt(i)
+            })
+        } else if *crate::IS_NATIVE.read().unwrap() {
+            Fr::from_str(x)
+                .map(Value::Native)
+                .map_err(|_| anyhow!("invalid value `{}`", x))
         } else {
-            Value::BigInt(BigInt::from_str(x).unwrap())
+            BigInt::from_str(x)
+                .map(Value::BigInt)
+                .map_err(|_| anyhow!("invalid value `{}`", x))
         }
     }
 }
@@ -524,7 +651,7 @@ pub struct FieldRegister {
 // #[derive(Debug)]
 pub enum ValueBacking {
     Vector {
-        v: Vec<Value>,
+        v: Box<dyn ColumnStorage>,
         spilling: isize,
     },
     Expression {
@@ -546,7 +673,8 @@ impl std::fmt::Debug for ValueBacking {
             ValueBacking::Vector { v, spilling } => {
                 write!(
                     f,
-                    "Vector-backed: len ({}) = {} + {}",
+                    "Vector-backed{}: len ({}) = {} + {}",
+                    if v.is_mapped() { " (mapped)" } else { "" },
                     v.len(),
                     v.len() - *spilling as usize,
                     spilling
@@ -564,14 +692,44 @@ impl std::fmt::Debug for ValueBacking {
 impl std::default::Default for ValueBacking {
     fn default() -> Self {
         ValueBacking::Vector {
-            v: Vec::new(),
+            v: Box::<Vec<Value>>::default(),
             spilling: 0,
         }
     }
 }
 impl ValueBacking {
     pub fn from_vec(v: Vec<Value>, spilling: isize) -> Self {
-        ValueBacking::Vector { v, spilling }
+        ValueBacking::Vector {
+            v: Box::new(v),
+            spilling,
+        }
+    }
+
+    /// Whether the values are stored in a memory-mapped file
+    pub fn is_mapped(&self) -> bool {
+        matches!(self, ValueBacking::Vector { v, .. } if v.is_mapped())
+    }
+
+    /// Move the values of a large enough vector-backed register to a
+    /// memory-mapped file.
+    fn spill(self, settings: &MmapSettings) -> Self {
+        match self {
+            ValueBacking::Vector { v, spilling }
+                if !v.is_mapped() && v.len() >= settings.threshold =>
+            {
+                match MmapStorage::from_storage(settings, v.as_ref()) {
+                    Result::Ok(mapped) => ValueBacking::Vector {
+                        v: Box::new(mapped),
+                        spilling,
+                    },
+                    Err(e) => {
+                        warn!("keeping register in memory: {:?}", e);
+                        ValueBacking::Vector { v, spilling }
+                    }
+                }
+            }
+            _ => self,
+        }
     }
 
     pub fn from_expression(e: Node, len: usize, spilling: isize) -> Self {
@@ -621,13 +779,15 @@ impl ValueBacking {
                         v.len()
                     );
                 }
-                for (x, y) in v.iter_mut().zip(_v.iter()) {
+                let mut ys = _v.iter();
+                v.update(&mut |x| {
                     if !x.is_zero() {
                         bail!("overwriting non-zero value in shared register")
                     } else {
-                        x.add_assign(y)
+                        x.add_assign(ys.next().unwrap());
+                        Ok(())
                     }
-                }
+                })?;
             }
             ValueBacking::Expression { .. } => {
                 bail!("can not update value of expression-based register backing")
@@ -658,7 +818,6 @@ impl ValueBacking {
                     v.get((i + spilling) as usize)
                 }
             }
-            .cloned(),
             ValueBacking::Expression { e, .. } => e.eval(
                 i,
                 |handle, j, _| {
@@ -666,7 +825,7 @@ impl ValueBacking {
                         .or_else(|| cs.column(handle).unwrap().padding_value.as_ref().cloned())
                 },
                 &mut None,
-                &EvalSettings { wrap: false },
+                &EvalSettings::new().wrap(false),
             ),
             ValueBacking::Function { f, .. } => f(i, cs),
         }
@@ -685,7 +844,6 @@ impl ValueBacking {
                     v.get((i + spilling) as usize)
                 }
             }
-            .cloned(),
             ValueBacking::Expression { e, .. } => e.eval(
                 i,
                 |handle, j, _| {
@@ -693,7 +851,7 @@ impl ValueBacking {
                         .or_else(|| cs.column(handle).unwrap().padding_value.as_ref().cloned())
                 },
                 &mut None,
-                &EvalSettings { wrap: false },
+                &EvalSettings::new().wrap(false),
             ),
             ValueBacking::Function { f, .. } => f(i, cs),
         }
@@ -702,7 +860,12 @@ impl ValueBacking {
     fn concretize(mut self) -> Self {
         match self {
             ValueBacking::Vector { mut v, spilling } => {
-                v.iter_mut().for_each(|x| x.to_native());
+                // native values always fit in a storage cell
+                v.update(&mut |x| {
+                    x.to_native();
+                    Ok(())
+                })
+                .unwrap();
                 ValueBacking::Vector { v, spilling }
             }
             ValueBacking::Expression { ref mut e, .. } => {
@@ -762,7 +925,7 @@ impl<'a> Iterator for ValueBackingIter<'a> {
                     None
                 } else {
                     self.i += 1;
-                    v.get((self.i + self.spilling - 1) as usize).cloned()
+                    v.get((self.i + self.spilling - 1) as usize)
                 }
             }
             ValueBacking::Expression { .. } => {
@@ -825,7 +988,12 @@ impl Register {
         } else {
             let _ = self.backing.insert(ValueBacking::from_vec(
                 Self::make_with_spilling(
-                    &mut |i| v.get(i as usize).cloned().unwrap_or_else(Value::zero),
+                    &mut |i| {
+                        v.as_slice()
+                            .get(i as usize)
+                            .cloned()
+                            .unwrap_or_else(Value::zero)
+                    },
                     v.len(),
                     spilling,
                 ),
@@ -875,6 +1043,14 @@ impl Register {
             let _ = self.backing.insert(v.concretize());
         }
     }
+
+    fn spill(&mut self, settings: &Option<MmapSettings>) {
+        if let Some(settings) = settings {
+            if let Some(v) = self.backing.take() {
+                let _ = self.backing.insert(v.spill(settings));
+            }
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -906,7 +1082,7 @@ impl Column {
     ) -> Self {
         Column {
             register,
-            padding_value: padding_value.map(|v| Value::from(v as usize)),
+            padding_value: padding_value.map(Value::from),
             used: used.unwrap_or(true),
             must_prove: must_prove.unwrap_or(false),
             kind: kind.unwrap_or(Kind::Computed),
@@ -919,6 +1095,57 @@ impl Column {
     }
 }
 
+/// Where the padding rows bringing a module to its minimal length are
+/// inserted when importing a trace
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum PaddingStrategy {
+    /// before the values of the trace
+    #[default]
+    Prepend,
+    /// after the values of the trace
+    Append,
+}
+impl PaddingStrategy {
+    /// Pad `xs` to `len` rows with the values generated by `value`
+    pub fn pad<T>(&self, xs: &mut Vec<T>, len: usize, value: impl FnMut() -> T) {
+        match self {
+            PaddingStrategy::Prepend => {
+                xs.reverse();
+                xs.resize_with(len, value);
+                xs.reverse();
+            }
+            PaddingStrategy::Append => xs.resize_with(len, value),
+        }
+    }
+
+    /// The number of padding rows inserted before the `len` values of a
+    /// column padded to `min_len` rows
+    pub fn leading_rows(&self, len: usize, min_len: usize) -> usize {
+        match self {
+            PaddingStrategy::Prepend => min_len.saturating_sub(len),
+            PaddingStrategy::Append => 0,
+        }
+    }
+
+    /// The number of padding rows inserted after the `len` values of a
+    /// column padded to `min_len` rows
+    pub fn trailing_rows(&self, len: usize, min_len: usize) -> usize {
+        match self {
+            PaddingStrategy::Prepend => 0,
+            PaddingStrategy::Append => min_len.saturating_sub(len),
+        }
+    }
+}
+impl std::fmt::Display for PaddingStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaddingStrategy::Prepend => write!(f, "prepend"),
+            PaddingStrategy::Append => write!(f, "append"),
+        }
+    }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct ColumnSet {
     pub _cols: Vec<Column>,
@@ -927,12 +1154,52 @@ pub struct ColumnSet {
     /// a module may have a lower bound on its columns length if it is involved
     /// in range proofs
     pub min_len: HashMap<String, usize>,
+    /// module -> length its columns are padded to, set by the user rather than
+    /// by range proofs; longer traces are rejected
+    #[serde(skip)]
+    pub forced_len: HashMap<String, usize>,
+    /// module -> first row added by the latest trace appended to the columns
+    #[serde(skip)]
+    pub appended_from: HashMap<String, isize>,
     pub field_registers: Vec<FieldRegister>,
     pub registers: Vec<Register>,
     pub spilling: HashMap<String, isize>, // module -> (past-spilling, future-spilling)
+    /// module -> number of padding rows prepended to its columns when importing
+    /// a trace
+    #[serde(default)]
+    pub padding: HashMap<String, isize>,
+    /// module -> number of padding rows appended to its columns when importing
+    /// a trace
+    #[serde(skip)]
+    pub trailing_padding: HashMap<String, isize>,
+    /// where the padding rows required by the minimal length of the modules
+    /// are inserted
+    #[serde(skip)]
+    pub padding_strategy: PaddingStrategy,
+    /// columns that shall be neither imported nor computed, e.g. because their
+    /// module has been filtered out
+    #[serde(skip)]
+    pub skipped: HashSet<Handle>,
+    /// if set, large registers are stored in memory-mapped files
+    #[serde(skip)]
+    pub mmap: Option<MmapSettings>,
+    /// whether to display a progress bar while computing the columns
+    #[serde(skip)]
+    pub progress: bool,
+    /// the separator between module and column names in the traces, if not
+    /// [`DEFAULT_TRACE_MODULE_SEPARATOR`](crate::structs::DEFAULT_TRACE_MODULE_SEPARATOR)
+    #[serde(skip)]
+    pub module_separator: Option<String>,
 }
 
 impl ColumnSet {
+    /// The separator between module and column names in the traces
+    pub fn module_separator(&self) -> &str {
+        self.module_separator
+            .as_deref()
+            .unwrap_or(crate::structs::DEFAULT_TRACE_MODULE_SEPARATOR)
+    }
+
     pub(crate) fn module_of(&self, c: &ColumnRef) -> String {
         self.column(c).unwrap().handle.module.clone()
     }
@@ -1112,7 +1379,8 @@ impl ColumnSet {
         self.cols.get(handle).and_then(|i| self._cols.get_mut(*i))
     }
 
-    pub fn modules(&self) -> HashSet<String> {
+    /// The names of all the modules, in lexicographic order
+    pub fn modules(&self) -> BTreeSet<String> {
         self.cols.keys().map(|h| h.module.clone()).collect()
     }
 
@@ -1148,6 +1416,8 @@ impl ColumnSet {
         }
     }
 
+    /// Iterate over the columns in the order they have been declared in the
+    /// source; the ones generated during the compilation come last.
     pub fn iter(&self) -> impl Iterator<Item = (ColumnRef, &Column)> {
         (0..self._cols.len()).map(|i| (ColumnRef::from(i), &self._cols[i]))
     }
@@ -1284,6 +1554,14 @@ impl ColumnSet {
         self.register_of(h).backing.as_ref()
     }
 
+    pub fn is_skipped(&self, h: &ColumnRef) -> bool {
+        !self.skipped.is_empty()
+            && self
+                .column(h)
+                .map(|c| self.skipped.contains(&c.handle))
+                .unwrap_or(false)
+    }
+
     pub fn is_computed(&self, h: &ColumnRef) -> bool {
         self.column(h).unwrap().computed
     }
@@ -1295,9 +1573,13 @@ impl ColumnSet {
         spilling: isize,
     ) -> Result<()> {
         self.get_col_mut(h).unwrap().computed = true;
-        self.register_of_mut(h)
+        let mmap = self.mmap.clone();
+        let register = self.register_of_mut(h);
+        register
             .set_value(v, spilling)
-            .with_context(|| anyhow!("while filling {}", h.pretty()))
+            .with_context(|| anyhow!("while filling {}", h.pretty()))?;
+        register.spill(&mmap);
+        Ok(())
     }
 
     pub fn set_register_value(
@@ -1329,20 +1611,41 @@ impl ColumnSet {
             column.computed = true;
         }
 
-        self.get_register_mut(h)
-            .unwrap()
+        let mmap = self.mmap.clone();
+        let register = self.get_register_mut(h).unwrap();
+        register
             .set_value(v, spilling)
-            .with_context(|| anyhow!("while filling {}", h.pretty()))
+            .with_context(|| anyhow!("while filling {}", h.pretty()))?;
+        register.spill(&mmap);
+        Ok(())
     }
 
     pub fn set_raw_value(&mut self, h: &ColumnRef, v: Vec<Value>, spilling: isize) -> Result<()> {
         self.get_col_mut(h).unwrap().computed = true;
-        self.register_of_mut(h).set_raw_value(v, spilling)
+        let mmap = self.mmap.clone();
+        let register = self.register_of_mut(h);
+        register.set_raw_value(v, spilling)?;
+        register.spill(&mmap);
+        Ok(())
     }
 
     pub fn set_backing(&mut self, h: &ColumnRef, v: ValueBacking) -> Result<()> {
         self.get_col_mut(h).unwrap().computed = true;
-        self.register_of_mut(h).set_backing(v)
+        let mmap = self.mmap.clone();
+        let register = self.register_of_mut(h);
+        register.set_backing(v)?;
+        register.spill(&mmap);
+        Ok(())
+    }
+
+    /// Forget the value of the given column, and of all the columns sharing its
+    /// register, so that it may be filled again.
+    pub fn reset_column(&mut self, h: &ColumnRef) {
+        let reg = self.column(h).unwrap().register.unwrap();
+        for column in self._cols.iter_mut().filter(|c| c.register == Some(reg)) {
+            column.computed = false;
+        }
+        self.registers[reg].backing = None;
     }
 }
 
@@ -1409,6 +1712,11 @@ pub enum Computation {
         eq: ColumnRef,
         delta: ColumnRef,
         delta_bytes: Vec<ColumnRef>,
+        /// set from the first sorted row that is not all zeros; only present
+        /// when sorting on a descending key, as the padding may then not sort
+        /// before the actual rows
+        #[serde(default)]
+        started: Option<ColumnRef>,
         signs: Vec<bool>,
         froms: Vec<ColumnRef>,
         sorted: Vec<ColumnRef>,
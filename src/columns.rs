@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::{
+    column::Computation,
+    compiler::{ColumnRef, ConstraintSet, Kind},
+};
+
+/// A column declared or generated by a constraint set, as listed by the
+/// `columns` command
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub(crate) struct ColumnListing {
+    pub module: String,
+    pub name: String,
+    /// how the column is filled: `Commitment`, `Expression`, or the kind of
+    /// computation producing it for computed columns
+    pub kind: String,
+    pub magma: String,
+    /// the columns the computed column is derived from
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+    /// the `;;` comment documenting the column
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+}
+
+fn names<'a>(cs: &ConstraintSet, rs: impl IntoIterator<Item = &'a ColumnRef>) -> Vec<String> {
+    rs.into_iter().map(|r| cs.handle(r).to_string()).collect()
+}
+
+/// The kind of `r`, and the columns it is computed from
+fn kind_and_sources(cs: &ConstraintSet, r: &ColumnRef, kind: &Kind<()>) -> (String, Vec<String>) {
+    match kind {
+        Kind::Commitment => ("Commitment".into(), vec![]),
+        Kind::Expression(_) => ("Expression".into(), vec![]),
+        Kind::Computed => match cs.computations.computation_for(r) {
+            None => ("Computed".into(), vec![]),
+            Some(c) => match c {
+                Computation::Composite { exp, .. } => (
+                    "Composite".into(),
+                    names(cs, &exp.dependencies())
+                        .into_iter()
+                        .sorted()
+                        .collect(),
+                ),
+                Computation::ExoOperation { sources, .. } => (
+                    "ExoOperation".into(),
+                    names(
+                        cs,
+                        &sources
+                            .iter()
+                            .flat_map(|s| s.dependencies())
+                            .collect::<HashSet<_>>(),
+                    )
+                    .into_iter()
+                    .sorted()
+                    .collect(),
+                ),
+                Computation::ExoConstant { .. } => ("ExoConstant".into(), vec![]),
+                Computation::Interleaved { froms, .. } => ("Interleaved".into(), names(cs, froms)),
+                Computation::Sorted { froms, .. } => ("Sorted".into(), names(cs, froms)),
+                Computation::CyclicFrom { froms, .. } => ("CyclicFrom".into(), names(cs, froms)),
+                Computation::SortingConstraints { froms, .. } => {
+                    ("SortingConstraints".into(), names(cs, froms))
+                }
+            },
+        },
+    }
+}
+
+/// List all the columns of `cs` by module, in the order they have been
+/// declared
+pub(crate) fn list(cs: &ConstraintSet) -> Vec<ColumnListing> {
+    cs.columns
+        .iter()
+        .map(|(r, c)| {
+            let (kind, sources) = kind_and_sources(cs, &r, &c.kind);
+            ColumnListing {
+                module: c.handle.module.clone(),
+                name: c.handle.name.clone(),
+                kind,
+                magma: c.t.to_string(),
+                sources,
+                doc: cs.docs.columns.get(&c.handle).cloned(),
+            }
+        })
+        // the sort is stable, so the columns stay in declaration order
+        .sorted_by(|a, b| a.module.cmp(&b.module))
+        .collect()
+}
+
+/// Render `columns` as `module.name : kind : magma`, grouped by module and
+/// followed by their documentation
+pub(crate) fn render(columns: &[ColumnListing]) -> String {
+    columns
+        .iter()
+        .group_by(|c| &c.module)
+        .into_iter()
+        .map(|(module, cs)| {
+            format!(
+                "{}:\n{}",
+                module,
+                cs.map(|c| {
+                    format!(
+                        "  {}.{} : {} : {}{}{}",
+                        c.module,
+                        c.name,
+                        c.kind,
+                        c.magma,
+                        if c.sources.is_empty() {
+                            String::new()
+                        } else {
+                            format!(" ⟵ {}", c.sources.join(", "))
+                        },
+                        c.doc
+                            .iter()
+                            .flat_map(|doc| doc.lines())
+                            .map(|l| format!("\n      ;; {}", l))
+                            .collect::<String>()
+                    )
+                })
+                .join("\n")
+            )
+        })
+        .join("\n")
+}
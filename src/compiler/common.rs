@@ -5,6 +5,7 @@ use anyhow::*;
 use serde::{Deserialize, Serialize};
 
 use crate::errors::CompileError;
+use crate::pretty::Pretty;
 
 use super::parser::{AstNode, Token};
 use super::{max_type, Expression, Magma, Node, RawMagma, Type};
@@ -30,7 +31,7 @@ impl<T> Kind<T> {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Domain<T> {
     Range(T, T),
     SteppedRange(T, T, T),
@@ -123,6 +124,9 @@ impl Domain<isize> {
 pub enum Form {
     For,
     Let,
+    /// `(let-column NAME EXPR BODY)` materializes EXPR as a computed column,
+    /// proven to be equal to it, that is referred to as NAME in BODY
+    LetColumn,
     Debug,
     Todo,
     Reduce,
@@ -132,12 +136,32 @@ pub enum Form {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Builtin {
     Len,
+    Nth,
     Shift,
     /// This represents normalisation in the presence of
     /// field agnosticity.  Perhaps it might be considered
     /// "vector normalisation"?
     NormFlat,
     If,
+    /// Comparisons of operands bounded by a bit width, either inferred from
+    /// their magma or given as an optional third argument
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Reductions over all the elements of an array
+    Sum,
+    Prod,
+    /// 1 if its argument is not zero, 0 otherwise; lowered to a normalization,
+    /// and thus backed by an inverse column once expanded
+    IsNotZero,
+    /// An anonymous computed column interleaving its arguments
+    Interleave,
+    /// Explicitly widen a boolean to an integer
+    AsInteger,
+    /// Explicitly type its argument as a boolean, constraining it to 0 or 1
+    /// unless its optional second argument is 0
+    AsBool,
 }
 impl std::fmt::Display for Builtin {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -146,9 +170,20 @@ impl std::fmt::Display for Builtin {
             "{}",
             match self {
                 Builtin::Len => "len",
+                Builtin::Nth => "nth",
                 Builtin::Shift => "shift",
                 Builtin::NormFlat => "~>>",
                 Builtin::If => "if?",
+                Builtin::Lt => "<",
+                Builtin::Le => "<=",
+                Builtin::Gt => ">",
+                Builtin::Ge => ">=",
+                Builtin::Sum => "sum",
+                Builtin::Prod => "prod",
+                Builtin::IsNotZero => "!=0?",
+                Builtin::Interleave => "interleave",
+                Builtin::AsInteger => "as-integer",
+                Builtin::AsBool => "as-bool",
             }
         )
     }
@@ -156,7 +191,7 @@ impl std::fmt::Display for Builtin {
 
 /// An intrinsic is a function that can appear in the final compiled form
 /// of an expression
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum Intrinsic {
     Add,
     Sub,
@@ -176,6 +211,10 @@ pub enum Intrinsic {
 
     IfZero,
     IfNotZero,
+
+    /// Whether the first argument is strictly smaller than the second one,
+    /// both of them fitting in the bit width given by the third argument
+    Lt,
 }
 impl Intrinsic {
     pub fn call(self, args: &[Node]) -> Result<Node> {
@@ -194,6 +233,23 @@ impl Intrinsic {
         }
     }
 
+    /// Under strict typing, ensure that no boolean argument is implicitly
+    /// widened to an integer by an operation it is not stable under
+    pub fn validate_strict_types(&self, args: &[Node]) -> Result<()> {
+        if matches!(self, Intrinsic::Add | Intrinsic::Sub | Intrinsic::Neg) {
+            if let Some(arg) = args
+                .iter()
+                .find(|a| a.t().is_binary() && a.pure_eval().is_err())
+            {
+                bail!(CompileError::ImplicitWidening(
+                    self.to_string(),
+                    arg.pretty()
+                ))
+            }
+        }
+        Ok(())
+    }
+
     pub fn typing(&self, argtype: &[Type]) -> Result<Type> {
         Ok(match self {
             Intrinsic::Inv => argtype[0],
@@ -213,6 +269,9 @@ impl Intrinsic {
                 argtype[1].max(argtype.get(2).cloned().unwrap_or(Type::INFIMUM))
             }
             Intrinsic::Begin => Type::List(max_type(argtype)?.m()),
+            Intrinsic::Lt => argtype[0]
+                .max(argtype[1])
+                .with_magma(Magma::binary().with_conditioning(super::Conditioning::Boolean)),
         })
     }
 }
@@ -235,6 +294,7 @@ impl std::fmt::Display for Intrinsic {
                 Intrinsic::Begin => "begin",
                 Intrinsic::IfZero => "if-zero",
                 Intrinsic::IfNotZero => "if-not-zero",
+                Intrinsic::Lt => "<",
             }
         )
     }
@@ -248,21 +308,16 @@ pub enum Arity {
     Between(usize, usize),
 }
 impl Arity {
-    fn make_error(&self, l: usize) -> String {
+    fn expected(&self) -> String {
         fn arg_count(x: usize) -> String {
             format!("{} argument{}", x, if x > 1 { "s" } else { "" })
         }
         match self {
-            Arity::AtLeast(x) => format!("expected at least {}, but received {}", arg_count(*x), l),
-            Arity::Monadic => format!("expected {}, but received {}", arg_count(1), l),
-            Arity::Dyadic => format!("expected {}, but received {}", arg_count(2), l),
-            Arity::Exactly(x) => format!("expected {}, but received {}", arg_count(*x), l),
-            Arity::Between(x, y) => format!(
-                "expected between {} and {}, but received {}",
-                arg_count(*x),
-                arg_count(*y),
-                l
-            ),
+            Arity::AtLeast(x) => format!("at least {}", arg_count(*x)),
+            Arity::Monadic => arg_count(1),
+            Arity::Dyadic => arg_count(2),
+            Arity::Exactly(x) => arg_count(*x),
+            Arity::Between(x, y) => format!("between {} and {}", arg_count(*x), arg_count(*y)),
         }
     }
 
@@ -276,7 +331,7 @@ impl Arity {
         } {
             Ok(())
         } else {
-            bail!(self.make_error(l))
+            bail!(CompileError::ArityMismatch(self.expected(), l))
         }
     }
 }
@@ -305,27 +360,57 @@ impl FuncVerifier<Node> for Builtin {
     fn arity(&self) -> Arity {
         match self {
             Builtin::Len => Arity::Monadic,
+            Builtin::Nth => Arity::Dyadic,
             Builtin::Shift => Arity::Dyadic,
             Builtin::NormFlat => Arity::Monadic,
             Builtin::If => Arity::Between(2, 3),
+            Builtin::Lt | Builtin::Le | Builtin::Gt | Builtin::Ge => Arity::Between(2, 3),
+            Builtin::Sum | Builtin::Prod => Arity::Monadic,
+            Builtin::IsNotZero => Arity::Monadic,
+            Builtin::Interleave => Arity::AtLeast(1),
+            Builtin::AsInteger => Arity::Monadic,
+            Builtin::AsBool => Arity::Between(1, 2),
         }
     }
 
     fn validate_types(&self, args: &[Node]) -> Result<()> {
         let args_t = args.iter().map(|a| a.t()).collect::<Vec<_>>();
         let expected_t: &[&[Type]] = match self {
-            Builtin::Len => &[&[Type::ArrayColumn(Magma::ANY)]],
+            Builtin::Len => &[&[
+                Type::ArrayColumn(Magma::ANY),
+                Type::List(Magma::ANY),
+                Type::Column(Magma::ANY),
+            ]],
+            Builtin::Sum | Builtin::Prod => {
+                &[&[Type::ArrayColumn(Magma::ANY), Type::List(Magma::ANY)]]
+            }
+            Builtin::Nth => &[
+                &[Type::ArrayColumn(Magma::ANY), Type::List(Magma::ANY)],
+                &[Type::Scalar(Magma::ANY)],
+            ],
             Builtin::Shift => &[&[Type::Column(Magma::ANY)], &[Type::Scalar(Magma::ANY)]],
-            Builtin::NormFlat => &[&[Type::Column(Magma::ANY)]],
+            Builtin::NormFlat | Builtin::Interleave => &[&[Type::Column(Magma::ANY)]],
+            Builtin::IsNotZero | Builtin::AsInteger => {
+                &[&[Type::Scalar(Magma::ANY), Type::Column(Magma::ANY)]]
+            }
+            Builtin::AsBool => &[
+                &[Type::Scalar(Magma::ANY), Type::Column(Magma::ANY)],
+                &[Type::Scalar(Magma::ANY)],
+            ],
             Builtin::If => &[&[Type::Any(Magma::ANY)], &[Type::Any(Magma::ANY)]],
+            Builtin::Lt | Builtin::Le | Builtin::Gt | Builtin::Ge => &[
+                &[Type::Scalar(Magma::ANY), Type::Column(Magma::ANY)],
+                &[Type::Scalar(Magma::ANY), Type::Column(Magma::ANY)],
+                &[Type::Scalar(Magma::ANY)],
+            ],
         };
 
         if super::compatible_with_repeating(expected_t, &args_t) {
             Ok(())
         } else {
-            bail!(CompileError::TypeError(
+            bail!(CompileError::TypeMismatch(
                 self.to_string(),
-                expected_t,
+                expected_t.iter().map(|ts| ts.to_vec()).collect(),
                 args_t
             ))
         }
@@ -339,22 +424,43 @@ impl FuncVerifier<AstNode> for Form {
             Form::Debug => Arity::AtLeast(1),
             Form::Todo => Arity::AtLeast(0),
             Form::Let => Arity::Dyadic,
+            Form::LetColumn => Arity::Exactly(3),
             Form::Reduce => Arity::Dyadic,
         }
     }
     fn validate_types(&self, args: &[AstNode]) -> Result<()> {
         match self {
-            Form::For => {
-                if matches!(args[0].class, Token::Symbol(_)) {
+            Form::For => match (&args[0].class, &args[1].class) {
+                (Token::Symbol(_), Token::Domain(_)) => Ok(()),
+                // (for (i j) ([1:2] [1:3]) ...)
+                (Token::List(names), Token::List(domains)) => {
+                    if names.is_empty() || names.len() != domains.len() {
+                        bail!(
+                            "FOR expects as many ranges as indices, found {} indices and {} ranges",
+                            names.len(),
+                            domains.len()
+                        )
+                    }
+                    if let Some(name) = names
+                        .iter()
+                        .find(|n| !matches!(n.class, Token::Symbol(_)))
+                    {
+                        bail!("FOR expects an index name, found `{:?}`", name)
+                    }
+                    if let Some(domain) = domains
+                        .iter()
+                        .find(|d| !matches!(d.class, Token::Domain(_)))
+                    {
+                        bail!("FOR expects a range, found `{:?}`", domain)
+                    }
                     Ok(())
-                } else {
-                    bail!(
-                        "`{:?}` expects [SYMBOL ITERABLE EXPR] but received {:?}",
-                        self,
-                        args
-                    )
                 }
-            }
+                _ => bail!(
+                    "`{:?}` expects [SYMBOL ITERABLE EXPR] or [(SYMBOL...) (ITERABLE...) EXPR] but received {:?}",
+                    self,
+                    args
+                ),
+            },
             Form::Debug => Ok(()),
             Form::Todo => Ok(()),
             Form::Let => {
@@ -373,6 +479,12 @@ impl FuncVerifier<AstNode> for Form {
                     bail!("LET expects a list of bindings, found `{:?}`", args[0])
                 }
             }
+            Form::LetColumn => {
+                if args[0].as_symbol().is_err() {
+                    bail!("LET-COLUMN expects a column name, found `{:?}`", args[0])
+                }
+                Ok(())
+            }
             Form::Reduce => {
                 if args[0].as_symbol().is_err() {
                     bail!("REDUCE expects a symbol, found `{:?}`", args[0])
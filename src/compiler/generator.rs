@@ -1,4 +1,6 @@
+use crate::field::Fr;
 use anyhow::*;
+use ark_ff::{BigInteger, PrimeField};
 use cached::Cached;
 use itertools::Itertools;
 use log::*;
@@ -8,7 +10,7 @@ use num_traits::cast::ToPrimitive;
 use num_traits::{One, Zero};
 use owo_colors::OwoColorize;
 use serde::{Deserialize, Serialize};
-use std::sync::OnceLock;
+use std::sync::{Arc, Mutex, OnceLock};
 
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Debug;
@@ -19,7 +21,7 @@ use std::sync::atomic::AtomicUsize;
 use super::node::ColumnRef;
 use super::parser::{Ast, AstNode, Token};
 use super::tables::{ComputationTable, Scope};
-use super::{common::*, CompileSettings, Conditioning, Expression, Magma, Node, Type};
+use super::{common::*, CompileSettings, Conditioning, Expression, Magma, Node, RawMagma, Type};
 use crate::column::{Column, ColumnSet, Computation, RegisterID, Value, ValueBacking};
 use crate::dag::ComputationDag;
 use crate::errors::{self, CompileError, RuntimeError};
@@ -39,12 +41,43 @@ fn uniquify(n: String) -> String {
     )
 }
 
+thread_local! {
+    /// The constraint being reduced, if any, to which the constant foldings
+    /// are attributed in the diagnostics
+    static FOLDING_IN: std::cell::RefCell<Option<Handle>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Attribute the constant foldings to a constraint for as long as it lives
+struct FoldingIn;
+impl FoldingIn {
+    fn constraint(handle: &Handle) -> Self {
+        FOLDING_IN.with(|c| c.replace(Some(handle.clone())));
+        FoldingIn
+    }
+}
+impl Drop for FoldingIn {
+    fn drop(&mut self) {
+        FOLDING_IN.with(|c| c.take());
+    }
+}
+
+/// Report, at the debug level, that `from` has been folded into `to`
+fn report_folding(from: &Node, to: &Node) {
+    FOLDING_IN.with(|c| match c.borrow().as_ref() {
+        Some(handle) => debug!("{}: folded {} into {}", handle.pretty(), from, to),
+        None => debug!("folded {} into {}", from, to),
+    })
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum Constraint {
     Vanishes {
         handle: Handle,
         domain: Option<Domain<isize>>,
         expr: Box<Node>,
+        /// user-defined labels, to select constraints beyond their name
+        #[serde(default)]
+        tags: Vec<String>,
     },
     Lookup {
         handle: Handle,
@@ -56,6 +89,7 @@ pub enum Constraint {
         from: Vec<ColumnRef>,
         to: Vec<ColumnRef>,
     },
+    // Ensures that 0 ≤ exp < max on every row; max is a bound, not a bit width
     InRange {
         handle: Handle,
         exp: Node,
@@ -79,6 +113,35 @@ impl Constraint {
         }
     }
 
+    /// The tags attached to this constraint; only user-defined constraints
+    /// may be tagged
+    pub fn tags(&self) -> &[String] {
+        match self {
+            Constraint::Vanishes { tags, .. } => tags,
+            _ => &[],
+        }
+    }
+
+    /// Whether this constraint is designated by `pattern`, either its name, or
+    /// one of its tags as `@tag`
+    pub fn is_selected_by(&self, pattern: &str) -> bool {
+        if let Some(tag) = pattern.strip_prefix('@') {
+            self.tags().iter().any(|t| t == tag)
+        } else {
+            self.name() == pattern
+        }
+    }
+
+    pub fn handle(&self) -> &Handle {
+        match self {
+            Constraint::Vanishes { handle, .. }
+            | Constraint::Lookup { handle, .. }
+            | Constraint::Permutation { handle, .. }
+            | Constraint::InRange { handle, .. }
+            | Constraint::Normalization { handle, .. } => handle,
+        }
+    }
+
     pub fn add_id_to_handles(&mut self, set_id: &dyn Fn(&mut ColumnRef)) {
         match self {
             Constraint::Vanishes { expr, .. } => expr.add_id_to_handles(set_id),
@@ -105,6 +168,35 @@ impl Constraint {
         }
     }
 
+    /// The columns referenced by this constraint
+    pub fn dependencies(&self) -> HashSet<ColumnRef> {
+        match self {
+            Constraint::Vanishes { expr, .. } => expr.dependencies(),
+            Constraint::Lookup {
+                including: xs,
+                included: ys,
+                ..
+            } => xs
+                .iter()
+                .chain(ys.iter())
+                .flat_map(|e| e.dependencies())
+                .collect(),
+            Constraint::Permutation { from, to, .. } => {
+                from.iter().chain(to.iter()).cloned().collect()
+            }
+            Constraint::InRange { exp, .. } => exp.dependencies(),
+            Constraint::Normalization {
+                reference,
+                inverted,
+                ..
+            } => {
+                let mut r = reference.dependencies();
+                r.insert(inverted.clone());
+                r
+            }
+        }
+    }
+
     pub(crate) fn size(&self) -> usize {
         match self {
             Constraint::Vanishes { expr, .. } => expr.size(),
@@ -115,17 +207,229 @@ impl Constraint {
         }
     }
 }
+/// Constraints are equal if they are structurally identical, whatever their
+/// name.
+impl PartialEq for Constraint {
+    fn eq(&self, other: &Self) -> bool {
+        let nodes = |xs: &[Node], ys: &[Node]| {
+            xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| same_structure(x, y))
+        };
+        match (self, other) {
+            (
+                Constraint::Vanishes {
+                    domain: d1,
+                    expr: e1,
+                    ..
+                },
+                Constraint::Vanishes {
+                    domain: d2,
+                    expr: e2,
+                    ..
+                },
+            ) => d1 == d2 && same_structure(e1, e2),
+            (
+                Constraint::Lookup {
+                    including: i1,
+                    included: j1,
+                    ..
+                },
+                Constraint::Lookup {
+                    including: i2,
+                    included: j2,
+                    ..
+                },
+            ) => nodes(i1, i2) && nodes(j1, j2),
+            (
+                Constraint::Permutation {
+                    from: f1, to: t1, ..
+                },
+                Constraint::Permutation {
+                    from: f2, to: t2, ..
+                },
+            ) => f1 == f2 && t1 == t2,
+            (
+                Constraint::InRange {
+                    exp: e1, max: m1, ..
+                },
+                Constraint::InRange {
+                    exp: e2, max: m2, ..
+                },
+            ) => same_structure(e1, e2) && m1.to_bi() == m2.to_bi(),
+            (
+                Constraint::Normalization {
+                    reference: r1,
+                    inverted: i1,
+                    ..
+                },
+                Constraint::Normalization {
+                    reference: r2,
+                    inverted: i2,
+                    ..
+                },
+            ) => same_structure(r1, r2) && i1 == i2,
+            _ => false,
+        }
+    }
+}
+impl Eq for Constraint {}
+/// Consistent with the structural equality, so that duplicated constraints can
+/// be found in linear time.
+impl std::hash::Hash for Constraint {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Constraint::Vanishes { domain, expr, .. } => {
+                domain.hash(state);
+                structural_hash(expr).hash(state);
+            }
+            Constraint::Lookup {
+                including,
+                included,
+                ..
+            } => {
+                for n in including.iter().chain(included.iter()) {
+                    structural_hash(n).hash(state);
+                }
+            }
+            Constraint::Permutation { from, to, .. } => {
+                from.hash(state);
+                to.hash(state);
+            }
+            Constraint::InRange { exp, max, .. } => {
+                structural_hash(exp).hash(state);
+                max.to_bi().hash(state);
+            }
+            Constraint::Normalization {
+                reference,
+                inverted,
+                ..
+            } => {
+                structural_hash(reference).hash(state);
+                inverted.hash(state);
+            }
+        }
+    }
+}
 
-/// Options used when evaluating an expression
-pub struct EvalSettings {
-    /// If true, negative indices will loop from the end of the column;
-    /// otherwise, they will go up in the padding.
-    pub wrap: bool,
+fn is_commutative(f: &Intrinsic) -> bool {
+    matches!(
+        f,
+        Intrinsic::Add | Intrinsic::Mul | Intrinsic::VectorAdd | Intrinsic::VectorMul
+    )
 }
-impl Default for EvalSettings {
-    fn default() -> Self {
-        EvalSettings { wrap: true }
+
+/// Whether `a` and `b` are structurally identical: the arguments of commutative
+/// operations may come in any order, and types and debug information are
+/// disregarded.
+pub(crate) fn same_structure(a: &Node, b: &Node) -> bool {
+    match (a.e(), b.e()) {
+        (Expression::Funcall { func: f, args: xs }, Expression::Funcall { func: g, args: ys }) => {
+            if f != g || xs.len() != ys.len() {
+                return false;
+            }
+            if is_commutative(f) {
+                // structural identity is an equivalence, so greedily pairing
+                // the arguments is enough
+                let mut unpaired = ys.iter().collect::<Vec<_>>();
+                xs.iter().all(|x| {
+                    if let Some(i) = unpaired.iter().position(|y| same_structure(x, y)) {
+                        unpaired.swap_remove(i);
+                        true
+                    } else {
+                        false
+                    }
+                })
+            } else {
+                xs.iter().zip(ys).all(|(x, y)| same_structure(x, y))
+            }
+        }
+        (Expression::Const(x), Expression::Const(y)) => x.to_bi() == y.to_bi(),
+        (
+            Expression::Column {
+                handle: h1,
+                shift: s1,
+                ..
+            },
+            Expression::Column {
+                handle: h2,
+                shift: s2,
+                ..
+            },
+        )
+        | (
+            Expression::ExoColumn {
+                handle: h1,
+                shift: s1,
+                ..
+            },
+            Expression::ExoColumn {
+                handle: h2,
+                shift: s2,
+                ..
+            },
+        ) => h1 == h2 && s1 == s2,
+        (
+            Expression::ArrayColumn { handle: h1, .. },
+            Expression::ArrayColumn { handle: h2, .. },
+        ) => h1 == h2,
+        (Expression::List(xs), Expression::List(ys)) => {
+            xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| same_structure(x, y))
+        }
+        (Expression::Void, Expression::Void) => true,
+        _ => false,
+    }
+}
+
+/// A hash of `n` consistent with [`same_structure`]
+fn structural_hash(n: &Node) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut h = std::collections::hash_map::DefaultHasher::new();
+    std::mem::discriminant(n.e()).hash(&mut h);
+    match n.e() {
+        Expression::Funcall { func, args } => {
+            func.hash(&mut h);
+            let mut args = args.iter().map(structural_hash).collect::<Vec<_>>();
+            if is_commutative(func) {
+                args.sort();
+            }
+            args.hash(&mut h);
+        }
+        Expression::Const(x) => x.to_bi().hash(&mut h),
+        Expression::Column { handle, shift, .. } | Expression::ExoColumn { handle, shift, .. } => {
+            handle.hash(&mut h);
+            shift.hash(&mut h);
+        }
+        Expression::ArrayColumn { handle, .. } => handle.hash(&mut h),
+        Expression::List(xs) => xs
+            .iter()
+            .map(structural_hash)
+            .collect::<Vec<_>>()
+            .hash(&mut h),
+        Expression::Void => {}
     }
+    h.finish()
+}
+
+/// What reading a shifted column outside of its trace yields
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ShiftMode {
+    /// negative indices loop from the end of the column
+    #[default]
+    Wrap,
+    /// negative indices go up in the padding
+    Zero,
+    /// negative indices go up in the padding, but reading before the spilling
+    /// of the module is an error
+    Error,
+}
+
+/// Options used when evaluating an expression
+#[derive(Default)]
+pub struct EvalSettings {
+    /// How shifted columns are read before the start of their trace
+    pub shift: ShiftMode,
 }
 impl EvalSettings {
     pub fn new() -> Self {
@@ -133,7 +437,171 @@ impl EvalSettings {
     }
 
     pub fn wrap(self, w: bool) -> Self {
-        Self { wrap: w, ..self }
+        self.shift(if w { ShiftMode::Wrap } else { ShiftMode::Zero })
+    }
+
+    pub fn shift(self, shift: ShiftMode) -> Self {
+        Self { shift }
+    }
+
+    /// Whether negative indices loop from the end of the columns
+    pub fn wraps(&self) -> bool {
+        matches!(self.shift, ShiftMode::Wrap)
+    }
+}
+
+/// Values reused across successive evaluations of expressions
+pub struct EvalCache {
+    /// the inverses of already inverted values
+    inverses: cached::SizedCache<Value, Value>,
+    /// if set, the values of already evaluated subexpressions
+    memo: Option<Arc<SubexpressionMemo>>,
+}
+
+/// Memoizes the values of subexpressions, keyed by `(subexpression id, row,
+/// shift mode)`, so that structurally identical subexpressions are only
+/// evaluated once per row; it may be shared by several [`EvalCache`], e.g. by
+/// those of constraints checked in parallel.
+pub(crate) struct SubexpressionMemo {
+    /// the structural ids of the memoized nodes, indexed by their address
+    ids: HashMap<usize, u64>,
+    values: Mutex<cached::SizedCache<(u64, isize, ShiftMode), Value>>,
+}
+
+impl Default for EvalCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl EvalCache {
+    pub fn new() -> Self {
+        EvalCache {
+            inverses: cached::SizedCache::with_size(200000), // ~1.60MB cache
+            memo: None,
+        }
+    }
+
+    /// Look up and store the values of subexpressions in `memo`
+    pub(crate) fn memoize(self, memo: Arc<SubexpressionMemo>) -> Self {
+        EvalCache {
+            memo: Some(memo),
+            ..self
+        }
+    }
+
+    pub(crate) fn inverse(&mut self, x: Value) -> Value {
+        self.inverses
+            .cache_get_or_set_with(x.clone(), || x.inverse())
+            .to_owned()
+    }
+
+    /// The id of `n` if its values are memoized
+    pub(crate) fn subexpression_id(&self, n: &Node) -> Option<u64> {
+        self.memo
+            .as_ref()
+            .and_then(|memo| memo.ids.get(&(n as *const Node as usize)).cloned())
+    }
+
+    pub(crate) fn memoized(&mut self, id: u64, i: isize, shift: ShiftMode) -> Option<Value> {
+        self.memo.as_ref().and_then(|memo| {
+            memo.values
+                .lock()
+                .unwrap()
+                .cache_get(&(id, i, shift))
+                .cloned()
+        })
+    }
+
+    pub(crate) fn set_memoized(&mut self, id: u64, i: isize, shift: ShiftMode, x: Value) {
+        if let Some(memo) = self.memo.as_ref() {
+            memo.values.lock().unwrap().cache_set((id, i, shift), x);
+        }
+    }
+}
+
+impl SubexpressionMemo {
+    /// Memoize the subexpressions occurring several times in `exps`, keeping
+    /// at most `size` values.
+    ///
+    /// Nodes are identified by their address, so `exps` must outlive the
+    /// memo and must not be modified while it is used.
+    pub(crate) fn new(exps: &[&Node], size: usize) -> Self {
+        /// Compute the structural id of all the nodes in `n`, and count how
+        /// many times each id occurs
+        fn index(n: &Node, ids: &mut HashMap<usize, u64>, counts: &mut HashMap<u64, usize>) -> u64 {
+            use std::hash::{Hash, Hasher};
+
+            let mut h = std::collections::hash_map::DefaultHasher::new();
+            std::mem::discriminant(n.e()).hash(&mut h);
+            match n.e() {
+                Expression::Funcall { func, args } => {
+                    func.hash(&mut h);
+                    for arg in args {
+                        index(arg, ids, counts).hash(&mut h);
+                    }
+                }
+                Expression::List(xs) => {
+                    for x in xs {
+                        index(x, ids, counts).hash(&mut h);
+                    }
+                }
+                Expression::Const(x) => x.hash(&mut h),
+                Expression::Column { handle, shift, .. }
+                | Expression::ExoColumn { handle, shift, .. } => {
+                    handle.hash(&mut h);
+                    shift.hash(&mut h);
+                }
+                _ => n.to_string().hash(&mut h),
+            }
+            let id = h.finish();
+            // Leaves are cheaper to read than to memoize
+            if matches!(n.e(), Expression::Funcall { .. }) {
+                ids.insert(n as *const Node as usize, id);
+                *counts.entry(id).or_default() += 1;
+            }
+            id
+        }
+
+        /// Only keep the largest repeated subexpressions: a node only
+        /// occurring within copies of its parent will never be reached once
+        /// the parent is memoized.
+        fn select(
+            n: &Node,
+            parent_count: usize,
+            ids: &HashMap<usize, u64>,
+            counts: &HashMap<u64, usize>,
+            selected: &mut HashMap<usize, u64>,
+        ) {
+            let mut count = parent_count;
+            if let Some(id) = ids.get(&(n as *const Node as usize)) {
+                count = counts[id];
+                if count > 1 && count > parent_count {
+                    selected.insert(n as *const Node as usize, *id);
+                }
+            }
+            match n.e() {
+                Expression::Funcall { args: xs, .. } | Expression::List(xs) => {
+                    for x in xs {
+                        select(x, count, ids, counts, selected);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut ids = HashMap::new();
+        let mut counts = HashMap::new();
+        for exp in exps {
+            index(exp, &mut ids, &mut counts);
+        }
+        let mut selected = HashMap::new();
+        for exp in exps {
+            select(exp, 0, &ids, &counts, &mut selected);
+        }
+        SubexpressionMemo {
+            ids: selected,
+            values: Mutex::new(cached::SizedCache::with_size(size)),
+        }
     }
 }
 
@@ -154,6 +622,9 @@ pub enum FunctionClass {
     Intrinsic(Intrinsic),
     /// A name alias to any other function (including another alias)
     Alias(String),
+    /// A user-defined function partially applied to its first arguments,
+    /// waiting for the remaining ones
+    Partial(Box<Function>, Vec<AstNode>),
 }
 
 /// A user-defined function is defined by its specializations, of which
@@ -194,7 +665,16 @@ impl Defined {
                 expected_str, found_str
             );
         }
-        bail!(msg)
+        bail!(CompileError::NoMatchingSpecialization(msg))
+    }
+
+    /// The largest number of arguments accepted by one of the specializations
+    fn max_arity(&self) -> usize {
+        self.specializations
+            .iter()
+            .map(|s| s.args.len())
+            .max()
+            .unwrap_or(0)
     }
 }
 // User-defined function do not need to implement [`FunctionVerifier`], because
@@ -251,8 +731,9 @@ impl FuncVerifier<Node> for Intrinsic {
             Intrinsic::Neg => Arity::Monadic,
             Intrinsic::Inv => Arity::Monadic,
             Intrinsic::Normalize => Arity::Monadic,
-            Intrinsic::Begin => Arity::AtLeast(1),
+            Intrinsic::Begin => Arity::AtLeast(0),
             Intrinsic::IfZero | Intrinsic::IfNotZero => Arity::Between(2, 3),
+            Intrinsic::Lt => Arity::Exactly(3),
         }
     }
     fn validate_types(&self, args: &[Node]) -> Result<()> {
@@ -267,7 +748,8 @@ impl FuncVerifier<Node> for Intrinsic {
             | Intrinsic::Normalize
             | Intrinsic::VectorAdd
             | Intrinsic::VectorSub
-            | Intrinsic::VectorMul => {
+            | Intrinsic::VectorMul
+            | Intrinsic::Lt => {
                 for (_, arg) in args.iter().enumerate() {
                     if arg.is_list() {
                         bail!("unexpected list operand for {}", self.to_string())
@@ -334,15 +816,20 @@ impl FuncVerifier<Node> for Intrinsic {
                 // then/else arms typ
                 &[Type::Any(Magma::ANY)],
             ],
-            Intrinsic::Begin => &[&[Type::Any(Magma::ANY)]],
+            Intrinsic::Begin => &[&[Type::Any(Magma::ANY), Type::Void]],
+            Intrinsic::Lt => &[
+                &[Type::Any(Magma::ANY)],
+                &[Type::Any(Magma::ANY)],
+                &[Type::Scalar(Magma::ANY)],
+            ],
         };
 
         if super::compatible_with_repeating(expected_t, &args_t) {
             Ok(())
         } else {
-            bail!(CompileError::TypeError(
+            bail!(CompileError::TypeMismatch(
                 self.to_string(),
-                expected_t,
+                expected_t.iter().map(|ts| ts.to_vec()).collect(),
                 args_t
             ))
         }
@@ -354,8 +841,196 @@ pub type PerspectiveTable = HashMap<String, HashMap<String, Node>>;
 pub const ADDER_MODULE: &str = "#adder";
 pub const MULER_MODULE: &str = "#muler";
 
+/// How the field elements are written in computed traces
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum TraceRadix {
+    /// base 10
+    Decimal,
+    /// base 16 with a `0x` prefix
+    Hex,
+    /// base 16 with a `0x` prefix, zero-padded to the size of a field element
+    Canonical,
+    /// `0x0` followed by the decimal representation, as written by the former
+    /// versions
+    #[default]
+    Legacy,
+}
+impl TraceRadix {
+    pub fn render(&self, x: &Value) -> String {
+        if let TraceRadix::Legacy = self {
+            return format!("0x0{}", x);
+        }
+        let Some(fr) = x.to_fr() else {
+            // values spanning several field elements are left as-is
+            return x.to_string();
+        };
+        let bytes = fr.into_bigint().to_bytes_be();
+        match self {
+            TraceRadix::Decimal => num_bigint::BigUint::from_bytes_be(&bytes).to_string(),
+            TraceRadix::Hex => format!(
+                "0x{}",
+                num_bigint::BigUint::from_bytes_be(&bytes).to_str_radix(16)
+            ),
+            TraceRadix::Canonical => {
+                format!("0x{}", bytes.iter().map(|b| format!("{:02x}", b)).join(""))
+            }
+            TraceRadix::Legacy => unreachable!(),
+        }
+    }
+}
+
+/// Apply `f` to all the column references of a constraint set held outside of
+/// its column set
+fn map_refs<'a>(
+    constraints: impl Iterator<Item = &'a mut Constraint>,
+    computations: &mut ComputationTable,
+    perspectives: &mut PerspectiveTable,
+    f: &dyn Fn(&mut ColumnRef),
+) {
+    constraints.for_each(|x| x.add_id_to_handles(f));
+
+    computations.dependencies = computations
+        .dependencies
+        .drain()
+        .map(|(mut k, v)| {
+            f(&mut k);
+            (k, v)
+        })
+        .collect();
+
+    for c in computations.iter_mut() {
+        match c {
+            Computation::Composite { target, exp } => {
+                f(target);
+                exp.add_id_to_handles(f);
+            }
+            Computation::Interleaved { target, froms } => {
+                std::iter::once(target).chain(froms.iter_mut()).for_each(f)
+            }
+            Computation::Sorted { froms, tos, .. } => {
+                froms.iter_mut().chain(tos.iter_mut()).for_each(f)
+            }
+            Computation::CyclicFrom { target, froms, .. } => {
+                std::iter::once(target).chain(froms.iter_mut()).for_each(f)
+            }
+            Computation::SortingConstraints {
+                ats,
+                eq,
+                delta,
+                delta_bytes,
+                started,
+                froms,
+                sorted,
+                ..
+            } => ats
+                .iter_mut()
+                .chain([eq, delta])
+                .chain(delta_bytes.iter_mut())
+                .chain(started.iter_mut())
+                .chain(froms.iter_mut())
+                .chain(sorted.iter_mut())
+                .for_each(f),
+            Computation::ExoOperation {
+                sources, target, ..
+            } => {
+                for source in sources.iter_mut() {
+                    source.add_id_to_handles(f);
+                }
+                f(target);
+            }
+            Computation::ExoConstant { target, .. } => {
+                f(target);
+            }
+        }
+    }
+
+    for p in perspectives.values_mut().flat_map(|k| k.values_mut()) {
+        p.add_id_to_handles(f)
+    }
+}
+
+/// The `;;` comments documenting the declarations of a constraint set
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct Docs {
+    pub columns: HashMap<Handle, String>,
+    pub constraints: HashMap<Handle, String>,
+    pub functions: HashMap<Handle, String>,
+}
+
+/// The version of the serialized format of [`ConstraintSet`]; it must be
+/// bumped whenever one of the serialized types, e.g. [`Expression`],
+/// [`Builtin`], or [`Constraint`], changes. Fields added with
+/// `#[serde(default)]` are the exception, as former builds ignore them and
+/// later ones default them when missing.
+pub const FORMAT_VERSION: u32 = 4;
+
+/// The size of a constraint set, reported once it has been compiled
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub modules: usize,
+    pub atomic_columns: usize,
+    pub computed_columns: usize,
+    pub vanishes: usize,
+    pub lookups: usize,
+    pub permutations: usize,
+    pub ranges: usize,
+    pub normalizations: usize,
+    pub functions: usize,
+}
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} modules, {} columns ({} atomic, {} computed), {} constraints ({} vanishing, {} lookups, {} permutations, {} ranges, {} normalizations), {} functions",
+            self.modules,
+            self.atomic_columns + self.computed_columns,
+            self.atomic_columns,
+            self.computed_columns,
+            self.vanishes + self.lookups + self.permutations + self.ranges + self.normalizations,
+            self.vanishes,
+            self.lookups,
+            self.permutations,
+            self.ranges,
+            self.normalizations,
+            self.functions
+        )
+    }
+}
+
+/// A fixed table declared with `(deflookup name (columns...) (file "table.csv"))`;
+/// its columns are filled from the CSV file `file`, whose first line is a header
+/// naming them, when computing the trace. A relative `file` is resolved against
+/// the directory of the source declaring the table.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LookupTable {
+    pub file: String,
+    pub columns: Vec<Handle>,
+}
+
+/// The format version and field of a serialized constraint set, read before
+/// the constraint set itself so that incompatible files are reported as such
+#[derive(Deserialize)]
+struct SerializedVersion {
+    /// constraint sets older than versioning are v0
+    #[serde(default)]
+    format_version: u32,
+    #[serde(default = "legacy_field")]
+    field: String,
+}
+
+/// Constraint sets older than the choice of the field are over BLS12-377
+fn legacy_field() -> String {
+    "bls12-377".to_string()
+}
+
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct ConstraintSet {
+    #[serde(default)]
+    pub format_version: u32,
+    /// the scalar field the constraint set has been compiled for
+    #[serde(default = "legacy_field")]
+    pub field: String,
     pub columns: ColumnSet,
     pub constraints: Vec<Constraint>,
     pub constants: HashMap<Handle, BigInt>,
@@ -363,16 +1038,66 @@ pub struct ConstraintSet {
     pub perspectives: PerspectiveTable,
     pub transformations: u32,
     pub auto_constraints: u32,
+    #[serde(default)]
+    pub docs: Docs,
+    #[serde(default)]
+    pub lookup_tables: Vec<LookupTable>,
+    /// the number of user-defined functions; as the functions themselves do
+    /// not survive the compilation, it is serialized for the summary of the
+    /// constraint sets read from a compiled file
+    #[serde(default)]
+    pub functions: usize,
+    /// constraints verified when checking a trace, but never exported
+    #[serde(default)]
+    pub assertions: Vec<Constraint>,
 }
 impl ConstraintSet {
+    /// Ensure that a constraint set serialized with `version` can be read
+    fn check_format_version(version: &SerializedVersion) -> Result<()> {
+        if version.format_version != FORMAT_VERSION {
+            bail!(
+                "compiled with Corset format v{}, this build expects v{}; recompile",
+                version.format_version,
+                FORMAT_VERSION
+            )
+        }
+        if version.field != crate::field::FIELD {
+            bail!(
+                "compiled for the {} field, this build uses {}; recompile",
+                version.field,
+                crate::field::FIELD
+            )
+        }
+        Ok(())
+    }
+
+    /// Deserialize a constraint set serialized in RON
+    pub fn from_ron(s: &str) -> Result<Self> {
+        if let Result::Ok(v) = ron::from_str::<SerializedVersion>(s) {
+            Self::check_format_version(&v)?;
+        }
+        Ok(ron::from_str(s)?)
+    }
+
+    /// Deserialize a constraint set serialized in JSON
+    pub fn from_json(s: &str) -> Result<Self> {
+        if let Result::Ok(v) = serde_json::from_str::<SerializedVersion>(s) {
+            Self::check_format_version(&v)?;
+        }
+        Ok(serde_json::from_str(s)?)
+    }
+
     pub fn new(
         columns: ColumnSet,
         constraints: Vec<Constraint>,
+        assertions: Vec<Constraint>,
         constants: HashMap<Handle, BigInt>,
         computations: ComputationTable,
         perspectives: PerspectiveTable,
     ) -> Result<Self> {
         let mut r = ConstraintSet {
+            format_version: FORMAT_VERSION,
+            field: crate::field::FIELD.to_string(),
             constraints,
             columns,
             constants,
@@ -380,7 +1105,12 @@ impl ConstraintSet {
             perspectives,
             transformations: 0,
             auto_constraints: 0,
+            docs: Default::default(),
+            lookup_tables: Default::default(),
+            functions: 0,
+            assertions,
         };
+        r.validate_computations()?;
         r.convert_refs_to_ids()?;
         r.allocate_registers();
         r.fill_perspectives()?;
@@ -514,12 +1244,14 @@ impl ConstraintSet {
                         eq,
                         delta,
                         delta_bytes,
+                        started,
                         ..
                     } => {
                         for r in std::iter::once(&eq)
                             .chain(std::iter::once(&delta))
                             .chain(ats.iter())
                             .chain(delta_bytes.iter())
+                            .chain(started.iter())
                         {
                             let col = self.columns.column(r).unwrap();
                             let reg = self.columns.new_register(col.handle.clone(), col.t);
@@ -777,73 +1509,74 @@ impl ConstraintSet {
     }
 
     pub fn convert_refs_to_ids(&mut self) -> Result<()> {
-        let convert_to_id = |h: &mut ColumnRef| {
-            let id = self.columns.id_of(h);
-            h.set_id(id);
+        let columns = &self.columns;
+        map_refs(
+            self.constraints
+                .iter_mut()
+                .chain(self.assertions.iter_mut()),
+            &mut self.computations,
+            &mut self.perspectives,
+            &|h: &mut ColumnRef| h.set_id(columns.id_of(h)),
+        );
+        Ok(())
+    }
+
+    /// Count the modules, columns, and constraints of each kind of this
+    /// constraint set
+    pub fn summary(&self) -> Summary {
+        let mut r = Summary {
+            modules: self.columns.modules().len(),
+            functions: self.functions,
+            ..Default::default()
         };
-        self.constraints
-            .iter_mut()
-            .for_each(|x| x.add_id_to_handles(&convert_to_id));
+        for c in self.columns.iter_cols() {
+            if c.kind == Kind::Commitment {
+                r.atomic_columns += 1;
+            } else {
+                r.computed_columns += 1;
+            }
+        }
+        for c in self.constraints.iter() {
+            match c {
+                Constraint::Vanishes { .. } => r.vanishes += 1,
+                Constraint::Lookup { .. } => r.lookups += 1,
+                Constraint::Permutation { .. } => r.permutations += 1,
+                Constraint::InRange { .. } => r.ranges += 1,
+                Constraint::Normalization { .. } => r.normalizations += 1,
+            }
+        }
+        r
+    }
 
-        self.computations.dependencies = self
-            .computations
-            .dependencies
-            .iter()
-            .map(|(k, v)| {
-                let mut k = k.clone();
-                convert_to_id(&mut k);
-                (k, *v)
-            })
-            .collect();
+    pub fn effective_len_for(&self, m: &str) -> Option<isize> {
+        self.columns.effective_len.get(m).copied()
+    }
 
-        for c in self.computations.iter_mut() {
-            match c {
-                Computation::Composite { target, exp } => {
-                    convert_to_id(target);
-                    exp.add_id_to_handles(&convert_to_id);
-                }
-                Computation::Interleaved { target, froms } => std::iter::once(target)
-                    .chain(froms.iter_mut())
-                    .for_each(convert_to_id),
-                Computation::Sorted { froms, tos, .. } => froms
-                    .iter_mut()
-                    .chain(tos.iter_mut())
-                    .for_each(convert_to_id),
-                Computation::CyclicFrom { target, froms, .. } => std::iter::once(target)
-                    .chain(froms.iter_mut())
-                    .for_each(convert_to_id),
-                Computation::SortingConstraints { .. } => {
-                    // These computations are built with IDs from the very start
-                }
-                Computation::ExoOperation {
-                    sources, target, ..
-                } => {
-                    for source in sources.iter_mut() {
-                        source.add_id_to_handles(&convert_to_id);
-                    }
-                    convert_to_id(target);
-                }
-                Computation::ExoConstant { target, .. } => {
-                    convert_to_id(target);
-                }
-            }
-        }
+    pub fn effective_len_or_set(&mut self, m: &str, x: isize) -> isize {
+        *self.columns.effective_len.entry(m.to_string()).or_insert(x)
+    }
 
-        for p in self.perspectives.values_mut().flat_map(|k| k.values_mut()) {
-            p.add_id_to_handles(&convert_to_id)
+    /// Pad the columns of `module` to `len` rows when importing a trace, as if
+    /// the trace had been that long; it is an error for the trace to be longer.
+    pub fn force_module_len(&mut self, module: &str, len: usize) -> Result<()> {
+        if !self.columns.modules().contains(module) {
+            bail!("unknown module `{}`", module);
         }
-
+        if let Some(min_len) = self.columns.min_len.get(module) {
+            if *min_len > len {
+                bail!(
+                    "module `{}` requires at least {} rows, cannot force it to {}",
+                    module,
+                    min_len,
+                    len
+                );
+            }
+        }
+        self.columns.min_len.insert(module.to_string(), len);
+        self.columns.forced_len.insert(module.to_string(), len);
         Ok(())
     }
 
-    pub fn effective_len_for(&self, m: &str) -> Option<isize> {
-        self.columns.effective_len.get(m).copied()
-    }
-
-    pub fn effective_len_or_set(&mut self, m: &str, x: isize) -> isize {
-        *self.columns.effective_len.entry(m.to_string()).or_insert(x)
-    }
-
     pub fn spilling_for_column(&self, h: &ColumnRef) -> Option<isize> {
         let module = if h.is_handle() {
             &h.as_handle().module
@@ -853,6 +1586,13 @@ impl ConstraintSet {
         self.spilling_of(module)
     }
 
+    /// The number of leading padding rows of the column `h`, i.e. rows that
+    /// have been prepended to the imported trace rather than read from it.
+    pub(crate) fn padding_rows_for_column(&self, h: &ColumnRef) -> isize {
+        let module = &self.handle(h).module;
+        self.columns.padding.get(module).cloned().unwrap_or(0) * self.length_multiplier(h) as isize
+    }
+
     pub(crate) fn compute_spilling(&mut self, m: &str) -> isize {
         let spilling = self
             .computations
@@ -894,6 +1634,138 @@ impl ConstraintSet {
         self.columns.spilling.get(m).cloned()
     }
 
+    /// Returns the values of the column `handle`, excluding its spilling, or
+    /// `None` if the column does not exist or has not been filled yet.
+    pub fn column_values(&self, handle: &Handle) -> Option<Vec<Fr>> {
+        let r: ColumnRef = handle.clone().into();
+        self.columns.column(&r).ok()?;
+        let len = self.columns.len(&r)? as isize;
+        (0..len)
+            .map(|i| self.columns.get(&r, i, false).and_then(|x| x.to_fr()))
+            .collect()
+    }
+
+    /// Returns the value of the column `handle` at row `i`; negative indices
+    /// reach into the spilling of the column module.
+    pub fn column_value_at(&self, handle: &Handle, i: isize) -> Option<Fr> {
+        let r: ColumnRef = handle.clone().into();
+        self.columns.column(&r).ok()?;
+        self.columns.get(&r, i, false).and_then(|x| x.to_fr())
+    }
+
+    /// Recompute only the computed columns depending, directly or not, on the
+    /// `changed` columns, whose values must already have been replaced.
+    pub fn recompute_dirty(&mut self, changed: &[Handle]) -> Result<()> {
+        crate::compute::recompute_dirty(self, changed)
+    }
+
+    /// Ensure that no vanishing constraint has a degree larger than `max`, as
+    /// required by backends limiting the degree of their constraints.
+    pub fn check_max_degree(&self, max: usize) -> Result<()> {
+        let mut too_high = self
+            .constraints
+            .iter()
+            .filter_map(|c| match c {
+                Constraint::Vanishes { handle, expr, .. } => Some((handle, expr.degree())),
+                _ => None,
+            })
+            .filter(|(_, degree)| *degree > max)
+            .collect::<Vec<_>>();
+        if too_high.is_empty() {
+            Ok(())
+        } else {
+            too_high.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+            bail!(
+                "{} constraint(s) exceed the maximal degree of {}:\n{}\nconsider lowering their degree by introducing intermediate computed columns",
+                too_high.len(),
+                max,
+                too_high
+                    .iter()
+                    .map(|(h, d)| format!("  - {}: degree {}", h.pretty(), d))
+                    .join("\n")
+            )
+        }
+    }
+
+    /// Only keep the constraints of the modules selected by `only` and `skip`,
+    /// and mark the columns they do not depend on as skipped, so that they are
+    /// neither imported nor computed.
+    pub fn restrict_to_modules(&mut self, only: &Option<Vec<String>>, skip: &[String]) {
+        let keep = |m: &str| {
+            only.as_ref()
+                .map(|o| o.iter().any(|x| x == m))
+                .unwrap_or(true)
+                && !skip.iter().any(|x| x == m)
+        };
+
+        self.constraints.retain(|c| {
+            keep(match c {
+                Constraint::Vanishes { handle, .. }
+                | Constraint::Lookup { handle, .. }
+                | Constraint::Permutation { handle, .. }
+                | Constraint::InRange { handle, .. }
+                | Constraint::Normalization { handle, .. } => &handle.module,
+            })
+        });
+
+        // Columns of kept modules, or used by a kept constraint, must be
+        // filled, along with all the columns they are computed from.
+        let mut roots = self
+            .columns
+            .iter()
+            .filter(|(_, c)| keep(&c.handle.module))
+            .map(|(r, _)| r)
+            .collect::<HashSet<_>>();
+        for c in self.constraints.iter() {
+            match c {
+                Constraint::Vanishes { expr, .. } => roots.extend(expr.dependencies()),
+                Constraint::Lookup {
+                    including,
+                    included,
+                    ..
+                } => roots.extend(
+                    including
+                        .iter()
+                        .chain(included.iter())
+                        .flat_map(|e| e.dependencies()),
+                ),
+                Constraint::Permutation { from, to, .. } => {
+                    roots.extend(from.iter().chain(to.iter()).cloned())
+                }
+                Constraint::InRange { exp, .. } => roots.extend(exp.dependencies()),
+                Constraint::Normalization {
+                    reference,
+                    inverted,
+                    ..
+                } => {
+                    roots.extend(reference.dependencies());
+                    roots.insert(inverted.clone());
+                }
+            }
+        }
+        let required = ComputationDag::from_computations(self.computations.iter())
+            .ancestors(&roots)
+            .into_iter()
+            .chain(roots.iter().cloned())
+            .collect::<HashSet<_>>();
+
+        self.columns.skipped = self
+            .columns
+            .iter()
+            .filter(|(r, _)| !required.contains(r))
+            .map(|(_, c)| c.handle.clone())
+            .collect();
+    }
+
+    /// Iterates over all the columns that are computed from other columns,
+    /// rather than imported from a trace.
+    pub fn computed_columns(&self) -> impl Iterator<Item = (&Handle, &Column)> {
+        self.columns
+            .iter()
+            .filter(|(r, _)| self.computations.computation_for(r).is_some())
+            .map(|(_, c)| (&c.handle, c))
+    }
+
     fn compute_spillings(&mut self) {
         let all_modules = self.columns.modules();
         for m in all_modules {
@@ -945,12 +1817,22 @@ impl ConstraintSet {
     }
 
     #[time("info", "Exporting expanded trace")]
-    pub fn write(&mut self, out: &mut impl Write) -> Result<()> {
+    pub fn write(&mut self, out: &mut impl Write, radix: TraceRadix) -> Result<()> {
         let mut cache = cached::SizedCache::with_size(200000); // ~1.60MB cache
 
         out.write_all("{\"columns\":{\n".as_bytes())?;
 
-        for (i, module) in self.columns.modules().into_iter().enumerate() {
+        let modules = self
+            .columns
+            .modules()
+            .into_iter()
+            .filter(|m| {
+                self.columns
+                    .iter_cols()
+                    .any(|c| &c.handle.module == m && !self.columns.skipped.contains(&c.handle))
+            })
+            .collect::<Vec<_>>();
+        for (i, module) in modules.into_iter().enumerate() {
             debug!("Exporting {}", &module);
             if i > 0 {
                 out.write_all(b",")?;
@@ -962,39 +1844,52 @@ impl ConstraintSet {
                 .into_iter()
                 .map(|h| (h.clone(), self.columns.column(&h).unwrap()))
                 .filter(|(_, c)| c.handle.module == module)
+                .filter(|(_, c)| !self.columns.skipped.contains(&c.handle))
                 .peekable();
             let empty_backing: ValueBacking = ValueBacking::default();
             while let Some((r, column)) = current_col.next() {
                 let handle = &column.handle;
                 trace!("Writing {}", handle);
                 let backing = self.columns.backing(&r).unwrap_or(&empty_backing);
+                // without padding rows, e.g. with `compute --no-pad`, the
+                // first row holds an actual value of the trace
+                let unpadded = self.columns.padding.get(&module) == Some(&0);
                 let padding: Value = if let Some(v) = column.padding_value.as_ref() {
                     v.clone()
                 } else {
-                    backing.get(0, false, &self.columns).unwrap_or_else(|| {
-                        self.computations
-                            .computation_for(&r)
-                            .map(|c| match c {
-                                Computation::Composite { exp, .. } => exp
-                                    .eval(
-                                        0,
-                                        |_, _, _| Some(Value::zero()),
-                                        &mut None,
-                                        &EvalSettings::default(),
-                                    )
-                                    .unwrap_or_else(Value::zero),
-                                Computation::Interleaved { .. } => Value::zero(),
-                                Computation::Sorted { .. } => Value::zero(),
-                                Computation::CyclicFrom { .. } => Value::zero(),
-                                Computation::SortingConstraints { .. } => Value::zero(),
-                                Computation::ExoOperation { .. } => Value::zero(), // TODO: FIXME:
-                                Computation::ExoConstant { .. } => Value::zero(),  // TODO: FIXME:
-                            })
-                            .unwrap_or_else(Value::zero)
-                    })
+                    (!unpadded)
+                        .then(|| backing.get(0, false, &self.columns))
+                        .flatten()
+                        .unwrap_or_else(|| {
+                            self.computations
+                                .computation_for(&r)
+                                .map(|c| match c {
+                                    Computation::Composite { exp, .. } => exp
+                                        .eval(
+                                            0,
+                                            |_, _, _| Some(Value::zero()),
+                                            &mut None,
+                                            &EvalSettings::default(),
+                                        )
+                                        .unwrap_or_else(Value::zero),
+                                    Computation::Interleaved { .. } => Value::zero(),
+                                    Computation::Sorted { .. } => Value::zero(),
+                                    Computation::CyclicFrom { .. } => Value::zero(),
+                                    Computation::SortingConstraints { .. } => Value::zero(),
+                                    Computation::ExoOperation { .. } => Value::zero(), // TODO: FIXME:
+                                    Computation::ExoConstant { .. } => Value::zero(), // TODO: FIXME:
+                                })
+                                .unwrap_or_else(Value::zero)
+                        })
                 };
 
-                out.write_all(format!("\"{}\":{{\n", handle).as_bytes())?;
+                out.write_all(
+                    format!(
+                        "\"{}\":{{\n",
+                        handle.to_trace_key(self.columns.module_separator())
+                    )
+                    .as_bytes(),
+                )?;
                 out.write_all("\"values\":[".as_bytes())?;
 
                 let mut value = backing.iter(&self.columns).peekable();
@@ -1002,7 +1897,7 @@ impl ConstraintSet {
                     out.write_all(
                         cache
                             .cache_get_or_set_with(x.to_owned(), || {
-                                format!("\"0x0{}\"", x.to_string())
+                                format!("\"{}\"", radix.render(&x))
                             })
                             .as_bytes(),
                     )?;
@@ -1013,7 +1908,8 @@ impl ConstraintSet {
                 out.write_all(b"],\n")?;
                 out.write_all(
                     format!(
-                        "\"padding_strategy\": {{\"action\": \"prepend\", \"value\": \"{}\"}}",
+                        "\"padding_strategy\": {{\"action\": \"{}\", \"value\": \"{}\"}}",
+                        self.columns.padding_strategy,
                         padding.pretty()
                     )
                     .as_bytes(),
@@ -1029,13 +1925,234 @@ impl ConstraintSet {
         Ok(())
     }
 
+    /// Merge `other`, compiled independently, into this constraint set.
+    ///
+    /// Columns, constraints, perspectives and constants declared in both sets
+    /// are an error, unless `allow_dups` is set, in which case those of `self`
+    /// are kept.
+    pub fn merge(mut self, mut other: ConstraintSet, allow_dups: bool) -> Result<Self> {
+        if self.transformations != other.transformations {
+            bail!("can not merge constraint sets expanded to different levels")
+        }
+        let dup = |what: &str, name: &dyn std::fmt::Display| -> Result<()> {
+            if allow_dups {
+                Ok(())
+            } else {
+                bail!(
+                    "{} {} is defined in both constraint sets",
+                    what,
+                    name.to_string().red().bold()
+                )
+            }
+        };
+
+        // The columns of `other` are appended to those of `self`, save for the
+        // duplicated ones, that are mapped to their counterpart in `self`
+        let mut ids = Vec::with_capacity(other.columns._cols.len());
+        let mut registers: HashMap<RegisterID, RegisterID> = HashMap::new();
+        let mut new_columns = Vec::new();
+        for column in std::mem::take(&mut other.columns._cols) {
+            if let Some(&id) = self.columns.cols.get(&column.handle) {
+                dup("column", &column.handle)?;
+                if let Some((theirs, ours)) = column.register.zip(self.columns._cols[id].register) {
+                    registers.insert(theirs, ours);
+                }
+                ids.push(id);
+            } else {
+                ids.push(self.columns._cols.len() + new_columns.len());
+                new_columns.push(column);
+            }
+        }
+        for (i, register) in std::mem::take(&mut other.columns.registers)
+            .into_iter()
+            .enumerate()
+        {
+            if let std::collections::hash_map::Entry::Vacant(e) = registers.entry(i) {
+                e.insert(self.columns.registers.len());
+                self.columns.registers.push(register);
+            }
+        }
+        for mut column in new_columns {
+            column.register = column.register.map(|r| registers[&r]);
+            self.columns.insert_column(column)?;
+        }
+        map_refs(
+            other
+                .constraints
+                .iter_mut()
+                .chain(other.assertions.iter_mut()),
+            &mut other.computations,
+            &mut other.perspectives,
+            &|r: &mut ColumnRef| r.reset_id(ids[r.as_id()]),
+        );
+
+        let names = self
+            .constraints
+            .iter()
+            .map(|c| c.name())
+            .collect::<HashSet<_>>();
+        for c in other.constraints {
+            if names.contains(&c.name()) {
+                dup("constraint", &c.name())?;
+            } else {
+                self.constraints.push(c);
+            }
+        }
+        for c in other.assertions {
+            if !self.assertions.contains(&c) {
+                self.assertions.push(c);
+            }
+        }
+
+        let mut targets: HashMap<usize, Vec<ColumnRef>> = HashMap::new();
+        for (target, i) in other.computations.dependencies.drain() {
+            targets.entry(i).or_default().push(target);
+        }
+        for (i, computation) in other.computations.computations.into_iter().enumerate() {
+            let targets = targets.remove(&i).unwrap_or_default();
+            // duplicated columns are computed as in `self`
+            if !targets
+                .iter()
+                .any(|t| self.computations.dependencies.contains_key(t))
+            {
+                self.computations.insert_many(&targets, computation)?;
+            }
+        }
+
+        for (handle, value) in other.constants {
+            match self.constants.get(&handle) {
+                Some(ours) if *ours != value => dup("constant", &handle)?,
+                Some(_) => {}
+                None => {
+                    self.constants.insert(handle, value);
+                }
+            }
+        }
+        for (module, perspectives) in other.perspectives {
+            let ours = self.perspectives.entry(module).or_default();
+            for (name, trigger) in perspectives {
+                if let std::collections::hash_map::Entry::Vacant(e) = ours.entry(name.clone()) {
+                    e.insert(trigger);
+                } else {
+                    dup("perspective", &name)?;
+                }
+            }
+        }
+
+        for (module, len) in other.columns.min_len {
+            let ours = self.columns.min_len.entry(module).or_default();
+            *ours = (*ours).max(len);
+        }
+        for (ours, theirs) in [
+            (&mut self.docs.columns, other.docs.columns),
+            (&mut self.docs.constraints, other.docs.constraints),
+            (&mut self.docs.functions, other.docs.functions),
+        ] {
+            for (handle, doc) in theirs {
+                ours.entry(handle).or_insert(doc);
+            }
+        }
+        self.auto_constraints |= other.auto_constraints;
+
+        self.compute_spillings();
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Ensure that every computed column is the target of a computation, and
+    /// that every computation target is a declared column.
+    fn validate_computations(&self) -> Result<()> {
+        let mut targets = HashSet::new();
+        for (target, &i) in self.computations.dependencies.iter() {
+            let id = if target.is_id() {
+                Some(target.as_id()).filter(|id| *id < self.columns._cols.len())
+            } else {
+                self.columns.cols.get(target.as_handle()).copied()
+            };
+            if let Some(id) = id {
+                targets.insert(id);
+            } else {
+                bail!(errors::compiler::Error::UndeclaredTarget(
+                    self.computations
+                        .get(i)
+                        .map(|c| c.to_string())
+                        .unwrap_or_default(),
+                    target.pretty()
+                ))
+            }
+        }
+        for (r, column) in self.columns.iter() {
+            if !matches!(column.kind, Kind::Commitment) && !targets.contains(&r.as_id()) {
+                bail!(errors::compiler::Error::MissingComputation(
+                    column.handle.pretty()
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a deserialized constraint set is self-consistent, so that
+    /// corrupted or mismatched artifacts are rejected before being used.
+    ///
+    /// All the column references must point to the same column by handle and
+    /// by ID, the latter being filled in when missing; then, the computations
+    /// and constraints are validated, and the constants must be field elements.
+    pub fn verify(&mut self) -> Result<()> {
+        for (handle, &id) in self.columns.cols.iter() {
+            if self.columns._cols.get(id).map(|c| &c.handle) != Some(handle) {
+                bail!(
+                    "column {} is registered with ID {}, that belongs to another column",
+                    handle.pretty(),
+                    id
+                )
+            }
+        }
+
+        let dangling = std::cell::RefCell::new(Vec::new());
+        let columns = &self.columns;
+        map_refs(
+            self.constraints
+                .iter_mut()
+                .chain(self.assertions.iter_mut()),
+            &mut self.computations,
+            &mut self.perspectives,
+            &|r: &mut ColumnRef| {
+                let by_handle = r
+                    .is_handle()
+                    .then(|| columns.cols.get(r.as_handle()).copied());
+                match (by_handle, r.is_id().then(|| r.as_id())) {
+                    (Some(Some(i)), None) => r.set_id(i),
+                    (Some(Some(i)), Some(j)) if i == j => {}
+                    (None, Some(j)) if j < columns._cols.len() => {}
+                    _ => dangling.borrow_mut().push(r.to_string()),
+                }
+            },
+        );
+        let dangling = dangling.into_inner();
+        if !dangling.is_empty() {
+            bail!(
+                "dangling column references: {}",
+                dangling.iter().sorted().dedup().join(", ")
+            )
+        }
+
+        self.validate()?;
+        for (handle, value) in self.constants.iter() {
+            validate_field_literal(value)
+                .with_context(|| anyhow!("invalid constant {}", handle.pretty()))?;
+        }
+        Ok(())
+    }
+
     pub fn validate(&self) -> Result<()> {
+        self.validate_computations()?;
+
         //
         // Check that all ColumnRef are IDs
         //
 
         // Check the constraints
-        for c in self.constraints.iter() {
+        for c in self.constraints.iter().chain(self.assertions.iter()) {
             match c {
                 Constraint::Vanishes { handle, expr, .. } => {
                     if expr.dependencies().into_iter().any(|r| !r.is_id()) {
@@ -1127,6 +2244,7 @@ impl ConstraintSet {
                     eq,
                     delta,
                     delta_bytes,
+                    started,
                     froms,
                     sorted,
                     ..
@@ -1136,6 +2254,7 @@ impl ConstraintSet {
                         .chain(ats.iter())
                         .chain(std::iter::once(delta))
                         .chain(delta_bytes.iter())
+                        .chain(started.iter())
                         .chain(froms.iter())
                         .any(|r| !r.is_id())
                     {
@@ -1169,11 +2288,7 @@ impl ConstraintSet {
         // Check that no constraint mixes cardinalities
         for c in self.constraints.iter() {
             match c {
-                Constraint::Vanishes {
-                    handle,
-                    domain: _,
-                    expr,
-                } => {
+                Constraint::Vanishes { handle, expr, .. } => {
                     let mut sizes = expr.dependencies().into_iter();
                     if let Some(first) = sizes.next() {
                         let first_size = self.length_multiplier(&first);
@@ -1242,35 +2357,68 @@ fn apply_form(
 
     match f {
         Form::For => {
-            if let (Token::Symbol(i_name), Token::Domain(is), body) =
-                (&args[0].class, &args[1].class, &args[2])
-            {
-                let is = is.concretize(|n| {
-                    crate::compiler::generator::reduce(n, &mut ctx.clone(), settings)
-                        .transpose()
-                        .unwrap()
-                        .and_then(|r| r.pure_eval())
-                        .and_then(|bi| bi.to_isize().ok_or_else(|| anyhow!("{} is not an i64", bi)))
-                })?;
-                let mut l = vec![];
-                let mut t = Type::INFIMUM;
-                for i in is.iter() {
-                    let mut for_ctx = ctx.derive(&uniquify(format!("{}-for-{}", ctx.name(), i)))?;
-
-                    for_ctx.insert_symbol(i_name, Expression::Const(Value::from(i)).into())?;
+            // Either a single index over a domain, or a list of indices
+            // iterating over the Cartesian product of as many domains
+            let (names, domains) = match (&args[0].class, &args[1].class) {
+                (Token::Symbol(i_name), Token::Domain(is)) => {
+                    (vec![i_name.as_str()], vec![is.as_ref()])
+                }
+                (Token::List(names), Token::List(domains)) => (
+                    names
+                        .iter()
+                        .map(|n| n.as_symbol())
+                        .collect::<Result<Vec<_>, _>>()?,
+                    domains
+                        .iter()
+                        .map(|d| match &d.class {
+                            Token::Domain(d) => d.as_ref(),
+                            _ => unreachable!(),
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+                _ => unreachable!(),
+            };
+            let body = &args[2];
 
-                    if let Some(r) = reduce(&body.clone(), &mut for_ctx, settings)? {
-                        t = t.max(r.t());
-                        l.push(r);
-                    } else {
-                        warn!("empty for loop body: {}", body.src.white().bold())
-                    };
+            let domains = domains
+                .into_iter()
+                .map(|is| {
+                    is.concretize(|n| {
+                        crate::compiler::generator::reduce(n, &mut ctx.clone(), settings)
+                            .transpose()
+                            .unwrap()
+                            .and_then(|r| r.pure_eval())
+                            .and_then(|bi| {
+                                bi.to_isize().ok_or_else(|| anyhow!("{} is not an i64", bi))
+                            })
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let mut l = vec![];
+            let mut t = Type::INFIMUM;
+            for is in domains
+                .iter()
+                .map(|d| d.iter().collect::<Vec<_>>())
+                .multi_cartesian_product()
+            {
+                let mut for_ctx = ctx.derive(&uniquify(format!(
+                    "{}-for-{}",
+                    ctx.name(),
+                    is.iter().join("-")
+                )))?;
+                for (i_name, i) in names.iter().zip(is.iter()) {
+                    for_ctx.insert_symbol(i_name, Expression::Const(Value::from(*i)).into())?;
                 }
 
-                Ok(Some(Node::from(Expression::List(l)).with_type(t)))
-            } else {
-                unreachable!()
+                if let Some(r) = reduce(&body.clone(), &mut for_ctx, settings)? {
+                    t = t.max(r.t());
+                    l.push(r);
+                } else {
+                    warn!("empty for loop body: {}", body.src.white().bold())
+                };
             }
+
+            Ok(Some(Node::from(Expression::List(l)).with_type(t)))
         }
         Form::Debug => {
             if !settings.debug {
@@ -1311,6 +2459,31 @@ fn apply_form(
 
             Ok(Some(body))
         }
+        Form::LetColumn => {
+            let name = args[0].as_symbol().unwrap();
+            let exp = reduce(&args[1], ctx, settings)?
+                .filter(|n| !n.t().is_list())
+                .ok_or_else(|| anyhow!("{} is not a scalar expression", args[1].src.red()))?;
+
+            // the column is declared under a unique name alongside the other
+            // ones of the module, where it is turned into a computation and a
+            // constraint; NAME only refers to it within BODY
+            let module = ctx.module();
+            let handle = Handle::new(&module, uniquify(format!("__LET__{}", name)));
+            let mut module_ctx = ctx.module_scope(&module)?;
+            module_ctx.insert_symbol(
+                &handle.name,
+                Node::column()
+                    .handle(handle.clone())
+                    .kind(Kind::Expression(Box::new(exp.clone())))
+                    .t(exp.t().m())
+                    .build(),
+            )?;
+
+            let mut body_ctx = ctx.derive(&uniquify(format!("{}-let-column", ctx.name())))?;
+            body_ctx.insert_symbol(name, module_ctx.resolve_symbol(&handle.name, true)?)?;
+            reduce(&args[2], &mut body_ctx, settings)
+        }
         Form::Reduce => {
             let f_name = args[0].as_symbol().unwrap();
             let f = ctx.resolve_function(f_name)?;
@@ -1368,7 +2541,35 @@ fn apply_defined(
     for (i, f_arg) in b.args.iter().enumerate() {
         f_ctx.insert_symbol(f_arg, traversed_args[i].clone())?;
     }
-    Ok(if let Some(r) = reduce(&b.body, &mut f_ctx, settings)? {
+    // a forced return type is an explicit cast of the value of the body, i.e.
+    // of its outermost operation alone
+    let body = match &b.body.class {
+        Token::List(xs) if b.force && settings.strict_types && !xs.is_empty() => {
+            let func =
+                resolve_callee(&xs[0], &mut f_ctx).map_err(|err| locate_ast(err, &b.body))?;
+            if let FunctionClass::Intrinsic(_) = func.class {
+                let mut traversed_args = vec![];
+                for arg in xs[1..].iter() {
+                    if let Some(traversed) = reduce(arg, &mut f_ctx, settings)? {
+                        traversed_args.push(traversed);
+                    }
+                }
+                apply_function(
+                    &func,
+                    traversed_args,
+                    &mut f_ctx,
+                    &CompileSettings {
+                        strict_types: false,
+                        ..settings.clone()
+                    },
+                )?
+            } else {
+                reduce(&b.body, &mut f_ctx, settings)?
+            }
+        }
+        _ => reduce(&b.body, &mut f_ctx, settings)?,
+    };
+    Ok(if let Some(r) = body {
         let found_type = r.t();
         let final_type = if let Some(expected_type) = b.out_type {
             if found_type > expected_type {
@@ -1401,29 +2602,146 @@ fn apply_defined(
     })
 }
 
+/// Resolve the `i`th element of the array column `array`, which may itself be an
+/// array column in the case of two-dimensional arrays.
+fn index_array(array: &Node, i: usize, ctx: &mut Scope) -> Result<Node> {
+    match array.e() {
+        Expression::ArrayColumn { handle, domain, .. } => {
+            // Sanity check access within bounds
+            if domain.contains(i.try_into().unwrap()) {
+                // Construct indexed handle
+                let name = handle.as_handle().ith(i).to_string();
+                // Resolve it properly this time.
+                Ok(ctx.resolve_symbol_with_path(&name, true)?)
+            } else {
+                bail!("tried to access {} at index {}", array.pretty().bold(), i)
+            }
+        }
+        // Constant lists are 0-indexed
+        Expression::List(xs) if array.t().is_list() => xs
+            .get(i)
+            .cloned()
+            .ok_or_else(|| anyhow!("tried to access {} at index {}", array.pretty().bold(), i)),
+        _ => bail!(
+            "{} of type {} is not indexable",
+            array.pretty().red().bold(),
+            array.t()
+        ),
+    }
+}
+
+/// Fold `x` to a constant integer, possibly negative, as used by shift
+/// offsets and array indices; once folded in the field, negative values are
+/// represented by p - x.
+fn signed_index(x: &Node) -> Option<isize> {
+    let m = crate::column::field_modulus();
+    x.pure_eval()
+        .ok()
+        .map(|x| if x > m / 2 { x - m } else { x })
+        .and_then(|x| x.to_isize())
+}
+
+/// Resolve a possibly negative index into `array`; negative indices count
+/// from the end of its domain, `-1` being its last index.
+fn resolve_index(array: &Node, i: isize) -> Result<usize> {
+    if let Result::Ok(i) = usize::try_from(i) {
+        return Ok(i);
+    }
+    let from_end = i.unsigned_abs() - 1;
+    match array.e() {
+        Expression::ArrayColumn { domain, .. } => domain
+            .iter()
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .nth(from_end)
+            .and_then(|i| usize::try_from(i).ok()),
+        Expression::List(xs) if array.t().is_list() => xs.len().checked_sub(from_end + 1),
+        _ => None,
+    }
+    .ok_or_else(|| anyhow!("tried to access {} at index {}", array.pretty().bold(), i))
+}
+
 fn apply_builtin(
     b: &Builtin,
     traversed_args: Vec<Node>,
-    _ctx: &mut Scope,
-    _settings: &CompileSettings,
+    ctx: &mut Scope,
+    settings: &CompileSettings,
 ) -> Result<Option<Node>> {
     b.validate_args(&traversed_args)?;
 
     match b {
-        Builtin::Len => {
-            if let Expression::ArrayColumn {
+        Builtin::Len => match traversed_args[0].e() {
+            Expression::ArrayColumn {
                 handle: _,
                 domain,
                 base: _,
-            } = traversed_args[0].e()
-            {
-                Ok(Some(Node::from_isize(domain.len().try_into().unwrap())))
-            } else {
-                bail!(RuntimeError::NotAnArray(traversed_args[0].e().clone()))
+            } => Ok(Some(Node::from_isize(domain.len().try_into().unwrap()))),
+            Expression::List(xs) if traversed_args[0].t().is_list() => {
+                Ok(Some(Node::from_isize(xs.len().try_into().unwrap())))
             }
+            // the length of a column is only known if its module has been forced
+            // to a given length; that of computed columns, e.g. interleaved
+            // ones, may differ from that of their module
+            Expression::Column {
+                handle,
+                kind: Kind::Commitment | Kind::Expression(_),
+                ..
+            } => settings
+                .module_lens
+                .get(&handle.as_handle().module)
+                .map(|len| Some(Node::from_isize(*len as isize)))
+                .ok_or_else(|| {
+                    anyhow!(CompileError::TypeMismatch(
+                        b.to_string(),
+                        vec![vec![Type::ArrayColumn(Magma::ANY), Type::List(Magma::ANY)]],
+                        vec![traversed_args[0].t()]
+                    ))
+                }),
+            _ => bail!(RuntimeError::NotAnArray(traversed_args[0].e().clone())),
+        },
+        Builtin::Nth => {
+            let i = signed_index(&traversed_args[1])
+                .ok_or_else(|| anyhow!("{} is not a valid index", traversed_args[1].pretty()))?;
+            let i = resolve_index(&traversed_args[0], i)?;
+            Ok(Some(index_array(&traversed_args[0], i, ctx)?))
+        }
+        Builtin::Sum | Builtin::Prod => {
+            let array = &traversed_args[0];
+            let elements = match array.e() {
+                Expression::ArrayColumn { domain, .. } => domain
+                    .iter()
+                    .map(|i| index_array(array, i as usize, ctx))
+                    .collect::<Result<Vec<_>>>()?,
+                Expression::List(xs) if array.t().is_list() => xs.clone(),
+                _ => bail!(RuntimeError::NotAnArray(array.e().clone())),
+            };
+            if elements.is_empty() {
+                bail!("{} can not reduce the empty {}", b, array.pretty());
+            }
+            Ok(Some(fold_constants(
+                if *b == Builtin::Sum {
+                    Intrinsic::Add
+                } else {
+                    Intrinsic::Mul
+                }
+                .call(&elements)?,
+            )))
         }
         Builtin::Shift => {
-            let shift = traversed_args[1].pure_eval()?.to_i16().unwrap();
+            // the offset may be any expression, e.g. `(+ 1 OFFSET)`, as long
+            // as it folds to a constant fitting in a shift
+            let offset = &traversed_args[1];
+            let shift = signed_index(offset)
+                .and_then(|x| i16::try_from(x).ok())
+                .with_context(|| {
+                    anyhow!(
+                        "shift offset {} does not fold to an integer in [{}, {}]",
+                        offset.pretty().red().bold(),
+                        i16::MIN,
+                        i16::MAX
+                    )
+                })?;
             Ok(Some(traversed_args.get(0).unwrap().clone().shift(shift)))
         }
         Builtin::NormFlat => {
@@ -1433,6 +2751,113 @@ fn apply_builtin(
                 Ok(Some(traversed_args[0].clone()))
             }
         }
+        Builtin::IsNotZero => {
+            let x = &traversed_args[0];
+            let t = x
+                .t()
+                .with_magma(Magma::binary().with_conditioning(super::Conditioning::Boolean));
+            Ok(Some(if let Result::Ok(x) = x.pure_eval() {
+                Node::from_isize(isize::from(!x.is_zero())).with_type(t)
+            } else {
+                Intrinsic::Normalize.call(&traversed_args)?.with_type(t)
+            }))
+        }
+        Builtin::AsInteger => {
+            let x = &traversed_args[0];
+            let t = x.t().with_magma(Magma::native());
+            Ok(Some(x.clone().with_type(t)))
+        }
+        Builtin::AsBool => {
+            let x = &traversed_args[0];
+            let t = x
+                .t()
+                .with_magma(Magma::binary().with_conditioning(super::Conditioning::Boolean));
+            // unless told otherwise, the booleanity is exported as a constraint
+            let exported = match traversed_args.get(1) {
+                Some(flag) => !flag.pure_eval()?.is_zero(),
+                None => true,
+            };
+            if let Result::Ok(v) = x.pure_eval() {
+                if !(v.is_zero() || v.is_one()) {
+                    bail!("{} is not a boolean", v.to_string().red().bold())
+                }
+            } else {
+                // `#` can not appear in symbols, so these names are reserved
+                let handle = match x.e() {
+                    Expression::Column {
+                        handle, shift: 0, ..
+                    } => Handle::new(
+                        &handle.as_handle().module,
+                        format!("#as-bool-{}", handle.as_handle().name),
+                    ),
+                    _ => Handle::new(
+                        ctx.module(),
+                        format!("#as-bool-{}", hash_strings(std::iter::once(x.to_string()))),
+                    ),
+                };
+                ctx.insert_booleanity(handle, x.clone(), exported)?;
+            }
+            Ok(Some(x.clone().with_type(t)))
+        }
+        Builtin::Interleave => {
+            let mut froms = Vec::new();
+            for arg in traversed_args.iter() {
+                match arg.e() {
+                    Expression::Column { handle, shift, .. } if *shift == 0 => {
+                        froms.push(handle.clone())
+                    }
+                    _ => bail!("{} is not a column", arg.pretty()),
+                }
+            }
+
+            let module = froms[0].as_handle().module.clone();
+            if let Some(other) = froms.iter().find(|f| f.as_handle().module != module) {
+                bail!(
+                    "can not interleave {} and {}, as they belong to different modules",
+                    froms[0].pretty(),
+                    other.pretty()
+                );
+            }
+            let computations = ctx.computations();
+            let factor = |h: &ColumnRef| match computations.computation_for(h) {
+                Some(Computation::Interleaved { froms, .. }) => froms.len(),
+                _ => 1,
+            };
+            if let Some(other) = froms.iter().find(|f| factor(f) != factor(&froms[0])) {
+                bail!(
+                    "can not interleave {} and {}, as they are of different lengths",
+                    froms[0].pretty(),
+                    other.pretty()
+                );
+            }
+
+            let name = format!(
+                "interleaved-{}",
+                hash_strings(froms.iter().map(|f| f.as_handle().name.to_owned()))
+            );
+            let mut module_ctx = ctx.module_scope(&module)?;
+            if module_ctx.resolve_symbol(&name, false).is_err() {
+                let target = ColumnRef::from_handle(Handle::new(&module, &name));
+                module_ctx.insert_symbol(
+                    &name,
+                    Node::column()
+                        .handle(target.as_handle().clone())
+                        .kind(Kind::Computed)
+                        .t(traversed_args
+                            .iter()
+                            .fold(Magma::BINARY, |ax, a| a.t().m().max(ax)))
+                        .build(),
+                )?;
+                ctx.insert_computation(
+                    &target,
+                    Computation::Interleaved {
+                        target: target.clone(),
+                        froms,
+                    },
+                )?;
+            }
+            Ok(Some(module_ctx.resolve_symbol(&name, true)?))
+        }
         Builtin::If => match traversed_args[0].t().c() {
             super::Conditioning::None => {
                 bail!("{} is not a valid condition", traversed_args[0].pretty())
@@ -1440,34 +2865,105 @@ fn apply_builtin(
             super::Conditioning::Boolean => Ok(Some(Intrinsic::IfNotZero.call(&traversed_args)?)),
             super::Conditioning::Loobean => Ok(Some(Intrinsic::IfZero.call(&traversed_args)?)),
         },
+        Builtin::Lt | Builtin::Le | Builtin::Gt | Builtin::Ge => {
+            let width = Node::from_isize(comparison_width(b, &traversed_args)? as isize);
+            let (x, y) = (traversed_args[0].clone(), traversed_args[1].clone());
+            // All the comparisons boil down to a strict one
+            let (x, y, negated) = match b {
+                Builtin::Lt => (x, y, false),
+                Builtin::Gt => (y, x, false),
+                Builtin::Le => (y, x, true),
+                Builtin::Ge => (x, y, true),
+                _ => unreachable!(),
+            };
+            let lt = Intrinsic::Lt.call(&[x, y, width])?;
+            Ok(Some(if negated {
+                let t = lt.t();
+                Intrinsic::Sub
+                    .call(&[Node::from_isize(1), lt])?
+                    .with_type(t)
+            } else {
+                lt
+            }))
+        }
+    }
+}
+
+/// The bit width bounding the operands of the comparison `b`; it is either
+/// explicitly given as its third argument, or inferred from the magmas of the
+/// operands.
+fn comparison_width(b: &Builtin, args: &[Node]) -> Result<usize> {
+    let valid = |w: usize| w > 0 && w <= crate::constants::MAX_COMPARISON_WIDTH;
+    if let Some(width) = args.get(2) {
+        width
+            .pure_eval()
+            .ok()
+            .and_then(|w| w.to_usize())
+            .filter(|w| valid(*w))
+            .ok_or_else(|| anyhow!("{} is not a valid bit width", width.pretty()))
+    } else {
+        args.iter()
+            .map(|arg| {
+                if let Result::Ok(x) = arg.pure_eval() {
+                    // Constants are bounded by their own value
+                    x.to_biguint().map(|x| (x.bits() as usize).max(1))
+                } else {
+                    match arg.t().rm() {
+                        RawMagma::Binary
+                        | RawMagma::Nibble
+                        | RawMagma::Byte
+                        | RawMagma::Integer(_) => Some(arg.t().m().bit_size()),
+                        _ => None,
+                    }
+                }
+            })
+            .collect::<Option<Vec<_>>>()
+            .and_then(|ws| ws.into_iter().max())
+            .filter(|w| valid(*w))
+            .ok_or_else(|| {
+                anyhow!(
+                    "unable to bound the operands of {}: they must be of sized types (e.g. :u32), or the bit width must be given as a third argument",
+                    b.to_string().bold()
+                )
+            })
     }
 }
 
 fn apply_intrinsic(
     b: &Intrinsic,
     traversed_args: Vec<Node>,
-    _settings: &CompileSettings,
+    settings: &CompileSettings,
 ) -> Result<Option<Node>> {
     b.validate_args(&traversed_args)?;
+    if settings.strict_types {
+        b.validate_strict_types(&traversed_args)?;
+    }
     let traversed_args_t = traversed_args.iter().map(|a| a.t()).collect::<Vec<_>>();
     match b {
-        // Begin flattens & concatenate any list argument
-        Intrinsic::Begin => Ok(Some(
-            Node::from(Expression::List(traversed_args.into_iter().fold(
-                vec![],
-                |mut ax, mut e| match e.e_mut() {
+        // Begin flattens & concatenate any list argument, dropping the void
+        // ones; then, a single expression stands for itself, and none for void
+        Intrinsic::Begin => {
+            let mut es = traversed_args
+                .into_iter()
+                .fold(vec![], |mut ax, mut e| match e.e_mut() {
                     Expression::List(ref mut es) => {
-                        ax.append(es);
+                        ax.extend(es.drain(..).filter(|e| !matches!(e.e(), Expression::Void)));
                         ax
                     }
+                    Expression::Void => ax,
                     _ => {
-                        ax.push(e.to_owned());
+                        ax.push(e);
                         ax
                     }
-                },
-            )))
-            .with_type(super::max_type(&traversed_args_t)?),
-        )),
+                });
+            Ok(Some(match es.len() {
+                0 => Expression::Void.into(),
+                1 => es.pop().unwrap(),
+                _ => {
+                    Node::from(Expression::List(es)).with_type(super::max_type(&traversed_args_t)?)
+                }
+            }))
+        }
 
         b @ Intrinsic::IfZero | b @ Intrinsic::IfNotZero => {
             let r = b.call(&traversed_args)?;
@@ -1482,17 +2978,56 @@ fn apply_intrinsic(
             Ok(Some(r))
         }
 
-        b @ (Intrinsic::Add
-        | Intrinsic::Sub
-        | Intrinsic::Mul
-        | Intrinsic::VectorAdd
+        Intrinsic::Exp => {
+            let exp = traversed_args[1]
+                .pure_eval()
+                .ok()
+                .and_then(|x| x.to_usize())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "exponent {} must be a non-negative integer known at compile-time",
+                        traversed_args[1].to_string().red()
+                    )
+                })?;
+            match exp {
+                0 => Ok(Some(Node::one())),
+                1 => Ok(Some(traversed_args[0].clone())),
+                _ => Ok(Some(fold_constants(b.call(&traversed_args)?))),
+            }
+        }
+
+        b @ (Intrinsic::Add | Intrinsic::Sub | Intrinsic::Mul | Intrinsic::Neg) => {
+            Ok(Some(fold_constants(b.call(&traversed_args)?)))
+        }
+
+        b @ (Intrinsic::VectorAdd
         | Intrinsic::VectorSub
         | Intrinsic::VectorMul
-        | Intrinsic::Exp
-        | Intrinsic::Neg
         | Intrinsic::Inv
-        | Intrinsic::Normalize) => Ok(Some(b.call(&traversed_args)?)),
+        | Intrinsic::Normalize
+        | Intrinsic::Lt) => Ok(Some(b.call(&traversed_args)?)),
+    }
+}
+
+/// If all the arguments of the arithmetic operation `n` are constants, replace
+/// it with its result; its type is kept, so that e.g. a boolean conditioning
+/// survives the folding.
+fn fold_constants(n: Node) -> Node {
+    if let Expression::Funcall { args, .. } = n.e() {
+        if args.iter().all(|a| matches!(a.e(), Expression::Const(_))) {
+            if let Some(v) = n.pure_eval().ok().and_then(|x| Value::try_from(x).ok()) {
+                let t = if v.is_zero() || v.is_one() {
+                    n.t().with_raw_magma(RawMagma::Binary)
+                } else {
+                    n.t()
+                };
+                let folded = Node::from_value(v).with_type(t);
+                report_folding(&n, &folded);
+                return folded;
+            }
+        }
     }
+    n
 }
 
 fn apply_function(
@@ -1502,7 +3037,10 @@ fn apply_function(
     settings: &CompileSettings,
 ) -> Result<Option<Node>> {
     match &f.class {
-        FunctionClass::UserDefined(d) => apply_defined(d, &f.handle, args, ctx, settings),
+        FunctionClass::UserDefined(d) => {
+            ctx.mark_called(&f.handle);
+            apply_defined(d, &f.handle, args, ctx, settings)
+        }
         FunctionClass::Intrinsic(i) => apply_intrinsic(i, args, settings),
         FunctionClass::Builtin(b) => apply_builtin(b, args, ctx, settings),
         _ => unreachable!(),
@@ -1517,6 +3055,16 @@ fn apply(
 ) -> Result<Option<Node>> {
     match f.class {
         FunctionClass::Form(sf) => apply_form(sf, args, ctx, settings),
+        FunctionClass::Partial(ref inner, ref bound) => {
+            let all_args = bound.iter().chain(args.iter()).cloned().collect::<Vec<_>>();
+            apply(inner, &all_args, ctx, settings)
+        }
+        FunctionClass::Builtin(Builtin::Shift)
+            if args.len() == 2 && matches!(args[1].class, Token::Domain(_)) =>
+        {
+            apply_shift_range(&args[0], &args[1], ctx, settings)
+        }
+        FunctionClass::Builtin(Builtin::If) if !args.is_empty() => apply_if(f, args, ctx, settings),
         FunctionClass::Intrinsic(_) | FunctionClass::UserDefined(_) | FunctionClass::Builtin(_) => {
             let mut traversed_args = vec![];
             for arg in args.iter() {
@@ -1532,73 +3080,195 @@ fn apply(
     }
 }
 
+/// Reduce the branches of an `if` knowing the condition under which they are
+/// taken, so that the casts they contain only hold there
+fn apply_if(
+    f: &Function,
+    args: &[AstNode],
+    ctx: &mut Scope,
+    settings: &CompileSettings,
+) -> Result<Option<Node>> {
+    let cond = reduce(&args[0], ctx, settings)?;
+    // the guards under which the then and else branches are taken; an invalid
+    // condition is reported when applying the `if`
+    let guards = cond.as_ref().and_then(|cond| match cond.t().c() {
+        Conditioning::Boolean => Some([Intrinsic::IfNotZero, Intrinsic::IfZero]),
+        Conditioning::Loobean => Some([Intrinsic::IfZero, Intrinsic::IfNotZero]),
+        Conditioning::None => None,
+    });
+    let mut traversed_args = cond.iter().cloned().collect::<Vec<_>>();
+    for (i, arg) in args[1..].iter().enumerate() {
+        let guard = guards.and_then(|g| g.get(i).copied());
+        if let (Some(guard), Some(cond)) = (guard, cond.as_ref()) {
+            ctx.push_guard(guard, cond.clone());
+        }
+        let traversed = reduce(arg, ctx, settings);
+        if guard.is_some() {
+            ctx.pop_guard();
+        }
+        if let Some(traversed) = traversed? {
+            traversed_args.push(traversed);
+        }
+    }
+    apply_function(f, traversed_args, ctx, settings)
+}
+
+/// Expand the shift of `column` by a domain, e.g. `(shift A [-1:1])`, into the
+/// list of `column` shifted by each of its values, e.g. `(begin A[-1] A A[1])`
+fn apply_shift_range(
+    column: &AstNode,
+    shifts: &AstNode,
+    ctx: &mut Scope,
+    settings: &CompileSettings,
+) -> Result<Option<Node>> {
+    let column = match reduce(column, ctx, settings)? {
+        Some(n)
+            if matches!(
+                n.e(),
+                Expression::Column { .. } | Expression::ExoColumn { .. }
+            ) =>
+        {
+            n
+        }
+        _ => bail!("{} is not a column", column.src.red().bold()),
+    };
+    let shifts = if let Token::Domain(shifts) = &shifts.class {
+        shifts.concretize(|n| {
+            crate::compiler::generator::reduce(n, &mut ctx.clone(), settings)
+                .transpose()
+                .unwrap()
+                .and_then(|r| r.pure_eval())
+                .and_then(|bi| bi.to_isize().ok_or_else(|| anyhow!("{} is not an i64", bi)))
+        })?
+    } else {
+        unreachable!()
+    };
+
+    let mut l = vec![];
+    for shift in shifts.iter() {
+        if i16::try_from(shift).is_err() {
+            bail!("shift {} is out of range", shift.to_string().red().bold())
+        }
+        l.extend(apply_builtin(
+            &Builtin::Shift,
+            vec![column.clone(), Node::from_isize(shift)],
+            ctx,
+            settings,
+        )?);
+    }
+    let t = super::max_type(l.iter().map(Node::t).collect::<Vec<_>>().iter())?;
+    Ok(Some(Node::from(Expression::List(l)).with_type(t)))
+}
+
+/// Resolve the function called at the head of a list, which is either a
+/// function name, or the partial application of a user-defined function to
+/// fewer arguments than it expects, e.g. `(add3 1 2)` in `((add3 1 2) x)`.
+fn resolve_callee(head: &AstNode, ctx: &mut Scope) -> Result<Function> {
+    match &head.class {
+        Token::Symbol(verb) => ctx.resolve_function(verb),
+        Token::List(args) if !args.is_empty() => {
+            let f = resolve_callee(&args[0], ctx)?;
+            let (inner, mut bound) = match f.class {
+                FunctionClass::Partial(inner, bound) => (*inner, bound),
+                FunctionClass::UserDefined(_) => (f, vec![]),
+                _ => bail!(CompileError::NotAFunction(format!("{:?}", head))),
+            };
+            bound.extend(args[1..].iter().cloned());
+            let max_arity = if let FunctionClass::UserDefined(d) = &inner.class {
+                d.max_arity()
+            } else {
+                unreachable!()
+            };
+            if bound.len() >= max_arity {
+                bail!(CompileError::ArityMismatch(
+                    format!(
+                        "fewer than {} arguments to partially apply {}",
+                        max_arity,
+                        inner.handle.pretty()
+                    ),
+                    bound.len()
+                ))
+            }
+            Ok(Function {
+                handle: inner.handle.clone(),
+                class: FunctionClass::Partial(Box::new(inner), bound),
+            })
+        }
+        _ => bail!(CompileError::NotAFunction(format!("{:?}", head))),
+    }
+}
+
+/// Ensure that the literal `x` can be represented in the field, i.e. that its
+/// magnitude is smaller than the field modulus
+pub(crate) fn validate_field_literal(x: &BigInt) -> Result<()> {
+    if x.magnitude() >= crate::column::field_modulus().magnitude() {
+        bail!(CompileError::OutOfRange(x.clone()))
+    }
+    Ok(())
+}
+
 pub fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<Option<Node>> {
     match &e.class {
         Token::Keyword(_) | Token::Domain(_) => Ok(None),
-        Token::Value(x) => Ok(Some(
-            // We want the value to specifically be a BigInt here, as we may
-            // have negative ones, e.g. as shift arguments.
-            Node::from(Expression::Const(Value::big_int(x.clone()))).with_type(
-                if *x >= Zero::zero() && *x <= One::one() {
-                    Type::Scalar(Magma::binary())
-                } else {
-                    Type::Scalar(Magma::native())
-                },
-            ),
-        )),
+        Token::Text(s) => bail!("unexpected string {:?} in an expression", s),
+        Token::Values(xs) => {
+            let xs = xs
+                .iter()
+                .map(|x| reduce(x, ctx, settings).map(Option::unwrap))
+                .collect::<Result<Vec<_>>>()?;
+            let t = super::max_type(xs.iter().map(Node::t).collect::<Vec<_>>().iter())?;
+            Ok(Some(
+                Node::from(Expression::List(xs)).with_type(Type::List(t.m())),
+            ))
+        }
+        Token::Value(x) => {
+            validate_field_literal(x).map_err(|err| locate_ast(err, e))?;
+            Ok(Some(
+                // We want the value to specifically be a BigInt here, as we may
+                // have negative ones, e.g. as shift arguments.
+                Node::from(Expression::Const(Value::big_int(x.clone()))).with_type(
+                    if *x >= Zero::zero() && *x <= One::one() {
+                        Type::Scalar(Magma::binary())
+                    } else {
+                        Type::Scalar(Magma::native())
+                    },
+                ),
+            ))
+        }
         Token::Symbol(name) => Ok(Some(
             ctx.resolve_symbol(name, true)
-                .with_context(|| make_ast_error(e))?,
+                .map_err(|err| locate_ast(err, e))?,
         )),
         Token::IndexedSymbol { name, index } => {
             let symbol = ctx.resolve_symbol(name, true)?;
-            if let Expression::ArrayColumn {
-                handle,
-                domain,
-                base: _,
-            } = symbol.e()
-            {
-                let i = reduce(index, ctx, settings)?
-                    .and_then(|n| n.pure_eval().ok())
-                    .and_then(|b| b.to_usize())
-                    .ok_or_else(|| anyhow!("{:?} is not a valid index", index))?;
-                // Sanity check access within bounds
-                if domain.contains(i.try_into().unwrap()) {
-                    // Construct indexed handle
-                    let name = handle.as_handle().ith(i.try_into().unwrap()).to_string();
-                    // Resolve it properly this time.
-                    Ok(Some(
-                        ctx.resolve_symbol_with_path(&name, true)
-                            .with_context(|| make_ast_error(e))?,
-                    ))
-                } else {
-                    bail!("tried to access {} at index {}", symbol.pretty().bold(), i)
-                }
-            } else {
-                bail!(anyhow!(
+            if !matches!(symbol.e(), Expression::ArrayColumn { .. }) && !symbol.t().is_list() {
+                bail!(
                     "{} of type {} is not indexable",
                     name.red().bold(),
                     symbol.t()
-                ))
+                )
             }
+            let i = reduce(index, ctx, settings)?
+                .and_then(|n| signed_index(&n))
+                .ok_or_else(|| anyhow!("{:?} is not a valid index", index))?;
+            let i = resolve_index(&symbol, i).map_err(|err| locate_ast(err, e))?;
+            Ok(Some(
+                index_array(&symbol, i, ctx).map_err(|err| locate_ast(err, e))?,
+            ))
         }
         Token::List(args) => {
             if args.is_empty() {
                 Ok(Some(Expression::List(vec![]).into()))
-            } else if let Token::Symbol(verb) = &args[0].class {
-                let func = ctx
-                    .resolve_function(verb)
-                    .with_context(|| make_ast_error(e))?;
+            } else {
+                let func = resolve_callee(&args[0], ctx).map_err(|err| locate_ast(err, e))?;
 
                 let r = apply(&func, &args[1..], ctx, settings);
                 match func.class {
-                    FunctionClass::UserDefined(_) => {
+                    FunctionClass::UserDefined(_) | FunctionClass::Partial(..) => {
                         r.map(|o| o.map(|n| n.with_debug(e.debug_info())))
                     }
                     _ => r,
                 }
-            } else {
-                Err(anyhow!("not a function: `{:?}`", args[0])).with_context(|| make_ast_error(e))
             }
         }
         Token::DefColumn {
@@ -1627,12 +3297,13 @@ pub fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Resul
                 unreachable!()
             };
 
+            let mut sources_ctx = ctx.jump_in(&format!("interleaving-{}", target.name))?;
             let mut from_handles = Vec::new();
             for from in froms {
                 match &from.class {
                     Token::Symbol(name) => {
                         if let Expression::Column { handle, .. } =
-                            ctx.resolve_symbol(name, true)?.e()
+                            sources_ctx.resolve_symbol(name, true)?.e()
                         {
                             from_handles.push(handle.clone());
                         } else {
@@ -1641,9 +3312,9 @@ pub fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Resul
                     }
                     Token::IndexedSymbol { name, index } => {
                         if let Expression::ArrayColumn { handle, domain, .. } =
-                            ctx.resolve_symbol(name, true)?.e()
+                            sources_ctx.resolve_symbol(name, true)?.e()
                         {
-                            let index_usize = reduce(index, ctx, settings)?
+                            let index_usize = reduce(index, &mut sources_ctx, settings)?
                                 .and_then(|n| n.pure_eval().ok())
                                 .and_then(|b| b.to_usize())
                                 .ok_or_else(|| {
@@ -1684,10 +3355,13 @@ pub fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Resul
         | Token::Defpurefun { .. }
         | Token::DefPermutation { .. }
         | Token::DefLookup { .. }
-        | Token::DefInrange(..) => Ok(None),
+        | Token::DefLookupTable { .. }
+        | Token::DefInrange(..)
+        | Token::DefPadding(..)
+        | Token::DefTest { .. } => Ok(None),
         Token::BlockComment(_) | Token::InlineComment(_) => unreachable!(),
     }
-    .with_context(|| make_ast_error(e))
+    .map_err(|err| locate_ast(err, e))
 }
 
 pub(crate) fn reduce_toplevel(
@@ -1702,16 +3376,22 @@ pub(crate) fn reduce_toplevel(
             guard,
             perspective,
             body,
+            tags,
+            ..
         } => {
             let handle = Handle::new(ctx.module(), name);
+            let _folding = FoldingIn::constraint(&handle);
             let module = ctx.module();
             let mut ctx = if let Some(perspective) = perspective {
                 ctx.jump_in(&format!("in-{perspective}"))?
             } else {
                 ctx.clone()
             };
+            // the casts of the body only hold where the constraint does
+            let casts_from = ctx.booleanities_count();
             let body = reduce(body, &mut ctx, settings)?.unwrap_or_else(|| Expression::Void.into());
-            let body = if let Some(guard) = guard {
+            let casts_to = ctx.booleanities_count();
+            let guard_expr = if let Some(guard) = guard {
                 let guard_expr = reduce(guard, &mut ctx, settings)?
                     .with_context(|| anyhow!("guard `{:?}` is empty", guard))?;
                 // Sanity check guard does not do strange things.
@@ -1719,31 +3399,42 @@ pub(crate) fn reduce_toplevel(
                     bail!("unexpected non-atomic guard in {}", handle.pretty())
                 }
                 // Sanity check guard has the expected type.
-                match guard_expr.t().c() {
-                    Conditioning::Loobean => {
-                        bail!("unexpected loobean guard in {}", handle.pretty())
-                    }
-                    _ => Intrinsic::IfNotZero.call(&[guard_expr, body])?,
+                if matches!(guard_expr.t().c(), Conditioning::Loobean) {
+                    bail!("unexpected loobean guard in {}", handle.pretty())
                 }
+                Some(guard_expr)
             } else {
-                body
+                None
             };
-            let body = if let Some(perspective) = perspective {
-                let persp_guard = ctx
-                    .tree
-                    .borrow()
-                    .metadata()
-                    .get_perspective_trigger(&module, perspective)?;
-                // Perspectives are just multiplicative coefficients, and are
-                // controlled exceptions to the usual loobean typing rules
-                let body_type = body.t();
-                Intrinsic::Mul
-                    .unchecked_call(&[persp_guard, body])
-                    .with_context(|| anyhow!("constraint {}", name))?
-                    .with_type(body_type)
+            let persp_guard = if let Some(perspective) = perspective {
+                Some(
+                    ctx.tree
+                        .borrow()
+                        .metadata()
+                        .get_perspective_trigger(&module, perspective)?,
+                )
             } else {
-                body
+                None
             };
+            let guarded = |body: Node| -> Result<Node> {
+                let body = if let Some(guard_expr) = guard_expr.as_ref() {
+                    Intrinsic::IfNotZero.call(&[guard_expr.clone(), body])?
+                } else {
+                    body
+                };
+                Ok(if let Some(persp_guard) = persp_guard.as_ref() {
+                    // Perspectives are just multiplicative coefficients, and are
+                    // controlled exceptions to the usual loobean typing rules
+                    let body_type = body.t();
+                    Intrinsic::Mul
+                        .unchecked_call(&[persp_guard.clone(), body])
+                        .with_context(|| anyhow!("constraint {}", name))?
+                        .with_type(body_type)
+                } else {
+                    body
+                })
+            };
+            let body = guarded(body)?;
             if body.t() == Type::Void {
                 warn!(
                     "constraint {} should be of type {}, found {}",
@@ -1774,10 +3465,12 @@ pub(crate) fn reduce_toplevel(
                 } else {
                     None
                 };
+                ctx.guard_booleanities(casts_from..casts_to, guarded, &domain)?;
 
                 Ok(Some(Constraint::Vanishes {
                     handle,
                     domain,
+                    tags: tags.to_owned(),
                     expr: Box::new(body),
                 }))
             }
@@ -1812,6 +3505,27 @@ pub(crate) fn reduce_toplevel(
                 }))
             }
         }
+        Token::DefLookupTable { name, columns, .. } => {
+            let mut table_ctx = ctx.switch_to_module(&format!("table-{}", name))?;
+            *ctx = ctx.derive(&format!("lookup-{}", name))?.global(true);
+            let handle = Handle::new(ctx.module(), name);
+            let including = (0..columns.len())
+                .map(|i| {
+                    table_ctx
+                        .resolve_symbol(&format!("c{}", i), true)
+                        .map_err(Into::into)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let included = columns
+                .iter()
+                .map(|e| reduce(e, ctx, settings).map(Option::unwrap))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Some(Constraint::Lookup {
+                handle,
+                including,
+                included,
+            }))
+        }
         Token::DefInrange(e, range) => {
             let handle = Handle::new(ctx.module(), format!("{}_lt_{}", e, range));
             Ok(Some(Constraint::InRange {
@@ -1820,6 +3534,46 @@ pub(crate) fn reduce_toplevel(
                 max: Value::from(*range),
             }))
         }
+        Token::DefPadding(name, value) => {
+            match ctx.resolve_symbol(name, false)?.e() {
+                Expression::Column {
+                    padding_value: Some(previous),
+                    ..
+                } => bail!(
+                    "padding value for {} already set to {}",
+                    name.bold().yellow(),
+                    previous
+                ),
+                Expression::Column { .. } => {
+                    ctx.edit_symbol(name, &|x| {
+                        if let Expression::Column { padding_value, .. } = x {
+                            *padding_value = Some(*value)
+                        }
+                    })?;
+                }
+                _ => bail!("{} is not a column", name.bold().yellow()),
+            }
+            Ok(None)
+        }
+        Token::DefTest { name, inputs, .. } => {
+            // tests may only fill the columns that would come from a trace
+            for (column, _) in inputs {
+                if !matches!(
+                    ctx.resolve_symbol(column, false)?.e(),
+                    Expression::Column {
+                        kind: Kind::Commitment,
+                        ..
+                    }
+                ) {
+                    bail!(
+                        "{} in test {} is not an input column",
+                        column.bold().yellow(),
+                        name.bold().yellow()
+                    )
+                }
+            }
+            Ok(None)
+        }
         Token::DefColumns(columns) => {
             for c in columns {
                 reduce(c, ctx, settings)?;
@@ -1879,7 +3633,7 @@ pub(crate) fn reduce_toplevel(
             // TODO is this needed?
             to.iter()
                 .map(|f| ctx.resolve_symbol(&f.name, true))
-                .collect::<Result<Vec<_>, errors::symbols::Error>>()
+                .collect::<Result<Vec<_>, errors::CompileError>>()
                 .with_context(|| anyhow!("while defining permutation"))?;
             let suffix = hash_strings(froms.iter().map(|f| f.as_handle().name.clone()));
 
@@ -1929,8 +3683,8 @@ pub(crate) fn reduce_toplevel(
     }
 }
 
-pub fn make_ast_error(exp: &AstNode) -> String {
-    errors::parser::make_src_error(&exp.src, exp.lc)
+pub fn locate_ast<E: Into<anyhow::Error>>(err: E, exp: &AstNode) -> anyhow::Error {
+    errors::parser::locate(err, &exp.src, exp.lc)
 }
 
 pub fn pass(ast: &Ast, ctx: Scope, settings: &CompileSettings) -> Vec<Result<Constraint>> {
@@ -3,18 +3,22 @@ use crate::{
     structs::Handle,
 };
 use anyhow::*;
+use itertools::Itertools;
 use log::*;
 use std::collections::HashMap;
 
 pub use common::*;
-pub use generator::{Constraint, ConstraintSet, EvalSettings};
+pub use generator::{Constraint, ConstraintSet, Docs, EvalCache, EvalSettings, ShiftMode};
+// This is only used by the lib
+#[allow(unused_imports)]
+pub use generator::Summary;
 pub use node::{ColumnRef, Expression, Node};
 use num_bigint::BigInt;
 use owo_colors::OwoColorize;
 pub use tables::ComputationTable;
 pub use types::*;
 
-use self::parser::Ast;
+use self::parser::{Ast, Token};
 use crate::{column::Column, compiler::tables::Symbol, errors::CompileError};
 
 pub mod codetyper;
@@ -26,9 +30,21 @@ pub mod tables;
 mod types;
 
 pub(crate) const MAIN_MODULE: &str = "<prelude>";
+/// the name under which the standard library is prepended to the sources
+pub(crate) const STDLIB: &str = "stdlib";
 
+#[derive(Clone, Default)]
 pub struct CompileSettings {
     pub debug: bool,
+    /// reject the implicit widening of booleans to integers by the
+    /// arithmetic operations, unless explicitly cast with `as-integer`
+    pub strict_types: bool,
+    /// fail on the columns never used and the functions never called, rather
+    /// than merely warning about them
+    pub deny_unused: bool,
+    /// the number of rows some modules are forced to, so that the length of
+    /// their columns is known at compile time
+    pub module_lens: HashMap<String, usize>,
 }
 
 pub fn make<S1: AsRef<str>, S2: AsRef<str>>(
@@ -41,11 +57,15 @@ pub fn make<S1: AsRef<str>, S2: AsRef<str>>(
     // Reduce the AST and create the constraints
     //
     let mut constraints = vec![];
-    for (name, ast) in asts.iter() {
+    for ((name, ast), (_, content)) in asts.iter().zip(sources.iter()) {
         for constraint in generator::pass(ast, ctx.clone(), settings) {
-            constraints.push(
-                constraint.with_context(|| anyhow!("compiling {}", name.bright_white().bold()))?,
-            );
+            constraints.push(constraint.with_context(|| {
+                crate::errors::parser::make_file_error(
+                    format!("compiling {}", name.bright_white().bold()),
+                    name,
+                    content.as_ref(),
+                )
+            })?);
         }
     }
     // Sort by decreasing complexity for more efficient multi-threaded computation
@@ -54,13 +74,14 @@ pub fn make<S1: AsRef<str>, S2: AsRef<str>>(
     let mut columns: ColumnSet = Default::default();
     let mut constants: HashMap<Handle, BigInt> = Default::default();
     let mut computations = ctx.computations();
+    let mut unused = vec![];
 
     ctx.visit_mut::<()>(&mut |handle, symbol| {
         match symbol {
             Symbol::Alias(_) => {}
             Symbol::Final(symbol, used) => {
                 if !*used {
-                    warn!("{}", CompileError::NotUsed(handle.clone()));
+                    unused.push(CompileError::NotUsed(handle.clone()));
                 }
 
                 match symbol.e() {
@@ -100,6 +121,7 @@ pub fn make<S1: AsRef<str>, S2: AsRef<str>>(
                                         format!("prove-{}", handle.as_handle().name),
                                     ),
                                     domain: None,
+                                    tags: vec![],
                                     expr: Box::new(
                                         Intrinsic::Sub
                                             .call(&[Node::column().handle(id).build(), *e.clone()])
@@ -135,6 +157,60 @@ pub fn make<S1: AsRef<str>, S2: AsRef<str>>(
         Ok(())
     })?;
 
+    // Only the functions of the standard library may never be called
+    let functions = asts
+        .iter()
+        .filter(|(name, _)| name != STDLIB)
+        .flat_map(|(_, ast)| defined_functions(ast))
+        .unique()
+        .collect::<Vec<_>>();
+    unused.extend(
+        functions
+            .iter()
+            .filter(|f| !ctx.is_called(f))
+            .cloned()
+            .map(CompileError::NotCalled),
+    );
+    if settings.deny_unused && !unused.is_empty() {
+        bail!("{}", unused.iter().join("\n"));
+    } else {
+        for e in unused {
+            warn!("{}", e);
+        }
+    }
+
+    // The expressions cast with `as-bool` must be either 0 or 1; the same
+    // cast may occur several times, but is only constrained once
+    let mut booleanities: Vec<(Constraint, bool)> = vec![];
+    for b in ctx.booleanities() {
+        let mut c = Constraint::Vanishes {
+            handle: b.handle.clone(),
+            domain: b.domain,
+            tags: vec![],
+            expr: Box::new(b.expr),
+        };
+        if let Some(i) = booleanities.iter().position(|(d, _)| *d == c) {
+            booleanities[i].1 |= b.exported;
+            continue;
+        }
+        let mut i = 0;
+        while booleanities.iter().any(|(d, _)| d.name() == c.name()) {
+            i += 1;
+            if let Constraint::Vanishes { handle, .. } = &mut c {
+                handle.name = format!("{}-{}", b.handle.name, i);
+            }
+        }
+        booleanities.push((c, b.exported));
+    }
+    let mut assertions = vec![];
+    for (c, exported) in booleanities {
+        if exported {
+            constraints.push(c);
+        } else {
+            assertions.push(c);
+        }
+    }
+
     let perspectives = ctx
         .tree
         .borrow()
@@ -154,7 +230,101 @@ pub fn make<S1: AsRef<str>, S2: AsRef<str>>(
         })
         .collect::<HashMap<_, _>>();
 
-    let mut cs = ConstraintSet::new(columns, constraints, constants, computations, perspectives)?;
+    let mut cs = ConstraintSet::new(
+        columns,
+        constraints,
+        assertions,
+        constants,
+        computations,
+        perspectives,
+    )?;
+    cs.docs = docs(asts.iter().map(|(_, ast)| ast));
+    cs.lookup_tables = ctx.lookup_tables();
+    cs.functions = functions.len();
+    for (module, len) in settings.module_lens.iter() {
+        cs.force_module_len(module, *len)?;
+    }
+    if cs.constraints.is_empty() {
+        warn!("no constraints have been produced");
+    }
+    info!("{}", cs.summary());
     crate::transformer::precompute(&mut cs);
     Ok((asts.into_iter().map(|x| x.1).collect(), cs))
 }
+
+/// The user-defined functions declared in `ast`
+fn defined_functions(ast: &Ast) -> Vec<Handle> {
+    let mut module = MAIN_MODULE.to_owned();
+    let mut r = vec![];
+    for def in ast.exprs.iter() {
+        match &def.class {
+            Token::DefModule(m) => module = m.to_owned(),
+            Token::Defun { name, .. } | Token::Defpurefun { name, .. } => {
+                r.push(Handle::new(&module, name))
+            }
+            _ => {}
+        }
+    }
+    r
+}
+
+/// Gather the doc comments attached to the declarations of `asts`
+fn docs<'a>(asts: impl Iterator<Item = &'a Ast>) -> Docs {
+    let mut docs = Docs::default();
+    for ast in asts {
+        let mut module = MAIN_MODULE.to_owned();
+        for def in ast.exprs.iter() {
+            let (columns, perspective) = match &def.class {
+                Token::DefModule(m) => {
+                    module = m.to_owned();
+                    continue;
+                }
+                Token::DefConstraint {
+                    name,
+                    doc: Some(doc),
+                    ..
+                } => {
+                    docs.constraints
+                        .insert(Handle::new(&module, name), doc.to_owned());
+                    continue;
+                }
+                Token::Defun {
+                    name,
+                    doc: Some(doc),
+                    ..
+                }
+                | Token::Defpurefun {
+                    name,
+                    doc: Some(doc),
+                    ..
+                } => {
+                    docs.functions
+                        .insert(Handle::new(&module, name), doc.to_owned());
+                    continue;
+                }
+                Token::DefColumns(columns) => (columns, None),
+                Token::DefPerspective { name, columns, .. } => (columns, Some(name.to_owned())),
+                _ => continue,
+            };
+            for column in columns {
+                if let Token::DefColumn {
+                    name,
+                    doc: Some(doc),
+                    ..
+                }
+                | Token::DefArrayColumn {
+                    name,
+                    doc: Some(doc),
+                    ..
+                } = &column.class
+                {
+                    docs.columns.insert(
+                        Handle::maybe_with_perspective(&module, name, perspective.clone()),
+                        doc.to_owned(),
+                    );
+                }
+            }
+        }
+    }
+    docs
+}
@@ -1,6 +1,5 @@
 use crate::column::{ColumnID, Value};
 use anyhow::*;
-use cached::Cached;
 use num_bigint::BigInt;
 use num_traits::{One, ToPrimitive, Zero};
 use owo_colors::{colored::Color, OwoColorize};
@@ -16,7 +15,7 @@ use crate::compiler::codetyper::Tty;
 use crate::pretty::{Base, Pretty, COLORS};
 use crate::structs::Handle;
 
-use super::{ConstraintSet, Domain, EvalSettings, Intrinsic, Kind, Magma, Type};
+use super::{ConstraintSet, Domain, EvalCache, EvalSettings, Intrinsic, Kind, Magma, Type};
 
 #[derive(Clone, Debug, Eq)]
 pub struct ColumnRef {
@@ -94,6 +93,10 @@ impl ColumnRef {
 
         self.id = Some(i);
     }
+    /// Point to the column `i`, whatever the column pointed to so far
+    pub(crate) fn reset_id(&mut self, i: ColumnID) {
+        self.id = Some(i);
+    }
     pub fn to_string_short(&self) -> String {
         self.map(|id| format!("col#{}", id), |handle| handle.name.to_owned())
     }
@@ -657,6 +660,47 @@ impl Node {
             .collect()
     }
 
+    /// The multiplicative degree of this expression in its columns, i.e. the
+    /// degree of the polynomial it will be turned into once fully expanded.
+    ///
+    /// Shifted columns are of degree 1, like any other column; an inverse is
+    /// of degree 1 as well, as it is expanded into a computed column; and an
+    /// exponentiation by a non-constant exponent is counted as its base.
+    pub fn degree(&self) -> usize {
+        match self.e() {
+            Expression::Funcall { func, args } => match func {
+                Intrinsic::Add
+                | Intrinsic::Sub
+                | Intrinsic::VectorAdd
+                | Intrinsic::VectorSub
+                | Intrinsic::Neg
+                | Intrinsic::Begin => args.iter().map(Node::degree).max().unwrap_or(0),
+                Intrinsic::Mul | Intrinsic::VectorMul => args.iter().map(Node::degree).sum(),
+                Intrinsic::Exp => {
+                    let exponent = args[1]
+                        .pure_eval()
+                        .ok()
+                        .and_then(|x| x.to_usize())
+                        .unwrap_or(1);
+                    args[0].degree() * exponent
+                }
+                // inverses and comparisons are expanded into fresh columns
+                Intrinsic::Inv | Intrinsic::Lt => 1,
+                // x × inv(x)
+                Intrinsic::Normalize => args[0].degree() + 1,
+                // the condition is normalized, then multiplies the branches
+                Intrinsic::IfZero | Intrinsic::IfNotZero => {
+                    args[0].degree() + 1 + args[1..].iter().map(Node::degree).max().unwrap_or(0)
+                }
+            },
+            Expression::Const(_) | Expression::Void => 0,
+            Expression::Column { .. }
+            | Expression::ArrayColumn { .. }
+            | Expression::ExoColumn { .. } => 1,
+            Expression::List(xs) => xs.iter().map(Node::degree).max().unwrap_or(0),
+        }
+    }
+
     /// Try to evalaute a Node from compile-time information, return an `Err` otherwise
     pub fn pure_eval(&self) -> Result<BigInt> {
         match self.e() {
@@ -702,9 +746,11 @@ impl Node {
                         .iter()
                         .map(|x| x.pure_eval())
                         .collect::<Result<Vec<_>>>()?;
-                    Ok(args[0].pow(args[1].to_u32().ok_or_else(|| {
-                        anyhow!("exponent {} is not an u32", args[1].to_string())
-                    })?))
+                    let exp = args[1]
+                        .to_u32()
+                        .ok_or_else(|| anyhow!("exponent {} is not an u32", args[1].to_string()))?;
+                    // large exponents are raised within the field
+                    Ok((&Value::BigInt(args[0].clone()).pow(exp as usize)).into())
                 }
                 x => bail!("{} is not known at compile-time", x.to_string().red()),
             },
@@ -717,7 +763,7 @@ impl Node {
         &self,
         i: isize,
         get: F,
-        cache: &mut Option<cached::SizedCache<Value, Value>>,
+        cache: &mut Option<EvalCache>,
         settings: &EvalSettings,
     ) -> Option<Value> {
         self.eval_fold(i, &get, cache, settings, &mut |_, _| {})
@@ -727,10 +773,22 @@ impl Node {
         &self,
         i: isize,
         get: &F,
-        cache: &mut Option<cached::SizedCache<Value, Value>>,
+        cache: &mut Option<EvalCache>,
         settings: &EvalSettings,
         f: &mut dyn FnMut(&Node, &Option<Value>),
     ) -> Option<Value> {
+        let memo_id = cache.as_ref().and_then(|c| c.subexpression_id(self));
+        if let Some(id) = memo_id {
+            if let Some(x) = cache
+                .as_mut()
+                .and_then(|c| c.memoized(id, i, settings.shift))
+            {
+                let r = Some(x);
+                f(self, &r);
+                return r;
+            }
+        }
+
         let r = match self.e() {
             Expression::Funcall { func, args } => match func {
                 Intrinsic::Add => {
@@ -779,13 +837,8 @@ impl Node {
                     Some(ax)
                 }
                 Intrinsic::Exp => {
-                    let mantissa = args[0].eval_fold(i, get, cache, settings, f)?;
-                    let mut ax = mantissa.clone();
                     let exp = args[1].pure_eval().unwrap().to_usize().unwrap();
-                    for _ in 1..exp {
-                        ax.mul_assign(&mantissa);
-                    }
-                    Some(ax)
+                    Some(args[0].eval_fold(i, get, cache, settings, f)?.pow(exp))
                 }
                 Intrinsic::Neg => args[0].eval_fold(i, get, cache, settings, f).map(|mut x| {
                     x.negate();
@@ -794,11 +847,7 @@ impl Node {
                 Intrinsic::Inv => {
                     let x = args[0].eval_fold(i, get, cache, settings, f);
                     if let Some(ref mut rcache) = cache {
-                        x.map(|x| {
-                            rcache
-                                .cache_get_or_set_with(x.clone(), || x.inverse())
-                                .to_owned()
-                        })
+                        x.map(|x| rcache.inverse(x))
                     } else {
                         x.map(|x| x.inverse())
                     }
@@ -806,6 +855,15 @@ impl Node {
                 Intrinsic::Normalize => args[0]
                     .eval_fold(i, get, cache, settings, f)
                     .map(|x| x.normalize()),
+                Intrinsic::Lt => {
+                    let x = args[0].eval_fold(i, get, cache, settings, f)?;
+                    let y = args[1].eval_fold(i, get, cache, settings, f)?;
+                    Some(if x.to_field_bi() < y.to_field_bi() {
+                        Value::one()
+                    } else {
+                        Value::zero()
+                    })
+                }
                 Intrinsic::Begin => unreachable!(),
                 Intrinsic::IfZero => {
                     if args[0].eval_fold(i, get, cache, settings, f)?.is_zero() {
@@ -828,10 +886,10 @@ impl Node {
             },
             Expression::Const(v) => Some(v.clone()),
             Expression::Column { handle, shift, .. } => {
-                get(handle, i + (*shift as isize), settings.wrap)
+                get(handle, i + (*shift as isize), settings.wraps())
             }
             Expression::ExoColumn { handle, shift, .. } => {
-                get(handle, i + (*shift as isize), settings.wrap)
+                get(handle, i + (*shift as isize), settings.wraps())
             }
             Expression::List(xs) => xs
                 .iter()
@@ -840,6 +898,12 @@ impl Node {
                 .or_else(|| Some(Value::zero())),
             _ => unreachable!("{:?}", self),
         };
+        if let (Some(id), Some(x)) = (memo_id, r.as_ref()) {
+            cache
+                .as_mut()
+                .unwrap()
+                .set_memoized(id, i, settings.shift, x.clone());
+        }
         f(self, &r);
         r
     }
@@ -850,6 +914,7 @@ impl Node {
         unclutter: bool,
         dim: bool,
         src: bool,
+        raw_values: bool,
     ) -> String {
         fn spacer(tty: &mut Tty, with_newlines: bool) {
             if with_newlines {
@@ -869,6 +934,7 @@ impl Node {
             with_newlines: bool, // whether we want the expression to span several lines
             with_src: bool,
             show_value: bool,
+            raw_values: bool, // whether to show field elements as is, rather than signed
         ) {
             let colors = [
                 Color::Red,
@@ -932,6 +998,7 @@ impl Node {
                                         a.depth() > 2,
                                         with_src,
                                         show_value,
+                                        raw_values,
                                     );
                                     spacer(tty, with_newlines);
                                 }
@@ -948,6 +1015,7 @@ impl Node {
                                         a.depth() > 2,
                                         with_src,
                                         show_value,
+                                        raw_values,
                                     );
                                     if args.peek().is_some() {
                                         spacer(tty, with_newlines)
@@ -969,6 +1037,7 @@ impl Node {
                                 a.depth() > 2,
                                 with_src,
                                 show_value,
+                                raw_values,
                             );
                             spacer(tty, with_newlines);
                         }
@@ -985,6 +1054,7 @@ impl Node {
                                 a.depth() > 2,
                                 with_src,
                                 show_value,
+                                raw_values,
                             );
                             if args.peek().is_some() {
                                 spacer(tty, with_newlines)
@@ -994,7 +1064,13 @@ impl Node {
                     }
                     tty.annotate(format!(
                         "→ {}",
-                        v.pretty_with_base(Base::Hex).color(c_v).bold()
+                        if raw_values {
+                            v.pretty_with_base(Base::Hex)
+                        } else {
+                            v.pretty_signed(Base::Hex)
+                        }
+                        .color(c_v)
+                        .bold()
                     ));
                 }
                 Expression::Const(x) => {
@@ -1039,9 +1115,16 @@ impl Node {
                     }
                     if show_value {
                         tty.write(
-                            format!("<{}>", v.pretty_with_base(*base))
-                                .color(c)
-                                .to_string(),
+                            format!(
+                                "<{}>",
+                                if raw_values {
+                                    v.pretty_with_base(*base)
+                                } else {
+                                    v.pretty_signed(*base)
+                                }
+                            )
+                            .color(c)
+                            .to_string(),
                         );
                     }
                 }
@@ -1075,6 +1158,7 @@ impl Node {
                                     n.depth() > 2,
                                     with_src,
                                     show_value,
+                                    raw_values,
                                 );
                                 if ns.peek().is_some() {
                                     spacer(tty, true);
@@ -1090,7 +1174,7 @@ impl Node {
         let mut tty = Tty::new().with_guides();
         let faulty = f(self).unwrap_or_else(Value::fr_zero);
         _debug(
-            self, &mut tty, f, &faulty, unclutter, dim, false, true, src, true,
+            self, &mut tty, f, &faulty, unclutter, dim, false, true, src, true, raw_values,
         );
         tty.page_feed()
     }
@@ -1,10 +1,60 @@
+use std::collections::HashSet;
+
 use anyhow::*;
 
-use crate::compiler::{generator::make_ast_error, tables::Scope, CompileSettings, Node};
+use crate::{
+    compiler::{
+        generator::{locate_ast, validate_field_literal},
+        tables::Scope,
+        CompileSettings, Node,
+    },
+    errors::CompileError,
+};
 
 use super::{Ast, AstNode, Token};
 
-fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()> {
+/// The names, both bare and qualified by their module, of the columns
+/// declared in `ast`
+fn column_names(ast: &Ast) -> HashSet<String> {
+    let mut module = crate::compiler::MAIN_MODULE.to_string();
+    let mut names = HashSet::new();
+    for e in ast.exprs.iter() {
+        let columns = match &e.class {
+            Token::DefModule(name) => {
+                module = name.to_owned();
+                continue;
+            }
+            Token::DefColumns(columns) | Token::DefPerspective { columns, .. } => columns,
+            _ => continue,
+        };
+        for c in columns {
+            if let Token::DefColumn { name, .. } | Token::DefArrayColumn { name, .. } = &c.class {
+                names.insert(name.to_owned());
+                names.insert(format!("{}.{}", module, name));
+            }
+        }
+    }
+    names
+}
+
+/// The first symbol of `e` naming one of the `columns`, if any
+fn referenced_column(e: &AstNode, columns: &HashSet<String>) -> Option<String> {
+    match &e.class {
+        Token::Symbol(name) | Token::IndexedSymbol { name, .. } if columns.contains(name) => {
+            Some(name.to_owned())
+        }
+        Token::IndexedSymbol { index, .. } => referenced_column(index, columns),
+        Token::List(xs) => xs.iter().find_map(|x| referenced_column(x, columns)),
+        _ => None,
+    }
+}
+
+fn reduce(
+    e: &AstNode,
+    ctx: &mut Scope,
+    columns: &HashSet<String>,
+    settings: &CompileSettings,
+) -> Result<()> {
     match &e.class {
         Token::DefModule(name) => {
             *ctx = ctx.switch_to_module(name)?.public(true);
@@ -13,6 +63,23 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
 
         Token::DefConsts(cs) => {
             for (name, exp) in cs.iter() {
+                if let Token::Values(xs) = &exp.class {
+                    let values = xs
+                        .iter()
+                        .map(|x| {
+                            let value = crate::compiler::generator::reduce(x, ctx, settings)?
+                                .unwrap()
+                                .pure_eval()?;
+                            validate_field_literal(&value)?;
+                            Ok(value)
+                        })
+                        .collect::<Result<Vec<_>>>()
+                        .with_context(|| anyhow!("while defining constant `{}`", name))
+                        .map_err(|err| locate_ast(err, exp))?;
+                    ctx.insert_constant_list(name, values, true)?;
+                    continue;
+                }
+
                 let value = match &exp.class {
                     // If the constant value is iota, assign it to a deterministic pseudo-random value
                     Token::Symbol(x) if ["iota", "ι", "ɩ"].contains(&x.as_str()) => {
@@ -20,13 +87,23 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
                         std::hash::Hash::hash(&name, &mut hasher);
                         Node::from_isize((std::hash::Hasher::finish(&hasher) >> 1) as isize)
                     }
-                    _ => crate::compiler::generator::reduce(exp, ctx, settings)?.unwrap(),
+                    // Constants are defined before the columns, that are thus
+                    // not found in the symbol table
+                    _ => crate::compiler::generator::reduce(exp, ctx, settings)
+                        .map_err(|err| match referenced_column(exp, columns) {
+                            Some(column) => locate_ast(
+                                CompileError::ColumnInConstant(name.to_owned(), column),
+                                exp,
+                            ),
+                            None => err,
+                        })?
+                        .unwrap(),
                 };
-                ctx.insert_constant(
-                    name,
-                    value.pure_eval().with_context(|| make_ast_error(exp))?,
-                    true,
-                )?;
+                let value = value.pure_eval().map_err(|err| locate_ast(err, exp))?;
+                validate_field_literal(&value)
+                    .with_context(|| anyhow!("while defining constant `{}`", name))
+                    .map_err(|err| locate_ast(err, exp))?;
+                ctx.insert_constant(name, value, true)?;
             }
             Ok(())
         }
@@ -39,8 +116,9 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
 /// perspectives, constraints, aliases, ...)
 pub fn pass(ast: &Ast, ctx: Scope, settings: &CompileSettings) -> Result<()> {
     let mut module = ctx;
+    let columns = column_names(ast);
     for e in ast.exprs.iter() {
-        reduce(e, &mut module, settings)?;
+        reduce(e, &mut module, &columns, settings)?;
     }
 
     Ok(())
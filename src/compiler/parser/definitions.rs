@@ -1,11 +1,13 @@
 use anyhow::Context;
 use anyhow::*;
-use crossterm::style::Stylize;
 use num_traits::ToPrimitive;
 use owo_colors::OwoColorize;
 
-use crate::compiler::generator::{self, Defined, Function, FunctionClass, Specialization};
+use crate::compiler::generator::{
+    self, Defined, Function, FunctionClass, LookupTable, Specialization,
+};
 use crate::compiler::tables::Scope;
+use crate::compiler::Domain;
 use crate::compiler::{CompileSettings, Magma, Node};
 use crate::structs::Handle;
 use crate::utils::hash_strings;
@@ -17,12 +19,16 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
         Token::Value(_)
         | Token::Symbol(_)
         | Token::Keyword(_)
+        | Token::Text(_)
         | Token::List(_)
+        | Token::Values(_)
         | Token::Domain(_)
         | Token::DefLookup { .. }
         | Token::Defpurefun { .. }
         | Token::DefConsts { .. }
-        | Token::DefInrange(..) => Ok(()),
+        | Token::DefInrange(..)
+        | Token::DefPadding(..)
+        | Token::DefTest { .. } => Ok(()),
 
         Token::IndexedSymbol { name: _, index } => reduce(index, ctx, settings),
         Token::DefConstraint { name, .. } => ctx.insert_constraint(name),
@@ -30,6 +36,34 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
             *ctx = ctx.switch_to_module(name)?.public(true);
             Ok(())
         }
+        Token::DefLookupTable {
+            name,
+            columns,
+            file,
+        } => {
+            // the table lives in its own module, as its length is unrelated
+            // to the one of the columns looked up in it
+            let module = format!("table-{}", name);
+            let mut table_ctx = ctx.switch_to_module(&module)?.public(true);
+            let handles = (0..columns.len())
+                .map(|i| Handle::new(&module, format!("c{}", i)))
+                .collect::<Vec<_>>();
+            for handle in handles.iter() {
+                table_ctx.insert_symbol(
+                    &handle.name,
+                    Node::column()
+                        .handle(handle.clone())
+                        .kind(Kind::Commitment)
+                        .t(Magma::native())
+                        .build(),
+                )?;
+            }
+            ctx.insert_lookup_table(LookupTable {
+                file: file.to_owned(),
+                columns: handles,
+            });
+            Ok(())
+        }
         Token::DefColumns(columns) => columns
             .iter()
             .fold(Ok(()), |ax, col| ax.and(reduce(col, ctx, settings))),
@@ -49,6 +83,7 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
             padding_value,
             must_prove,
             base,
+            ..
         } => {
             let module_name = ctx.module();
             let symbol = Node::column()
@@ -72,42 +107,66 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
         Token::DefArrayColumn {
             name,
             domain,
+            inner_domain,
             t,
             padding_value,
             must_prove,
             base,
+            ..
         } => {
             let handle = Handle::maybe_with_perspective(ctx.module(), name, ctx.perspective());
             // those are inserted for symbol lookups
-            let domain = domain.concretize(|n| {
-                crate::compiler::generator::reduce(n, &mut ctx.clone(), settings)
-                    .transpose()
-                    .unwrap()
-                    .and_then(|r| r.pure_eval())
-                    .and_then(|bi| bi.to_isize().ok_or_else(|| anyhow!("{} is not an i64", bi)))
-            })?;
-
-            if domain.is_empty() {
-                bail!(
-                    "empty domain {} for {}",
-                    domain.to_string().bold().yellow(),
-                    name.bold().bright_white()
-                );
-            }
+            let concretize = |domain: &Domain<AstNode>| -> Result<Domain<isize>> {
+                let domain = domain.concretize(|n| {
+                    crate::compiler::generator::reduce(n, &mut ctx.clone(), settings)
+                        .transpose()
+                        .unwrap()
+                        .and_then(|r| r.pure_eval())
+                        .and_then(|bi| bi.to_isize().ok_or_else(|| anyhow!("{} is not an i64", bi)))
+                })?;
+                if domain.is_empty() {
+                    bail!(
+                        "empty domain {} for {}",
+                        domain.to_string().bold().yellow(),
+                        name.bold().bright_white()
+                    );
+                }
+                Ok(domain)
+            };
+            let domain = concretize(domain)?;
+            let inner_domain = inner_domain.as_ref().map(|d| concretize(d)).transpose()?;
 
+            let atomic_column = |handle: &Handle| {
+                Node::column()
+                    .handle(handle.clone())
+                    .kind(Kind::Commitment)
+                    .and_padding_value(*padding_value)
+                    .t(t.m())
+                    .must_prove(*must_prove)
+                    .base(*base)
+                    .build()
+            };
             for i in domain.iter() {
                 let ith_handle = handle.ith(i.try_into().unwrap());
-                ctx.insert_symbol(
-                    &ith_handle.name,
-                    Node::column()
-                        .handle(ith_handle.clone())
-                        .kind(Kind::Commitment)
-                        .and_padding_value(*padding_value)
-                        .t(t.m())
-                        .must_prove(*must_prove)
-                        .base(*base)
-                        .build(),
-                )?;
+                if let Some(inner_domain) = inner_domain.as_ref() {
+                    // in a two-dimensional array, each row is itself an
+                    // array of the atomic columns NAME_i_j
+                    for j in inner_domain.iter() {
+                        let ijth_handle = ith_handle.ith(j.try_into().unwrap());
+                        ctx.insert_symbol(&ijth_handle.name, atomic_column(&ijth_handle))?;
+                    }
+                    ctx.insert_symbol(
+                        &ith_handle.name,
+                        Node::array_column()
+                            .handle(ith_handle.clone())
+                            .domain(inner_domain.clone())
+                            .base(*base)
+                            .t(t.m())
+                            .build(),
+                    )?;
+                } else {
+                    ctx.insert_symbol(&ith_handle.name, atomic_column(&ith_handle))?;
+                }
             }
 
             // and this one for validating calls to `nth`
@@ -123,6 +182,10 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
             Ok(())
         }
         Token::DefInterleaving { target, froms } => {
+            // Sources may live in other modules, so they are resolved in a global scope
+            let mut sources_ctx = ctx
+                .derive(&format!("interleaving-{}", target.name))?
+                .global(true);
             let node = Node::column()
                 .handle(Handle::maybe_with_perspective(
                     // TODO unsure about this
@@ -133,7 +196,7 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
                 .kind(Kind::Computed)
                 .base(target.base)
                 .t(froms.iter().try_fold(Magma::BINARY, |ax, f| {
-                    generator::reduce(f, ctx, settings)
+                    generator::reduce(f, &mut sources_ctx, settings)
                         .transpose()
                         .unwrap()
                         .map(|s| s.t().m().max(ax))
@@ -200,6 +263,7 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
             in_types,
             out_type,
             force,
+            ..
         } => {
             let module_name = ctx.module();
             ctx.insert_function(
@@ -241,10 +305,11 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
 /// [`SymbolTableTree`] with all the required elements (columns, functions,
 /// perspectives, constraints, aliases, ...)
 pub fn pass(ast: &Ast, ctx: Scope, settings: &CompileSettings) -> Result<()> {
-    let mut module = ctx;
+    let mut module = ctx.clone();
     for e in ast.exprs.iter() {
         reduce(e, &mut module, settings)?;
     }
 
-    Ok(())
+    // Catch alias cycles right away rather than when they are first used
+    ctx.validate_aliases()
 }
@@ -197,6 +197,20 @@ fn rec_parse(source: &str, pair: Pair<Rule>) -> Result<AstNode> {
             src,
             lc,
         }),
+        Rule::string => Ok(AstNode {
+            class: Token::Text(pair.as_str().trim_matches('"').to_owned()),
+            src,
+            lc,
+        }),
+        Rule::values => Ok(AstNode {
+            class: Token::Values(
+                pair.into_inner()
+                    .map(|x| rec_parse(source, x))
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            lc,
+            src,
+        }),
         Rule::nth => {
             let mut args = pair
                 .into_inner()
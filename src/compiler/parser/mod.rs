@@ -7,7 +7,7 @@ use self::parser::DisplayableColumn;
 
 use crate::{
     compiler::{tables::Scope, Type},
-    errors::symbols,
+    errors::{self, CompileError},
     pretty::Base,
 };
 
@@ -43,57 +43,48 @@ impl AstNode {
         self.class.depth()
     }
     /// If possible, returns the i64 encoded by this node
-    pub fn as_i64(&self) -> Result<i64, symbols::Error> {
+    pub fn as_i64(&self) -> Result<i64, CompileError> {
         if let Token::Value(r) = &self.class {
             r.try_into()
-                .map_err(|_| symbols::Error::InvalidConversion("i64", format!("{:?}", self)))
+                .map_err(|_| CompileError::InvalidConversion("i64", format!("{:?}", self)))
         } else {
-            Err(symbols::Error::NotASomethings("i64", format!("{:?}", self)))
+            Err(CompileError::NotASomethings("i64", format!("{:?}", self)))
         }
     }
     /// If possible, returns the u64 encoded by this node
-    pub fn as_u64(&self) -> Result<u64, symbols::Error> {
+    pub fn as_u64(&self) -> Result<u64, CompileError> {
         if let Token::Value(r) = &self.class {
             r.try_into()
-                .map_err(|_| symbols::Error::InvalidConversion("u64", format!("{:?}", self)))
+                .map_err(|_| CompileError::InvalidConversion("u64", format!("{:?}", self)))
         } else {
-            Err(symbols::Error::NotASomethings(
-                "usize",
-                format!("{:?}", self),
-            ))
+            Err(CompileError::NotASomethings("usize", format!("{:?}", self)))
         }
     }
     /// If possible, returns the symbol encoded by this node
-    pub fn as_symbol(&self) -> Result<&str, symbols::Error> {
+    pub fn as_symbol(&self) -> Result<&str, CompileError> {
         if let Token::Symbol(x) = &self.class {
             Result::Ok(x)
         } else {
-            Err(symbols::Error::NotASomethings(
+            Err(CompileError::NotASomethings(
                 "symbol",
                 format!("{:?}", self),
             ))
         }
     }
     /// If possible, returns the list of nodes encoded by this node
-    pub fn as_list(&self) -> Result<&[AstNode], symbols::Error> {
+    pub fn as_list(&self) -> Result<&[AstNode], CompileError> {
         if let Token::List(xs) = &self.class {
             Result::Ok(xs)
         } else {
-            Err(symbols::Error::NotASomethings(
-                "list",
-                format!("{:?}", self),
-            ))
+            Err(CompileError::NotASomethings("list", format!("{:?}", self)))
         }
     }
     /// If possible, returns the domain/range encoded by this node
-    pub fn as_domain(&self) -> Result<Domain<AstNode>, symbols::Error> {
+    pub fn as_domain(&self) -> Result<Domain<AstNode>, CompileError> {
         if let Token::Domain(d) = &self.class {
             Result::Ok(*d.clone())
         } else {
-            Err(symbols::Error::NotASomethings(
-                "range",
-                format!("{:?}", self),
-            ))
+            Err(CompileError::NotASomethings("range", format!("{:?}", self)))
         }
     }
     /// A formatting function optimizing for debug informations
@@ -142,8 +133,12 @@ pub enum Token {
     },
     /// a keyword
     Keyword(String),
+    /// a string literal, e.g. a file path; stored without its quotes
+    Text(String),
     /// a list of nodes
     List(Vec<AstNode>),
+    /// a literal list of values, e.g. `[1 0 1 1]`
+    Values(Vec<AstNode>),
     /// a range; typically used in discrete constraints declaration and loops
     /// as it may contain any static expression, it is numerically converted
     /// from Domain<AstNode> to Domain<isize> after the parsing.
@@ -176,6 +171,8 @@ pub enum Token {
         must_prove: bool,
         /// which numeric base should be used to display column values; this is a purely aesthetic setting
         base: Base,
+        /// the `;;` comment documenting the column, if any
+        doc: Option<String>,
     },
     /// defines an array
     DefArrayColumn {
@@ -183,6 +180,8 @@ pub enum Token {
         name: String,
         /// where is the array defined
         domain: Box<Domain<AstNode>>,
+        /// for two-dimensional arrays, the domain of each of the rows
+        inner_domain: Option<Box<Domain<AstNode>>>,
         /// type of the array
         t: Type,
         /// the value to pad the column with; defaults to 0 if None
@@ -191,6 +190,8 @@ pub enum Token {
         must_prove: bool,
         /// which numeric base should be used to display column values; this is a purely aesthetic setting
         base: Base,
+        /// the `;;` comment documenting the array, if any
+        doc: Option<String>,
     },
     /// definition of a function
     Defun {
@@ -206,6 +207,8 @@ pub enum Token {
         body: Box<AstNode>,
         /// if set, do not warn on type override
         force: bool,
+        /// the `;;` comment documenting the function, if any
+        doc: Option<String>,
     },
     Defpurefun {
         name: String,
@@ -214,6 +217,7 @@ pub enum Token {
         out_type: Option<Type>,
         body: Box<AstNode>,
         force: bool,
+        doc: Option<String>,
     },
     /// a list of aliases declaration, normally only DefAlias -- FIXME: should probably be removed
     DefAliases(Vec<AstNode>),
@@ -234,6 +238,10 @@ pub enum Token {
         perspective: Option<String>,
         /// this expression has to reduce to 0 for the constraint to be satisfied
         body: Box<AstNode>,
+        /// user-defined labels, to select the constraint beyond its name
+        tags: Vec<String>,
+        /// the `;;` comment documenting the constraint, if any
+        doc: Option<String>,
     },
     /// declaration of a permutation constraint between two sets of columns
     DefPermutation {
@@ -253,8 +261,25 @@ pub enum Token {
         including: Vec<AstNode>,
         included: Vec<AstNode>,
     },
+    /// declaration of a lookup constraint between a set of columns and a
+    /// fixed table, loaded from a CSV file when computing the trace
+    DefLookupTable {
+        name: String,
+        columns: Vec<AstNode>,
+        file: String,
+    },
     /// this constraint ensures that exp remains lesser than max
     DefInrange(Box<AstNode>, u64),
+    /// set the value used to pad a column, overriding the default zero
+    DefPadding(String, i64),
+    /// an inline test, checking the constraints against a few hand-written rows
+    DefTest {
+        name: String,
+        /// the values of the columns filled by the test
+        inputs: Vec<(String, Vec<BigInt>)>,
+        /// the constraints expected to fail on these rows
+        failing: Vec<String>,
+    },
 }
 const LIST_DISPLAY_THRESHOLD: usize = 4;
 impl Token {
@@ -272,6 +297,7 @@ impl Token {
             Token::Value(x) => Some(format!("{}", x)),
             Token::Symbol(ref name) => Some(name.to_string()),
             Token::Keyword(ref name) => Some(name.to_string()),
+            Token::Text(ref s) => Some(format!("{:?}", s)),
             Token::List(ref args) => {
                 if let Some(verb) = args.get(0) {
                     if let Result::Ok(verb) = verb.as_symbol() {
@@ -347,10 +373,14 @@ impl std::fmt::Debug for Token {
                 ref index,
             } => write!(f, "[{} {}]", name, index),
             Token::Keyword(ref name) => write!(f, "{}", name),
+            Token::Text(ref s) => write!(f, "{:?}", s),
             Token::List(ref args) => {
                 write!(f, "({})", Token::format_list(args, LIST_DISPLAY_THRESHOLD))
             }
             Token::Domain(ref args) => write!(f, "{:?}", args),
+            Token::Values(ref xs) => {
+                write!(f, "[{}]", Token::format_list(xs, LIST_DISPLAY_THRESHOLD))
+            }
 
             Token::DefModule(name) => write!(f, "MODULE {}", name),
             Token::DefConsts(v) => {
@@ -371,6 +401,8 @@ impl std::fmt::Debug for Token {
                 write!(f, "({:?}):PERMUTATION({:?})", to, from)
             }
             Token::DefInrange(exp, max) => write!(f, "{:?}E{}", exp, max),
+            Token::DefTest { name, .. } => write!(f, "{}:TEST", name),
+            Token::DefPadding(column, value) => write!(f, "{}:PADDING({})", column, value),
             Token::DefArrayColumn {
                 name,
                 domain: range,
@@ -403,6 +435,13 @@ impl std::fmt::Debug for Token {
             } => {
                 write!(f, "{}: {:?} ⊂ {:?}", name, including, included)
             }
+            Token::DefLookupTable {
+                name,
+                columns,
+                file,
+            } => {
+                write!(f, "{}: {:?} ⊂ {:?}", name, columns, file)
+            }
             Token::DefPerspective {
                 name,
                 trigger,
@@ -454,7 +493,13 @@ pub(crate) fn parse_ast<S1: AsRef<str>, S2: AsRef<str>>(
             .map(|(name, content)| {
                 info!("Parsing {}", name.as_ref().bright_white().bold());
                 parser::parse(content.as_ref())
-                    .with_context(|| anyhow!("parsing `{}`", name.as_ref()))
+                    .with_context(|| {
+                        errors::parser::make_file_error(
+                            format!("parsing `{}`", name.as_ref()),
+                            name.as_ref(),
+                            content.as_ref(),
+                        )
+                    })
                     .map(|ast| (name.as_ref().to_string(), ast))
             })
             .collect::<Vec<_>>(),
@@ -482,6 +527,22 @@ pub fn parse_simple_ast<S1: AsRef<str>, S2: AsRef<str>>(
     )
 }
 
+/// Resolve the relative paths of the lookup tables declared in `ast` against
+/// the directory of its source file `name`, rather than the working directory
+fn resolve_lookup_tables(ast: &mut Ast, name: &str) {
+    let source = std::path::Path::new(name);
+    let Some(dir) = source.parent().filter(|_| source.is_file()) else {
+        return;
+    };
+    for e in ast.exprs.iter_mut() {
+        if let Token::DefLookupTable { file, .. } = &mut e.class {
+            if std::path::Path::new(file).is_relative() {
+                *file = dir.join(&file).display().to_string();
+            }
+        }
+    }
+}
+
 pub fn parse<S1: AsRef<str>, S2: AsRef<str>>(
     sources: &[(S1, S2)],
     settings: &CompileSettings,
@@ -497,8 +558,17 @@ pub fn parse<S1: AsRef<str>, S2: AsRef<str>>(
             .map(|(name, content)| {
                 info!("Parsing {}", name.as_ref().bright_white().bold());
                 parser::parse(content.as_ref())
-                    .with_context(|| anyhow!("parsing `{}`", name.as_ref()))
-                    .map(|ast| (name.as_ref().to_string(), ast))
+                    .with_context(|| {
+                        errors::parser::make_file_error(
+                            format!("parsing `{}`", name.as_ref()),
+                            name.as_ref(),
+                            content.as_ref(),
+                        )
+                    })
+                    .map(|mut ast| {
+                        resolve_lookup_tables(&mut ast, name.as_ref());
+                        (name.as_ref().to_string(), ast)
+                    })
             })
             .collect::<Vec<_>>(),
     )?;
@@ -512,19 +582,34 @@ pub fn parse<S1: AsRef<str>, S2: AsRef<str>>(
     // 3 - the remaining elements, which may be dependent on everything else.
 
     // 1. Pure functions
-    for (name, ast) in asts.iter() {
-        purefuns::pass(&ast, ctx.clone())
-            .with_context(|| anyhow!("parsing definitions in `{}`", name))?;
+    for ((name, ast), (_, content)) in asts.iter().zip(sources.iter()) {
+        purefuns::pass(&ast, ctx.clone()).with_context(|| {
+            errors::parser::make_file_error(
+                format!("parsing definitions in `{}`", name),
+                name,
+                content.as_ref(),
+            )
+        })?;
     }
     // 2. Constants
-    for (name, ast) in asts.iter() {
-        constants::pass(&ast, ctx.clone(), settings)
-            .with_context(|| anyhow!("parsing definitions in `{}`", name))?;
+    for ((name, ast), (_, content)) in asts.iter().zip(sources.iter()) {
+        constants::pass(&ast, ctx.clone(), settings).with_context(|| {
+            errors::parser::make_file_error(
+                format!("parsing definitions in `{}`", name),
+                name,
+                content.as_ref(),
+            )
+        })?;
     }
     // 3. The rest
-    for (name, ast) in asts.iter() {
-        definitions::pass(&ast, ctx.clone(), settings)
-            .with_context(|| anyhow!("parsing definitions in `{}`", name))?;
+    for ((name, ast), (_, content)) in asts.iter().zip(sources.iter()) {
+        definitions::pass(&ast, ctx.clone(), settings).with_context(|| {
+            errors::parser::make_file_error(
+                format!("parsing definitions in `{}`", name),
+                name,
+                content.as_ref(),
+            )
+        })?;
     }
 
     Ok((ctx, asts))
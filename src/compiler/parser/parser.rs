@@ -11,7 +11,7 @@ use std::fmt::Debug;
 use std::str::FromStr;
 use std::{fmt, vec};
 
-use super::{Ast, AstNode, Domain, Kind, Token};
+use super::{Ast, AstNode, Domain, Kind, LinCol, Token};
 
 #[derive(Parser)]
 #[grammar = "corset.pest"]
@@ -53,6 +53,7 @@ fn parse_defconstraint<I: Iterator<Item = Result<AstNode>>>(
         Guard,
         Domain,
         Perspective,
+        Tags,
     }
 
     let name = tokens
@@ -61,7 +62,7 @@ fn parse_defconstraint<I: Iterator<Item = Result<AstNode>>>(
         .as_symbol()?
         .to_owned();
 
-    let (domain, guard, perspective) = {
+    let (domain, guard, perspective, tags) = {
         let guards = tokens
             .next()
             .with_context(|| anyhow!("missing guards in constraint definitions"))??
@@ -71,7 +72,19 @@ fn parse_defconstraint<I: Iterator<Item = Result<AstNode>>>(
         let mut domain = None;
         let mut guard = None;
         let mut perspective = None;
+        let mut tags: Vec<String> = Vec::new();
         for x in guards.iter() {
+            // Tags run until the next keyword
+            if matches!(status, GuardParser::Tags) {
+                if let Token::Symbol(ref tag) = x.class {
+                    tags.push(tag.to_owned());
+                    continue;
+                } else if tags.is_empty() {
+                    bail!("expected tag, found `{:?}`", x)
+                } else {
+                    status = GuardParser::Begin;
+                }
+            }
             match status {
                 GuardParser::Begin => match x.class {
                     Token::Keyword(ref kw) if kw == ":guard" => status = GuardParser::Guard,
@@ -79,7 +92,16 @@ fn parse_defconstraint<I: Iterator<Item = Result<AstNode>>>(
                     Token::Keyword(ref kw) if kw == ":perspective" => {
                         status = GuardParser::Perspective
                     }
-                    _ => bail!("expected :guard, :domain or :perspective, found `{:?}`", x),
+                    Token::Keyword(ref kw) if kw == ":tags" => {
+                        if !tags.is_empty() {
+                            bail!("tags already defined: `{}`", tags.join(" "))
+                        }
+                        status = GuardParser::Tags
+                    }
+                    _ => bail!(
+                        "expected :guard, :domain, :perspective or :tags, found `{:?}`",
+                        x
+                    ),
                 },
                 GuardParser::Guard => {
                     if guard.is_some() {
@@ -109,17 +131,20 @@ fn parse_defconstraint<I: Iterator<Item = Result<AstNode>>>(
                         status = GuardParser::Begin;
                     }
                 }
+                GuardParser::Tags => unreachable!(),
             }
         }
 
         match status {
             GuardParser::Begin => {}
+            GuardParser::Tags if !tags.is_empty() => {}
+            GuardParser::Tags => bail!("expected tags, found nothing"),
             GuardParser::Guard => bail!("expected guard expression, found nothing"),
             GuardParser::Domain => bail!("expected domain value, found nothing"),
             GuardParser::Perspective => bail!("expected perspective name, found nothing"),
         }
 
-        (domain, guard, perspective)
+        (domain, guard, perspective, tags)
     };
 
     let body = Box::new(
@@ -139,6 +164,8 @@ fn parse_defconstraint<I: Iterator<Item = Result<AstNode>>>(
             guard,
             perspective,
             body,
+            tags,
+            doc: None,
         },
         src,
         lc,
@@ -187,6 +214,7 @@ struct ColumnAttributes {
     t: OnceCell<Magma>,
     must_prove: bool,
     range: OnceCell<Box<Domain<AstNode>>>,
+    inner_range: OnceCell<Box<Domain<AstNode>>>,
     padding_value: OnceCell<i64>,
     base: OnceCell<Base>,
     computation: Option<AstNode>,
@@ -223,7 +251,7 @@ fn parse_column_attributes(source: AstNode) -> Result<ColumnAttributes> {
         Base,
     }
     let re_type = regex_lite::Regex::new(
-        r"^:(?<RawMagma>i(?<Integer>\d+)|[a-z]+)?(@(?<Conditioning>bool|loob))?(?<Proven>@prove)?$",
+        r"^:(?<RawMagma>[iu](?<Integer>\d+)|[a-z]+)?(@(?<Conditioning>bool|loob))?(?<Proven>@prove)?$",
     )?;
     let mut attributes = ColumnAttributes::default();
     let mut state = ColumnParser::Begin;
@@ -259,20 +287,31 @@ fn parse_column_attributes(source: AstNode) -> Result<ColumnAttributes> {
                         ":display" => ColumnParser::Base,
                         _ => {
                             if let Some(caps) = re_type.captures(kw) {
+                                // :bool and :boolean are shorthands for :binary@bool
+                                let is_boolean = caps
+                                    .name("RawMagma")
+                                    .is_some_and(|s| matches!(s.as_str(), "bool" | "boolean"));
                                 let raw_magma = if let Some(integer) = caps.name("Integer") {
                                     let bit_size = integer.as_str().parse::<usize>().unwrap();
                                     if bit_size > crate::constants::FIELD_BITSIZE {
                                         panic!("Not yet :(");
                                     }
                                     RawMagma::Integer(bit_size)
+                                } else if is_boolean {
+                                    RawMagma::Binary
                                 } else {
                                     caps.name("RawMagma")
                                         .map_or(Ok(RawMagma::Native), |s| s.as_str().try_into())?
                                 };
 
-                                let conditioning = caps
-                                    .name("Conditioning")
-                                    .map_or(Ok(Conditioning::None), |s| s.as_str().try_into())?;
+                                let conditioning = caps.name("Conditioning").map_or(
+                                    Ok(if is_boolean {
+                                        Conditioning::Boolean
+                                    } else {
+                                        Conditioning::None
+                                    }),
+                                    |s| s.as_str().try_into(),
+                                )?;
 
                                 let must_prove = caps.name("Proven").is_some();
 
@@ -295,9 +334,15 @@ fn parse_column_attributes(source: AstNode) -> Result<ColumnAttributes> {
                         }
                     }
                 }
-                // A range alone treated as if it were preceded by :array
+                // A range alone treated as if it were preceded by :array; a
+                // second one makes the array two-dimensional
                 Token::Domain(ref _range) => {
-                    attributes.range.set(_range.to_owned()).map_err(|_| {
+                    let target = if attributes.range.get().is_none() {
+                        &attributes.range
+                    } else {
+                        &attributes.inner_range
+                    };
+                    target.set(_range.to_owned()).map_err(|_| {
                         anyhow!(
                             "trying to redefine column {} of type {:?} as {:?}",
                             attributes.name,
@@ -368,13 +413,81 @@ fn parse_column_attributes(source: AstNode) -> Result<ColumnAttributes> {
     Ok(attributes)
 }
 
+/// The part of `src`, starting at `origin`, preceding the position `lc`
+fn preceding(src: &str, origin: LinCol, lc: LinCol) -> &str {
+    let (line, col) = if lc.0 == origin.0 {
+        (0, lc.1.saturating_sub(origin.1))
+    } else {
+        (lc.0 - origin.0, lc.1 - 1)
+    };
+    let line_start = src
+        .split_inclusive('\n')
+        .take(line)
+        .map(str::len)
+        .sum::<usize>();
+    let end = src[line_start..]
+        .char_indices()
+        .nth(col)
+        .map(|(i, _)| line_start + i)
+        .unwrap_or(src.len());
+    &src[..end]
+}
+
+/// The `;;` comment lines directly above a definition, if the definition
+/// starts its own line; `before` is the source text preceding it.
+fn doc_comment(before: &str) -> Option<String> {
+    let mut lines = before.rsplit('\n');
+    if !lines.next()?.trim().is_empty() {
+        return None;
+    }
+    let doc = lines
+        .map_while(|l| {
+            l.trim()
+                .strip_prefix(";;")
+                .filter(|l| !l.starts_with(';'))
+                .map(str::trim)
+        })
+        .collect::<Vec<_>>();
+    if doc.is_empty() {
+        None
+    } else {
+        Some(doc.into_iter().rev().join("\n"))
+    }
+}
+
 fn parse_defcolumns<I: Iterator<Item = Result<AstNode>>>(
     tokens: I,
     lc: (usize, usize),
     src: String,
 ) -> Result<AstNode> {
+    // A type keyword, e.g. in `(defcolumns :boolean A B)`, applies to all the
+    // bare column names following it, until the next one
+    let mut group_type: Option<AstNode> = None;
+    let mut dangling_type = false;
     // A columns definition is a list of column definition
     let columns = tokens
+        .filter_map(|c| match c {
+            Ok(
+                t @ AstNode {
+                    class: Token::Keyword(_),
+                    ..
+                },
+            ) => {
+                group_type = Some(t);
+                dangling_type = true;
+                None
+            }
+            c => {
+                dangling_type = false;
+                Some(c.map(|c| match &group_type {
+                    Some(t) if c.is_symbol() => AstNode {
+                        class: Token::List(vec![c.clone(), t.clone()]),
+                        ..c
+                    },
+                    _ => c,
+                }))
+            }
+        })
         .map(|c| {
             c.and_then(|c| {
                 let column_attributes = parse_column_attributes(c.clone())?;
@@ -393,8 +506,10 @@ fn parse_defcolumns<I: Iterator<Item = Result<AstNode>>>(
                             ),
                             padding_value: column_attributes.padding_value.get().cloned(),
                             domain: range.clone(),
+                            inner_domain: column_attributes.inner_range.get().cloned(),
                             must_prove: column_attributes.must_prove,
                             base,
+                            doc: doc_comment(preceding(&src, lc, c.lc)),
                         }
                     } else {
                         Token::DefColumn {
@@ -413,6 +528,7 @@ fn parse_defcolumns<I: Iterator<Item = Result<AstNode>>>(
                             padding_value: column_attributes.padding_value.get().cloned(),
                             must_prove: column_attributes.must_prove,
                             base,
+                            doc: doc_comment(preceding(&src, lc, c.lc)),
                         }
                     },
                     lc: c.lc,
@@ -421,7 +537,13 @@ fn parse_defcolumns<I: Iterator<Item = Result<AstNode>>>(
             })
         })
         .collect::<Result<Vec<_>>>()
-        .with_context(|| errors::parser::make_src_error(&src, lc))?;
+        .map_err(|err| errors::parser::locate(err, &src, lc))?;
+    if dangling_type {
+        bail!(
+            "type {} does not apply to any column",
+            group_type.unwrap().to_string().red().bold()
+        )
+    }
 
     Ok(AstNode {
         class: Token::DefColumns(columns),
@@ -534,15 +656,36 @@ fn parse_definition(pair: Pair<Rule>) -> Result<AstNode> {
                 .as_list()
                 .with_context(|| anyhow!("invalid function declaration"))?
                 .to_vec()
-                .into_iter();
+                .into_iter()
+                .peekable();
 
             // Try to parse the function name, bail out if it fails...
-            let function_name = parse_typed_symbols(
+            let mut function_name = parse_typed_symbols(
                 decl.next()
                     .with_context(|| anyhow!("missing function name"))?,
             )
             .with_context(|| anyhow!("invalid function declaration"))?;
 
+            // ...then the return type, that may also directly follow the
+            // function name, e.g. (defun (f :binary a b) ...)
+            while let Some(AstNode {
+                class: Token::Keyword(k),
+                ..
+            }) = decl.peek()
+            {
+                if k == ":nowarn" || k == ":force" {
+                    function_name.force = true;
+                } else if function_name.t.is_some() {
+                    bail!(
+                        "return type already declared for {}",
+                        function_name.name.bold().yellow()
+                    )
+                } else {
+                    function_name.t = Some(Type::Any(Magma::try_from(k.as_str())?));
+                }
+                decl.next();
+            }
+
             // ...then parse the arguments, bailing out on failure
             let (args, in_types): (Vec<String>, Vec<Type>) = decl
                 .map(parse_typed_symbols)
@@ -574,6 +717,7 @@ fn parse_definition(pair: Pair<Rule>) -> Result<AstNode> {
                         out_type: function_name.t,
                         body,
                         force: function_name.force,
+                        doc: None,
                     }
                 } else {
                     Token::Defpurefun {
@@ -583,6 +727,7 @@ fn parse_definition(pair: Pair<Rule>) -> Result<AstNode> {
                         out_type: function_name.t,
                         body,
                         force: function_name.force,
+                        doc: None,
                     }
                 },
                 src,
@@ -647,6 +792,76 @@ fn parse_definition(pair: Pair<Rule>) -> Result<AstNode> {
                 lc,
             })
         }
+        "defpadding" => {
+            let column = tokens
+                .next()
+                .with_context(|| anyhow!("expected column name"))??
+                .as_symbol()?
+                .to_owned();
+
+            let value = tokens
+                .next()
+                .with_context(|| anyhow!("missing padding value"))??
+                .as_i64()?;
+
+            Ok(AstNode {
+                class: Token::DefPadding(column, value),
+                src,
+                lc,
+            })
+        }
+        "deftest" => {
+            let name = tokens
+                .next()
+                .with_context(|| anyhow!("expected test name"))??
+                .as_symbol()?
+                .to_owned();
+
+            let inputs = tokens
+                .next()
+                .with_context(|| anyhow!("missing test inputs"))??
+                .as_list()?
+                .iter()
+                .map(|column| {
+                    let column = column.as_list()?;
+                    let name = column
+                        .first()
+                        .ok_or_else(|| anyhow!("missing column name"))?
+                        .as_symbol()?
+                        .to_owned();
+                    let values = column[1..]
+                        .iter()
+                        .map(|x| match &x.class {
+                            Token::Value(x) => Ok(x.clone()),
+                            _ => bail!("expected a value for {}, found {}", name, x.src),
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok((name, values))
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let failing = tokens
+                .next()
+                .with_context(|| anyhow!("missing expected failing constraints"))??
+                .as_list()?
+                .iter()
+                .map(|c| c.as_symbol().map(str::to_owned).map_err(Into::into))
+                .collect::<Result<Vec<_>>>()?;
+
+            if tokens.next().is_some() {
+                bail!("too many arguments in test {}", name)
+            }
+
+            Ok(AstNode {
+                class: Token::DefTest {
+                    name,
+                    inputs,
+                    failing,
+                },
+                src,
+                lc,
+            })
+        }
         "deflookup" => {
             let name = tokens
                 .next()
@@ -666,6 +881,29 @@ fn parse_definition(pair: Pair<Rule>) -> Result<AstNode> {
                 .as_list()?
                 .to_vec();
 
+            // (deflookup name (columns...) (file "table.csv")) looks the
+            // columns up in a fixed table rather than in other columns
+            if let [AstNode {
+                class: Token::Symbol(verb),
+                ..
+            }, AstNode {
+                class: Token::Text(file),
+                ..
+            }] = included.as_slice()
+            {
+                if verb == "file" {
+                    return Ok(AstNode {
+                        class: Token::DefLookupTable {
+                            name,
+                            columns: including,
+                            file: file.to_owned(),
+                        },
+                        src,
+                        lc,
+                    });
+                }
+            }
+
             Ok(AstNode {
                 class: Token::DefLookup {
                     name,
@@ -694,17 +932,42 @@ fn parse_definition(pair: Pair<Rule>) -> Result<AstNode> {
                 .with_context(|| anyhow!("missing source columns"))??
                 .as_list()?
                 .to_vec();
+            let parse_sign = |s: &str| {
+                if s == "+" || s == "↓" {
+                    Some(true)
+                } else if s == "-" || s == "↑" {
+                    Some(false)
+                } else {
+                    None
+                }
+            };
             for from_w_sign in froms_with_sign {
+                // the sign may be glued to the column, e.g. `-A`
+                if let Token::Symbol(s) = &from_w_sign.class {
+                    if let Some((sign, column)) = s
+                        .chars()
+                        .next()
+                        .and_then(|c| Some((parse_sign(&c.to_string())?, &s[c.len_utf8()..])))
+                        .filter(|(_, column)| !column.is_empty())
+                    {
+                        if !ordering_ongoing {
+                            bail!(
+                                "found sorting column {} after non-sorting column",
+                                from_w_sign.src
+                            )
+                        }
+                        signs.push(sign);
+                        from.push(AstNode {
+                            class: Token::Symbol(column.to_owned()),
+                            lc: from_w_sign.lc,
+                            src: column.to_owned(),
+                        });
+                        continue;
+                    }
+                }
                 if let Result::Ok(list) = from_w_sign.as_list() {
                     if let Some(s) = list.get(0).and_then(|a| a.as_symbol().ok()) {
-                        let sign = if s == "+" || s == "↓" {
-                            Some(true)
-                        } else if s == "-" || s == "↑" {
-                            Some(false)
-                        } else {
-                            None
-                        };
-                        if let Some(sign) = sign {
+                        if let Some(sign) = parse_sign(s) {
                             if !ordering_ongoing {
                                 bail!(
                                     "found sorting column {} after non-sorting column",
@@ -778,7 +1041,17 @@ fn rec_parse(pair: Pair<Rule>) -> Result<AstNode> {
     match pair.as_rule() {
         Rule::expr => rec_parse(pair.into_inner().next().unwrap()),
         Rule::toplevel => {
-            parse_definition(pair).with_context(|| errors::parser::make_src_error(&src, lc))
+            let span = pair.as_span();
+            let doc = doc_comment(&span.get_input()[..span.start()]);
+            let mut definition =
+                parse_definition(pair).map_err(|err| errors::parser::locate(err, &src, lc))?;
+            if let Token::DefConstraint { doc: d, .. }
+            | Token::Defun { doc: d, .. }
+            | Token::Defpurefun { doc: d, .. } = &mut definition.class
+            {
+                *d = doc;
+            }
+            Ok(definition)
         }
         Rule::sexpr => {
             let args = pair
@@ -793,7 +1066,7 @@ fn rec_parse(pair: Pair<Rule>) -> Result<AstNode> {
                 src,
             })
         }
-        Rule::symbol | Rule::definition_kw => Ok(AstNode {
+        Rule::symbol | Rule::definition_kw | Rule::columns_kw => Ok(AstNode {
             class: Token::Symbol(pair.as_str().to_owned()),
             lc,
             src,
@@ -860,6 +1133,20 @@ fn rec_parse(pair: Pair<Rule>) -> Result<AstNode> {
             src,
             lc,
         }),
+        Rule::string => Ok(AstNode {
+            class: Token::Text(pair.as_str().trim_matches('"').to_owned()),
+            src,
+            lc,
+        }),
+        Rule::values => Ok(AstNode {
+            class: Token::Values(
+                pair.into_inner()
+                    .map(rec_parse)
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            lc,
+            src,
+        }),
         Rule::nth => {
             let mut args = pair
                 .into_inner()
@@ -19,6 +19,7 @@ fn reduce(e: &AstNode, ctx: &mut Scope) -> Result<()> {
             in_types,
             out_type,
             force,
+            ..
         } => {
             let module_name = ctx.module();
             ctx.insert_function(
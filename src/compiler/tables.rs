@@ -1,8 +1,11 @@
-use super::{generator::Function, ColumnRef, Expression, Magma, Node, Type};
+use super::{
+    generator::{Function, LookupTable},
+    ColumnRef, Expression, Magma, Node, Type,
+};
 use crate::{
     column::Computation,
-    compiler::{generator::FunctionClass, Builtin, Form, Intrinsic},
-    errors::symbols,
+    compiler::{generator::FunctionClass, Builtin, Domain, Form, Intrinsic},
+    errors::CompileError,
     structs::{Handle, PERSPECTIVE_SEPARATOR},
 };
 use anyhow::*;
@@ -40,6 +43,10 @@ lazy_static::lazy_static! {
             handle: Handle::new(super::MAIN_MODULE, "let"),
             class: FunctionClass::Form(Form::Let),
         },
+        "let-column" => Function {
+            handle: Handle::new(super::MAIN_MODULE, "let-column"),
+            class: FunctionClass::Form(Form::LetColumn),
+        },
         "reduce" => Function {
             handle: Handle::new(super::MAIN_MODULE, "reduce"),
             class: FunctionClass::Form(Form::Reduce)
@@ -50,6 +57,18 @@ lazy_static::lazy_static! {
             handle: Handle::new(super::MAIN_MODULE, Builtin::Len.to_string()),
             class: FunctionClass::Builtin(Builtin::Len),
         },
+        "nth" => Function {
+            handle: Handle::new(super::MAIN_MODULE, Builtin::Nth.to_string()),
+            class: FunctionClass::Builtin(Builtin::Nth),
+        },
+        "sum" => Function {
+            handle: Handle::new(super::MAIN_MODULE, Builtin::Sum.to_string()),
+            class: FunctionClass::Builtin(Builtin::Sum),
+        },
+        "prod" => Function {
+            handle: Handle::new(super::MAIN_MODULE, Builtin::Prod.to_string()),
+            class: FunctionClass::Builtin(Builtin::Prod),
+        },
         "shift" => Function{
             handle: Handle::new(super::MAIN_MODULE, "shift"),
             class: FunctionClass::Builtin(Builtin::Shift),
@@ -62,6 +81,38 @@ lazy_static::lazy_static! {
             handle: Handle::new(super::MAIN_MODULE, "if"),
             class: FunctionClass::Builtin(Builtin::If)
         },
+        "<" => Function {
+            handle: Handle::new(super::MAIN_MODULE, Builtin::Lt.to_string()),
+            class: FunctionClass::Builtin(Builtin::Lt),
+        },
+        "<=" => Function {
+            handle: Handle::new(super::MAIN_MODULE, Builtin::Le.to_string()),
+            class: FunctionClass::Builtin(Builtin::Le),
+        },
+        ">" => Function {
+            handle: Handle::new(super::MAIN_MODULE, Builtin::Gt.to_string()),
+            class: FunctionClass::Builtin(Builtin::Gt),
+        },
+        "!=0?" => Function {
+            handle: Handle::new(super::MAIN_MODULE, Builtin::IsNotZero.to_string()),
+            class: FunctionClass::Builtin(Builtin::IsNotZero),
+        },
+        ">=" => Function {
+            handle: Handle::new(super::MAIN_MODULE, Builtin::Ge.to_string()),
+            class: FunctionClass::Builtin(Builtin::Ge),
+        },
+        "as-integer" => Function {
+            handle: Handle::new(super::MAIN_MODULE, Builtin::AsInteger.to_string()),
+            class: FunctionClass::Builtin(Builtin::AsInteger),
+        },
+        "as-bool" => Function {
+            handle: Handle::new(super::MAIN_MODULE, Builtin::AsBool.to_string()),
+            class: FunctionClass::Builtin(Builtin::AsBool),
+        },
+        "interleave" => Function {
+            handle: Handle::new(super::MAIN_MODULE, Builtin::Interleave.to_string()),
+            class: FunctionClass::Builtin(Builtin::Interleave),
+        },
 
         // Intrinsics
         "+" => Function {
@@ -191,10 +242,33 @@ pub enum Symbol {
     Final(Node, bool),
 }
 
+/// The constraint ensuring that an expression cast with `as-bool` is either 0
+/// or 1, where it is cast
+#[derive(Debug, Clone)]
+pub struct Booleanity {
+    /// the name of the constraint, to be made unique
+    pub handle: Handle,
+    /// `x(1 - x)`, under the conditions of the cast
+    pub expr: Node,
+    /// the domain of the constraint where `x` is cast, if any
+    pub domain: Option<Domain<isize>>,
+    /// if false, the booleanity is only verified when checking a trace, rather
+    /// than exported with the constraints
+    pub exported: bool,
+}
+
 #[derive(Default)]
 pub struct GlobalData {
     computations: ComputationTable,
     pub perspectives: HashMap<String, HashMap<String, Option<Node>>>, // module -> {Perspectives}
+    /// the expressions cast with `as-bool`, to be constrained to 0 or 1
+    booleanities: Vec<Booleanity>,
+    /// the conditions of the `if` branches being reduced, innermost last
+    guards: Vec<(Intrinsic, Node)>,
+    /// the user-defined functions called at least once
+    called: HashSet<Handle>,
+    /// the fixed tables declared by `deflookup`, to be loaded from files
+    lookup_tables: Vec<LookupTable>,
 }
 impl GlobalData {
     pub fn set_perspective_trigger(
@@ -279,6 +353,7 @@ impl Scope {
                     .map(|(k, f)| (k.to_string(), f.clone()))
                     .collect(),
                 symbols: Default::default(),
+                declared: Default::default(),
             }),
         );
         tree.set_root(root);
@@ -309,6 +384,7 @@ impl Scope {
                         constraints: Default::default(),
                         funcs: Default::default(),
                         symbols: Default::default(),
+                        declared: Default::default(),
                         perspective: None,
                     }),
                 );
@@ -341,6 +417,7 @@ impl Scope {
                         constraints: Default::default(),
                         funcs: Default::default(),
                         symbols: Default::default(),
+                        declared: Default::default(),
                         perspective: None,
                     }),
                 );
@@ -352,6 +429,16 @@ impl Scope {
         }
     }
 
+    /// Return the top-level scope of the module `name`, the prelude
+    /// being the root scope itself.
+    pub fn module_scope(&mut self, name: &str) -> Result<Scope> {
+        if name == super::MAIN_MODULE {
+            Ok(self.root())
+        } else {
+            self.switch_to_module(name)
+        }
+    }
+
     pub fn jump_in(&mut self, name: &str) -> Result<Scope> {
         let maybe_child = self.tree.borrow().find_child(self.id, |n| n.name == name);
         match maybe_child {
@@ -421,6 +508,92 @@ impl Scope {
         self.tree.borrow().metadata().computations.clone()
     }
 
+    pub fn booleanities(&self) -> Vec<Booleanity> {
+        self.tree.borrow().metadata().booleanities.clone()
+    }
+
+    /// The number of booleanities registered so far
+    pub fn booleanities_count(&self) -> usize {
+        self.tree.borrow().metadata().booleanities.len()
+    }
+
+    /// Put the booleanities registered in `range` under `guard`, and restrict
+    /// them to `domain`; this is used to carry the guard, perspective and
+    /// domain of a constraint to the casts of its body.
+    pub fn guard_booleanities(
+        &self,
+        range: std::ops::Range<usize>,
+        guard: impl Fn(Node) -> Result<Node>,
+        domain: &Option<Domain<isize>>,
+    ) -> Result<()> {
+        let mut tree = self.tree.borrow_mut();
+        for b in tree.metadata_mut().booleanities[range].iter_mut() {
+            b.expr = guard(b.expr.clone())?;
+            b.domain = domain.clone();
+        }
+        Ok(())
+    }
+
+    /// Enter a branch of an `if`, taken when `Intrinsic::call(&[cond, ...])`
+    /// selects its second argument
+    pub fn push_guard(&self, guard: Intrinsic, cond: Node) {
+        self.tree
+            .borrow_mut()
+            .metadata_mut()
+            .guards
+            .push((guard, cond));
+    }
+
+    pub fn pop_guard(&self) {
+        self.tree.borrow_mut().metadata_mut().guards.pop();
+    }
+
+    pub fn lookup_tables(&self) -> Vec<LookupTable> {
+        self.tree.borrow().metadata().lookup_tables.clone()
+    }
+
+    /// Register a fixed table, whose columns are filled from its file when
+    /// computing the trace
+    pub fn insert_lookup_table(&self, table: LookupTable) {
+        self.tree
+            .borrow_mut()
+            .metadata_mut()
+            .lookup_tables
+            .push(table);
+    }
+
+    /// Register the constraint `handle` ensuring that `e` is either 0 or 1
+    /// under the conditions of the `if` branches it is in
+    pub fn insert_booleanity(&self, handle: Handle, e: Node, exported: bool) -> Result<()> {
+        let mut tree = self.tree.borrow_mut();
+        let data = tree.metadata_mut();
+        let mut expr =
+            Intrinsic::Mul.call(&[e.clone(), Intrinsic::Sub.call(&[Node::from_isize(1), e])?])?;
+        for (guard, cond) in data.guards.iter().rev() {
+            expr = guard.call(&[cond.clone(), expr])?;
+        }
+        data.booleanities.push(Booleanity {
+            handle,
+            expr,
+            domain: None,
+            exported,
+        });
+        Ok(())
+    }
+
+    /// Record that the user-defined function `f` has been called
+    pub fn mark_called(&self, f: &Handle) {
+        self.tree
+            .borrow_mut()
+            .metadata_mut()
+            .called
+            .insert(f.clone());
+    }
+
+    pub fn is_called(&self, f: &Handle) -> bool {
+        self.tree.borrow().metadata().called.contains(f)
+    }
+
     pub fn insert_many_computations(
         &self,
         targets: &[ColumnRef],
@@ -475,20 +648,29 @@ impl Scope {
         }
 
         let module = data!(self).name.clone();
-        for (handle, symbol) in data_mut!(self)
+        // symbols are visited in the order they have been declared, so that
+        // everything built from them (e.g. the column set) follows the
+        // source, and is laid out identically across runs
+        let tree = self.tree.clone();
+        let mut tree = tree.borrow_mut();
+        let table = tree[self.id].unwrap_data_mut();
+        let declared = &table.declared;
+        for (handle, symbol) in table
             .symbols
             .iter_mut()
+            .sorted_by_key(|(k, _)| declared[k.as_str()])
             .map(|(k, v)| (Handle::new(&module, k), v))
         {
             f(handle, symbol)?;
         }
+        drop(tree);
         for c in self.children().iter_mut() {
             c.visit_mut::<T>(f)?;
         }
         Ok(())
     }
 
-    pub fn resolve_symbol(&mut self, name: &str, used: bool) -> Result<Node, symbols::Error> {
+    pub fn resolve_symbol(&mut self, name: &str, used: bool) -> Result<Node, CompileError> {
         let module = self.module();
         let global = data!(self).global;
 
@@ -496,7 +678,7 @@ impl Scope {
             if global {
                 self.resolve_symbol_with_path(name, used)
             } else {
-                Err(symbols::Error::NotAGlobalScope(
+                Err(CompileError::NotAGlobalScope(
                     name.split('.').next().unwrap().to_owned(),
                 ))
             }
@@ -504,10 +686,10 @@ impl Scope {
             let mut s = name.split(PERSPECTIVE_SEPARATOR);
             let perspective = s
                 .next()
-                .ok_or_else(|| symbols::Error::MissingColumn(name.into()))?;
+                .ok_or_else(|| CompileError::MissingColumn(name.into()))?;
             let name = s
                 .next()
-                .ok_or_else(|| symbols::Error::MissingPerspective(name.into()))?;
+                .ok_or_else(|| CompileError::MissingPerspective(name.into()))?;
             Self::_resolve_symbol_in_perspective(
                 self.id,
                 &mut self.tree.borrow_mut(),
@@ -516,11 +698,11 @@ impl Scope {
                 used,
             )
             .map_err(|e| match e {
-                symbols::Error::SymbolNotFound(s, _, _) => {
-                    symbols::Error::SymbolNotFound(s, module, Some(perspective.into()))
+                CompileError::UnknownSymbol(s, _, _) => {
+                    CompileError::UnknownSymbol(s, module, Some(perspective.into()))
                 }
-                symbols::Error::PerspectiveNotFound(p, _) => {
-                    symbols::Error::PerspectiveNotFound(p, module)
+                CompileError::PerspectiveNotFound(p, _) => {
+                    CompileError::PerspectiveNotFound(p, module)
                 }
                 _ => unreachable!(),
             })
@@ -534,7 +716,7 @@ impl Scope {
                 false,
                 used,
             )
-            .map_err(|_| symbols::Error::SymbolNotFound(name.to_owned(), module, None))
+            .map_err(|_| CompileError::UnknownSymbol(name.to_owned(), module, None))
         }
     }
 
@@ -542,7 +724,7 @@ impl Scope {
         &mut self,
         name: &str,
         used: bool,
-    ) -> Result<Node, symbols::Error> {
+    ) -> Result<Node, CompileError> {
         let components = name.split('.').collect::<Vec<_>>();
         self.root()._resolve_symbol_with_path(&components, used)
     }
@@ -555,9 +737,9 @@ impl Scope {
         absolute_path: bool,
         pure: bool,
         used: bool,
-    ) -> Result<Node, symbols::Error> {
+    ) -> Result<Node, CompileError> {
         if ax.contains(name) {
-            Err(symbols::Error::CircularDefinition(name.to_string()))
+            Err(CompileError::CircularDefinition(name.to_string()))
         } else {
             ax.insert(name.to_owned());
             match tree[n].unwrap_data_mut().symbols.get_mut(name) {
@@ -567,7 +749,7 @@ impl Scope {
                 }
                 Some(Symbol::Final(exp, ref mut visited)) => {
                     if pure && !matches!(exp.e(), Expression::Const(..)) {
-                        Err(symbols::Error::UnavailableInPureContext(exp.to_string()))
+                        Err(CompileError::UnavailableInPureContext(exp.to_string()))
                     } else {
                         if used {
                             *visited = true;
@@ -577,10 +759,10 @@ impl Scope {
                 }
                 None => {
                     if absolute_path {
-                        Err(symbols::Error::SymbolNotFound(name.into(), "".into(), None))
+                        Err(CompileError::UnknownSymbol(name.into(), "".into(), None))
                     } else {
                         tree.parent(n).map_or(
-                            Err(symbols::Error::SymbolNotFound(name.into(), "".into(), None)),
+                            Err(CompileError::UnknownSymbol(name.into(), "".into(), None)),
                             |parent| {
                                 Self::_resolve_symbol(
                                     parent,
@@ -605,7 +787,7 @@ impl Scope {
         name: &str,
         perspective: &str,
         used: bool,
-    ) -> Result<Node, symbols::Error> {
+    ) -> Result<Node, CompileError> {
         match tree.find_child(n, |o| {
             o.perspective
                 .as_ref()
@@ -614,7 +796,7 @@ impl Scope {
         }) {
             Some(o) => Self::_resolve_symbol(o, tree, name, &mut HashSet::new(), true, false, used),
             None => tree.parent(n).map_or(
-                Err(symbols::Error::PerspectiveNotFound(
+                Err(CompileError::PerspectiveNotFound(
                     perspective.into(),
                     tree[n].data().unwrap().module.clone(),
                 )),
@@ -629,7 +811,7 @@ impl Scope {
         &mut self,
         path: &[&str],
         used: bool,
-    ) -> Result<Node, symbols::Error> {
+    ) -> Result<Node, CompileError> {
         if path.len() == 1 {
             self.resolve_symbol(path[0], used)
         } else {
@@ -638,7 +820,7 @@ impl Scope {
                     return self.at(c.id)._resolve_symbol_with_path(&path[1..], used);
                 }
             }
-            return Err(symbols::Error::ModuleNotFound(
+            return Err(CompileError::ModuleNotFound(
                 path.join("."),
                 self.tree.borrow()[self.id].unwrap_data().name.to_string(),
             ));
@@ -653,7 +835,7 @@ impl Scope {
         ax: &mut HashSet<String>,
     ) -> Result<()> {
         if ax.contains(name) {
-            Err(anyhow!(symbols::Error::CircularDefinition(name.to_owned())))
+            Err(anyhow!(CompileError::CircularDefinition(name.to_owned())))
         } else {
             ax.insert(name.to_owned());
             match tree[n].unwrap_data_mut().symbols.get_mut(name) {
@@ -666,7 +848,7 @@ impl Scope {
                     Ok(())
                 }
                 None => tree.parent(n).map_or(
-                    Err(anyhow!(symbols::Error::SymbolNotFound(
+                    Err(anyhow!(CompileError::UnknownSymbol(
                         name.to_owned(),
                         tree[n].unwrap_data().name.to_owned(),
                         None,
@@ -679,14 +861,14 @@ impl Scope {
 
     fn _resolve_function(&self, name: &str, ax: &mut HashSet<String>) -> Result<Function> {
         if ax.contains(name) {
-            bail!(symbols::Error::CircularDefinition(name.to_owned()))
+            bail!(CompileError::CircularDefinition(name.to_owned()))
         } else {
             ax.insert(name.to_owned());
             match data!(self).funcs.get(name) {
                 Some(Function {
                     class: FunctionClass::Alias(ref to),
                     ..
-                }) => self.resolve_function(to),
+                }) => self._resolve_function(to, ax),
                 Some(f) => Ok(f.to_owned()),
                 None => self
                     .parent()
@@ -713,14 +895,12 @@ impl Scope {
             bail!("names starting with `#` are reserved for intenal usage")
         }
         if data!(self).symbols.contains_key(name) {
-            bail!(symbols::Error::SymbolAlreadyExists(
+            bail!(CompileError::SymbolAlreadyExists(
                 name.to_owned(),
                 data!(self).name.to_owned()
             ))
         } else {
-            data_mut!(self)
-                .symbols
-                .insert(name.to_owned(), Symbol::Final(e, false));
+            data_mut!(self).declare(name, Symbol::Final(e, false));
             Ok(())
         }
     }
@@ -739,7 +919,7 @@ impl Scope {
                             .add_specialization(new_specialization)
                             .with_context(|| anyhow!("while defining {}", name.yellow())),
                         _ => {
-                            bail!(symbols::Error::FunctionAlreadyExists(
+                            bail!(CompileError::FunctionAlreadyExists(
                                 name.to_owned(),
                                 my_name,
                             ))
@@ -753,7 +933,7 @@ impl Scope {
             }
             _ => {
                 if data!(self).funcs.contains_key(name) {
-                    bail!(symbols::Error::FunctionAlreadyExists(
+                    bail!(CompileError::FunctionAlreadyExists(
                         name.to_owned(),
                         data!(self).name.to_owned()
                     ))
@@ -767,21 +947,19 @@ impl Scope {
 
     pub fn insert_alias(&mut self, from: &str, to: &str) -> Result<()> {
         if data!(self).symbols.contains_key(from) {
-            bail!(symbols::Error::SymbolAlreadyExists(
+            bail!(CompileError::SymbolAlreadyExists(
                 from.to_owned(),
                 data!(self).name.to_owned()
             ))
         } else {
-            data_mut!(self)
-                .symbols
-                .insert(from.to_owned(), Symbol::Alias(to.to_owned()));
+            data_mut!(self).declare(from, Symbol::Alias(to.to_owned()));
             Ok(())
         }
     }
 
     pub fn insert_funalias(&mut self, from: &str, to: &str) -> Result<()> {
         if data!(self).funcs.contains_key(from) {
-            bail!(symbols::Error::AliasAlreadyExists(
+            bail!(CompileError::AliasAlreadyExists(
                 from.to_owned(),
                 to.to_owned()
             ))
@@ -818,19 +996,41 @@ impl Scope {
         } else {
             Type::Scalar(Magma::native())
         };
+        self.insert_final(
+            name,
+            Node::from_expr(Expression::Const(value.try_into().unwrap())).with_type(t),
+            replace,
+        )
+    }
+
+    /// Insert a constant list of values, that can be indexed with `nth`
+    pub fn insert_constant_list(
+        &mut self,
+        name: &str,
+        values: Vec<BigInt>,
+        replace: bool,
+    ) -> Result<()> {
+        let m = if values.iter().all(|x| Zero::is_zero(x) || One::is_one(x)) {
+            Magma::binary()
+        } else {
+            Magma::native()
+        };
+        let xs = values.into_iter().map(Node::from_bigint).collect();
+        self.insert_final(
+            name,
+            Node::from_expr(Expression::List(xs)).with_type(Type::List(m)),
+            replace,
+        )
+    }
+
+    fn insert_final(&mut self, name: &str, value: Node, replace: bool) -> Result<()> {
         if data!(self).symbols.contains_key(name) && !replace {
-            bail!(symbols::Error::SymbolAlreadyExists(
+            bail!(CompileError::SymbolAlreadyExists(
                 name.to_owned(),
                 data!(self).name.to_owned()
             ))
         } else {
-            data_mut!(self).symbols.insert(
-                name.to_owned(),
-                Symbol::Final(
-                    Node::from_expr(Expression::Const(value.try_into().unwrap())).with_type(t),
-                    false,
-                ),
-            );
+            data_mut!(self).declare(name, Symbol::Final(value, false));
             Ok(())
         }
     }
@@ -839,6 +1039,42 @@ impl Scope {
         self.at(self.tree.borrow().root())
     }
 
+    /// Ensure that no chain of symbol or function aliases loops, in this scope
+    /// and all of its descendants.
+    pub fn validate_aliases(&self) -> Result<()> {
+        let (symbol_aliases, function_aliases) = {
+            let tree = self.tree.borrow();
+            let table = tree[self.id].unwrap_data();
+            (
+                table
+                    .symbols
+                    .iter()
+                    .filter_map(|(k, s)| match s {
+                        Symbol::Alias(to) => Some((k.to_owned(), to.to_owned())),
+                        Symbol::Final(..) => None,
+                    })
+                    .collect::<HashMap<_, _>>(),
+                table
+                    .funcs
+                    .iter()
+                    .filter_map(|(k, f)| match &f.class {
+                        FunctionClass::Alias(to) => Some((k.to_owned(), to.to_owned())),
+                        _ => None,
+                    })
+                    .collect::<HashMap<_, _>>(),
+            )
+        };
+        for aliases in [symbol_aliases, function_aliases] {
+            for from in aliases.keys().sorted() {
+                check_alias_chain(from, &aliases)?;
+            }
+        }
+        for c in self.children() {
+            c.validate_aliases()?;
+        }
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub fn print(&self) {
         self.tree.borrow().print(|s| {
@@ -852,6 +1088,20 @@ impl Scope {
     }
 }
 
+/// Follow the chain of `aliases` starting from `from`, and fail with the full
+/// chain if it loops back on itself.
+fn check_alias_chain(from: &str, aliases: &HashMap<String, String>) -> Result<(), CompileError> {
+    let mut chain = vec![from];
+    while let Some(to) = aliases.get(*chain.last().unwrap()) {
+        let looping = chain.contains(&to.as_str());
+        chain.push(to);
+        if looping {
+            return Err(CompileError::CircularDefinition(chain.join(" → ")));
+        }
+    }
+    Result::Ok(())
+}
+
 impl std::clone::Clone for Scope {
     fn clone(&self) -> Self {
         Scope {
@@ -881,4 +1131,14 @@ pub struct SymbolTable {
     constraints: HashSet<String>,
     funcs: HashMap<String, Function>,
     symbols: HashMap<String, Symbol>,
+    // The rank at which each symbol has been declared, so that the columns
+    // can be laid out in the order of the source.
+    declared: HashMap<String, usize>,
+}
+impl SymbolTable {
+    fn declare(&mut self, name: &str, symbol: Symbol) {
+        let rank = self.declared.len();
+        self.declared.entry(name.to_owned()).or_insert(rank);
+        self.symbols.insert(name.to_owned(), symbol);
+    }
 }
@@ -22,8 +22,21 @@ pub enum Type {
     ArrayColumn(Magma),
     List(Magma),
 }
+/// The default format is the compact symbolic notation used in listings; the
+/// alternate one (`{:#}`) spells the type out, e.g. `boolean scalar`, and is
+/// meant for error messages.
 impl std::fmt::Display for Type {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if f.alternate() {
+            return match self {
+                Type::Void => write!(f, "void"),
+                Type::Scalar(m) => write!(f, "{:#} scalar", m),
+                Type::Column(m) => write!(f, "{:#} column", m),
+                Type::Any(m) => write!(f, "{:#} value", m),
+                Type::ArrayColumn(m) => write!(f, "{:#} array column", m),
+                Type::List(m) => write!(f, "list of {:#}", m),
+            };
+        }
         match self {
             Type::Void => write!(f, "∅"),
             Type::Scalar(m) => write!(f, "{}", m),
@@ -135,6 +148,10 @@ impl Type {
         }
     }
 
+    pub fn is_list(&self) -> bool {
+        matches!(self, Type::List(_))
+    }
+
     pub fn is_conditioned(&self) -> bool {
         self.m().is_conditioned()
     }
@@ -602,8 +619,15 @@ impl Magma {
         RawMagma::Any.into()
     }
     pub(crate) fn maxed(&self, other: &Magma) -> Result<Magma> {
+        // raw magmas are compared along the casting rules, so that e.g. a
+        // sized integer is always smaller than a field element
+        let m = if Magma::from(self.m) >= Magma::from(other.m) {
+            self.m
+        } else {
+            other.m
+        };
         Ok(Magma {
-            m: self.m.max(other.m),
+            m,
             c: self.c.max(&other.c)?,
         })
     }
@@ -613,7 +637,7 @@ impl std::convert::TryFrom<&str> for Magma {
 
     fn try_from(s: &str) -> Result<Self, Self::Error> {
         let re_global = regex_lite::Regex::new(
-            r":(?<RawMagma>i(?<Integer>\d+)|[a-z]+)?(@(?<Conditioning>bool|loob))?",
+            r":(?<RawMagma>[iu](?<Integer>\d+)|[a-z]+)?(@(?<Conditioning>bool|loob))?",
         )?;
 
         if let Some(caps) = re_global.captures(s) {
@@ -702,8 +726,33 @@ impl std::cmp::PartialOrd for Magma {
         }
     }
 }
+/// As for [`Type`], the alternate format (`{:#}`) is a readable description,
+/// e.g. `boolean` or `8-bit integer`.
 impl std::fmt::Display for Magma {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if f.alternate() {
+            match self.c {
+                Conditioning::None => {}
+                Conditioning::Boolean => write!(f, "boolean")?,
+                Conditioning::Loobean => write!(f, "loobean")?,
+            };
+            // a conditioned bit is fully described by its conditioning
+            if self.c != Conditioning::None {
+                if self.m == RawMagma::Binary {
+                    return std::fmt::Result::Ok(());
+                }
+                write!(f, " ")?;
+            }
+            return match self.m {
+                RawMagma::None => write!(f, "void"),
+                RawMagma::Binary => write!(f, "binary"),
+                RawMagma::Nibble => write!(f, "nibble"),
+                RawMagma::Byte => write!(f, "byte"),
+                RawMagma::Native => write!(f, "field"),
+                RawMagma::Integer(x) => write!(f, "{}-bit integer", x),
+                RawMagma::Any => write!(f, "any"),
+            };
+        }
         match self.m {
             RawMagma::None => write!(f, "NONE"),
             RawMagma::Binary => write!(f, "𝟙"),
@@ -3,11 +3,14 @@ use itertools::Itertools;
 use log::*;
 use logging_timer::time;
 use owo_colors::OwoColorize;
-use std::{cmp::Ordering, collections::HashSet};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+};
 
 use crate::{
     column::{ColumnSet, Computation, ExoOperation, Value, ValueBacking},
-    compiler::{ColumnRef, ConstraintSet, EvalSettings, Kind, Node},
+    compiler::{ColumnRef, ConstraintSet, EvalCache, EvalSettings, Kind, Node},
     dag::ComputationDag,
     errors::RuntimeError,
     import,
@@ -134,15 +137,30 @@ fn compute_all(cs: &mut ConstraintSet) -> Result<()> {
 
     let mut exo_operations = HashSet::new();
 
-    for processing_slice in jobs.job_slices() {
+    let slices = jobs
+        .job_slices()
+        .into_iter()
+        .map(|processing_slice| {
+            let comps = processing_slice
+                .iter()
+                .filter(|h| !cs.columns.is_skipped(h))
+                .filter_map(|h| cs.computations.computation_idx_for(h))
+                .collect::<HashSet<_>>();
+            (processing_slice, comps)
+        })
+        .collect::<Vec<_>>();
+    let progress = crate::progress::bar(
+        cs.columns.progress,
+        slices.iter().map(|s| s.1.len()).sum(),
+        "computing",
+    );
+
+    for (processing_slice, comps) in slices {
         trace!(
             "Processing computation slice {}",
             processing_slice.iter().join(" ")
         );
-        let comps = processing_slice
-            .iter()
-            .filter_map(|h| cs.computations.computation_idx_for(h))
-            .collect::<HashSet<_>>()
+        let comps = comps
             .iter()
             .map(|i| cs.computations.get(*i).unwrap().to_owned())
             .collect::<Vec<_>>();
@@ -150,7 +168,11 @@ fn compute_all(cs: &mut ConstraintSet) -> Result<()> {
         for r in comps
             .iter()
             // .into_par_iter() // TODO: is that a bottleneck?
-            .filter_map(|comp| apply_computation(cs, comp, &mut exo_operations))
+            .filter_map(|comp| {
+                let r = apply_computation(cs, comp, &mut exo_operations);
+                progress.inc(1);
+                r
+            })
             .collect::<Vec<_>>()
             .into_iter()
         {
@@ -173,6 +195,39 @@ fn compute_all(cs: &mut ConstraintSet) -> Result<()> {
     Ok(())
 }
 
+/// Recompute the columns transitively depending on the `changed` ones, whose
+/// values must already have been updated. All the other computed columns keep
+/// their current values.
+pub(crate) fn recompute_dirty(cs: &mut ConstraintSet, changed: &[Handle]) -> Result<()> {
+    let changed = changed
+        .iter()
+        .map(|h| {
+            cs.columns
+                .column(&h.clone().into())
+                .map(|_| h.clone().into())
+        })
+        .collect::<Result<Vec<ColumnRef>>>()?;
+
+    let dirty = ComputationDag::from_computations(cs.computations.iter()).descendants(&changed);
+    if let Some(h) = dirty.iter().find(|h| {
+        matches!(
+            cs.computations.computation_for(h),
+            Some(Computation::ExoOperation { .. })
+        )
+    }) {
+        bail!(
+            "{} is an exo-operation and can not be recomputed",
+            h.pretty()
+        )
+    }
+
+    for h in dirty.iter() {
+        trace!("Invalidating {}", h.pretty());
+        cs.columns.reset_column(h);
+    }
+    compute_all(cs)
+}
+
 fn ensure_is_computed(h: &ColumnRef, cs: &ConstraintSet) -> Result<()> {
     if !cs.columns.is_computed(h) {
         bail!(err_missing_column(cs.columns.column(h).unwrap()))
@@ -189,30 +244,105 @@ fn compute_interleaved(
         ensure_is_computed(from, cs)?;
     }
 
-    if !froms
+    // the sources may come from different modules, and thus have different
+    // lengths; but they must all be of the same one to be interleaved
+    let lens = froms
         .iter()
-        .map(|h| cs.columns.len(h).unwrap())
-        .collect::<Vec<_>>()
-        .windows(2)
-        .all(|w| w[0] == w[1])
-    {
-        bail!("interleaving columns of incoherent lengths")
+        .map(|h| match cs.columns.len(h) {
+            Some(len) if len > 0 => Ok(len),
+            _ => bail!(RuntimeError::EmptyColumn(cs.handle(h).clone())),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(i) = lens.iter().position(|l| *l != lens[0]) {
+        bail!(
+            "can not interleave {} of length {} with {} of length {} in {}",
+            cs.handle(&froms[0]).pretty(),
+            lens[0],
+            cs.handle(&froms[i]).pretty(),
+            lens[i],
+            cs.handle(target).pretty()
+        )
     }
 
-    let final_len = froms.iter().map(|h| cs.columns.len(h).unwrap()).sum();
     let count = froms.len();
-    let values = (0..final_len)
-        .map(|k| {
+    let multiplier = cs.length_multiplier(&froms[0]);
+    if let Some(from) = froms.iter().find(|f| cs.length_multiplier(f) != multiplier) {
+        bail!(
+            "can not interleave {} of length multiplier {} with {} of length multiplier {} in {}",
+            cs.handle(&froms[0]).pretty(),
+            multiplier,
+            cs.handle(from).pretty(),
+            cs.length_multiplier(from),
+            cs.handle(target).pretty()
+        )
+    }
+    if cs.length_multiplier(target) != count * multiplier {
+        bail!(
+            "{} has a length multiplier of {}, but interleaves {} columns of length multiplier {}",
+            cs.handle(target).pretty(),
+            cs.length_multiplier(target),
+            count,
+            multiplier
+        )
+    }
+
+    // interleavings within a single module keep their historical layout
+    let module = &cs.handle(target).module;
+    if froms.iter().all(|f| &cs.handle(f).module == module) {
+        let final_len = lens.iter().sum();
+        let values = (0..final_len)
+            .map(|k| {
+                let i = k / count;
+                let j = k % count;
+                cs.columns
+                    .get(&froms[j], i as isize, false)
+                    .unwrap()
+                    .clone()
+            })
+            .collect();
+
+        return Ok(vec![(target.to_owned(), ValueBacking::from_vec(values, 0))]);
+    }
+
+    // otherwise, the target lives in its own module, and is spilled as such
+    let spilling = cs.spilling_for_column(target).unwrap_or(0);
+    let values = std::iter::repeat(Value::zero())
+        .take(spilling as usize)
+        .chain((0..count * lens[0]).map(|k| {
             let i = k / count;
             let j = k % count;
-            cs.columns
-                .get(&froms[j], i as isize, false)
-                .unwrap()
-                .clone()
-        })
+            cs.columns.get(&froms[j], i as isize, false).unwrap()
+        }))
         .collect();
 
-    Ok(vec![(target.to_owned(), ValueBacking::from_vec(values, 0))])
+    Ok(vec![(
+        target.to_owned(),
+        ValueBacking::from_vec(values, spilling),
+    )])
+}
+
+/// The rows of `froms`, in the order they appear in the columns sorted from
+/// them
+pub(crate) fn sorting_permutation(
+    cs: &ConstraintSet,
+    froms: &[ColumnRef],
+    signs: &[bool],
+) -> Vec<usize> {
+    let len = cs.columns.len(&froms[0]).unwrap();
+
+    let mut sorted_is = (0..len).collect::<Vec<_>>();
+    // the sort is stable, so that tied rows keep their original order
+    sorted_is.sort_by(|i, j| {
+        for (sign, from) in signs.iter().zip(froms.iter()) {
+            let x_i = cs.columns.get(from, *i as isize, false).unwrap();
+            let x_j = cs.columns.get(from, *j as isize, false).unwrap();
+            if let x @ (Ordering::Greater | Ordering::Less) = x_i.cmp(&x_j) {
+                return if *sign { x } else { x.reverse() };
+            }
+        }
+        Ordering::Equal
+    });
+    sorted_is
 }
 
 fn compute_sorted(
@@ -232,19 +362,7 @@ fn compute_sorted(
     {
         bail!("sorted columns are of incoherent lengths")
     }
-    let len = cs.columns.len(&froms[0]).unwrap();
-
-    let mut sorted_is = (0..len).collect::<Vec<_>>();
-    sorted_is.sort_by(|i, j| {
-        for (sign, from) in signs.iter().zip(froms.iter()) {
-            let x_i = cs.columns.get(from, *i as isize, false).unwrap();
-            let x_j = cs.columns.get(from, *j as isize, false).unwrap();
-            if let x @ (Ordering::Greater | Ordering::Less) = x_i.cmp(&x_j) {
-                return if *sign { x } else { x.reverse() };
-            }
-        }
-        Ordering::Equal
-    });
+    let sorted_is = sorting_permutation(cs, froms, signs);
 
     Ok(froms
         .iter()
@@ -299,7 +417,7 @@ fn compute_exooperation(
         .effective_len_for(&cs.columns.column(target).unwrap().handle.module)
         .unwrap();
 
-    let mut cache = Some(cached::SizedCache::with_size(200000)); // ~1.60MB cache
+    let mut cache = Some(EvalCache::new());
     let getter = |handle: &ColumnRef, j, _| {
         cs.columns.get(handle, j, false).or_else(|| {
             cs.columns
@@ -314,10 +432,10 @@ fn compute_exooperation(
     let value: Vec<Value> = (-spilling..=len)
         .map(|i| {
             let mut r1 = sources[0]
-                .eval(i, getter, &mut cache, &EvalSettings { wrap: false })
+                .eval(i, getter, &mut cache, &EvalSettings::new().wrap(false))
                 .unwrap();
             let r2 = sources[1]
-                .eval(i, getter, &mut cache, &EvalSettings { wrap: false })
+                .eval(i, getter, &mut cache, &EvalSettings::new().wrap(false))
                 .unwrap();
             exo_operations.insert((op, r1.clone(), r2.clone()));
 
@@ -419,7 +537,7 @@ pub fn compute_expression(
             //                 })
             //             },
             //             &mut cache,
-            //             &EvalSettings { wrap: false },
+            //             &EvalSettings::new().wrap(false),
             //         )
             //         .unwrap_or_else(Value::zero)
             //     })
@@ -438,6 +556,7 @@ fn compute_sorting_auxs(cs: &ConstraintSet, comp: &Computation) -> Result<Vec<Co
         eq,
         delta,
         delta_bytes,
+        started,
         signs,
         froms,
         sorted,
@@ -461,7 +580,21 @@ fn compute_sorting_auxs(cs: &ConstraintSet, comp: &Computation) -> Result<Vec<Co
             std::iter::repeat_with(|| vec![Value::zero(); spilling as usize])
                 .take(delta_bytes.len())
                 .collect::<Vec<_>>();
+        let mut started_values = vec![Value::zero(); spilling as usize];
+        let mut is_started = false;
         for i in 0..len as isize {
+            // The ordering is not enforced on the row where it starts
+            let starts_here = started.is_some()
+                && !is_started
+                && (0..signs.len())
+                    .any(|l| !cs.columns.get(&sorted[l], i, false).unwrap().is_zero());
+            is_started |= starts_here;
+            started_values.push(if is_started {
+                Value::one()
+            } else {
+                Value::zero()
+            });
+
             // Compute @s
             let eqs = (0..ats.len())
                 .map(|l| {
@@ -482,10 +615,12 @@ fn compute_sorting_auxs(cs: &ConstraintSet, comp: &Computation) -> Result<Vec<Co
                         Value::zero()
                     } else {
                         found = true;
-                        delta = cs.columns.get(&sorted[l], i, false).unwrap().clone();
-                        delta.sub_assign(&cs.columns.get(&sorted[l], i - 1, false).unwrap());
-                        if !signs[l] {
-                            delta.negate();
+                        if !starts_here {
+                            delta = cs.columns.get(&sorted[l], i, false).unwrap().clone();
+                            delta.sub_assign(&cs.columns.get(&sorted[l], i - 1, false).unwrap());
+                            if !signs[l] {
+                                delta.negate();
+                            }
                         }
                         Value::one()
                     }
@@ -536,6 +671,12 @@ fn compute_sorting_auxs(cs: &ConstraintSet, comp: &Computation) -> Result<Vec<Co
                     )
                 }),
         )
+        .chain(started.iter().map(|started| {
+            (
+                started.to_owned(),
+                ValueBacking::from_vec(started_values.clone(), spilling),
+            )
+        }))
         .collect())
     } else {
         unreachable!()
@@ -620,10 +761,37 @@ fn err_missing_column(c: &crate::column::Column) -> RuntimeError {
     }
 }
 
+/// Ensure that no module is longer than the length it has been forced to
+fn check_forced_lens(cs: &ConstraintSet) -> Result<()> {
+    for (module, len) in cs.columns.forced_len.iter() {
+        if let Some(actual) = cs.effective_len_for(module) {
+            // the padding rows do not count against the forced length
+            let actual = actual
+                - cs.columns.padding.get(module).copied().unwrap_or(0)
+                - cs.columns
+                    .trailing_padding
+                    .get(module)
+                    .copied()
+                    .unwrap_or(0);
+            if actual > *len as isize {
+                bail!(
+                    "module `{}` has {} rows, more than the {} it has been forced to",
+                    module,
+                    actual,
+                    len
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
 pub fn prepare(cs: &mut ConstraintSet, fail_on_missing: bool) -> Result<()> {
+    check_forced_lens(cs)?;
+    import::import_lookup_tables(cs)?;
     compute_all(cs).with_context(|| "while computing columns")?;
     for h in cs.columns.all() {
-        if !cs.columns.is_computed(&h) {
+        if !cs.columns.is_computed(&h) && !cs.columns.is_skipped(&h) {
             let err = err_missing_column(cs.columns.column(&h).unwrap());
             if fail_on_missing {
                 bail!(err)
@@ -636,15 +804,255 @@ pub fn prepare(cs: &mut ConstraintSet, fail_on_missing: bool) -> Result<()> {
     Ok(())
 }
 
+/// Import the trace stored in `tracefile` into `cs`, without computing
+/// anything; binary (`.lt`) and JSON traces are both accepted.
+#[cfg(not(feature = "wasm"))]
+pub fn import_trace(tracefile: &str, cs: &mut ConstraintSet) -> Result<()> {
+    import::trace_reader_for(tracefile, None)?.read_file(tracefile, cs, false)
+}
+
+#[cfg(not(feature = "wasm"))]
 pub fn compute_trace(tracefile: &str, cs: &mut ConstraintSet, fail_on_missing: bool) -> Result<()> {
-    if tracefile.ends_with("lt") {
-        import::parse_binary_trace(tracefile, cs, false)?;
-    } else {
-        import::parse_json_trace(tracefile, cs, false)?;
+    import_trace(tracefile, cs)?;
+    prepare(cs, fail_on_missing)
+}
+
+// This is only used by the lib
+#[allow(dead_code)]
+/// Same as [`compute_trace`], reading `tracefile` with a custom `reader`.
+pub fn compute_trace_with(
+    tracefile: &str,
+    cs: &mut ConstraintSet,
+    reader: &dyn import::TraceReader,
+    fail_on_missing: bool,
+) -> Result<()> {
+    reader
+        .read_file(tracefile, cs, false)
+        .with_context(|| format!("while reading `{}` as {}", tracefile, reader.name()))?;
+    prepare(cs, fail_on_missing)
+}
+
+// This is only used by the lib
+#[allow(dead_code)]
+/// Merge a trace split across several files into `cs`, then compute it.
+///
+/// Every column may only be filled by a single file; padding and
+/// computations happen once the whole trace has been imported.
+#[cfg(not(feature = "wasm"))]
+pub fn compute_traces<S: AsRef<str>>(
+    tracefiles: &[S],
+    cs: &mut ConstraintSet,
+    fail_on_missing: bool,
+) -> Result<()> {
+    for tracefile in tracefiles.iter().map(AsRef::as_ref) {
+        import_trace(tracefile, cs).with_context(|| format!("while reading `{}`", tracefile))?;
     }
     prepare(cs, fail_on_missing)
 }
 
+// This is only used by the lib
+#[allow(dead_code)]
+/// Append the rows of `trace` to the trace already imported into `cs`, e.g.
+/// when processing the blocks of a chain one after the other.
+///
+/// The commitments of the modules present in `trace` are extended, and only
+/// the computed columns depending on them are computed again, as e.g. sorted
+/// columns can not be extended. The appended rows are inserted between the
+/// data rows of the former trace and the trailing padding rows of `trace`, if
+/// any. The first appended row of every module is recorded, so that the check
+/// may then be restricted to the appended rows.
+pub fn append_trace_str(trace: &[u8], cs: &mut ConstraintSet) -> Result<()> {
+    // The commitments imported so far, without their spilling
+    let previous = cs
+        .columns
+        .iter()
+        .filter(|(r, c)| matches!(c.kind, Kind::Commitment) && cs.columns.is_computed(r))
+        .map(|(r, _)| {
+            let len = cs.columns.len(&r).unwrap_or(0) as isize;
+            let values = (0..len)
+                .map(|i| cs.columns.get(&r, i, false).unwrap_or_default())
+                .collect::<Vec<_>>();
+            (r, values)
+        })
+        .collect::<HashMap<_, _>>();
+    let previous_len = std::mem::take(&mut cs.columns.effective_len);
+    let previous_padding = std::mem::take(&mut cs.columns.padding);
+    let previous_trailing = std::mem::take(&mut cs.columns.trailing_padding);
+
+    for r in previous.keys() {
+        cs.columns.reset_column(r);
+    }
+    import::read_trace_str(trace, cs, false)?;
+
+    let appended_len = std::mem::take(&mut cs.columns.effective_len);
+    let appended_padding = std::mem::take(&mut cs.columns.padding);
+    let appended_trailing = std::mem::take(&mut cs.columns.trailing_padding);
+    let rows = |m: &HashMap<String, isize>, module: &str| m.get(module).cloned().unwrap_or(0);
+    cs.columns.appended_from.clear();
+    for module in cs.columns.modules() {
+        let (len, padding, trailing, from) = match (
+            previous_len.contains_key(&module),
+            appended_len.contains_key(&module),
+        ) {
+            (false, false) => {
+                cs.columns.appended_from.insert(module, 0);
+                continue;
+            }
+            (false, true) => (
+                rows(&appended_len, &module),
+                rows(&appended_padding, &module),
+                rows(&appended_trailing, &module),
+                0,
+            ),
+            (true, false) => (
+                rows(&previous_len, &module),
+                rows(&previous_padding, &module),
+                rows(&previous_trailing, &module),
+                rows(&previous_len, &module),
+            ),
+            // the leading padding rows are only kept at the start of the
+            // trace, and the trailing ones at its end
+            (true, true) => {
+                let from = rows(&previous_len, &module) - rows(&previous_trailing, &module);
+                (
+                    from + rows(&appended_len, &module) - rows(&appended_padding, &module),
+                    rows(&previous_padding, &module),
+                    rows(&appended_trailing, &module),
+                    from,
+                )
+            }
+        };
+        cs.columns.effective_len.insert(module.clone(), len);
+        cs.columns.padding.insert(module.clone(), padding);
+        cs.columns.trailing_padding.insert(module.clone(), trailing);
+        cs.columns.appended_from.insert(module, from);
+    }
+
+    for (r, mut values) in previous.into_iter() {
+        let module = cs.handle(&r).module.clone();
+        let factor = cs.length_multiplier(&r) as isize;
+        if cs.columns.is_computed(&r) {
+            let len = cs.columns.len(&r).unwrap_or(0) as isize;
+            let trailing = rows(&previous_trailing, &module) * factor;
+            values.truncate(values.len().saturating_sub(trailing as usize));
+            let skip = rows(&appended_padding, &module) * factor;
+            values.extend((skip..len).map(|i| cs.columns.get(&r, i, false).unwrap_or_default()));
+        } else if appended_len.contains_key(&module) {
+            bail!(
+                "{} is missing from the appended trace",
+                cs.handle(&r).pretty()
+            );
+        }
+        let spilling = cs
+            .spilling_for_column(&r)
+            .ok_or_else(|| anyhow!("no spilling found for {}", r.pretty()))?;
+        cs.columns.reset_column(&r);
+        cs.columns.set_column_value(&r, values, spilling)?;
+    }
+    check_forced_lens(cs)?;
+
+    // Sorting reorders all the rows of a module, so they must all be checked again
+    for c in cs.computations.iter() {
+        if let Computation::Sorted { froms, .. } | Computation::SortingConstraints { froms, .. } = c
+        {
+            let module = cs.handle(&froms[0]).module.clone();
+            cs.columns.appended_from.insert(module, 0);
+        }
+    }
+
+    if cs
+        .computations
+        .iter()
+        .any(|c| matches!(c, Computation::ExoOperation { .. }))
+    {
+        let commitments = cs
+            .columns
+            .iter()
+            .filter(|(_, c)| matches!(c.kind, Kind::Commitment))
+            .map(|(r, _)| r)
+            .collect::<HashSet<_>>();
+        for r in cs.columns.all() {
+            if !commitments.contains(&r) {
+                cs.columns.reset_column(&r);
+            }
+        }
+        return prepare(cs, false);
+    }
+
+    // only the columns depending on the appended modules have to be computed again
+    let changed = cs
+        .columns
+        .iter()
+        .filter(|(_, c)| {
+            matches!(c.kind, Kind::Commitment) && appended_len.contains_key(&c.handle.module)
+        })
+        .map(|(_, c)| c.handle.to_owned())
+        .collect::<Vec<_>>();
+    recompute_dirty(cs, &changed)
+}
+
+// This is only used by the lib
+#[allow(dead_code)]
+/// Replace the trace imported into `cs` by `trace`, only computing again the
+/// columns depending on the commitments whose values changed, e.g. when
+/// successive traces share most of their modules.
+///
+/// If the length or the padding of any module changed, or if there are
+/// exo-operations, all the computed columns are computed again.
+pub fn update_trace_str(trace: &[u8], cs: &mut ConstraintSet) -> Result<()> {
+    let commitments = cs
+        .columns
+        .iter()
+        .filter(|(_, c)| matches!(c.kind, Kind::Commitment))
+        .map(|(r, _)| r)
+        .collect::<Vec<_>>();
+    let values_of = |cs: &ConstraintSet, r: &ColumnRef| {
+        cs.columns.is_computed(r).then(|| {
+            (0..cs.columns.len(r).unwrap_or(0) as isize)
+                .map(|i| cs.columns.get(r, i, false).unwrap_or_default())
+                .collect::<Vec<_>>()
+        })
+    };
+    let previous = commitments
+        .iter()
+        .map(|r| values_of(cs, r))
+        .collect::<Vec<_>>();
+    let previous_len = std::mem::take(&mut cs.columns.effective_len);
+    let previous_padding = std::mem::take(&mut cs.columns.padding);
+    cs.columns.trailing_padding.clear();
+
+    for r in commitments.iter() {
+        cs.columns.reset_column(r);
+    }
+    import::read_trace_str(trace, cs, false)?;
+    import::import_lookup_tables(cs)?;
+
+    if cs.columns.effective_len != previous_len
+        || cs.columns.padding != previous_padding
+        || cs
+            .computations
+            .iter()
+            .any(|c| matches!(c, Computation::ExoOperation { .. }))
+    {
+        let commitments = commitments.into_iter().collect::<HashSet<_>>();
+        for r in cs.columns.all() {
+            if !commitments.contains(&r) {
+                cs.columns.reset_column(&r);
+            }
+        }
+        return prepare(cs, false);
+    }
+
+    let changed = commitments
+        .iter()
+        .zip(previous)
+        .filter(|(r, previous)| values_of(cs, r) != *previous)
+        .map(|(r, _)| cs.handle(r).to_owned())
+        .collect::<Vec<_>>();
+    trace!("{} commitments changed", changed.len());
+    recompute_dirty(cs, &changed)
+}
+
 // This is only used by the lib
 #[allow(dead_code)]
 pub fn compute_trace_str(
@@ -1,6 +1,21 @@
 use crate::compiler::Magma;
+use crate::field::Fr;
+use ark_ff::PrimeField;
 
-pub const FIELD_BITSIZE: usize = 254;
+/// The size in bits of the largest values accepted as field elements. It is
+/// one bit more than the modulus, as it has always been for BLS12-377, so
+/// that every field element is accepted; the values above the modulus are
+/// reduced when turned into field elements.
+pub const FIELD_BITSIZE: usize = Fr::MODULUS_BIT_SIZE as usize + 1;
+
+/// The size in bytes of the chunks that values larger than [`FIELD_BITSIZE`]
+/// are split into, so that each of them fits in a field element
+pub const FIELD_CHUNK_BYTES: usize = (Fr::MODULUS_BIT_SIZE as usize - 1) / 8;
+
+/// The widest bound on the operands of a comparison: its lowering range-checks
+/// a difference δ < 2^w that may stand for either outcome, which is only sound
+/// when 2^(w+1) ≤ p
+pub const MAX_COMPARISON_WIDTH: usize = Fr::MODULUS_BIT_SIZE as usize - 2;
 
 pub fn col_count_bits(x: usize) -> usize {
     (x + FIELD_BITSIZE - 1) / FIELD_BITSIZE
@@ -0,0 +1,74 @@
+use anyhow::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, io::Write, path::Path};
+
+/// The progress of a check loop processing blocks by ascending ID, persisted on
+/// disk so that a restarted loop resumes where it left off.
+///
+/// Block IDs may have gaps, and a block may be inserted after blocks of larger
+/// IDs have been processed; the blocks processed past a gap are thus
+/// remembered individually, until the gap is filled.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Cursor {
+    /// all the blocks up to this ID have been processed
+    pub watermark: i64,
+    /// the blocks processed past the watermark
+    #[serde(default)]
+    pub done: BTreeSet<i64>,
+}
+impl Cursor {
+    /// A cursor considering all the blocks up to `watermark` as processed
+    pub fn since(watermark: i64) -> Self {
+        Cursor {
+            watermark,
+            done: BTreeSet::new(),
+        }
+    }
+
+    /// Read the cursor stored in `path`, or start a new one from `since` if
+    /// there is none.
+    pub fn load<P: AsRef<Path>>(path: P, since: i64) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Cursor::since(since));
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("while reading cursor from `{}`", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("while parsing cursor from `{}`", path.display()))
+    }
+
+    /// Atomically write the cursor to `path`, ensuring that it reached the
+    /// disk before returning.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        let mut f = std::fs::File::create(&tmp)
+            .with_context(|| format!("while creating `{}`", tmp.display()))?;
+        f.write_all(serde_json::to_string(self)?.as_bytes())?;
+        f.sync_all()?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("while writing cursor to `{}`", path.display()))?;
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// Whether the block `id` has already been processed
+    pub fn is_done(&self, id: i64) -> bool {
+        id <= self.watermark || self.done.contains(&id)
+    }
+
+    /// Mark the block `id` as processed, moving the watermark forward over
+    /// all the contiguous processed blocks.
+    pub fn advance(&mut self, id: i64) {
+        if self.is_done(id) {
+            return;
+        }
+        self.done.insert(id);
+        while self.done.remove(&(self.watermark + 1)) {
+            self.watermark += 1;
+        }
+    }
+}
@@ -23,6 +23,11 @@ impl ComputationDag {
         self.edges.insert((n1.clone(), n2.clone()));
     }
 
+    /// All the `(from, to)` pairs where `to` is computed from `from`
+    pub fn edges(&self) -> impl Iterator<Item = &(ColumnRef, ColumnRef)> {
+        self.edges.iter()
+    }
+
     fn sinks(&self) -> Vec<ColumnRef> {
         self.nodes
             .iter()
@@ -88,6 +93,7 @@ impl ComputationDag {
                 eq,
                 delta,
                 delta_bytes,
+                started,
                 sorted,
                 ..
             } => {
@@ -96,7 +102,7 @@ impl ComputationDag {
                 for s in sorted {
                     self.depends(s, eq);
                     self.depends(s, delta);
-                    for x in ats.iter().chain(delta_bytes.iter()) {
+                    for x in ats.iter().chain(delta_bytes.iter()).chain(started.iter()) {
                         self.depends(s, x);
                     }
                 }
@@ -104,6 +110,40 @@ impl ComputationDag {
         }
     }
 
+    /// Returns all the nodes transitively depending on any of the given ones
+    pub fn descendants<'a, I: IntoIterator<Item = &'a ColumnRef>>(
+        &self,
+        roots: I,
+    ) -> HashSet<ColumnRef> {
+        let mut r = HashSet::new();
+        let mut todo = roots.into_iter().cloned().collect::<Vec<_>>();
+        while let Some(n) = todo.pop() {
+            for o in self.outgoing(&n) {
+                if r.insert(o.clone()) {
+                    todo.push(o);
+                }
+            }
+        }
+        r
+    }
+
+    /// Returns all the nodes any of the given ones transitively depends on
+    pub fn ancestors<'a, I: IntoIterator<Item = &'a ColumnRef>>(
+        &self,
+        roots: I,
+    ) -> HashSet<ColumnRef> {
+        let mut r = HashSet::new();
+        let mut todo = roots.into_iter().cloned().collect::<Vec<_>>();
+        while let Some(n) = todo.pop() {
+            for i in self.incoming(&n) {
+                if r.insert(i.clone()) {
+                    todo.push(i);
+                }
+            }
+        }
+        r
+    }
+
     /// Returns a pseudo-topological sorting, a list of sets of independent columns
     pub fn job_slices(&self) -> Vec<HashSet<ColumnRef>> {
         let mut r = Vec::new();
@@ -0,0 +1,128 @@
+use anyhow::*;
+use itertools::Itertools;
+use num_bigint::BigInt;
+use owo_colors::OwoColorize;
+use serde_json::{json, Map};
+use std::collections::BTreeSet;
+
+use crate::{
+    check::{self, CheckFailure},
+    compiler::{
+        parser::{Ast, Token},
+        ConstraintSet, Kind, MAIN_MODULE,
+    },
+    compute,
+    pretty::Pretty,
+    structs::Handle,
+};
+
+/// A `deftest` block, checking the constraints of its module against a few
+/// hand-written rows
+#[derive(Debug)]
+pub(crate) struct InlineTest {
+    pub module: String,
+    pub name: String,
+    pub inputs: Vec<(String, Vec<BigInt>)>,
+    pub failing: Vec<String>,
+}
+impl InlineTest {
+    fn handle(&self) -> Handle {
+        Handle::new(&self.module, &self.name)
+    }
+}
+
+/// Collect all the `deftest` blocks of the given sources
+pub(crate) fn collect(asts: &[(String, Ast)]) -> Vec<InlineTest> {
+    let mut r = Vec::new();
+    for (_, ast) in asts.iter() {
+        let mut module = MAIN_MODULE.to_owned();
+        for e in ast.exprs.iter() {
+            match &e.class {
+                Token::DefModule(name) => module = name.to_owned(),
+                Token::DefTest {
+                    name,
+                    inputs,
+                    failing,
+                } => r.push(InlineTest {
+                    module: module.clone(),
+                    name: name.to_owned(),
+                    inputs: inputs.clone(),
+                    failing: failing.clone(),
+                }),
+                _ => {}
+            }
+        }
+    }
+    r
+}
+
+/// Check the constraints of the module of `test` against its rows, and
+/// return the constraints that failed. Columns of the module that are not
+/// filled by the test are set to zero.
+pub(crate) fn run(cs: &ConstraintSet, test: &InlineTest) -> Result<BTreeSet<String>> {
+    // ConstraintSet can not be cloned, as it owns the column data; but each
+    // test must start from an empty trace.
+    let mut cs: ConstraintSet = serde_json::from_str(&serde_json::to_string(cs)?)?;
+    cs.restrict_to_modules(&Some(vec![test.module.clone()]), &[]);
+
+    let len = test
+        .inputs
+        .iter()
+        .map(|(_, xs)| xs.len())
+        .max()
+        .unwrap_or(0);
+    let mut columns = Map::new();
+    for (_, c) in cs.columns.iter() {
+        if c.handle.module == test.module && matches!(c.kind, Kind::Commitment) {
+            columns.insert(c.handle.name.clone(), json!(vec![0; len]));
+        }
+    }
+    for (name, values) in test.inputs.iter() {
+        columns.insert(
+            name.to_owned(),
+            json!(values.iter().map(|x| x.to_string()).collect::<Vec<_>>()),
+        );
+    }
+    let trace = json!({ &test.module: columns });
+
+    compute::compute_trace_str(trace.to_string().as_bytes(), &mut cs, true)
+        .with_context(|| anyhow!("while filling the columns of {}", test.handle().pretty()))?;
+    match check::check(&cs, &None, &[], check::DebugSettings::new()) {
+        Result::Ok(()) => Ok(BTreeSet::new()),
+        Err(e) => match e.downcast_ref::<CheckFailure>() {
+            Some(failure) => Ok(failure.failed.iter().map(|f| f.name.clone()).collect()),
+            None => Err(e),
+        },
+    }
+}
+
+/// Run all the `tests`, and fail if any of them does not yield the expected
+/// failing constraints
+pub(crate) fn run_all(cs: &ConstraintSet, tests: &[InlineTest]) -> Result<()> {
+    let mut failed = 0;
+    for test in tests.iter() {
+        let expected = test
+            .failing
+            .iter()
+            .map(|c| Handle::new(&test.module, c).to_string())
+            .collect::<BTreeSet<_>>();
+        let found = run(cs, test)?;
+        if found == expected {
+            println!("test {} ... {}", test.handle().pretty(), "ok".green());
+        } else {
+            failed += 1;
+            println!(
+                "test {} ... {}: expected failures: [{}], found: [{}]",
+                test.handle().pretty(),
+                "FAILED".red().bold(),
+                expected.iter().join(", "),
+                found.iter().join(", ")
+            );
+        }
+    }
+
+    if failed > 0 {
+        bail!("{} of {} tests failed", failed, tests.len())
+    }
+    Ok(())
+}
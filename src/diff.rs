@@ -0,0 +1,178 @@
+use crate::field::Fr;
+use anyhow::*;
+use flate2::bufread::GzDecoder;
+use num_bigint::BigInt;
+use serde::Serialize;
+use serde_json::Value;
+use std::{collections::BTreeMap, io::BufReader, str::FromStr};
+
+use crate::{column::Value as CValue, compiler::generator::TraceRadix, pretty::Pretty};
+
+/// The columns of a trace, indexed by their fully qualified name
+pub(crate) type TraceColumns = BTreeMap<String, Vec<Fr>>;
+
+/// How a column differs between two traces
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub(crate) enum ColumnDiff {
+    /// The column is only present in the left trace
+    OnlyLeft,
+    /// The column is only present in the right trace
+    OnlyRight,
+    /// The column is present in both traces, but with different values
+    Differs {
+        /// the first row where the values differ
+        first_row: usize,
+        /// the left value at `first_row`, if the left column is long enough
+        left: Option<String>,
+        /// the right value at `first_row`, if the right column is long enough
+        right: Option<String>,
+        /// the number of differing rows
+        count: usize,
+    },
+}
+
+/// Parse a value of a trace; the strings of `computed` traces are written by
+/// `compute` in the given radix, while the other ones are either decimal or
+/// prefixed by `0x`.
+fn parse_value(x: &Value, computed: Option<TraceRadix>) -> Result<Fr> {
+    let (s, computed) = match x {
+        Value::Number(n) => (n.to_string(), None),
+        Value::String(s) => (s.to_owned(), computed),
+        _ => bail!("expected numeric value, found `{}`", x),
+    };
+    let i = match (computed, s.strip_prefix("0x")) {
+        (Some(TraceRadix::Legacy), Some(hex)) => hex
+            .strip_prefix('0')
+            .and_then(|dec| BigInt::from_str(if dec.is_empty() { "0" } else { dec }).ok()),
+        (Some(TraceRadix::Hex | TraceRadix::Canonical) | None, Some(hex)) => {
+            BigInt::parse_bytes(hex.as_bytes(), 16)
+        }
+        (Some(TraceRadix::Decimal) | None, None) => BigInt::from_str(&s).ok(),
+        _ => None,
+    }
+    .ok_or_else(|| anyhow!("invalid value `{}`", s))?;
+
+    let mut v = CValue::BigInt(i);
+    v.to_native();
+    v.to_fr()
+        .ok_or_else(|| anyhow!("`{}` does not fit in a field element", s))
+}
+
+/// Collect the columns of a JSON trace, either as fed to corset
+/// (`{module: {column: [...]}}`) or as written by `compute`
+/// (`{"columns": {handle: {"values": [...]}}}`).
+fn collect_columns(
+    v: &Value,
+    path: &mut Vec<String>,
+    computed: bool,
+    radix: TraceRadix,
+    columns: &mut TraceColumns,
+) -> Result<()> {
+    match v {
+        Value::Object(map) => {
+            if let Some(values @ Value::Array(_)) = map.get("values") {
+                return collect_columns(values, path, true, radix, columns);
+            }
+            for (k, v) in map.iter() {
+                // these levels do not contribute to the column names
+                let transparent = k == "Trace" || (path.is_empty() && k == "columns");
+                if !transparent {
+                    path.push(k.to_owned());
+                }
+                collect_columns(v, path, computed, radix, columns)?;
+                if !transparent {
+                    path.pop();
+                }
+            }
+            Ok(())
+        }
+        Value::Array(xs) => {
+            let name = path.join(".");
+            let values = xs
+                .iter()
+                .map(|x| parse_value(x, computed.then_some(radix)))
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| anyhow!("reading {}", name))?;
+            columns.insert(name, values);
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Read the columns of a (possibly gzipped) JSON trace, whose computed values
+/// are written in `radix`
+pub(crate) fn read_columns_str(trace: &[u8], radix: TraceRadix) -> Result<TraceColumns> {
+    let gz = GzDecoder::new(BufReader::new(trace));
+    let v: Value = match gz.header() {
+        Some(_) => serde_json::from_reader(gz),
+        None => serde_json::from_slice(trace),
+    }
+    .with_context(|| "while parsing json")?;
+
+    let mut columns = TraceColumns::new();
+    collect_columns(&v, &mut vec![], false, radix, &mut columns)?;
+    Ok(columns)
+}
+
+pub(crate) fn read_columns(tracefile: &str, radix: TraceRadix) -> Result<TraceColumns> {
+    let content =
+        std::fs::read(tracefile).with_context(|| format!("while reading `{}`", tracefile))?;
+    read_columns_str(&content, radix).with_context(|| format!("while reading `{}`", tracefile))
+}
+
+/// Compare two traces column by column; only the differing columns are
+/// reported.
+pub(crate) fn diff(left: &TraceColumns, right: &TraceColumns) -> BTreeMap<String, ColumnDiff> {
+    let mut r = BTreeMap::new();
+    for (name, xs) in left.iter() {
+        if let Some(ys) = right.get(name) {
+            let differing = (0..xs.len().max(ys.len()))
+                .filter(|&i| xs.get(i) != ys.get(i))
+                .collect::<Vec<_>>();
+            if let Some(&first_row) = differing.first() {
+                r.insert(
+                    name.to_owned(),
+                    ColumnDiff::Differs {
+                        first_row,
+                        left: xs.get(first_row).map(Pretty::pretty),
+                        right: ys.get(first_row).map(Pretty::pretty),
+                        count: differing.len(),
+                    },
+                );
+            }
+        } else {
+            r.insert(name.to_owned(), ColumnDiff::OnlyLeft);
+        }
+    }
+    for name in right.keys().filter(|name| !left.contains_key(*name)) {
+        r.insert(name.to_owned(), ColumnDiff::OnlyRight);
+    }
+    r
+}
+
+/// Render a diff with one line per column, so that it can be grepped and
+/// diffed itself.
+pub(crate) fn render(diff: &BTreeMap<String, ColumnDiff>) -> String {
+    diff.iter()
+        .map(|(name, d)| match d {
+            ColumnDiff::OnlyLeft => format!("{} only in left", name),
+            ColumnDiff::OnlyRight => format!("{} only in right", name),
+            ColumnDiff::Differs {
+                first_row,
+                left,
+                right,
+                count,
+            } => format!(
+                "{} differs on {} rows, first at row {}: {} vs. {}",
+                name,
+                count,
+                first_row,
+                left.as_deref().unwrap_or("∅"),
+                right.as_deref().unwrap_or("∅")
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
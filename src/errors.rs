@@ -1,5 +1,6 @@
 use super::pretty::Pretty;
 use crate::{column::Value, pretty::Base};
+use num_bigint::BigInt;
 use owo_colors::OwoColorize;
 use thiserror::Error;
 
@@ -8,22 +9,98 @@ use crate::{
     structs::Handle,
 };
 
+/// The errors that may be raised while compiling Corset sources.
+///
+/// They are generally wrapped in an [`anyhow::Error`], along with the
+/// [`SourceLocation`] where they occurred; both can be retrieved with
+/// [`anyhow::Error::downcast_ref`].
 #[derive(Error, Debug)]
-pub(crate) enum CompileError<'a> {
-    #[error("{}", compiler::make_type_error_msg(.0, .1, .2))]
-    TypeError(String, &'a [&'a [Type]], Vec<Type>),
+pub enum CompileError {
+    #[error("{}", compiler::make_type_error_msg(.0, &.1.iter().map(|t| t.as_slice()).collect::<Vec<_>>(), .2))]
+    TypeMismatch(String, Vec<Vec<Type>>, Vec<Type>),
 
-    #[error("{} expects a condition, found {}", .0, .1.red().bold())]
+    #[error("expected {}, but received {}", .0, .1)]
+    ArityMismatch(String, usize),
+
+    #[error("{}", .0)]
+    NoMatchingSpecialization(String),
+
+    #[error("not a function: `{}`", .0)]
+    NotAFunction(String),
+
+    #[error("{} expects a condition, found {}", .0, format!("{:#}", .1).red().bold())]
     ConditioningError(String, Type),
 
     #[error("{} is never used", .0.pretty())]
     NotUsed(Handle),
 
+    #[error("function {} is never called", .0.pretty())]
+    NotCalled(Handle),
+
     #[error("column {} not found", .0.pretty())]
     NotFound(Handle),
 
     #[error("ambiguous {} module for {} {}", .0, .1, .2.pretty())]
     AmbiguousModule(&'static str, &'static str, Handle),
+
+    #[error("{} does not fit in the field", .0.to_string().red().bold())]
+    OutOfRange(BigInt),
+
+    #[error("expected {}, found {}", .0.blue(), .1.yellow())]
+    NotASomethings(&'static str, String),
+
+    #[error("unable to convert {} to {}", .1.yellow(), .0.blue())]
+    InvalidConversion(&'static str, String),
+
+    #[error("module {} not found in {}", .0.red(), .1.blue())]
+    ModuleNotFound(String, String),
+
+    #[error("symbol {} not found in module {}{}", .0.red(), .1.blue(), if let Some(p) = .2 {format!("/{}", p.yellow())} else {"".to_string()})]
+    UnknownSymbol(String, String, Option<String>),
+
+    #[error("perspective {} not found in module {}", .0.red(), .1.blue())]
+    PerspectiveNotFound(String, String),
+
+    #[error("symbol {} already exists in {}", .0.yellow(), .1.blue())]
+    SymbolAlreadyExists(String, String),
+
+    #[error("function {} already defined in {}", .0.yellow(), .1.blue())]
+    FunctionAlreadyExists(String, String),
+
+    #[error("function {} already exists: {} → {}", .0.yellow(), .0.red(), .1.magenta())]
+    AliasAlreadyExists(String, String),
+
+    #[error("circular definition found for {}", .0.red())]
+    CircularDefinition(String),
+
+    #[error("explicit module {} can not be reached from here", .0.blue().bold())]
+    NotAGlobalScope(String),
+
+    #[error("symbol {} can not be used in a pure context", .0.red().bold())]
+    UnavailableInPureContext(String),
+
+    #[error("constant {} can not depend on column {}", .0.yellow(), .1.red().bold())]
+    ColumnInConstant(String, String),
+
+    #[error("{} implicitly widens boolean {} to an integer; cast it explicitly with {}", .0.blue(), .1.red().bold(), "as-integer".white().bold())]
+    ImplicitWidening(String, String),
+
+    #[error("missing column name in {}", 0.yellow().bold())]
+    MissingColumn(String),
+
+    #[error("missing perspective name in {}", 0.yellow().bold())]
+    MissingPerspective(String),
+}
+
+/// Marks the phase of the CLI an error stems from, so that it can be reported
+/// through the exit code; it is displayed as the context message it carries.
+#[derive(Error, Debug)]
+pub enum Failure {
+    #[error("{0}")]
+    Compilation(String),
+
+    #[error("{0}")]
+    Trace(String),
 }
 
 #[derive(Error, Debug)]
@@ -44,21 +121,132 @@ pub enum RuntimeError {
 pub mod parser {
     use owo_colors::OwoColorize;
 
-    pub fn make_src_error(src: &str, lc: (usize, usize)) -> String {
-        let src_str = src
-            .chars()
-            .take_while(|x| *x != '\n')
-            .collect::<String>()
-            .bold()
-            .bright_white()
-            .to_string();
+    /// The location in the source code of the expression an error stems from
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct SourceLocation {
+        pub line: usize,
+        pub column: usize,
+        pub src: String,
+        /// the innermost location of the error, if it is not this one
+        pub origin: Option<Box<SourceLocation>>,
+    }
+    impl SourceLocation {
+        /// The innermost location of the error, i.e. where it stems from
+        pub fn origin(&self) -> &SourceLocation {
+            self.origin.as_deref().unwrap_or(self)
+        }
+    }
+    impl std::fmt::Display for SourceLocation {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let first_line = self
+                .src
+                .chars()
+                .take_while(|x| *x != '\n')
+                .collect::<String>();
+            write!(
+                f,
+                "at line {}: {}{}",
+                self.line.to_string().blue(),
+                first_line.bold().bright_white(),
+                if first_line.len() < self.src.len() {
+                    "..."
+                } else {
+                    ""
+                }
+                .bright_white()
+            )
+        }
+    }
 
-        format!(
-            "at line {}: {}{}",
-            lc.0.to_string().blue(),
-            src_str,
-            if src_str.len() < src.len() { "..." } else { "" }.bright_white()
-        )
+    pub fn make_src_error(src: &str, lc: (usize, usize)) -> SourceLocation {
+        SourceLocation {
+            line: lc.0,
+            column: lc.1,
+            src: src.to_owned(),
+            origin: None,
+        }
+    }
+
+    /// Attach the location `src` & `lc` to `err`, keeping track of the
+    /// innermost location it has already been attached
+    pub fn locate<E: Into<anyhow::Error>>(err: E, src: &str, lc: (usize, usize)) -> anyhow::Error {
+        let err = err.into();
+        let mut location = make_src_error(src, lc);
+        location.origin = err
+            .downcast_ref::<SourceLocation>()
+            .map(|inner| Box::new(inner.origin().clone()));
+        err.context(location)
+    }
+
+    /// The source file an error occurred in; its content is kept along so that
+    /// the offending code can later be pointed at
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct SourceFile {
+        pub message: String,
+        pub name: String,
+        pub content: String,
+    }
+    impl std::fmt::Display for SourceFile {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.message)
+        }
+    }
+
+    pub fn make_file_error(message: String, name: &str, content: &str) -> SourceFile {
+        SourceFile {
+            message,
+            name: name.to_owned(),
+            content: content.to_owned(),
+        }
+    }
+
+    /// Render `err` in the style of rustc, underlining the innermost piece of
+    /// code it stems from. Returns `None` if `err` does not carry enough
+    /// information to locate it.
+    pub fn render_rich(err: &anyhow::Error) -> Option<String> {
+        let file = err.downcast_ref::<SourceFile>()?;
+        let location = err.downcast_ref::<SourceLocation>()?.origin();
+        let line = file.content.lines().nth(location.line.checked_sub(1)?)?;
+
+        // keep the tabs so that the carets stay aligned with the code
+        let offset = line
+            .chars()
+            .take(location.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        let width = location
+            .src
+            .lines()
+            .next()
+            .map(|l| l.chars().count())
+            .unwrap_or(0)
+            .clamp(
+                1,
+                (line.chars().count() + 1)
+                    .saturating_sub(location.column)
+                    .max(1),
+            );
+        let gutter = " ".repeat(location.line.to_string().len());
+
+        Some(format!(
+            "{}: {}\n{}{} {}:{}:{}\n{} {}\n{} {} {}\n{} {} {}{}\n",
+            "error".red().bold(),
+            err.root_cause().to_string().bold(),
+            gutter,
+            "-->".blue().bold(),
+            file.name,
+            location.line,
+            location.column,
+            gutter,
+            "|".blue().bold(),
+            location.line.to_string().blue().bold(),
+            "|".blue().bold(),
+            line,
+            gutter,
+            "|".blue().bold(),
+            offset,
+            "^".repeat(width).red().bold(),
+        ))
     }
 }
 
@@ -75,6 +263,12 @@ pub(crate) mod compiler {
 
         #[error("computation {} refers to non-ID columns", .0)]
         ComputationWithHandles(String),
+
+        #[error("computed column {} is not filled by any computation", .0)]
+        MissingComputation(String),
+
+        #[error("computation {} targets the undeclared column {}", .0, .1)]
+        UndeclaredTarget(String, String),
     }
 
     pub(crate) fn type_comparison_message(expected: &[Type], found: &[Type]) -> (String, String) {
@@ -92,12 +286,12 @@ pub(crate) mod compiler {
                 .zip(found.iter())
                 .map(|(e, f)| {
                     if e >= f {
-                        e.white().to_string()
+                        format!("{:#}", e).white().to_string()
                     } else {
-                        e.blue().to_string()
+                        format!("{:#}", e).blue().to_string()
                     }
                 })
-                .join(" ")
+                .join(", ")
                 .bold()
         );
         let found_str = format!(
@@ -108,12 +302,12 @@ pub(crate) mod compiler {
                 .zip(found.iter())
                 .map(|(e, f)| {
                     if e >= f {
-                        f.white().to_string()
+                        format!("{:#}", f).white().to_string()
                     } else {
-                        f.red().to_string()
+                        format!("{:#}", f).red().to_string()
                     }
                 })
-                .join(" ")
+                .join(", ")
                 .red()
                 .bold()
         );
@@ -134,16 +328,16 @@ pub(crate) mod compiler {
                 .map(|(es, f)| {
                     let es_str = es
                         .iter()
-                        .map(|e| e.to_string())
+                        .map(|e| format!("{:#}", e))
                         .collect::<Vec<_>>()
-                        .join("|");
+                        .join(" or ");
                     if es.iter().any(|e| f.can_cast_to(*e)) {
                         es_str.white().to_string()
                     } else {
                         es_str.blue().to_string()
                     }
                 })
-                .join(" ")
+                .join(", ")
                 .blue()
                 .bold()
         );
@@ -156,13 +350,13 @@ pub(crate) mod compiler {
                 .zip(found.iter())
                 .map(|(e, f)| {
                     if e.iter().any(|e| f.can_cast_to(*e)) {
-                        f.white().to_string()
+                        format!("{:#}", f).white().to_string()
                     } else {
-                        f.red().to_string()
+                        format!("{:#}", f).red().to_string()
                     }
                 })
                 .collect::<Vec<_>>()
-                .join(" ")
+                .join(", ")
                 .red()
                 .bold()
         );
@@ -179,50 +373,3 @@ pub(crate) mod compiler {
         )
     }
 }
-
-pub mod symbols {
-    use owo_colors::OwoColorize;
-    use thiserror::Error;
-
-    #[derive(Error, Debug)]
-    pub enum Error {
-        #[error("expected {}, found {}", .0.blue(), .1.yellow())]
-        NotASomethings(&'static str, String),
-
-        #[error("unable to convert {} to {}", .1.yellow(), .0.blue())]
-        InvalidConversion(&'static str, String),
-
-        #[error("module {} not found in {}", .0.red(), .1.blue())]
-        ModuleNotFound(String, String),
-
-        #[error("symbol {} not found in module {}{}", .0.red(), .1.blue(), if let Some(p) = .2 {format!("/{}", p.yellow())} else {"".to_string()})]
-        SymbolNotFound(String, String, Option<String>),
-
-        #[error("perspective {} not found in module {}", .0.red(), .1.blue())]
-        PerspectiveNotFound(String, String),
-
-        #[error("symbol {} already exists in {}", .0.yellow(), .1.blue())]
-        SymbolAlreadyExists(String, String),
-
-        #[error("function {} already defined in {}", .0.yellow(), .1.blue())]
-        FunctionAlreadyExists(String, String),
-
-        #[error("function {} already exists: {} → {}", .0.yellow(), .0.red(), .1.magenta())]
-        AliasAlreadyExists(String, String),
-
-        #[error("circular definition found for {}", .0.red())]
-        CircularDefinition(String),
-
-        #[error("explicit module {} can not be reached from here", .0.blue().bold())]
-        NotAGlobalScope(String),
-
-        #[error("symbol {} can not be used in a pure context", .0.red().bold())]
-        UnavailableInPureContext(String),
-
-        #[error("missing column name in {}", 0.yellow().bold())]
-        MissingColumn(String),
-
-        #[error("missing perspective name in {}", 0.yellow().bold())]
-        MissingPerspective(String),
-    }
-}
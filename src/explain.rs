@@ -0,0 +1,147 @@
+use anyhow::*;
+use itertools::Itertools;
+
+use crate::{
+    column::{Computation, Value},
+    compiler::{ColumnRef, ConstraintSet, EvalSettings, Expression, Node},
+    compute::sorting_permutation,
+    pretty::Pretty,
+    structs::Handle,
+};
+
+/// Find the column designated by `name`, either as `module.column` or as
+/// `column` for the main module.
+pub(crate) fn find_column(cs: &ConstraintSet, name: &str) -> Result<ColumnRef> {
+    cs.columns
+        .iter()
+        .find(|(_, c)| {
+            c.handle.to_string() == name || format!("{}.{}", c.handle.module, c.handle.name) == name
+        })
+        .map(|(r, _)| r)
+        .ok_or_else(|| anyhow!("column `{}` not found", name))
+}
+
+/// The value of `r` at row `i`, as read by the computations
+fn value_at(cs: &ConstraintSet, r: &ColumnRef, i: isize) -> Option<Value> {
+    cs.columns
+        .get(r, i, false)
+        .or_else(|| cs.columns.column(r).unwrap().padding_value.clone())
+}
+
+fn pretty_value(x: Option<Value>) -> String {
+    x.map(|x| x.pretty()).unwrap_or_else(|| "nil".into())
+}
+
+/// Render `n` with its columns replaced by their values at row `i`
+fn substitute(cs: &ConstraintSet, n: &Node, i: isize) -> String {
+    match n.e() {
+        Expression::Funcall { func, args } => format!(
+            "({} {})",
+            func,
+            args.iter().map(|a| substitute(cs, a, i)).join(" ")
+        ),
+        Expression::Const(x) => x.pretty(),
+        Expression::Column { handle, shift, .. } | Expression::ExoColumn { handle, shift, .. } => {
+            pretty_value(value_at(cs, handle, i + *shift as isize))
+        }
+        Expression::List(xs) => {
+            format!("{{{}}}", xs.iter().map(|x| substitute(cs, x, i)).join(" "))
+        }
+        Expression::ArrayColumn { .. } | Expression::Void => n.pretty(),
+    }
+}
+
+/// The computed columns read by `n`, with the row they are read at
+fn computed_leaves(cs: &ConstraintSet, n: &Node, i: isize) -> Vec<(ColumnRef, isize)> {
+    n.leaves()
+        .into_iter()
+        .filter_map(|l| match l.e() {
+            Expression::Column { handle, shift, .. } => Some((handle.clone(), i + *shift as isize)),
+            _ => None,
+        })
+        .filter(|(r, _)| cs.computations.computation_for(r).is_some())
+        .unique_by(|(r, j)| (cs.handle(r).to_owned(), *j))
+        .collect()
+}
+
+fn explain_at(cs: &ConstraintSet, r: &ColumnRef, i: isize, depth: usize, out: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    let handle: &Handle = cs.handle(r);
+    out.push(format!(
+        "{}{}[{}] = {}",
+        indent,
+        handle.pretty(),
+        i,
+        pretty_value(cs.columns.get(r, i, false))
+    ));
+
+    let indent = "  ".repeat(depth + 1);
+    match cs.computations.computation_for(r) {
+        None => out.push(format!("{}imported from the trace", indent)),
+        Some(Computation::Composite { exp, .. }) => {
+            let value = exp.eval(
+                i,
+                |h, j, _| value_at(cs, h, j),
+                &mut None,
+                &EvalSettings::new().wrap(false),
+            );
+            out.push(format!("{}= {}", indent, exp.pretty_in(&handle.module)));
+            out.push(format!("{}= {}", indent, substitute(cs, exp, i)));
+            out.push(format!("{}= {}", indent, pretty_value(value)));
+            for (leaf, j) in computed_leaves(cs, exp, i) {
+                explain_at(cs, &leaf, j, depth + 1, out);
+            }
+        }
+        Some(Computation::Interleaved { froms, .. }) => {
+            if i < 0 {
+                out.push(format!("{}padding", indent));
+            } else {
+                let from = &froms[i as usize % froms.len()];
+                let j = i / froms.len() as isize;
+                out.push(format!(
+                    "{}interleaved from {}[{}]",
+                    indent,
+                    cs.handle(from).pretty(),
+                    j
+                ));
+                explain_at(cs, from, j, depth + 1, out);
+            }
+        }
+        Some(Computation::Sorted { froms, tos, signs }) => {
+            let k = tos.iter().position(|t| cs.handle(t) == handle).unwrap();
+            match sorting_permutation(cs, froms, signs).get(i as usize) {
+                Some(&j) if i >= 0 => {
+                    out.push(format!(
+                        "{}sorted from {}[{}]",
+                        indent,
+                        cs.handle(&froms[k]).pretty(),
+                        j
+                    ));
+                    explain_at(cs, &froms[k], j as isize, depth + 1, out);
+                }
+                _ => out.push(format!("{}padding", indent)),
+            }
+        }
+        Some(Computation::CyclicFrom { modulo, .. }) => {
+            out.push(format!("{}= {} mod {}", indent, i, modulo));
+        }
+        Some(c) => out.push(format!("{}{}", indent, c)),
+    }
+}
+
+/// Explain how the value of column `name` at row `i` has been derived, by
+/// walking back the computations leading to it.
+pub(crate) fn explain(cs: &ConstraintSet, name: &str, i: isize) -> Result<String> {
+    let r = find_column(cs, name)?;
+    let len = cs
+        .columns
+        .len(&r)
+        .ok_or_else(|| anyhow!("column `{}` has not been computed", name))?;
+    if i >= len as isize {
+        bail!("row {} is out of the {} rows of `{}`", i, len, name)
+    }
+
+    let mut out = Vec::new();
+    explain_at(cs, &r, i, 0, &mut out);
+    Ok(out.join("\n"))
+}
@@ -1,7 +1,6 @@
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
-use std::println;
 
 use crate::compiler::RawMagma;
 use crate::{
@@ -1,3 +1,4 @@
+use crate::utils::parallel::*;
 use crate::{
     column::ValueBacking,
     compiler::{ColumnRef, ConstraintSet, Kind},
@@ -8,7 +9,6 @@ use anyhow::*;
 use itertools::Itertools;
 use log::*;
 use owo_colors::OwoColorize;
-use rayon::prelude::*;
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::Path;
@@ -38,7 +38,6 @@ pub(crate) fn to_csv(cs: &ConstraintSet, exclude: &[String], filename: &str) ->
                 .columns
                 .iter_module(&module)
                 .map(|c| (c.0.clone(), cs.handle(&c.0).clone()))
-                .sorted_by(|x, y| x.1.cmp(&y.1))
                 .unzip();
 
             file.write(column_names.iter().map(|h| &h.name).join(",").as_bytes())?;
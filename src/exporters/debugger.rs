@@ -104,6 +104,13 @@ fn pretty_expr(n: &Node, prev: Option<Intrinsic>, tty: &mut Tty, show_types: boo
                 pretty_expr(&args[0], prev, tty, show_types);
                 tty.write(")");
             }
+            Intrinsic::Lt => {
+                tty.write("(");
+                pretty_expr(&args[0], None, tty, show_types);
+                tty.write(" < ");
+                pretty_expr(&args[1], None, tty, show_types);
+                tty.write(")");
+            }
             Intrinsic::Begin => todo!(),
             Intrinsic::IfZero => {
                 tty.write("if-zero ".color(c).bold().to_string());
@@ -192,12 +199,17 @@ fn render_constraints(
 ) {
     println!("\n{}", "=== Constraints ===".bold().yellow());
     for c in cs.constraints.iter() {
-        if !skip.contains(&c.name()) && only.map(|o| o.contains(&c.name())).unwrap_or(true) {
+        if !skip.iter().any(|p| c.is_selected_by(p))
+            && only
+                .map(|o| o.iter().any(|p| c.is_selected_by(p)))
+                .unwrap_or(true)
+        {
             match c {
                 Constraint::Vanishes {
                     handle,
                     domain,
                     expr,
+                    ..
                 } => {
                     let mut tty = Tty::new().with_guides();
                     println!(
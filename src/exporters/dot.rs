@@ -0,0 +1,95 @@
+use anyhow::*;
+use itertools::Itertools;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    compiler::{ConstraintSet, Kind},
+    dag::ComputationDag,
+    structs::Handle,
+};
+
+fn node_id(h: &Handle) -> String {
+    format!("{:?}", h.to_serialized_string())
+}
+
+fn constraint_id(name: &str) -> String {
+    format!("{:?}", format!("constraint:{}", name))
+}
+
+/// Render the dependency graph of `cs` in the DOT format. Columns are
+/// clustered by module, and point to the computed columns derived from them
+/// as well as to the constraints referencing them; edges crossing a module
+/// boundary are dashed.
+pub fn to_dot(cs: &ConstraintSet) -> String {
+    let mut modules: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+    for (_, c) in cs.columns.iter() {
+        modules.entry(&c.handle.module).or_default().insert(format!(
+            "{} [label={:?}, style=filled, fillcolor={}];",
+            node_id(&c.handle),
+            c.handle.to_string(),
+            if matches!(c.kind, Kind::Commitment) {
+                "white"
+            } else {
+                "lightblue"
+            }
+        ));
+    }
+    for c in cs.constraints.iter() {
+        modules
+            .entry(&c.handle().module)
+            .or_default()
+            .insert(format!(
+                "{} [label={:?}, shape=box, style=filled, fillcolor=lightyellow];",
+                constraint_id(&c.name()),
+                c.name()
+            ));
+    }
+
+    let edge = |from: &Handle, to: String, to_module: &str| {
+        format!(
+            "{} -> {}{};",
+            node_id(from),
+            to,
+            if from.module != to_module {
+                " [style=dashed]"
+            } else {
+                ""
+            }
+        )
+    };
+    let mut edges = BTreeSet::new();
+    // computed columns depend on the columns they are computed from...
+    for (from, to) in ComputationDag::from_computations(cs.computations.iter()).edges() {
+        let to = cs.handle(to);
+        edges.insert(edge(cs.handle(from), node_id(to), &to.module));
+    }
+    // ...and constraints on the columns they reference
+    for c in cs.constraints.iter() {
+        for from in c.dependencies() {
+            edges.insert(edge(
+                cs.handle(&from),
+                constraint_id(&c.name()),
+                &c.handle().module,
+            ));
+        }
+    }
+
+    let mut r = String::from("digraph dependencies {\n  rankdir=LR;\n");
+    for (i, (module, nodes)) in modules.into_iter().enumerate() {
+        r.push_str(&format!(
+            "  subgraph cluster_{} {{\n    label={:?};\n{}\n  }}\n",
+            i,
+            module,
+            nodes.iter().map(|n| format!("    {}", n)).join("\n")
+        ));
+    }
+    for e in edges {
+        r.push_str(&format!("  {}\n", e));
+    }
+    r.push_str("}\n");
+    r
+}
+
+pub fn render(cs: &ConstraintSet, filename: &str) -> Result<()> {
+    std::fs::write(filename, to_dot(cs)).with_context(|| anyhow!("while writing {}", filename))
+}
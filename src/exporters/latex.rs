@@ -13,13 +13,14 @@ use itertools::Itertools;
 use log::error;
 use serde::Serialize;
 
-use std::{fs::File, io::Write};
+use std::io::Write;
 
 #[derive(Default)]
 pub struct LatexExporter {
     pub constraints_filename: Option<String>,
     pub columns_filename: Option<String>,
     pub render_columns: bool,
+    pub render_constraints: bool,
 }
 
 fn sanitize(s: &str) -> String {
@@ -262,6 +263,7 @@ fn render_node(n: &AstNode, state: State) -> Result<String> {
             guard: _,
             perspective: _,
             body,
+            ..
         } => Ok(format!(
             "\n\\begin{{constraint}}[{}{} {}]\n\\begin{{gather*}}\n{}\n\\end{{gather*}}\n\\end{{constraint}}\n",
             name.to_case(Case::Title),
@@ -293,6 +295,7 @@ const CONSTRAINT_TEMPLATE: &str = include_str!("constraint.tex");
 #[derive(Serialize)]
 struct LatexTemplate {
     caption: String,
+    doc: Option<String>,
     content: String,
 }
 fn render_constraints(asts: &[Ast], columns: &[String]) -> Result<String> {
@@ -308,6 +311,7 @@ fn render_constraints(asts: &[Ast], columns: &[String]) -> Result<String> {
             CONSTRAINT_TEMPLATE,
             &LatexTemplate {
                 caption: constraint.h.name.to_owned(),
+                doc: constraint.doc.as_deref().map(sanitize),
                 content: render_node(&constraint.e, state)?,
             },
         )?;
@@ -320,11 +324,13 @@ type LatexConst = (String, AstNode);
 struct LatexConstraint {
     h: Handle,
     e: AstNode,
+    doc: Option<String>,
 }
 struct LatexColumn {
     name: String,
     t: Type,
     is_array: bool,
+    doc: Option<String>,
 }
 
 fn constraints(ast: &Ast) -> Vec<LatexConstraint> {
@@ -342,12 +348,14 @@ fn constraints(ast: &Ast) -> Vec<LatexConstraint> {
                 domain: _domain,
                 guard: _guard,
                 body,
+                doc,
                 ..
             } => {
                 let h = Handle::new(&module, name);
                 Some(LatexConstraint {
                     h,
                     e: *body.to_owned(),
+                    doc: doc.to_owned(),
                 })
             }
             // Token::DefPermutation { from, to } => todo!(),
@@ -387,20 +395,23 @@ fn columns(ast: &Ast) -> Vec<LatexColumn> {
                     _columns(n, cols);
                 }
             }
-            Token::DefColumn { name, t, .. } => cols.push(LatexColumn {
+            Token::DefColumn { name, t, doc, .. } => cols.push(LatexColumn {
                 name: name.to_owned(),
                 t: *t,
                 is_array: false,
+                doc: doc.to_owned(),
             }),
-            Token::DefArrayColumn { name, t, .. } => cols.push(LatexColumn {
+            Token::DefArrayColumn { name, t, doc, .. } => cols.push(LatexColumn {
                 name: name.to_owned(),
                 t: *t,
                 is_array: true,
+                doc: doc.to_owned(),
             }),
             Token::DefAlias(from, _to) => cols.push(LatexColumn {
                 name: from.to_owned(),
                 t: Type::Void,
                 is_array: false,
+                doc: None,
             }),
             _ => (),
         }
@@ -412,6 +423,28 @@ fn columns(ast: &Ast) -> Vec<LatexColumn> {
     })
 }
 
+/// Describe the documented columns, if any
+fn render_column_docs(asts: &[Ast]) -> String {
+    let items = asts
+        .iter()
+        .flat_map(|ast| columns(ast).into_iter())
+        .filter_map(|col| {
+            col.doc.map(|doc| {
+                format!(
+                    "\\item[\\col{{{}}}] {}",
+                    sanitize(&col.name),
+                    sanitize(&doc).replace('\n', " ")
+                )
+            })
+        })
+        .join("\n");
+    if items.is_empty() {
+        items
+    } else {
+        wrap_env(items, "description")
+    }
+}
+
 fn render_columns(asts: &[Ast]) -> Result<(String, Vec<String>)> {
     let mut column_symbols = Vec::new();
     let mut r = String::new();
@@ -434,12 +467,7 @@ fn render_columns(asts: &[Ast]) -> Result<(String, Vec<String>)> {
     Ok((r, column_symbols))
 }
 
-pub fn render(asts: &[Ast], constraints_file: Option<String>) -> Result<()> {
-    if let Some(constraints_file) = constraints_file.as_ref() {
-        let mut out = File::create(constraints_file)
-            .with_context(|| anyhow!("while opening {}", constraints_file))?;
-        out.write_all(
-            r"
+const PREAMBLE: &str = r"
 \documentclass{article}
 \usepackage{algorithm2e}
 \usepackage{amsmath}
@@ -453,14 +481,64 @@ pub fn render(asts: &[Ast], constraints_file: Option<String>) -> Result<()> {
 }
 
 
-"
-            .as_bytes(),
-        )?;
-        let columns = render_columns(asts)?;
-        out.write_all(columns.0.as_bytes())?;
-        out.write_all("\n\n\\begin{document}\n".as_bytes())?;
-        out.write_all(render_constraints(asts, &columns.1)?.as_bytes())?;
-        out.write_all("\\end{document}".as_bytes())?;
+";
+
+/// Write a standalone document made of the given `(title, body)` sections to
+/// `filename`, or the standard output if it is `-`; the titles are only
+/// rendered if there are several sections.
+fn write_document(filename: &str, column_macros: &str, sections: &[(&str, String)]) -> Result<()> {
+    let mut out = super::create_output(filename, false, false)?;
+    out.write_all(PREAMBLE.as_bytes())?;
+    out.write_all(column_macros.as_bytes())?;
+    out.write_all("\n\n\\begin{document}\n".as_bytes())?;
+    for (title, body) in sections {
+        if sections.len() > 1 {
+            out.write_all(format!("\\section{{{}}}\n", title).as_bytes())?;
+        }
+        out.write_all(body.as_bytes())?;
     }
+    out.write_all("\\end{document}".as_bytes())?;
     Ok(())
 }
+
+impl LatexExporter {
+    /// Render the columns table and the constraints described by `asts`.
+    ///
+    /// If `columns_filename` is set to another file than
+    /// `constraints_filename`, each is rendered as its own document; otherwise,
+    /// both are merged as sections of a single document.
+    pub fn render(&self, asts: &[Ast]) -> Result<()> {
+        let (column_macros, column_symbols) = render_columns(asts)?;
+        let columns = self
+            .render_columns
+            .then(|| ("Columns", render_column_docs(asts)));
+        let constraints = if self.render_constraints {
+            Some(("Constraints", render_constraints(asts, &column_symbols)?))
+        } else {
+            None
+        };
+
+        match (
+            self.constraints_filename.as_ref(),
+            self.columns_filename.as_ref(),
+        ) {
+            (Some(constraints_file), Some(columns_file)) if constraints_file != columns_file => {
+                if let Some(constraints) = constraints {
+                    write_document(constraints_file, &column_macros, &[constraints])?;
+                }
+                if let Some(columns) = columns {
+                    write_document(columns_file, &column_macros, &[columns])?;
+                }
+            }
+            (Some(filename), _) | (None, Some(filename)) => {
+                write_document(
+                    filename,
+                    &column_macros,
+                    &columns.into_iter().chain(constraints).collect::<Vec<_>>(),
+                )?;
+            }
+            (None, None) => {}
+        }
+        Ok(())
+    }
+}
@@ -6,6 +6,7 @@ pub mod besu;
 pub mod conflater;
 pub mod convert;
 pub(crate) mod debugger;
+pub mod dot;
 #[cfg(feature = "exporters")]
 pub mod latex;
 #[cfg(feature = "exporters")]
@@ -14,6 +15,33 @@ pub mod wizardiop;
 pub mod zkgeth;
 
 use crate::column::Register;
+#[cfg(feature = "exporters")]
+use crate::compiler::{Constraint, ConstraintSet, Expression};
+use anyhow::*;
+#[cfg(feature = "exporters")]
+use itertools::Itertools;
+#[cfg(feature = "exporters")]
+use serde::Serialize;
+use std::io::{IsTerminal, Write};
+
+/// The output filename standing for the standard output
+pub(crate) const STDOUT: &str = "-";
+
+/// Open `filename` for writing, or the standard output if it is `-`; binary
+/// outputs are not written to a terminal unless `force` is set.
+pub(crate) fn create_output(filename: &str, binary: bool, force: bool) -> Result<Box<dyn Write>> {
+    if filename == STDOUT {
+        let stdout = std::io::stdout();
+        if binary && !force && stdout.is_terminal() {
+            bail!("refusing to write binary output to a terminal; use --force to do it anyway")
+        }
+        Ok(Box::new(stdout.lock()))
+    } else {
+        Ok(Box::new(std::fs::File::create(filename).with_context(
+            || format!("while creating `{}`", filename),
+        )?))
+    }
+}
 
 fn reg_to_string(r: &Register, i: usize) -> String {
     r.handle
@@ -22,6 +50,53 @@ fn reg_to_string(r: &Register, i: usize) -> String {
         .unwrap_or_else(|| format!("r{}", i))
 }
 
+/// The boundary semantics of a constraint involving shifted columns, so that
+/// backends know how to handle the first and last rows of its module
+#[cfg(feature = "exporters")]
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub(crate) struct Boundary {
+    pub constraint: String,
+    pub module: String,
+    /// the distinct non-null shifts of the columns in the constraint
+    pub shifts: Vec<isize>,
+    /// the number of padding rows prepended to the module to absorb
+    /// its shifts
+    pub spilling: isize,
+}
+
+/// List the boundary semantics of all the constraints of `cs` that reach
+/// beyond their current row
+#[cfg(feature = "exporters")]
+pub(crate) fn boundaries(cs: &ConstraintSet) -> Vec<Boundary> {
+    cs.constraints
+        .iter()
+        .filter_map(|c| match c {
+            Constraint::Vanishes { handle, expr, .. } => {
+                let shifts =
+                    expr.leaves()
+                        .iter()
+                        .filter_map(|n| match n.e() {
+                            Expression::Column { shift, .. }
+                            | Expression::ExoColumn { shift, .. } => Some(*shift as isize),
+                            _ => None,
+                        })
+                        .filter(|s| *s != 0)
+                        .sorted()
+                        .dedup()
+                        .collect::<Vec<_>>();
+                (!shifts.is_empty()).then(|| Boundary {
+                    constraint: handle.name.clone(),
+                    module: handle.module.clone(),
+                    shifts,
+                    spilling: cs.spilling_of(&handle.module).unwrap_or(0),
+                })
+            }
+            _ => None,
+        })
+        .sorted_by(|a, b| (&a.module, &a.constraint).cmp(&(&b.module, &b.constraint)))
+        .collect()
+}
+
 #[cfg(feature = "exporters")]
 fn gofmt(filename: &str) {
     info!("Running gofmt on {}... ", filename);
@@ -122,14 +122,8 @@ fn render_funcall(cs: &ConstraintSet, func: &Intrinsic, args: &[Node]) -> String
             match exp {
                 0 => "column.CONST_STRING(\"1\")".to_string(),
                 1 => render_expression(cs, &args[0]),
-                _ => make_chain(
-                    cs,
-                    &std::iter::repeat(args[0].clone())
-                        .take(exp)
-                        .collect::<Vec<_>>(),
-                    "Mul",
-                    false,
-                ),
+                // keep the exponent rather than unrolling it into a product
+                _ => format!("({}).Pow({})", render_expression(cs, &args[0]), exp),
             }
         }
         Intrinsic::Neg => format!("({}).Neg()", render_expression(cs, &args[0])),
@@ -148,6 +142,7 @@ fn render_constraints(cs: &ConstraintSet) -> Vec<String> {
                 handle,
                 domain,
                 expr,
+                ..
             } => render_constraint(cs, &handle.to_string(), domain.clone(), expr),
             Constraint::Lookup {
                 handle,
@@ -451,6 +446,7 @@ pub fn render(cs: &ConstraintSet, out_filename: &Option<String>) -> Result<()> {
         columns: Vec<WiopColumn>,
         interleaved: Vec<WiopInterleaved>,
         constraints: Vec<String>,
+        boundaries: Vec<super::Boundary>,
     }
     let mut sizes: HashSet<String> = HashSet::new();
 
@@ -464,15 +460,17 @@ pub fn render(cs: &ConstraintSet, out_filename: &Option<String>) -> Result<()> {
             columns: render_columns(cs, &mut sizes),
             interleaved: render_interleaved(cs, &mut sizes),
             constraints: render_constraints(cs),
+            boundaries: super::boundaries(cs),
         },
     )?;
 
     if let Some(filename) = out_filename.as_ref() {
-        std::fs::File::create(filename)
-            .with_context(|| format!("while creating `{}`", filename))?
+        super::create_output(filename, false, false)?
             .write_all(r.as_bytes())
             .with_context(|| format!("while writing to `{}`", filename))?;
-        super::gofmt(filename);
+        if filename.as_str() != super::STDOUT {
+            super::gofmt(filename);
+        }
     } else {
         println!("{}", r);
     }
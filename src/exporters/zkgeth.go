@@ -22,3 +22,17 @@ var AllRegisters = column.ColumnList{
 	{{this.1}},
 	{{/each}}
 }
+
+// The shifts of the constraints reaching beyond their current row, and the
+// spilling of their module, so that its first and last rows are handled
+// correctly
+var Boundaries = []struct {
+	Constraint string
+	Module     string
+	Shifts     []int
+	Spilling   int
+}{
+	{{#each boundaries}}
+	{"{{{this.constraint}}}", "{{{this.module}}}", []int{ {{#each this.shifts}}{{#unless @first}}, {{/unless}}{{this}}{{/each}} }, {{this.spilling}}},
+	{{/each}}
+}
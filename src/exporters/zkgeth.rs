@@ -5,7 +5,7 @@ use itertools::Itertools;
 use serde::Serialize;
 use std::io::Write;
 
-use crate::compiler::*;
+use crate::{column::Computation, compiler::*};
 
 #[derive(Serialize)]
 struct GoConstant {
@@ -24,9 +24,73 @@ struct TemplateData {
     columns: Vec<GoColumn>,
     constants: Vec<GoConstant>,
     registers: Vec<(usize, String)>,
+    boundaries: Vec<super::Boundary>,
 }
 
-pub fn render(cs: &ConstraintSet, package: &str, outfile: Option<&String>) -> Result<()> {
+/// A column, as listed in the registration manifest
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub(crate) struct ManifestColumn {
+    pub name: String,
+    pub module: String,
+    pub kind: String,
+    /// whether the column is computed by corset rather than filled by the
+    /// trace producers
+    pub computed: bool,
+    /// the handles of the columns a computed column is derived from
+    pub dependencies: Vec<String>,
+}
+
+/// The columns a computed column is derived from, according to `computation`
+fn computation_dependencies(computation: &Computation) -> Vec<ColumnRef> {
+    match computation {
+        Computation::Composite { exp, .. } => exp.dependencies().into_iter().collect(),
+        Computation::ExoOperation { sources, .. } => {
+            sources.iter().flat_map(|s| s.dependencies()).collect()
+        }
+        Computation::ExoConstant { .. } => vec![],
+        Computation::Interleaved { froms, .. }
+        | Computation::Sorted { froms, .. }
+        | Computation::CyclicFrom { froms, .. } => froms.clone(),
+        Computation::SortingConstraints { sorted, .. } => sorted.clone(),
+    }
+}
+
+/// List all the columns of `cs`, so that trace producers can be wired up
+/// without parsing the Go output.
+pub(crate) fn manifest(cs: &ConstraintSet) -> Vec<ManifestColumn> {
+    cs.columns
+        .iter()
+        .map(|(r, c)| ManifestColumn {
+            name: c.handle.mangled_name(),
+            module: c.handle.module.clone(),
+            kind: match c.kind {
+                Kind::Commitment => "Commitment",
+                Kind::Computed => "Computed",
+                Kind::Expression(_) => "Expression",
+            }
+            .to_owned(),
+            computed: !matches!(c.kind, Kind::Commitment),
+            dependencies: cs
+                .computations
+                .computation_for(&r)
+                .map(computation_dependencies)
+                .unwrap_or_default()
+                .iter()
+                .map(|d| cs.handle(d).to_string())
+                .sorted()
+                .dedup()
+                .collect(),
+        })
+        .sorted_by(|a, b| (&a.module, &a.name).cmp(&(&b.module, &b.name)))
+        .collect()
+}
+
+pub fn render(
+    cs: &ConstraintSet,
+    package: &str,
+    outfile: Option<&String>,
+    manifest_file: Option<&String>,
+) -> Result<()> {
     const TEMPLATE: &str = include_str!("zkgeth.go");
     let columns = cs
         .columns
@@ -72,15 +136,23 @@ pub fn render(cs: &ConstraintSet, package: &str, outfile: Option<&String>) -> Re
             columns,
             registers,
             constants,
+            boundaries: super::boundaries(cs),
         },
     )?;
 
+    if let Some(filename) = manifest_file {
+        super::create_output(filename, false, false)?
+            .write_all(serde_json::to_string_pretty(&manifest(cs))?.as_bytes())
+            .with_context(|| format!("while writing to `{}`", filename))?;
+    }
+
     if let Some(filename) = outfile.as_ref() {
-        std::fs::File::create(filename)
-            .with_context(|| format!("while creating `{}`", filename))?
+        super::create_output(filename, false, false)?
             .write_all(r.as_bytes())
             .with_context(|| format!("while writing to `{}`", filename))?;
-        super::gofmt(filename);
+        if filename.as_str() != super::STDOUT {
+            super::gofmt(filename);
+        }
         Ok(())
     } else {
         println!("{}", r);
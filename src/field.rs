@@ -0,0 +1,28 @@
+//! The scalar field in which the constraints are evaluated.
+//!
+//! It is the scalar field of BLS12-377 by default, or the one of BLS12-381
+//! if the `bls12-381` feature is enabled.
+
+#[cfg(not(feature = "bls12-381"))]
+pub use ark_bls12_377::Fr;
+
+/// The name of the scalar field, recorded in the compiled constraint sets
+#[cfg(not(feature = "bls12-381"))]
+pub const FIELD: &str = "bls12-377";
+
+#[cfg(feature = "bls12-381")]
+pub const FIELD: &str = "bls12-381";
+
+#[cfg(feature = "bls12-381")]
+pub use bls12_381::Fr;
+
+#[cfg(feature = "bls12-381")]
+mod bls12_381 {
+    use ark_ff::fields::{Fp256, MontBackend, MontConfig};
+
+    #[derive(MontConfig)]
+    #[modulus = "52435875175126190479447740508185965837690552500527637822603658699938581184513"]
+    #[generator = "7"]
+    pub struct FrConfig;
+    pub type Fr = Fp256<MontBackend<FrConfig, 4>>;
+}
@@ -555,6 +555,7 @@ impl AstNode {
             Token::BlockComment(_) => 0,
             Token::Value(x) => x.to_string().len(),
             Token::Symbol(s) | Token::Keyword(s) => s.len(),
+            Token::Text(s) => s.len() + 2,
             Token::List(ns) => ns.iter().map(|n| n.len() + 1).sum::<usize>() + 2,
             Token::Domain(domain) => {
                 domain
@@ -563,6 +564,7 @@ impl AstNode {
                     .sum::<usize>()
                     + 1
             }
+            Token::Values(xs) => xs.iter().map(|x| x.len() + 1).sum::<usize>() + 1,
             Token::DefModule(m) => 2 + "module".len() + 1 + m.len(),
             _ => 0,
         }
@@ -603,6 +605,10 @@ impl AstNode {
                         tty.write(kw.to_lowercase());
                         false
                     }
+                    Token::Text(_) => {
+                        tty.write(&self.src);
+                        false
+                    }
                     Token::IndexedSymbol { name, index } => {
                         tty.write(format!("[{} {}]", name, index));
                         false
@@ -628,7 +634,7 @@ impl AstNode {
                         };
                         false
                     }
-                    Token::Domain(_) => {
+                    Token::Domain(_) | Token::Values(_) => {
                         tty.write(&self.src);
                         false
                     }
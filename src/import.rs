@@ -1,24 +1,25 @@
 use super::compiler::{ColumnRef, Magma};
 use crate::column::Value as CValue;
+#[cfg(not(feature = "wasm"))]
+use crate::utils::parallel::*;
 use anyhow::*;
 use cached::Cached;
 use flate2::bufread::GzDecoder;
 use itertools::Itertools;
 use log::*;
 use logging_timer::time;
-use num_bigint::{BigInt, Sign};
+use num_bigint::BigInt;
+#[cfg(not(feature = "wasm"))]
+use num_bigint::Sign;
 use owo_colors::OwoColorize;
-use rayon::prelude::*;
 #[cfg(not(all(target_arch = "x86_64", target_feature = "avx")))]
 use serde_json::Value;
 #[cfg(all(target_arch = "x86_64", target_feature = "avx"))]
 use simd_json::BorrowedValue as Value;
-#[cfg(all(target_arch = "x86_64", target_feature = "avx"))]
+use std::io::BufReader;
 use std::io::Read;
-use std::{
-    fs::File,
-    io::{BufReader, Seek},
-};
+#[cfg(not(feature = "wasm"))]
+use std::{fs::File, io::Seek};
 
 use crate::{
     column::{Column, Register},
@@ -27,6 +28,94 @@ use crate::{
     structs::Handle,
 };
 
+/// Fill the columns of the fixed tables declared in `cs` from their CSV
+/// files, one row per line and one value per column; a leading header line is
+/// skipped. Tables already present in the imported trace are left as is.
+pub fn import_lookup_tables(cs: &mut ConstraintSet) -> Result<()> {
+    for table in cs.lookup_tables.clone() {
+        if table
+            .columns
+            .iter()
+            .all(|h| cs.columns.is_computed(&h.clone().into()))
+        {
+            continue;
+        }
+
+        let content = std::fs::read_to_string(&table.file)
+            .with_context(|| anyhow!("reading lookup table {}", table.file.bright_white()))?;
+        let mut columns = vec![Vec::new(); table.columns.len()];
+        let mut lines = content
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty());
+
+        // the first line is a header naming the columns of the table
+        let (i, header) = lines
+            .next()
+            .ok_or_else(|| anyhow!("{}: missing header", table.file))?;
+        let names = header.split(',').map(|x| x.trim()).collect::<Vec<_>>();
+        if names.iter().all(|x| parse_table_value(x).is_some()) {
+            bail!(
+                "{}:{}: expected a header naming the columns, found `{}`",
+                table.file,
+                i + 1,
+                header
+            );
+        }
+        if names.len() != columns.len() {
+            bail!(
+                "{}:{}: expected {} columns in the header, found {}",
+                table.file,
+                i + 1,
+                columns.len(),
+                names.len()
+            );
+        }
+
+        for (i, line) in lines {
+            let row = line
+                .split(',')
+                .map(|x| parse_table_value(x.trim()))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| anyhow!("{}:{}: invalid row `{}`", table.file, i + 1, line))?;
+            if row.len() != columns.len() {
+                bail!(
+                    "{}:{}: expected {} values, found {}",
+                    table.file,
+                    i + 1,
+                    columns.len(),
+                    row.len()
+                );
+            }
+            for (column, x) in columns.iter_mut().zip(row) {
+                column.push(CValue::try_from(x)?);
+            }
+        }
+
+        for (handle, xs) in table.columns.iter().zip(columns) {
+            import_column(cs, handle, xs, false)
+                .with_context(|| anyhow!("importing lookup table {}", table.file))?;
+        }
+    }
+    Ok(())
+}
+
+/// Parse a decimal or `0x`-prefixed hexadecimal value of a lookup table
+fn parse_table_value(x: &str) -> Option<BigInt> {
+    use num_traits::Num;
+
+    let (sign, x) = match x.strip_prefix('-') {
+        Some(x) => (-1, x),
+        None => (1, x),
+    };
+    match x.strip_prefix("0x") {
+        Some(hex) => BigInt::from_str_radix(hex, 16),
+        None => BigInt::from_str_radix(x, 10),
+    }
+    .ok()
+    .map(|x| x * sign)
+}
+
 #[derive(Debug)]
 struct RegisterHeader {
     handle: Handle,
@@ -56,13 +145,13 @@ impl std::fmt::Debug for TraceMap {
     }
 }
 
-struct TraceReader<Data: AsRef<[u8]>> {
+struct ByteCursor<Data: AsRef<[u8]>> {
     bytes: Data,
     cursor: usize,
 }
-impl<Data: AsRef<[u8]>> TraceReader<Data> {
+impl<Data: AsRef<[u8]>> ByteCursor<Data> {
     fn from(bytes: Data) -> Self {
-        TraceReader { bytes, cursor: 0 }
+        ByteCursor { bytes, cursor: 0 }
     }
 
     fn i8(&mut self) -> Result<i8> {
@@ -112,14 +201,13 @@ impl<Data: AsRef<[u8]>> TraceReader<Data> {
             .with_context(|| anyhow!("not enough bytes"))
     }
 
-    fn header(&mut self) -> Result<RegisterHeader> {
+    fn header(&mut self, separator: &str) -> Result<RegisterHeader> {
         let handle_length = self
             .i16()
             .with_context(|| anyhow!("parsing a register name length"))?;
         let handle_str = self
             .string(handle_length as usize)
             .with_context(|| anyhow!("parsing a register name"))?;
-        let mut splitted = handle_str.splitn(2, '.');
         let bytes_per_element =
             self.i8()
                 .with_context(|| anyhow!("parsing BPE for {}", handle_str))? as usize;
@@ -128,38 +216,58 @@ impl<Data: AsRef<[u8]>> TraceReader<Data> {
             .with_context(|| anyhow!("parsing length of {}", handle_str))?;
 
         Ok(RegisterHeader {
-            handle: Handle::new(splitted.next().unwrap(), splitted.next().unwrap()),
+            handle: Handle::from_trace_key(&handle_str, separator),
             bytes_per_element,
             length,
         })
     }
 
-    fn map(&mut self) -> Result<TraceMap> {
+    fn map(&mut self, separator: &str) -> Result<TraceMap> {
         let register_count = self.i32().with_context(|| "parsing register count")?;
         Ok(TraceMap {
             headers: (0..register_count)
-                .map(|_| self.header())
+                .map(|_| self.header(separator))
                 .collect::<Result<Vec<_>>>()?,
         })
     }
 }
 
+#[cfg(not(feature = "wasm"))]
 #[time("info", "Parsing binary traces")]
 pub fn parse_binary_trace(tracefile: &str, cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
     let file = File::open(tracefile)
         .with_context(|| anyhow!("opening {}", tracefile.bright_white().bold()))?;
-    let mut trace_reader = TraceReader::from(unsafe {
-        memmap2::MmapOptions::new()
-            .map(&file)
-            .with_context(|| anyhow!("memory mapping {}", tracefile.bright_white().bold()))?
-    });
-    let trace_map = trace_reader.map()?;
+    read_binary_trace(
+        unsafe {
+            memmap2::MmapOptions::new()
+                .map(&file)
+                .with_context(|| anyhow!("memory mapping {}", tracefile.bright_white().bold()))?
+        },
+        cs,
+        keep_raw,
+    )
+}
+
+/// Import the binary trace `bytes`, in the format of the `.lt` files, into `cs`
+#[cfg(not(feature = "wasm"))]
+pub fn read_binary_trace<Data: AsRef<[u8]>>(
+    bytes: Data,
+    cs: &mut ConstraintSet,
+    keep_raw: bool,
+) -> Result<()> {
+    let mut trace_reader = ByteCursor::from(bytes);
+    let trace_map = trace_reader.map(cs.columns.module_separator())?;
     for trace_register in trace_map.headers.into_iter() {
         let column_ref: ColumnRef = trace_register.handle.clone().into();
         let register_bytes = trace_reader
             .slice(trace_register.length as usize * trace_register.bytes_per_element)?;
 
-        if let Some(Register { magma, .. }) = cs.columns.register(&column_ref) {
+        if cs.columns.is_skipped(&column_ref) {
+            trace!("skipping {}", trace_register.handle.pretty());
+        } else if let Some(register @ Register { magma, .. }) = cs.columns.register(&column_ref) {
+            if register.len().is_some() {
+                bail!(duplicated_column(&column_ref));
+            }
             let mut xs = (if keep_raw { 0 } else { -1 }..trace_register.length)
                 .into_par_iter()
                 .map(|i| {
@@ -203,16 +311,31 @@ pub fn parse_binary_trace(tracefile: &str, cs: &mut ConstraintSet, keep_raw: boo
             })?;
 
             // If the parsed column is not long enought w.r.t. the
-            // minimal module length, prepend it with as many zeroes as
+            // minimal module length, pad it with as many zeroes as
             // required.
             // Atomic columns are always padded with zeroes, so there is
             // no need to trigger a more complex padding system.
+            let strategy = cs.columns.padding_strategy;
+            let (padding, trailing) = if keep_raw {
+                (0, 0)
+            } else {
+                (
+                    1 + strategy.leading_rows(xs.len(), module_min_len),
+                    strategy.trailing_rows(xs.len(), module_min_len),
+                )
+            };
             if !keep_raw && xs.len() < module_min_len {
-                xs.reverse();
-                xs.resize(module_min_len, CValue::zero()); // TODO: register padding values
-                xs.reverse();
+                strategy.pad(&mut xs, module_min_len, CValue::zero); // TODO: register padding values
             }
 
+            cs.columns
+                .padding
+                .entry(trace_register.handle.module.clone())
+                .or_insert(padding as isize);
+            cs.columns
+                .trailing_padding
+                .entry(trace_register.handle.module.clone())
+                .or_insert(trailing as isize);
             let module_raw_size =
                 cs.effective_len_or_set(&trace_register.handle.module, xs.len() as isize);
             if xs.len() as isize != module_raw_size {
@@ -234,6 +357,7 @@ pub fn parse_binary_trace(tracefile: &str, cs: &mut ConstraintSet, keep_raw: boo
     Ok(())
 }
 
+#[cfg(not(feature = "wasm"))]
 #[time("info", "Parsing trace from JSON file with SIMD")]
 pub fn parse_json_trace(tracefile: &str, cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
     let mut f = File::open(tracefile).with_context(|| format!("while opening `{}`", tracefile))?;
@@ -271,20 +395,24 @@ pub fn parse_json_trace(tracefile: &str, cs: &mut ConstraintSet, keep_raw: bool)
     }
 }
 
+/// Decompress `trace` if it is gzipped, or return it as is otherwise
+pub fn decompress_trace(trace: &[u8]) -> Result<Vec<u8>> {
+    let mut gz = GzDecoder::new(BufReader::new(trace));
+    if gz.header().is_some() {
+        let mut content = Vec::new();
+        gz.read_to_end(&mut content)
+            .with_context(|| "while decompressing trace")?;
+        Ok(content)
+    } else {
+        Ok(trace.to_vec())
+    }
+}
+
 #[time("info", "Parsing trace from JSON with SIMD")]
 pub fn read_trace_str(tracestr: &[u8], cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
     #[cfg(all(target_arch = "x86_64", target_feature = "avx"))]
     {
-        let mut content = Vec::new();
-        let mut gz = GzDecoder::new(BufReader::new(tracestr));
-        match gz.header() {
-            Some(_) => {
-                gz.read_to_end(&mut content)?;
-            }
-            None => {
-                content = tracestr.to_vec();
-            }
-        };
+        let mut content = decompress_trace(tracestr)?;
         let v = simd_json::to_borrowed_value(&mut content)
             .map_err(|e| anyhow!("while parsing json: {}", e))?;
         fill_traces_from_json(&v, vec![], cs, &mut None, keep_raw)
@@ -292,16 +420,173 @@ pub fn read_trace_str(tracestr: &[u8], cs: &mut ConstraintSet, keep_raw: bool) -
     }
     #[cfg(not(all(target_arch = "x86_64", target_feature = "avx")))]
     {
-        let gz = GzDecoder::new(BufReader::new(tracestr));
-        let v: Value = match gz.header() {
-            Some(_) => serde_json::from_reader(gz),
-            None => serde_json::from_reader(BufReader::new(tracestr)),
-        }?;
+        let v: Value = serde_json::from_slice(&decompress_trace(tracestr)?)?;
         fill_traces_from_json(&v, vec![], cs, &mut None, keep_raw)
             .with_context(|| "while reading columns")
     }
 }
 
+/// A trace format, turning trace files into the values of the columns of a
+/// [`ConstraintSet`].
+///
+/// JSON and binary traces are readily supported by [`JsonTrace`] and
+/// [`BinaryTrace`]; other formats may be imported by implementing this trait,
+/// typically relying on [`import_column`], and giving the reader to e.g.
+/// [`crate::compute::compute_trace_with`].
+pub trait TraceReader {
+    /// The name of the format, as given to `--input-format`
+    fn name(&self) -> &str;
+
+    /// Whether `tracefile` should be read with this reader when no format is
+    /// explicitly given
+    fn accepts(&self, tracefile: &str) -> bool;
+
+    /// Import the trace `trace`, already read from a file, into `cs`; if
+    /// `keep_raw` is set, the columns are not preceded by a padding row
+    fn read_bytes(&self, trace: &[u8], cs: &mut ConstraintSet, keep_raw: bool) -> Result<()>;
+
+    /// Import the trace stored in `tracefile` into `cs`
+    fn read_file(&self, tracefile: &str, cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
+        let trace = std::fs::read(tracefile)
+            .with_context(|| anyhow!("opening {}", tracefile.bright_white().bold()))?;
+        self.read_bytes(&trace, cs, keep_raw)
+    }
+}
+
+/// JSON traces, possibly gzipped
+pub struct JsonTrace;
+impl TraceReader for JsonTrace {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn accepts(&self, tracefile: &str) -> bool {
+        !BinaryTrace.accepts(tracefile)
+    }
+
+    fn read_bytes(&self, trace: &[u8], cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
+        read_trace_str(trace, cs, keep_raw)
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn read_file(&self, tracefile: &str, cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
+        parse_json_trace(tracefile, cs, keep_raw)
+    }
+}
+
+/// Binary traces, as stored in `.lt` files
+pub struct BinaryTrace;
+impl TraceReader for BinaryTrace {
+    fn name(&self) -> &str {
+        "binary"
+    }
+
+    fn accepts(&self, tracefile: &str) -> bool {
+        tracefile.ends_with("lt")
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn read_bytes(&self, trace: &[u8], cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
+        read_binary_trace(trace, cs, keep_raw)
+    }
+    #[cfg(feature = "wasm")]
+    fn read_bytes(&self, _trace: &[u8], _cs: &mut ConstraintSet, _keep_raw: bool) -> Result<()> {
+        bail!("binary traces are not supported in WASM")
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn read_file(&self, tracefile: &str, cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
+        parse_binary_trace(tracefile, cs, keep_raw)
+    }
+}
+
+/// The built-in trace readers
+pub const TRACE_READERS: [&dyn TraceReader; 2] = [&BinaryTrace, &JsonTrace];
+
+/// The reader to use for `tracefile`: the built-in one named `format` if any,
+/// or else the first one accepting `tracefile`
+pub fn trace_reader_for(tracefile: &str, format: Option<&str>) -> Result<&'static dyn TraceReader> {
+    if let Some(format) = format {
+        TRACE_READERS
+            .into_iter()
+            .find(|r| r.name() == format)
+            .ok_or_else(|| {
+                anyhow!(
+                    "unknown trace format {}; expected one of {}",
+                    format.red().bold(),
+                    TRACE_READERS.iter().map(|r| r.name()).join(", ")
+                )
+            })
+    } else {
+        Ok(TRACE_READERS
+            .into_iter()
+            .find(|r| r.accepts(tracefile))
+            .unwrap_or(&JsonTrace))
+    }
+}
+
+/// Import the values `xs` of the column `handle` into `cs`, with the
+/// validation, padding, and length checks applied to the built-in formats;
+/// columns that do not exist in `cs` are ignored.
+pub fn import_column(
+    cs: &mut ConstraintSet,
+    handle: &Handle,
+    xs: Vec<CValue>,
+    keep_raw: bool,
+) -> Result<()> {
+    let handle: ColumnRef = handle.clone().into();
+    let (magma, is_register) = if cs.columns.is_skipped(&handle) {
+        trace!("skipping {}", handle);
+        return Ok(());
+    } else if let Result::Ok(Column { t, .. }) = cs.columns.column(&handle) {
+        if cs.columns.is_computed(&handle) {
+            bail!(duplicated_column(&handle));
+        }
+        (*t, false)
+    } else if let Some(register @ Register { magma, .. }) = cs.columns.register(&handle) {
+        if register.len().is_some() {
+            bail!(duplicated_column(&handle));
+        }
+        (*magma, true)
+    } else {
+        debug!("ignoring unknown column {}", handle.pretty());
+        return Ok(());
+    };
+
+    let mut values = if keep_raw {
+        Vec::with_capacity(xs.len())
+    } else {
+        vec![CValue::zero()]
+    };
+    for x in xs.into_iter() {
+        values.push(
+            magma
+                .rm()
+                .validate(x)
+                .with_context(|| anyhow!("importing {}", handle.pretty()))?,
+        );
+    }
+    if is_register {
+        store_register(cs, &handle, values, None, keep_raw)
+    } else {
+        store_column(cs, &handle, values, None, keep_raw)
+    }
+}
+
+/// Parse `x`, going through `cache` for the values already encountered
+fn parse_cached<K: std::hash::Hash + Eq + Clone>(
+    cache: &mut cached::SizedCache<K, CValue>,
+    k: K,
+    x: &str,
+) -> Result<CValue> {
+    if let Some(v) = cache.cache_get(&k) {
+        return Ok(v.to_owned());
+    }
+    let v = CValue::try_from(x)?;
+    cache.cache_set(k, v.clone());
+    Ok(v)
+}
+
 #[cfg(not(all(target_arch = "x86_64", target_feature = "avx")))]
 fn parse_column(xs: &[Value], h: &Handle, t: Magma, keep_raw: bool) -> Result<Vec<CValue>> {
     let mut cache_num = cached::SizedCache::with_size(200000); // ~1.60MB cache
@@ -314,16 +599,13 @@ fn parse_column(xs: &[Value], h: &Handle, t: Magma, keep_raw: bool) -> Result<Ve
     let xs = xs
         .iter()
         .map(|x| match x {
-            Value::Number(n) => t.rm().validate(
-                cache_num
-                    .cache_get_or_set_with(n, || CValue::from(n.as_str()))
-                    .to_owned(),
-            ),
-            Value::String(s) => t.rm().validate(
-                cache_str
-                    .cache_get_or_set_with(s.clone(), || CValue::from(s.as_str()))
-                    .to_owned(),
-            ),
+            Value::Number(n) => t
+                .rm()
+                .validate(parse_cached(&mut cache_num, n, n.as_str())?),
+            Value::String(s) => {
+                t.rm()
+                    .validate(parse_cached(&mut cache_str, s.clone(), s.as_str())?)
+            }
             _ => bail!("expected numeric value, found `{}`", x),
         })
         .collect::<Result<Vec<_>>>()?;
@@ -357,11 +639,8 @@ fn parse_column(xs: &[Value], h: &Handle, t: Magma, keep_raw: bool) -> Result<Ve
                 Value::String(s) => s.to_string(),
                 _ => bail!("expected numeric value, found `{}`", x),
             };
-            t.rm().validate(
-                cache
-                    .cache_get_or_set_with(s.clone(), || CValue::from(s.as_str()))
-                    .to_owned(),
-            )
+            t.rm()
+                .validate(parse_cached(&mut cache, s.clone(), s.as_str())?)
         })
         .collect::<Result<Vec<_>>>()?;
     r.extend(xs);
@@ -371,6 +650,12 @@ fn parse_column(xs: &[Value], h: &Handle, t: Magma, keep_raw: bool) -> Result<Ve
     Ok(r)
 }
 
+/// A column may only be filled once, even when a trace is split across
+/// several files.
+fn duplicated_column(h: &ColumnRef) -> anyhow::Error {
+    anyhow!("{} is defined by several traces", h.to_string().blue())
+}
+
 pub fn fill_traces_from_json(
     v: &Value,
     path: Vec<String>,
@@ -399,15 +684,13 @@ pub fn fill_traces_from_json(
                 let module = path[path.len() - 2].to_string();
                 let handle: ColumnRef = Handle::new(&module, &path[path.len() - 1]).into();
 
-                // The min length can be set if the module contains range
-                // proofs, that require a minimal length of a certain power of 2
-                let module_min_len = cs.columns.min_len.get(&module).cloned().unwrap_or(0);
-                let module_spilling = cs.spilling_for_column(&handle);
-
-                if let Result::Ok(Column {
-                    t, padding_value, ..
-                }) = cs.columns.column(&handle)
-                {
+                if cs.columns.is_skipped(&handle) {
+                    trace!("skipping {}", handle);
+                } else if let Result::Ok(Column { t, .. }) = cs.columns.column(&handle) {
+                    let t = *t;
+                    if cs.columns.is_computed(&handle) {
+                        bail!(duplicated_column(&handle));
+                    }
                     trace!("inserting {} ({})", handle, xs.len());
                     if let Some(first_column) = initiator.as_mut() {
                         if first_column.is_empty() {
@@ -415,75 +698,29 @@ pub fn fill_traces_from_json(
                         }
                     }
 
-                    let module_spilling = module_spilling
-                        .ok_or_else(|| anyhow!("no spilling found for {}", handle.pretty()))?;
-
-                    let mut xs = parse_column(xs, handle.as_handle(), *t, keep_raw)
+                    let xs = parse_column(xs, handle.as_handle(), t, keep_raw)
                         .with_context(|| anyhow!("importing {}", handle.pretty()))?;
-
-                    // If the parsed column is not long enought w.r.t. the
-                    // minimal module length, prepend it with as many zeroes as
-                    // required.
-                    // Atomic columns are always padded with zeroes, so there is
-                    // no need to trigger a more complex padding system.
-                    if !keep_raw && xs.len() < module_min_len {
-                        trace!(
-                            "padding {} to min module length ({} => {})",
-                            handle,
-                            xs.len(),
-                            module_min_len
-                        );
-                        xs.reverse();
-                        xs.resize_with(module_min_len, || {
-                            padding_value.clone().unwrap_or_default()
-                        });
-                        xs.reverse();
-                    }
-
-                    // The first column sets the size of its module
-                    let module_raw_size = cs.effective_len_or_set(&module, xs.len() as isize);
-                    if xs.len() as isize != module_raw_size {
-                        bail!(
-                            "{} has an incorrect length: expected {} (from {}), found {}",
-                            handle.to_string().blue(),
-                            module_raw_size.to_string().red().bold(),
-                            initiator.as_ref().unwrap(),
-                            xs.len().to_string().yellow().bold(),
-                        );
+                    store_column(
+                        cs,
+                        &handle,
+                        xs,
+                        initiator.as_ref().map(|i| i.as_str()),
+                        keep_raw,
+                    )?;
+                } else if let Some(register @ Register { magma, .. }) = cs.columns.register(&handle)
+                {
+                    if register.len().is_some() {
+                        bail!(duplicated_column(&handle));
                     }
-
-                    cs.columns.set_column_value(&handle, xs, module_spilling)?
-                } else if let Some(Register { magma, .. }) = cs.columns.register(&handle) {
-                    let module_spilling = module_spilling
-                        .ok_or_else(|| anyhow!("no spilling found for {}", handle.pretty()))?;
-
-                    let mut xs = parse_column(xs, handle.as_handle(), *magma, keep_raw)
+                    let xs = parse_column(xs, handle.as_handle(), *magma, keep_raw)
                         .with_context(|| anyhow!("importing {}", handle.pretty()))?;
-
-                    // If the parsed column is not long enought w.r.t. the
-                    // minimal module length, prepend it with as many zeroes as
-                    // required.
-                    // Atomic columns are always padded with zeroes, so there is
-                    // no need to trigger a more complex padding system.
-                    if xs.len() < module_min_len {
-                        xs.reverse();
-                        xs.resize(module_min_len, CValue::zero()); // TODO: register padding values
-                        xs.reverse();
-                    }
-
-                    let module_raw_size = cs.effective_len_or_set(&module, xs.len() as isize);
-                    if xs.len() as isize != module_raw_size {
-                        bail!(
-                            "{} has an incorrect length: expected {} (from {}), found {}",
-                            handle.to_string().blue(),
-                            module_raw_size.to_string().red().bold(),
-                            initiator.as_ref().unwrap(),
-                            xs.len().to_string().yellow().bold(),
-                        );
-                    }
-
-                    cs.columns
-                        .set_register_value(&handle, xs, module_spilling)?
+                    store_register(
+                        cs,
+                        &handle,
+                        xs,
+                        initiator.as_ref().map(|i| i.as_str()),
+                        keep_raw,
+                    )?;
                 } else {
                     debug!("ignoring unknown column {}", handle.pretty());
                 }
@@ -493,3 +730,120 @@ pub fn fill_traces_from_json(
         _ => Ok(()),
     }
 }
+
+/// Store the values `xs` of the column `handle`, already validated and
+/// preceded by the padding row, in `cs`; `initiator` is the column that set
+/// the length of the module, if any.
+fn store_column(
+    cs: &mut ConstraintSet,
+    handle: &ColumnRef,
+    mut xs: Vec<CValue>,
+    initiator: Option<&str>,
+    keep_raw: bool,
+) -> Result<()> {
+    let module = handle.as_handle().module.clone();
+    // The min length can be set if the module contains range
+    // proofs, that require a minimal length of a certain power of 2
+    let module_min_len = cs.columns.min_len.get(&module).cloned().unwrap_or(0);
+    let module_spilling = cs
+        .spilling_for_column(handle)
+        .ok_or_else(|| anyhow!("no spilling found for {}", handle.pretty()))?;
+    let padding_value = cs.columns.column(handle)?.padding_value.clone();
+
+    // If the parsed column is not long enought w.r.t. the
+    // minimal module length, pad it with as many zeroes as
+    // required.
+    // Atomic columns are always padded with zeroes, so there is
+    // no need to trigger a more complex padding system.
+    // parse_column already prepended a padding row
+    let strategy = cs.columns.padding_strategy;
+    let (padding, trailing) = if keep_raw {
+        (0, 0)
+    } else {
+        (
+            1 + strategy.leading_rows(xs.len(), module_min_len),
+            strategy.trailing_rows(xs.len(), module_min_len),
+        )
+    };
+    if !keep_raw && xs.len() < module_min_len {
+        trace!(
+            "padding {} to min module length ({} => {})",
+            handle,
+            xs.len(),
+            module_min_len
+        );
+        strategy.pad(&mut xs, module_min_len, || {
+            padding_value.clone().unwrap_or_default()
+        });
+    }
+
+    // The first column sets the size of its module
+    cs.columns
+        .padding
+        .entry(module.clone())
+        .or_insert(padding as isize);
+    cs.columns
+        .trailing_padding
+        .entry(module.clone())
+        .or_insert(trailing as isize);
+    let module_raw_size = cs.effective_len_or_set(&module, xs.len() as isize);
+    if xs.len() as isize != module_raw_size {
+        bail!(
+            "{} has an incorrect length: expected {} (from {}), found {}",
+            handle.to_string().blue(),
+            module_raw_size.to_string().red().bold(),
+            initiator.unwrap_or("previous columns"),
+            xs.len().to_string().yellow().bold(),
+        );
+    }
+
+    cs.columns.set_column_value(handle, xs, module_spilling)
+}
+
+/// Same as [`store_column`], for the register `handle`
+fn store_register(
+    cs: &mut ConstraintSet,
+    handle: &ColumnRef,
+    mut xs: Vec<CValue>,
+    initiator: Option<&str>,
+    keep_raw: bool,
+) -> Result<()> {
+    let module = handle.as_handle().module.clone();
+    let module_min_len = cs.columns.min_len.get(&module).cloned().unwrap_or(0);
+    let module_spilling = cs
+        .spilling_for_column(handle)
+        .ok_or_else(|| anyhow!("no spilling found for {}", handle.pretty()))?;
+
+    // If the parsed column is not long enought w.r.t. the
+    // minimal module length, pad it with as many zeroes as
+    // required.
+    // Atomic columns are always padded with zeroes, so there is
+    // no need to trigger a more complex padding system.
+    let strategy = cs.columns.padding_strategy;
+    let padding = usize::from(!keep_raw) + strategy.leading_rows(xs.len(), module_min_len);
+    let trailing = strategy.trailing_rows(xs.len(), module_min_len);
+    if xs.len() < module_min_len {
+        strategy.pad(&mut xs, module_min_len, CValue::zero); // TODO: register padding values
+    }
+
+    cs.columns
+        .padding
+        .entry(module.clone())
+        .or_insert(padding as isize);
+    cs.columns
+        .trailing_padding
+        .entry(module.clone())
+        .or_insert(trailing as isize);
+    let module_raw_size = cs.effective_len_or_set(&module, xs.len() as isize);
+    if xs.len() as isize != module_raw_size {
+        bail!(
+            "{} has an incorrect length: expected {} (from {}), found {}",
+            handle.to_string().blue(),
+            module_raw_size.to_string().red().bold(),
+            initiator.unwrap_or("previous columns"),
+            xs.len().to_string().yellow().bold(),
+        );
+    }
+
+    cs.columns.set_register_value(handle, xs, module_spilling)
+}
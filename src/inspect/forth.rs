@@ -367,7 +367,7 @@ pub fn parse(s: &str, module: &str, columns: &HashMap<String, ColumnRef>) -> Res
                     }
                 });
             }
-            Token::Const(x) => stack.push(Node::Const(Value::from(x.to_string().as_str()))), // TODO: Value::from BigInt
+            Token::Const(x) => stack.push(Node::Const(Value::try_from(x.to_string().as_str())?)), // TODO: Value::from BigInt
             Token::Column(s, c) => stack.push(Node::Column(s, c)),
         }
     }
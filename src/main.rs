@@ -10,44 +10,61 @@ use logging_timer::time;
 use owo_colors::OwoColorize;
 use std::sync::RwLock;
 use std::{
-    io::{Read, Write},
+    collections::HashMap,
+    io::{IsTerminal, Read, Write},
     path::Path,
 };
 use transformer::{AutoConstraint, ExpansionLevel};
 
 use clap::{Parser, Subcommand};
 
+#[macro_use]
+mod output;
+
 mod check;
 mod column;
+mod columns;
 mod compiler;
 mod compute;
 mod constants;
+#[cfg(any(feature = "postgres", test))]
+mod cursor;
 mod dag;
+mod deftest;
+mod diff;
 mod errors;
 #[cfg(test)]
 mod evaluation_tests;
+mod explain;
 mod exporters;
+mod field;
 mod formatter;
 mod import;
 #[cfg(feature = "inspector")]
 mod inspect;
 mod pretty;
+mod progress;
+mod schema;
+mod stats;
+mod storage;
 mod structs;
 #[cfg(test)]
 mod tests;
 mod transformer;
 mod utils;
+mod watch;
 
 pub(crate) static IS_NATIVE: RwLock<bool> = RwLock::new(false);
 
 #[derive(Parser)]
-#[command(author, version = concat!(clap::crate_version!(), " ", std::env!("GIT_HASH"), " ", std::env!("SIMD_ENABLED")), propagate_version = true)]
+#[command(author, version = concat!(clap::crate_version!(), " ", std::env!("GIT_HASH"), " ", std::env!("SIMD_ENABLED"), ", ", std::env!("FIELD")), propagate_version = true,
+    after_help = "Exit codes: 0 on success, 1 if some constraints do not hold, 2 on compilation errors, 3 on trace or I/O errors, 4 on any other error.")]
 pub struct Args {
     #[clap(flatten)]
     verbose: clap_verbosity_flag::Verbosity,
 
     #[arg(
-        help = "Either a file or a string containing the Corset code to process",
+        help = "Either a file, a string containing the Corset code to process, or `-` to read it from stdin",
         global = true
     )]
     source: Vec<String>,
@@ -58,9 +75,37 @@ pub struct Args {
     #[arg(long="auto-constraints", value_parser=["sorts", "nhood"], value_delimiter=',', global=true)]
     auto_constraints: Vec<String>,
 
+    #[arg(
+        long = "no-auto-range",
+        help = "do not range-constrain the columns of sized integer types in the WizardIOP and Go exports",
+        global = true
+    )]
+    no_auto_range: bool,
+
     #[arg(long = "debug", help = "Compile code in debug mode", global = true)]
     debug: bool,
 
+    #[arg(
+        long = "strict-types",
+        help = "reject the implicit widening of booleans to integers by arithmetic operations",
+        global = true
+    )]
+    strict_types: bool,
+
+    #[arg(
+        long = "deny-unused",
+        help = "fail on the columns never used and the functions never called",
+        global = true
+    )]
+    deny_unused: bool,
+
+    #[arg(
+        long,
+        help = "report the number of modules, columns, constraints, and functions of the constraint set",
+        global = true
+    )]
+    summary: bool,
+
     #[arg(
         long,
         help = "generate binfile using Rusty Object Notation (RON) instead of JSON",
@@ -71,8 +116,9 @@ pub struct Args {
     #[arg(
         short = 't',
         long = "threads",
-        help = "number of threads to use",
-        default_value_t = 1,
+        help = "number of threads to use; `auto` or 0 to use all the available cores",
+        default_value = "1",
+        value_parser = parse_threads,
         global = true
     )]
     threads: usize,
@@ -88,6 +134,70 @@ pub struct Args {
     #[arg(long = "no-stdlib")]
     no_stdlib: bool,
 
+    #[arg(
+        long = "mmap",
+        help = "store the large columns in memory-mapped files created in this directory",
+        global = true
+    )]
+    mmap: Option<String>,
+
+    #[arg(
+        long = "cse",
+        help = "compute the subexpressions repeated at least this many times once, in dedicated columns",
+        num_args = 0..=1,
+        default_missing_value = "2",
+        value_name = "MIN_OCCURRENCES",
+        global = true
+    )]
+    cse: Option<usize>,
+
+    #[arg(
+        long = "warn-duplicates",
+        help = "warn about the constraints structurally identical to another one",
+        global = true
+    )]
+    warn_duplicates: bool,
+
+    #[arg(
+        long = "dedup",
+        help = "remove the constraints structurally identical to another one",
+        global = true
+    )]
+    dedup: bool,
+
+    #[arg(
+        long = "progress",
+        help = "report the progress of long computations and checks, even if stdout is not a terminal",
+        global = true
+    )]
+    progress: bool,
+
+    #[arg(
+        long = "module-separator",
+        help = "the separator between module and column names in the traces",
+        default_value = structs::DEFAULT_TRACE_MODULE_SEPARATOR,
+        global = true
+    )]
+    module_separator: String,
+
+    #[arg(
+        long = "color",
+        help = "when to color the output; `auto` colors terminals, unless NO_COLOR is set",
+        value_parser = ["auto", "always", "never"],
+        default_value = "auto",
+        global = true
+    )]
+    color: String,
+
+    #[arg(
+        long = "error-format",
+        help = "how to report errors; `rich` points at the offending code in its source",
+        value_parser = ["short", "rich"],
+        default_value = "short",
+        global = true
+    )]
+    error_format: String,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -108,15 +218,31 @@ enum Commands {
         #[arg(
             short = 'o',
             long = "columns-file",
-            help = "where to render the columns"
+            help = "where to render the columns; `-` for the standard output"
         )]
         filename: Option<String>,
+
+        #[arg(
+            long = "manifest",
+            help = "where to also list the columns as JSON, for the trace producers"
+        )]
+        manifest: Option<String>,
+
+        #[arg(long, help = "fail if a constraint has a larger degree")]
+        max_degree: Option<usize>,
     },
     #[cfg(feature = "exporters")]
     /// Produce a WizardIOP constraint system
     WizardIOP {
-        #[arg(short = 'o', long = "out", help = "where to render the constraints")]
+        #[arg(
+            short = 'o',
+            long = "out",
+            help = "where to render the constraints; `-` for the standard output"
+        )]
         out_filename: Option<String>,
+
+        #[arg(long, help = "fail if a constraint has a larger degree")]
+        max_degree: Option<usize>,
     },
     #[cfg(feature = "exporters")]
     /// Export columns in a format usable by zkBesu
@@ -144,9 +270,103 @@ enum Commands {
         #[arg(
             short = 'o',
             long = "constraints-file",
-            help = "where to render the constraints"
+            help = "where to render the constraints; `-` for the standard output"
         )]
         constraints_filename: Option<String>,
+
+        #[arg(
+            long = "columns-file",
+            help = "where to render the columns table, if not alongside the constraints"
+        )]
+        columns_filename: Option<String>,
+
+        #[arg(
+            long = "out",
+            conflicts_with_all = ["constraints_filename", "columns_filename"],
+            help = "render both the columns table and the constraints as sections of this file"
+        )]
+        out: Option<String>,
+
+        #[arg(
+            long = "constraints-only",
+            conflicts_with = "columns_only",
+            help = "do not render the columns table"
+        )]
+        constraints_only: bool,
+
+        #[arg(long = "columns-only", help = "do not render the constraints")]
+        columns_only: bool,
+    },
+    /// Export the dependencies between columns and constraints as a graphviz DOT graph
+    Deps {
+        #[arg(short = 'o', long = "out", help = "where to render the graph")]
+        out: String,
+    },
+    /// Export the JSON schema of the traces, listing the columns to provide
+    Schema {
+        #[arg(
+            short = 'o',
+            long = "out",
+            required = true,
+            help = "where to write the schema; `-` for the standard output"
+        )]
+        out: String,
+    },
+    /// List the columns defined by the sources, grouped by module
+    Columns {
+        #[arg(long, help = "list the columns as JSON")]
+        json: bool,
+    },
+    /// Run the deftest blocks of the sources
+    Test,
+    /// Compare the columns of two JSON traces
+    Diff {
+        #[arg(
+            short = 'L',
+            long,
+            required = true,
+            help = "the first trace to compare"
+        )]
+        left: String,
+
+        #[arg(
+            short = 'R',
+            long,
+            required = true,
+            help = "the second trace to compare"
+        )]
+        right: String,
+
+        #[arg(long, help = "report the differences as JSON")]
+        json: bool,
+
+        #[arg(
+            long = "trace-format",
+            help = "how the field elements of the computed traces are written",
+            value_enum,
+            default_value_t
+        )]
+        trace_format: compiler::generator::TraceRadix,
+    },
+    /// Report summary statistics of the values of the columns of a computed trace
+    Stats {
+        #[arg(
+            short = 'T',
+            long = "trace",
+            required = true,
+            help = "the trace to compute from"
+        )]
+        tracefile: String,
+
+        #[arg(
+            long,
+            default_value_t = 5,
+            help = "how many of the most frequent values to report per column"
+        )]
+        top: usize,
+
+        #[arg(long, help = "report the statistics as JSON")]
+        json: bool,
     },
     /// Given a set of constraints and a trace file, fill the computed columns
     Convert {
@@ -171,26 +391,119 @@ enum Commands {
         #[arg(short='F', long="format", help="output format", value_parser=["csv", "json", "lt"], default_value="sqlite")]
         format: String,
     },
+    /// Show how the value of a computed column at a given row is derived
+    Explain {
+        #[arg(
+            short = 'T',
+            long = "trace",
+            required = true,
+            help = "the trace to compute from"
+        )]
+        tracefile: String,
+
+        #[arg(
+            long,
+            required = true,
+            help = "the column to explain, as `module.column`"
+        )]
+        column: String,
+
+        #[arg(long, required = true, help = "the row to explain")]
+        row: usize,
+    },
     /// Given a set of constraints and a trace file, fill the computed columns
     Compute {
         #[arg(
             short = 'T',
             long = "trace",
             required = true,
-            help = "the trace to compute & verify"
+            help = "the trace to compute & verify; may be repeated to merge a trace split across several files"
         )]
-        tracefile: String,
+        tracefiles: Vec<String>,
+
+        #[arg(
+            long = "input-format",
+            help = "the format of the traces, instead of guessing it from their extension",
+            value_parser = ["json", "binary"]
+        )]
+        input_format: Option<String>,
 
         #[arg(
             short = 'o',
             long = "out",
-            help = "where to write the computed trace",
-            required = true
+            help = "where to write the computed trace; `-` for the standard output",
+            required_unless_present = "dry_run",
+            conflicts_with = "dry_run"
         )]
         outfile: Option<String>,
 
+        #[arg(long, help = "gzip the computed trace")]
+        gzip: bool,
+
+        #[arg(
+            long,
+            help = "write the gzipped trace to the standard output even if it is a terminal"
+        )]
+        force: bool,
+
+        #[arg(
+            long = "dry-run",
+            help = "only compute the trace and report the length of each module, without writing it"
+        )]
+        dry_run: bool,
+
         #[arg(long, help = "exit on failing columns")]
         fail_on_missing: bool,
+
+        #[arg(
+            long = "no-pad",
+            help = "compute the columns at the length of the trace, without prepending padding rows",
+            conflicts_with = "module_lens"
+        )]
+        no_pad: bool,
+
+        #[arg(
+            long = "pad",
+            help = "whether the rows padding the modules to their minimal length go before or after the values of the trace",
+            value_enum,
+            default_value_t
+        )]
+        pad: column::PaddingStrategy,
+
+        #[arg(
+            long = "trace-format",
+            help = "how to write the field elements",
+            value_enum,
+            default_value_t
+        )]
+        trace_format: compiler::generator::TraceRadix,
+
+        #[arg(
+            long = "only-modules",
+            alias = "only-module",
+            help = "only compute the columns of these modules",
+            value_delimiter = ','
+        )]
+        only_modules: Option<Vec<String>>,
+
+        #[arg(
+            long = "skip-modules",
+            alias = "skip-module",
+            help = "do not compute the columns of these modules",
+            value_delimiter = ','
+        )]
+        skip_modules: Vec<String>,
+
+        #[arg(
+            long = "module-len",
+            help = "pad MODULE to LENGTH rows, failing if the trace is longer, so that `len` knows the length of its columns; may be repeated",
+            value_name = "MODULE=LENGTH",
+            value_parser = parse_module_len
+        )]
+        module_lens: Vec<(String, usize)>,
+
+        #[arg(long, help = "compute again whenever a source file changes")]
+        watch: bool,
     },
     /// Given a set of constraints and a filled trace, check the validity of the constraints
     Check {
@@ -198,9 +511,16 @@ enum Commands {
             short = 'T',
             long = "trace",
             required = true,
-            help = "the trace to compute & verify"
+            help = "the trace to compute & verify; may be repeated to merge a trace split across several files"
         )]
-        tracefile: String,
+        tracefiles: Vec<String>,
+
+        #[arg(
+            long = "input-format",
+            help = "the format of the traces, instead of guessing it from their extension",
+            value_parser = ["json", "binary"]
+        )]
+        input_format: Option<String>,
 
         #[arg(
             short = 'F',
@@ -211,20 +531,58 @@ enum Commands {
 
         #[arg(
             long = "only",
-            help = "only check these constraints",
+            help = "only check these constraints, given by name or as `@tag`",
             value_delimiter = ','
         )]
         only: Option<Vec<String>>,
 
-        #[arg(long = "skip", help = "skip these constraints", value_delimiter = ',')]
+        #[arg(
+            long = "skip",
+            help = "skip these constraints, given by name or as `@tag`",
+            value_delimiter = ','
+        )]
         skip: Vec<String>,
 
+        #[arg(
+            long = "only-modules",
+            alias = "only-module",
+            help = "only check the constraints of these modules",
+            value_delimiter = ','
+        )]
+        only_modules: Option<Vec<String>>,
+
+        #[arg(
+            long = "skip-modules",
+            alias = "skip-module",
+            help = "skip the constraints of these modules",
+            value_delimiter = ','
+        )]
+        skip_modules: Vec<String>,
+
+        #[arg(
+            long = "module-len",
+            help = "pad MODULE to LENGTH rows, failing if the trace is longer, so that `len` knows the length of its columns; may be repeated",
+            value_name = "MODULE=LENGTH",
+            value_parser = parse_module_len
+        )]
+        module_lens: Vec<(String, usize)>,
+
+        #[arg(long, help = "check again whenever a source file changes")]
+        watch: bool,
+
         #[arg(
             long = "no-abort",
             help = "continue checking a constraint after it met an error"
         )]
         continue_on_error: bool,
 
+        #[arg(
+            long = "max-failures",
+            help = "with --no-abort, how many failing rows to report per constraint",
+            default_value_t = 10
+        )]
+        max_failures: usize,
+
         #[arg(short = 'r', long = "report", help = "detail the failing constraint")]
         report: bool,
 
@@ -260,6 +618,63 @@ enum Commands {
 
         #[arg(short = 'A', long = "trace-span-after", help = "")]
         trace_span_after: Option<isize>,
+
+        #[arg(
+            long = "sample",
+            help = "only check this many randomly sampled rows of each constraint"
+        )]
+        sample: Option<usize>,
+
+        #[arg(
+            long = "random-probe",
+            value_name = "N",
+            help = "report the passing constraints that do not vanish when their columns are filled with random values, on N attempts"
+        )]
+        random_probe: Option<usize>,
+
+        #[arg(
+            long = "seed",
+            help = "the seed used to sample rows and draw random probes"
+        )]
+        seed: Option<u64>,
+
+        #[arg(
+            long = "shift-mode",
+            help = "what reading a shifted column outside of the trace yields",
+            value_enum,
+            default_value_t = compiler::ShiftMode::Error
+        )]
+        shift_mode: compiler::ShiftMode,
+
+        #[arg(
+            long = "check-permutations",
+            help = "check that permuted columns hold exactly the rows of their sources"
+        )]
+        check_permutations: bool,
+
+        #[arg(
+            long,
+            help = "evaluate the subexpressions shared within a constraint only once per row"
+        )]
+        memoize: bool,
+
+        #[arg(long, help = "do not log anything when the constraints hold")]
+        quiet_success: bool,
+
+        #[arg(
+            long,
+            help = "warn (with -v) about suspicious traces, e.g. boolean columns that are constant over the whole trace"
+        )]
+        pedantic: bool,
+
+        #[arg(long, help = "fail the check on the --pedantic warnings")]
+        deny_warnings: bool,
+
+        #[arg(
+            long,
+            help = "report failing values as raw field elements, rather than showing p - x as -x"
+        )]
+        raw_values: bool,
     },
     /// Inspect a trace file
     #[cfg(feature = "inspector")]
@@ -326,14 +741,14 @@ enum Commands {
         show_types: bool,
         #[arg(
             long = "only",
-            help = "only show these constraints",
+            help = "only show these constraints, given by name or as `@tag`",
             value_delimiter = ',',
             requires = "show_constraints"
         )]
         only: Option<Vec<String>>,
         #[arg(
             long = "skip",
-            help = "do not show these constraints",
+            help = "do not show these constraints, given by name or as `@tag`",
             value_delimiter = ',',
             requires = "show_constraints"
         )]
@@ -374,12 +789,97 @@ enum Commands {
 
         #[arg(
             long = "only",
-            help = "only check these constraints",
+            help = "only check these constraints, given by name or as `@tag`",
+            value_delimiter = ','
+        )]
+        only: Option<Vec<String>>,
+
+        #[arg(
+            long = "skip",
+            help = "skip these constraints, given by name or as `@tag`",
+            value_delimiter = ','
+        )]
+        skip: Vec<String>,
+
+        #[arg(
+            long = "failures-table",
+            default_value = "failures",
+            help = "the table where the failing constraints of each block are recorded, see --failure-columns",
+            value_parser = parse_sql_table
+        )]
+        failures_table: String,
+
+        #[arg(
+            long = "failure-columns",
+            value_delimiter = ',',
+            default_values_t = ["block_id".to_string(), "constraint_name".to_string(), "row".to_string()],
+            help = "the columns of the failures table holding the block ID, the constraint name, and the row, in this order",
+            value_parser = parse_sql_column
+        )]
+        failure_columns: Vec<String>,
+
+        #[arg(
+            long = "block-timeout",
+            help = "give up on a block after this many seconds and mark it as `timeout`"
+        )]
+        block_timeout: Option<u64>,
+
+        #[arg(
+            long = "cursor-file",
+            conflicts_with = "rerun",
+            help = "process the blocks by ascending ID rather than by status, persisting the progress in this file"
+        )]
+        cursor_file: Option<String>,
+
+        #[arg(
+            long,
+            default_value_t = 0,
+            requires = "cursor_file",
+            help = "when starting a new cursor, skip the blocks up to this ID"
+        )]
+        since: i64,
+
+        #[arg(
+            long,
+            help = "append each block to the trace of the previous ones, and only check the appended rows and the rows reaching them through shifts"
+        )]
+        append: bool,
+    },
+    /// Fetch a single block from an SQL table, write its trace to a file, and
+    /// check it as `check-loop` would
+    #[cfg(feature = "postgres")]
+    Replay {
+        #[arg(help = "the ID of the block to replay")]
+        id: String,
+
+        #[arg(long, default_value = "localhost")]
+        host: String,
+        #[arg(long, default_value = "postgres")]
+        user: String,
+        #[arg(long)]
+        password: Option<String>,
+        #[arg(long, default_value = "zkevm")]
+        database: String,
+
+        #[arg(
+            short = 'o',
+            long = "out",
+            help = "where to write the decompressed trace; defaults to `<id>.json`"
+        )]
+        outfile: Option<String>,
+
+        #[arg(
+            long = "only",
+            help = "only check these constraints, given by name or as `@tag`",
             value_delimiter = ','
         )]
         only: Option<Vec<String>>,
 
-        #[arg(long = "skip", help = "skip these constraints", value_delimiter = ',')]
+        #[arg(
+            long = "skip",
+            help = "skip these constraints, given by name or as `@tag`",
+            value_delimiter = ','
+        )]
         skip: Vec<String>,
     },
     /// Given a set of Corset files, compile them into a single file for faster later use
@@ -388,10 +888,47 @@ enum Commands {
             short = 'o',
             long = "out",
             required = true,
-            help = "compiled Corset file to create"
+            help = "compiled Corset file to create; `-` for the standard output"
         )]
         outfile: String,
 
+        #[arg(long, help = "human-readably serialize the constraint system")]
+        pretty: bool,
+    },
+    /// Check the consistency of a compiled constraint set, and summarize it
+    VerifyCompiled {
+        #[arg(
+            short = 'i',
+            long = "input",
+            required = true,
+            help = "the compiled Corset file to verify"
+        )]
+        file: String,
+    },
+    /// Merge several independently compiled constraint sets into a single one
+    Merge {
+        #[arg(
+            short = 'i',
+            long = "input",
+            required = true,
+            help = "a compiled Corset file to merge; may be repeated"
+        )]
+        inputs: Vec<String>,
+
+        #[arg(
+            short = 'o',
+            long = "out",
+            required = true,
+            help = "compiled Corset file to create"
+        )]
+        out: String,
+
+        #[arg(
+            long = "allow-dups",
+            help = "keep the first of the columns, constraints and constants defined in several inputs instead of failing"
+        )]
+        allow_dups: bool,
+
         #[arg(long, help = "human-readably serialize the constraint system")]
         pretty: bool,
     },
@@ -400,44 +937,91 @@ enum Commands {
 type SourceMapping = Vec<(String, String)>;
 struct ConstraintSetBuilder {
     debug: bool,
+    strict_types: bool,
+    deny_unused: bool,
     no_stdlib: bool,
     source: Either<SourceMapping, ConstraintSet>,
     expand_to: ExpansionLevel,
     auto_constraints: Vec<AutoConstraint>,
+    mmap: Option<storage::MmapSettings>,
+    cse: Option<usize>,
+    /// whether to look for duplicated constraints, and then whether to
+    /// remove them
+    dedup: Option<bool>,
+    /// whether to report the size of the constraint set once built
+    summary: bool,
+    /// the lengths some modules are forced to
+    module_lens: HashMap<String, usize>,
+    /// whether to display a progress bar while computing the columns
+    progress: bool,
+    /// the separator between module and column names in the traces
+    module_separator: Option<String>,
 }
 impl ConstraintSetBuilder {
     fn from_sources(no_stdlib: bool, debug: bool) -> ConstraintSetBuilder {
         ConstraintSetBuilder {
             debug,
+            strict_types: false,
+            deny_unused: false,
             no_stdlib,
             source: Either::Left(Vec::new()),
             expand_to: Default::default(),
             auto_constraints: Default::default(),
+            mmap: None,
+            cse: None,
+            dedup: None,
+            summary: false,
+            module_lens: Default::default(),
+            progress: false,
+            module_separator: None,
         }
     }
 
     fn from_bin(ron: bool, filename: &str) -> Result<ConstraintSetBuilder> {
-        // Read the constraint-set bin file
-        let contents = &std::fs::read_to_string(filename)
-            .with_context(|| anyhow!("while reading `{}`", filename))?;
-        // format.
-        let cs = if ron {
-            ron::from_str(contents)
-                .with_context(|| anyhow!("while parsing `{}` (RON)", filename))?
-        } else {
-            serde_json::from_str(contents)
-                .with_context(|| anyhow!("while parsing `{}` (JSON)", filename))?
-        };
-        //
         Ok(ConstraintSetBuilder {
             debug: false,
+            strict_types: false,
+            deny_unused: false,
             no_stdlib: false,
-            source: Either::Right(cs),
+            source: Either::Right(read_bin(ron, filename)?),
             expand_to: Default::default(),
             auto_constraints: Default::default(),
+            mmap: None,
+            cse: None,
+            dedup: None,
+            summary: false,
+            module_lens: Default::default(),
+            progress: false,
+            module_separator: None,
         })
     }
 
+    /// The files the sources have been read from
+    fn source_files(&self) -> Vec<String> {
+        match self.source.as_ref() {
+            Either::Left(sources) => sources
+                .iter()
+                .map(|(name, _)| name)
+                .filter(|name| Path::new(name).is_file())
+                .cloned()
+                .collect(),
+            Either::Right(_) => Vec::new(),
+        }
+    }
+
+    /// Read again the sources from their files
+    fn reload(&mut self) -> Result<()> {
+        if let Either::Left(ref mut sources) = self.source {
+            for (name, content) in sources.iter_mut() {
+                if Path::new(name).is_file() {
+                    *content = std::fs::read_to_string(&name)
+                        .with_context(|| anyhow!("reading {}", name.yellow().bold()))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn expand_to(&mut self, to: ExpansionLevel) {
         self.expand_to = to;
     }
@@ -446,6 +1030,42 @@ impl ConstraintSetBuilder {
         self.auto_constraints = auto.to_vec();
     }
 
+    fn mmap(&mut self, mmap: Option<storage::MmapSettings>) {
+        self.mmap = mmap;
+    }
+
+    fn progress(&mut self, progress: bool) {
+        self.progress = progress;
+    }
+
+    fn module_separator(&mut self, separator: &str) {
+        self.module_separator = Some(separator.to_owned());
+    }
+
+    fn cse(&mut self, min_occurrences: Option<usize>) {
+        self.cse = min_occurrences;
+    }
+
+    fn dedup(&mut self, remove: Option<bool>) {
+        self.dedup = remove;
+    }
+
+    fn strict_types(&mut self, x: bool) {
+        self.strict_types = x;
+    }
+
+    fn deny_unused(&mut self, x: bool) {
+        self.deny_unused = x;
+    }
+
+    fn summary(&mut self, x: bool) {
+        self.summary = x;
+    }
+
+    fn module_lens(&mut self, lens: &[(String, usize)]) {
+        self.module_lens = lens.iter().cloned().collect();
+    }
+
     fn find_section(root: &Path, section: &str) -> Result<Option<SourceMapping>> {
         let section_file = root.join(format!("{}.lisp", section));
         let section_str = section_file.to_str().unwrap();
@@ -537,7 +1157,13 @@ impl ConstraintSetBuilder {
     fn add_source(&mut self, src: &str) -> Result<()> {
         if let Either::Left(ref mut sources) = self.source {
             let as_path = std::path::Path::new(src);
-            if as_path.is_dir() {
+            if src == "-" {
+                let mut buffer = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut buffer)
+                    .with_context(|| anyhow!("reading {}", "<stdin>".yellow().bold()))?;
+                sources.push(("<stdin>".to_string(), buffer));
+            } else if as_path.is_dir() {
                 sources.append(&mut Self::parse_dir(as_path)?);
             } else if as_path.is_file() {
                 sources.push((
@@ -545,10 +1171,6 @@ impl ConstraintSetBuilder {
                     std::fs::read_to_string(src)
                         .with_context(|| anyhow!("reading {}", src.yellow().bold()))?,
                 ));
-            } else if src == "-" {
-                let mut buffer = String::new();
-                std::io::stdin().read_to_string(&mut buffer)?;
-                sources.push(("STDIN".to_string(), buffer));
             } else {
                 sources.push(("Immediate expression".to_string(), src.into()));
             }
@@ -565,7 +1187,10 @@ impl ConstraintSetBuilder {
         if !self.no_stdlib {
             sources.insert(
                 0,
-                ("stdlib".to_string(), include_str!("stdlib.lisp").to_owned()),
+                (
+                    compiler::STDLIB.to_string(),
+                    include_str!("stdlib.lisp").to_owned(),
+                ),
             );
         }
         sources
@@ -589,38 +1214,372 @@ impl ConstraintSetBuilder {
         }
     }
 
-    #[time("info", "Compiling into constraint set")]
     fn into_constraint_set(self) -> Result<ConstraintSet> {
+        let summary = self.summary;
+        let cs = match self.source {
+            Either::Left(_) => self.to_constraint_set()?,
+            Either::Right(mut cs) => {
+                if let Some(min_occurrences) = self.cse {
+                    transformer::cse(&mut cs, min_occurrences)?;
+                }
+                transformer::expand_to(&mut cs, self.expand_to, &self.auto_constraints)?;
+                if let Some(remove) = self.dedup {
+                    transformer::dedup_constraints(&mut cs, remove);
+                }
+                transformer::concretize(&mut cs);
+                cs.columns.mmap = self.mmap.clone();
+                cs.columns.progress = self.progress;
+                cs.columns.module_separator = self.module_separator.clone();
+                cs
+            }
+        };
+        if summary {
+            eprintln!("{}", cs.summary());
+        }
+        Ok(cs)
+    }
+
+    /// Compile the sources into a constraint set, keeping the builder around
+    /// so that it can be compiled again later.
+    #[time("info", "Compiling into constraint set")]
+    fn to_constraint_set(&self) -> Result<ConstraintSet> {
         let mut cs = match self.source {
             Either::Left(ref sources) => compiler::make(
                 &self.prepare_sources(sources),
-                &compiler::CompileSettings { debug: self.debug },
+                &compiler::CompileSettings {
+                    debug: self.debug,
+                    strict_types: self.strict_types,
+                    deny_unused: self.deny_unused,
+                    module_lens: self.module_lens.clone(),
+                },
             )
+            .with_context(|| errors::Failure::Compilation("while compiling the sources".into()))
             .map(|r| r.1),
-            Either::Right(cs) => Ok(cs),
+            Either::Right(_) => bail!("unable to recompile an already compiled constraint set"),
         }?;
+        if let Some(min_occurrences) = self.cse {
+            transformer::cse(&mut cs, min_occurrences)?;
+        }
         transformer::expand_to(&mut cs, self.expand_to, &self.auto_constraints)?;
+        if let Some(remove) = self.dedup {
+            transformer::dedup_constraints(&mut cs, remove);
+        }
         transformer::concretize(&mut cs);
+        cs.columns.mmap = self.mmap.clone();
+        cs.columns.progress = self.progress;
+        cs.columns.module_separator = self.module_separator.clone();
         Ok(cs)
     }
 }
 
+/// In watch mode, JSON traces are only read once and kept in memory; binary
+/// traces are memory-mapped anyway.
+fn watched_trace(tracefile: &str, watch: bool) -> Result<Option<Vec<u8>>> {
+    if watch && !tracefile.ends_with("lt") {
+        std::fs::read(tracefile)
+            .map(Some)
+            .with_context(|| format!("while reading `{}`", tracefile))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Summarize the content of the constraint set `cs` read from `filename`
+fn verify_summary(filename: &str, cs: &ConstraintSet) -> String {
+    format!(
+        "{}: {} modules, {} columns ({} computed), {} constraints, {} computations, {} constants",
+        filename,
+        cs.columns.modules().len(),
+        cs.columns.all().len(),
+        cs.columns
+            .iter_cols()
+            .filter(|c| !matches!(c.kind, compiler::Kind::Commitment))
+            .count(),
+        cs.constraints.len(),
+        cs.computations.iter().count(),
+        cs.constants.len()
+    )
+}
+
+/// Read a constraint set compiled to `filename`, in RON or JSON
+fn read_bin(ron: bool, filename: &str) -> Result<ConstraintSet> {
+    let contents = &std::fs::read_to_string(filename)
+        .with_context(|| anyhow!("while reading `{}`", filename))?;
+    if ron {
+        ConstraintSet::from_ron(contents)
+            .with_context(|| anyhow!("while parsing `{}` (RON)", filename))
+    } else {
+        ConstraintSet::from_json(contents)
+            .with_context(|| anyhow!("while parsing `{}` (JSON)", filename))
+    }
+}
+
+/// Parse a thread count, where `auto` and 0 stand for all the available cores
+fn parse_threads(s: &str) -> Result<usize> {
+    match s {
+        "auto" | "0" => Ok(std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)),
+        _ => s
+            .parse()
+            .with_context(|| anyhow!("expected a thread count or `auto`, found `{}`", s)),
+    }
+}
+
+/// Parse a `module=N` module length override
+fn parse_module_len(s: &str) -> Result<(String, usize)> {
+    let (module, len) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected `MODULE=LENGTH`, found `{}`", s))?;
+    Ok((
+        module.to_string(),
+        len.parse()
+            .with_context(|| anyhow!("invalid length `{}` for module `{}`", len, module))?,
+    ))
+}
+
+#[cfg(any(feature = "postgres", test))]
+fn is_sql_identifier(x: &str) -> bool {
+    x.chars()
+        .next()
+        .map(|c| c.is_ascii_alphabetic() || c == '_')
+        .unwrap_or(false)
+        && x.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parse the name of an SQL table, possibly qualified by its schema; as it is
+/// spliced into queries, only plain identifiers are accepted
+#[cfg(feature = "postgres")]
+fn parse_sql_table(s: &str) -> Result<String> {
+    if s.split('.').count() <= 2 && s.split('.').all(is_sql_identifier) {
+        Ok(s.to_string())
+    } else {
+        bail!("expected a table name, found `{}`", s)
+    }
+}
+
+/// Parse the name of an SQL column; as it is spliced into queries, only plain
+/// identifiers are accepted
+#[cfg(any(feature = "postgres", test))]
+fn parse_sql_column(s: &str) -> Result<String> {
+    if is_sql_identifier(s) {
+        Ok(s.to_string())
+    } else {
+        bail!("expected a column name, found `{}`", s)
+    }
+}
+
+/// Build the statement recording a failing constraint into `table`, whose
+/// `columns` hold the block ID, the constraint name, and the row
+#[cfg(any(feature = "postgres", test))]
+fn failures_insert(table: &str, columns: &[String]) -> Result<String> {
+    if columns.len() != 3 {
+        bail!(
+            "expected the block ID, constraint name, and row columns, found {} columns",
+            columns.len()
+        )
+    }
+    Ok(format!(
+        "INSERT INTO {} ({}) VALUES ($1, $2, $3)",
+        table,
+        columns.join(", ")
+    ))
+}
+
+/// Write the compiled constraint set `cs` to `filename`, or the standard output
+/// if it is `-`, in RON or JSON
+fn write_bin(cs: &ConstraintSet, ron: bool, pretty: bool, filename: &str) -> Result<()> {
+    exporters::create_output(filename, false, false)?
+        .write_all(
+            if ron && pretty {
+                ron::ser::to_string_pretty(cs, ron::ser::PrettyConfig::default())?
+            } else if ron {
+                ron::ser::to_string(cs)?
+            } else if pretty {
+                serde_json::to_string_pretty(cs)?
+            } else {
+                serde_json::to_string(cs)?
+            }
+            .as_bytes(),
+        )
+        .with_context(|| format!("while writing to `{}`", filename))
+}
+
+/// Merge all the trace files into `cs`, read in `format` or in the format
+/// matching their extension, then compute the resulting trace; unless
+/// `no_pad` is set, the imported columns are preceded by padding rows.
+fn compute_watched_traces(
+    tracefiles: &[String],
+    traces: &[Option<Vec<u8>>],
+    format: Option<&str>,
+    cs: &mut ConstraintSet,
+    fail_on_missing: bool,
+    no_pad: bool,
+) -> Result<()> {
+    for (tracefile, trace) in tracefiles.iter().zip(traces.iter()) {
+        let reader = import::trace_reader_for(tracefile, format)?;
+        if let Some(trace) = trace {
+            reader.read_bytes(trace, cs, no_pad)
+        } else {
+            reader.read_file(tracefile, cs, no_pad)
+        }
+        .with_context(|| format!("while reading `{}`", tracefile))?;
+    }
+    compute::prepare(cs, fail_on_missing)
+}
+
+/// Expand `constraints` the way the blocks are checked in `check-loop`
+#[cfg(feature = "postgres")]
+fn expand_for_loop(mut constraints: ConstraintSet) -> Result<ConstraintSet> {
+    transformer::validate_nhood(&mut constraints)
+        .with_context(|| anyhow!("while creating nhood constraints"))?;
+    transformer::lower_shifts(&mut constraints);
+    transformer::expand_ifs(&mut constraints);
+    transformer::expand_constraints(&mut constraints)
+        .with_context(|| anyhow!("while expanding constraints"))?;
+    transformer::sorts(&mut constraints)
+        .with_context(|| anyhow!("while creating sorting constraints"))?;
+    transformer::expand_invs(&mut constraints)
+        .with_context(|| anyhow!("while expanding inverses"))?;
+    Ok(constraints)
+}
+
+/// Compute the trace of a block from its (possibly gzipped) `payload`, then
+/// check it; if `append` is set, the block is appended to the trace already
+/// in `cs`, and only the appended rows are checked.
+#[cfg(feature = "postgres")]
+fn check_payload(
+    payload: &[u8],
+    mut cs: ConstraintSet,
+    only: &Option<Vec<String>>,
+    skip: &[String],
+    report: bool,
+    append: bool,
+) -> Result<(ConstraintSet, Result<()>)> {
+    let payload = import::decompress_trace(payload)?;
+    if append {
+        compute::append_trace_str(&payload, &mut cs)?;
+    } else if !cs.columns.effective_len.is_empty() {
+        // only recompute what depends on the columns differing from the previous trace
+        compute::update_trace_str(&payload, &mut cs)?;
+    } else {
+        compute::compute_trace_str(&payload, &mut cs, false)?;
+    }
+    let checked = check::check(
+        &cs,
+        only,
+        skip,
+        check::DebugSettings::new()
+            .unclutter(true)
+            .report(report)
+            .appended_only(append),
+    );
+    Ok((cs, checked))
+}
+
 #[cfg(feature = "cli")]
-fn main() -> Result<()> {
+/// Set whether the outputs of corset are colored, from the `--color` argument;
+/// see the [`output`] module.
+fn set_color_choice(color: &str) {
+    anstream::ColorChoice::write_global(match color {
+        "always" => anstream::ColorChoice::Always,
+        "never" => anstream::ColorChoice::Never,
+        _ => anstream::ColorChoice::Auto,
+    });
+}
+
+/// The exit codes of corset, so that scripts can tell its failures apart
+mod exit_code {
+    pub const SUCCESS: u8 = 0;
+    /// some constraints do not hold
+    pub const VIOLATION: u8 = 1;
+    /// the sources could not be compiled
+    pub const COMPILATION: u8 = 2;
+    /// a trace could not be read or imported, or a file could not be accessed
+    pub const TRACE: u8 = 3;
+    /// any other error
+    pub const OTHER: u8 = 4;
+}
+
+/// The exit code reporting the error `e`
+fn exit_code(e: &Error) -> u8 {
+    if e.downcast_ref::<check::CheckFailure>().is_some() {
+        exit_code::VIOLATION
+    } else if let Some(failure) = e.downcast_ref::<errors::Failure>() {
+        match failure {
+            errors::Failure::Compilation(_) => exit_code::COMPILATION,
+            errors::Failure::Trace(_) => exit_code::TRACE,
+        }
+    } else if e.downcast_ref::<errors::parser::SourceFile>().is_some()
+        || e.downcast_ref::<errors::CompileError>().is_some()
+    {
+        exit_code::COMPILATION
+    } else if e.downcast_ref::<errors::RuntimeError>().is_some()
+        || e.chain().any(|c| c.is::<std::io::Error>())
+    {
+        exit_code::TRACE
+    } else {
+        exit_code::OTHER
+    }
+}
+
+fn main() -> std::process::ExitCode {
+    let args = match Args::try_parse() {
+        Result::Ok(args) => args,
+        // clap would exit with 2 on invalid arguments, that stands for
+        // compilation errors here
+        Err(e) => {
+            let _ = e.print();
+            return std::process::ExitCode::from(if e.use_stderr() {
+                exit_code::OTHER
+            } else {
+                exit_code::SUCCESS
+            });
+        }
+    };
+    let rich_errors = args.error_format == "rich";
+    match run(args) {
+        Result::Ok(()) => std::process::ExitCode::from(exit_code::SUCCESS),
+        Err(e) => {
+            // errors messages may be colored as well
+            match errors::parser::render_rich(&e).filter(|_| rich_errors) {
+                Some(rendered) => eprint!("{}", rendered),
+                None => eprintln!("Error: {:?}", e),
+            }
+            std::process::ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+fn run(args: Args) -> Result<()> {
     use crate::{inspect::InspectorSettings, transformer::concretize};
 
-    let args = Args::parse();
     *crate::IS_NATIVE.write().unwrap() = args.native_arithmetic;
-    buche::new()
-        .verbosity(args.verbose.log_level_filter())
-        .quiet(args.verbose.is_silent())
-        .init()
-        .unwrap();
+    set_color_choice(&args.color);
+    let color_stderr =
+        anstream::AutoStream::choice(&std::io::stderr()) != anstream::ColorChoice::Never;
+    // progress bars are drawn on stderr, so the logger must not write over them
+    log::set_max_level(args.verbose.log_level_filter());
+    log::set_boxed_logger(Box::new(progress::SuspendingLogger::new(
+        buche::new()
+            .verbosity(args.verbose.log_level_filter())
+            .quiet(args.verbose.is_silent())
+            .color(if color_stderr {
+                buche::ColorChoice::Always
+            } else {
+                buche::ColorChoice::Never
+            })
+            .clone(),
+        !color_stderr,
+    )))
+    .unwrap();
+    let progress = args.progress || std::io::stdout().is_terminal();
 
     rayon::ThreadPoolBuilder::new()
         .num_threads(args.threads)
         .build_global()
         .unwrap();
+    info!("Using {} threads", args.threads);
 
     let mut builder = if matches!(args.command, Commands::Format { .. }) {
         if args.source.len() != 1 {
@@ -661,15 +1620,33 @@ fn main() -> Result<()> {
 
     builder.expand_to(args.expand.into());
     builder.auto_constraints(&AutoConstraint::parse(&args.auto_constraints));
+    builder.mmap(args.mmap.as_ref().map(storage::MmapSettings::new));
+    builder.progress(progress);
+    builder.module_separator(&args.module_separator);
+    builder.cse(args.cse);
+    builder.dedup((args.warn_duplicates || args.dedup).then_some(args.dedup));
+    #[cfg(feature = "exporters")]
+    let auto_range = !args.no_auto_range;
+    builder.strict_types(args.strict_types);
+    builder.deny_unused(args.deny_unused);
+    builder.summary(args.summary);
 
     match args.command {
         #[cfg(feature = "exporters")]
-        Commands::Go { package, filename } => {
-            exporters::zkgeth::render(
-                &builder.into_constraint_set()?,
-                &package,
-                filename.as_ref(),
-            )?;
+        Commands::Go {
+            package,
+            filename,
+            manifest,
+            max_degree,
+        } => {
+            let mut cs = builder.into_constraint_set()?;
+            if auto_range {
+                transformer::enforce_magma_ranges(&mut cs);
+            }
+            if let Some(max_degree) = max_degree {
+                cs.check_max_degree(max_degree)?;
+            }
+            exporters::zkgeth::render(&cs, &package, filename.as_ref(), manifest.as_ref())?;
         }
         #[cfg(feature = "exporters")]
         Commands::Besu {
@@ -687,29 +1664,125 @@ fn main() -> Result<()> {
             exporters::conflater::render(&builder.to_constraint_set(), filename.as_ref())?;
         }
         #[cfg(feature = "exporters")]
-        Commands::WizardIOP { out_filename } => {
+        Commands::WizardIOP {
+            out_filename,
+            max_degree,
+        } => {
             *crate::IS_NATIVE.write().unwrap() = true;
             builder.expand_to(ExpansionLevel::top());
             builder.auto_constraints(AutoConstraint::all());
             let mut cs = builder.into_constraint_set()?;
             concretize(&mut cs);
+            if auto_range {
+                transformer::enforce_magma_ranges(&mut cs);
+            }
+            if let Some(max_degree) = max_degree {
+                cs.check_max_degree(max_degree)?;
+            }
 
             exporters::wizardiop::render(&cs, &out_filename)?;
         }
         #[cfg(feature = "exporters")]
         Commands::Latex {
             constraints_filename,
+            columns_filename,
+            out,
+            constraints_only,
+            columns_only,
         } => {
-            exporters::latex::render(
+            exporters::latex::LatexExporter {
+                constraints_filename: out.clone().or(constraints_filename),
+                columns_filename: out.or(columns_filename),
+                render_columns: !constraints_only,
+                render_constraints: !columns_only,
+            }
+            .render(
                 builder
                     .to_ast()?
                     .into_iter()
                     .map(|x| x.1)
                     .collect::<Vec<_>>()
                     .as_slice(),
-                constraints_filename,
             )?;
         }
+        Commands::Deps { out } => {
+            exporters::dot::render(&builder.into_constraint_set()?, &out)?;
+        }
+        Commands::Schema { out } => {
+            builder.expand_to(ExpansionLevel::top());
+            builder.auto_constraints(AutoConstraint::all());
+            let schema = schema::schema(&builder.into_constraint_set()?);
+            exporters::create_output(&out, false, false)?
+                .write_all(serde_json::to_string_pretty(&schema)?.as_bytes())
+                .with_context(|| format!("while writing to `{}`", out))?;
+        }
+        Commands::Columns { json } => {
+            builder.expand_to(ExpansionLevel::top());
+            builder.auto_constraints(AutoConstraint::all());
+            let columns = columns::list(&builder.into_constraint_set()?);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&columns)?);
+            } else {
+                println!("{}", columns::render(&columns));
+            }
+        }
+        Commands::Test => {
+            let tests = deftest::collect(&builder.to_ast()?);
+            if tests.is_empty() {
+                warn!("no tests found");
+                return Ok(());
+            }
+            builder.expand_to(ExpansionLevel::top());
+            builder.auto_constraints(AutoConstraint::all());
+            deftest::run_all(&builder.into_constraint_set()?, &tests)?;
+        }
+        Commands::Diff {
+            left,
+            right,
+            json,
+            trace_format,
+        } => {
+            let diff = diff::diff(
+                &diff::read_columns(&left, trace_format)?,
+                &diff::read_columns(&right, trace_format)?,
+            );
+            if json {
+                println!("{}", serde_json::to_string_pretty(&diff)?);
+            } else if diff.is_empty() {
+                info!("no difference found");
+            } else {
+                println!("{}", diff::render(&diff));
+            }
+        }
+        Commands::Explain {
+            tracefile,
+            column,
+            row,
+        } => {
+            builder.expand_to(ExpansionLevel::top());
+            builder.auto_constraints(AutoConstraint::all());
+            let mut cs = builder.into_constraint_set()?;
+            compute::compute_trace(&tracefile, &mut cs, false)
+                .with_context(|| format!("while computing from `{}`", tracefile))?;
+            println!("{}", explain::explain(&cs, &column, row as isize)?);
+        }
+        Commands::Stats {
+            tracefile,
+            top,
+            json,
+        } => {
+            builder.expand_to(ExpansionLevel::top());
+            builder.auto_constraints(AutoConstraint::all());
+            let mut cs = builder.into_constraint_set()?;
+            compute::compute_trace(&tracefile, &mut cs, false)
+                .with_context(|| format!("while computing from `{}`", tracefile))?;
+            let stats = stats::stats(&cs, top);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+            } else {
+                println!("{}", stats::render(&stats));
+            }
+        }
         Commands::Convert {
             tracefile,
             outfile,
@@ -740,25 +1813,84 @@ fn main() -> Result<()> {
             }?;
         }
         Commands::Compute {
-            tracefile,
+            tracefiles,
+            input_format,
             outfile,
+            gzip,
+            force,
+            dry_run,
             fail_on_missing,
+            no_pad,
+            pad,
+            trace_format,
+            only_modules,
+            skip_modules,
+            module_lens,
+            watch,
         } => {
             builder.expand_to(ExpansionLevel::top());
             builder.auto_constraints(AutoConstraint::all());
-            let mut cs = builder.into_constraint_set()?;
-
-            compute::compute_trace(&tracefile, &mut cs, fail_on_missing)
-                .with_context(|| format!("while computing from `{}`", tracefile))?;
+            builder.module_lens(&module_lens);
+
+            let traces = tracefiles
+                .iter()
+                .map(|tracefile| watched_trace(tracefile, watch))
+                .collect::<Result<Vec<_>>>()?;
+            let compute_once = |mut cs: ConstraintSet| -> Result<()> {
+                cs.restrict_to_modules(&only_modules, &skip_modules);
+                for (module, len) in module_lens.iter() {
+                    cs.force_module_len(module, *len)?;
+                }
+                cs.columns.padding_strategy = pad;
+
+                compute_watched_traces(
+                    &tracefiles,
+                    &traces,
+                    input_format.as_deref(),
+                    &mut cs,
+                    fail_on_missing,
+                    no_pad,
+                )
+                .with_context(|| {
+                    errors::Failure::Trace(format!(
+                        "while computing from `{}`",
+                        tracefiles.join(", ")
+                    ))
+                })?;
+
+                if dry_run {
+                    for module in cs.columns.modules() {
+                        if let Some(len) = cs.effective_len_for(&module) {
+                            println!("{}: {} rows", module, len);
+                        }
+                    }
+                    return Ok(());
+                }
 
-            let outfile = outfile.as_ref().unwrap();
-            let mut f = std::fs::File::create(outfile)
-                .with_context(|| format!("while creating `{}`", &outfile))?;
+                let outfile = outfile.as_ref().unwrap();
+                let mut f = exporters::create_output(outfile, gzip, force)?;
+                if gzip {
+                    f = Box::new(flate2::write::GzEncoder::new(
+                        f,
+                        flate2::Compression::default(),
+                    ));
+                }
 
-            let mut out = std::io::BufWriter::with_capacity(10_000_000, &mut f);
-            cs.write(&mut out)
-                .with_context(|| format!("while writing to `{}`", &outfile))?;
-            out.flush()?;
+                let mut out = std::io::BufWriter::with_capacity(10_000_000, &mut f);
+                cs.write(&mut out, trace_format)
+                    .with_context(|| format!("while writing to `{}`", &outfile))?;
+                out.flush()?;
+                Ok(())
+            };
+
+            if watch {
+                watch::watch(&builder.source_files(), || {
+                    builder.reload()?;
+                    compute_once(builder.to_constraint_set()?)
+                })?;
+            } else {
+                compute_once(builder.into_constraint_set()?)?;
+            }
         }
         #[cfg(feature = "postgres")]
         Commands::CheckLoop {
@@ -770,53 +1902,81 @@ fn main() -> Result<()> {
             rerun,
             only,
             skip,
+            failures_table,
+            failure_columns,
+            block_timeout,
+            cursor_file,
+            since,
+            append,
         } => {
-            let mut constraints = builder.to_constraint_set()?;
-            transformer::validate_nhood(&mut constraints)
-                .with_context(|| anyhow!("while creating nhood constraints"))?;
-            transformer::lower_shifts(&mut constraints);
-            transformer::expand_ifs(&mut constraints);
-            transformer::expand_constraints(&mut constraints)
-                .with_context(|| anyhow!("while expanding constraints"))?;
-            transformer::sorts(&mut constraints)
-                .with_context(|| anyhow!("while creating sorting constraints"))?;
-            transformer::expand_invs(&mut constraints)
-                .with_context(|| anyhow!("while expanding inverses"))?;
+            let insert_failure = failures_insert(&failures_table, &failure_columns)?;
+            let constraints = expand_for_loop(builder.to_constraint_set()?)?;
 
             let mut db = utils::connect_to_db(&user, &password, &host, &database)?;
-
+            let mut cursor = cursor_file
+                .as_ref()
+                .map(|f| cursor::Cursor::load(f, since))
+                .transpose()?;
+
+            // the trace of the previous block, or when appending, the trace
+            // built from all the blocks processed so far
+            let mut previous: Option<ConstraintSet> = None;
             info!("Initiating waiting loop");
             loop {
-                let mut local_constraints = constraints.clone();
-
                 let mut tx = db.transaction()?;
-                let todo = if rerun { "failed" } else { "to_corset" };
-                for row in tx.query(
-                    &format!("SELECT id, status, payload FROM blocks WHERE STATUS='{}' ORDER BY length(payload) ASC LIMIT 1 FOR UPDATE SKIP LOCKED", todo),
-                    &[],
-                )? {
+                let rows = if let Some(cursor) = cursor.as_ref() {
+                    let done = cursor.done.iter().cloned().collect::<Vec<_>>();
+                    tx.query(
+                        "SELECT id, status, payload FROM blocks WHERE id::bigint > $1 AND NOT (id::bigint = ANY($2)) ORDER BY id::bigint ASC LIMIT 1 FOR UPDATE SKIP LOCKED",
+                        &[&cursor.watermark, &done],
+                    )?
+                } else {
+                    let todo = if rerun { "failed" } else { "to_corset" };
+                    tx.query(
+                        &format!("SELECT id, status, payload FROM blocks WHERE STATUS='{}' ORDER BY length(payload) ASC LIMIT 1 FOR UPDATE SKIP LOCKED", todo),
+                        &[],
+                    )?
+                };
+                let mut processed = vec![];
+                for row in rows {
                     let id: &str = row.get(0);
                     let payload: &[u8] = row.get(2);
                     info!("Processing {}", id);
 
-                    compute::compute_trace_str(
-                        payload,
-                        &mut local_constraints,
-                        false,
-                    )
-                        .with_context(|| format!("while expanding from {}", id))?;
-
-                    match check::check(
-                        &local_constraints,
-                        &only,
-                        &skip,
-                        args.verbose.log_level_filter() >= log::Level::Warn
-                            && std::io::stdout().is_terminal(),
-                        false,
-                        check::DebugSettings::new()
-                            .unclutter(true)
-                            .report(args.verbose.log_level_filter() >= log::Level::Warn)
-                    ) {
+                    let local_constraints = previous.take().unwrap_or_else(|| constraints.clone());
+                    let payload = payload.to_vec();
+                    let only = only.clone();
+                    let skip = skip.clone();
+                    let report = args.verbose.log_level_filter() >= log::Level::Warn;
+                    let checked = check::run_with_timeout(
+                        block_timeout.map(std::time::Duration::from_secs),
+                        move || {
+                            check_payload(&payload, local_constraints, &only, &skip, report, append)
+                        },
+                    );
+                    let checked = match checked {
+                        Ok(r) => {
+                            let (cs, checked) =
+                                r.with_context(|| format!("while expanding from {}", id))?;
+                            previous = Some(cs);
+                            checked
+                        }
+                        Err(e) if e.is::<check::TimedOut>() => {
+                            warn!("{}: {}", id, e);
+                            if append {
+                                warn!(
+                                    "the appended trace is lost, starting over from the next block"
+                                );
+                            }
+                            tx.execute("UPDATE blocks SET status='timeout' WHERE id=$1", &[&id])
+                                .with_context(|| "while inserting timed out back row")?;
+                            processed.push(id.to_owned());
+                            continue;
+                        }
+                        Err(e) => return Err(e),
+                    };
+
+                    match checked {
                         Ok(_) => {
                             if remove {
                                 tx.execute("DELETE FROM blocks WHERE id=$1", &[&id])
@@ -825,61 +1985,206 @@ fn main() -> Result<()> {
                                 tx.execute("UPDATE blocks SET status='done' WHERE id=$1", &[&id])
                                     .with_context(|| "while inserting failed back row")?;
                             }
-                        },
-                        Err(_) => {
+                        }
+                        Err(e) => {
                             tx.execute("UPDATE blocks SET status='failed' WHERE id=$1", &[&id])
                                 .with_context(|| "while inserting failed back row")?;
-                        },
+                            if let Some(failure) = e.downcast_ref::<check::CheckFailure>() {
+                                let insert = tx.prepare(&insert_failure)?;
+                                for failed in failure.failed.iter() {
+                                    let row = failed.row.map(|r| r as i64);
+                                    tx.execute(&insert, &[&id, &failed.name, &row])
+                                        .with_context(|| {
+                                            format!("while recording failure of {}", failed.name)
+                                        })?;
+                                }
+                            } else {
+                                error!("{}: {:?}", id, e);
+                            }
+                        }
                     }
-
+                    processed.push(id.to_owned());
                 }
                 if let Err(e) = tx.commit() {
                     error!("{:?}", e);
+                } else if let Some(cursor) = cursor.as_mut() {
+                    // only move forward once the outcome of the blocks is recorded
+                    for id in processed {
+                        cursor.advance(
+                            id.parse()
+                                .with_context(|| format!("block ID `{}` is not an integer", id))?,
+                        );
+                    }
+                    cursor.save(cursor_file.as_ref().unwrap())?;
                 }
 
                 std::thread::sleep(std::time::Duration::from_secs(1));
             }
         }
+        #[cfg(feature = "postgres")]
+        Commands::Replay {
+            id,
+            host,
+            user,
+            password,
+            database,
+            outfile,
+            only,
+            skip,
+        } => {
+            let constraints = expand_for_loop(builder.to_constraint_set()?)?;
+
+            let mut db = utils::connect_to_db(&user, &password, &host, &database)?;
+            let row = db
+                .query_opt("SELECT payload FROM blocks WHERE id=$1", &[&id])
+                .with_context(|| format!("while fetching block {}", id))?
+                .ok_or_else(|| anyhow!("block {} not found", id))?;
+            let payload: &[u8] = row.get(0);
+            let trace = import::decompress_trace(payload)
+                .with_context(|| format!("while decoding block {}", id))?;
+
+            let outfile = outfile.unwrap_or_else(|| format!("{}.json", id));
+            std::fs::write(&outfile, &trace)
+                .with_context(|| format!("while writing `{}`", outfile))?;
+            info!("Trace of block {} written to `{}`", id, outfile);
+
+            let report = args.verbose.log_level_filter() >= log::Level::Warn;
+            let (_, checked) = check_payload(&trace, constraints, &only, &skip, report, false)
+                .with_context(|| format!("while expanding from {}", id))?;
+            checked?;
+        }
         Commands::Check {
-            tracefile,
+            tracefiles,
+            input_format,
             full_trace,
             report,
             only,
             skip,
+            only_modules,
+            skip_modules,
+            module_lens,
             continue_on_error,
+            max_failures,
             unclutter,
             dim,
             with_src,
             trace_span,
             trace_span_before,
             trace_span_after,
+            sample,
+            random_probe,
+            seed,
+            watch,
+            shift_mode,
+            check_permutations,
+            memoize,
+            quiet_success,
+            pedantic,
+            deny_warnings,
+            raw_values,
         } => {
-            if utils::is_file_empty(&tracefile)? {
-                warn!("`{}` is empty, exiting", tracefile);
+            let mut non_empty = Vec::with_capacity(tracefiles.len());
+            for tracefile in tracefiles {
+                if utils::is_file_empty(&tracefile)? {
+                    warn!("`{}` is empty, skipping", tracefile);
+                } else {
+                    non_empty.push(tracefile);
+                }
+            }
+            if non_empty.is_empty() {
+                warn!("all traces are empty, exiting");
                 return Ok(());
             }
+            let tracefiles = non_empty;
+            let tracefile = tracefiles.join(", ");
+
+            let seed = seed.unwrap_or_else(rand::random);
+            if let Some(count) = sample {
+                warn!(
+                    "partial check: only {} rows sampled with seed {} will be checked",
+                    count, seed
+                );
+            }
+            builder.module_lens(&module_lens);
+
+            let traces = tracefiles
+                .iter()
+                .map(|tracefile| watched_trace(tracefile, watch))
+                .collect::<Result<Vec<_>>>()?;
+            let check_once = |mut cs: ConstraintSet| -> Result<()> {
+                cs.restrict_to_modules(&only_modules, &skip_modules);
+                for (module, len) in module_lens.iter() {
+                    cs.force_module_len(module, *len)?;
+                }
 
-            let mut cs = builder.into_constraint_set()?;
-
-            compute::compute_trace(&tracefile, &mut cs, false)
-                .with_context(|| format!("while expanding `{}`", tracefile))?;
-            check::check(
-                &cs,
-                &only,
-                &skip,
-                check::DebugSettings::new()
-                    .unclutter(unclutter)
-                    .dim(dim)
-                    .src(with_src)
-                    .continue_on_error(continue_on_error)
-                    .report(report)
-                    .full_trace(full_trace)
-                    .context_span(trace_span)
-                    .and_context_span_before(trace_span_before)
-                    .and_context_span_after(trace_span_after),
-            )
-            .with_context(|| format!("while checking {}", tracefile.bright_white().bold()))?;
-            info!("{}: SUCCESS", tracefile)
+                compute_watched_traces(
+                    &tracefiles,
+                    &traces,
+                    input_format.as_deref(),
+                    &mut cs,
+                    false,
+                    false,
+                )
+                .with_context(|| {
+                    errors::Failure::Trace(format!("while expanding `{}`", tracefile))
+                })?;
+                check::check(
+                    &cs,
+                    &only,
+                    &skip,
+                    check::DebugSettings::new()
+                        .unclutter(unclutter)
+                        .dim(dim)
+                        .src(with_src)
+                        .continue_on_error(continue_on_error)
+                        .max_failures(max_failures)
+                        .progress(progress)
+                        .report(report)
+                        .full_trace(full_trace)
+                        .context_span(trace_span)
+                        .and_context_span_before(trace_span_before)
+                        .and_context_span_after(trace_span_after)
+                        .and_sample(sample)
+                        .seed(seed)
+                        .shift_mode(shift_mode)
+                        .check_permutations(check_permutations)
+                        .memoize(memoize)
+                        .pedantic(pedantic)
+                        .deny_warnings(deny_warnings)
+                        .raw_values(raw_values),
+                )
+                .with_context(|| format!("while checking {}", tracefile.bright_white().bold()))?;
+                if !quiet_success {
+                    if let Some(count) = sample {
+                        info!(
+                            "{}: no violations found in {} sampled rows",
+                            tracefile, count
+                        )
+                    } else {
+                        info!("{}: SUCCESS", tracefile)
+                    }
+                }
+                if let Some(probes) = random_probe {
+                    for h in check::random_probe(&cs, &only, &skip, probes, seed) {
+                        warn!(
+                            "{} holds on {}, but does not identically vanish (seed {})",
+                            h.to_string().bold(),
+                            tracefile,
+                            seed
+                        );
+                    }
+                }
+                Ok(())
+            };
+
+            if watch {
+                watch::watch(&builder.source_files(), || {
+                    builder.reload()?;
+                    check_once(builder.to_constraint_set()?)
+                })?;
+            } else {
+                check_once(builder.into_constraint_set()?)?;
+            }
         }
         #[cfg(feature = "inspector")]
         Commands::Inspect {
@@ -953,21 +2258,29 @@ fn main() -> Result<()> {
         }
         Commands::Compile { outfile, pretty } => {
             let constraints = builder.into_constraint_set()?;
-            std::fs::File::create(&outfile)
-                .with_context(|| format!("while creating `{}`", &outfile))?
-                .write_all(
-                    if args.ron && pretty {
-                        ron::ser::to_string_pretty(&constraints, ron::ser::PrettyConfig::default())?
-                    } else if args.ron {
-                        ron::ser::to_string(&constraints)?
-                    } else if pretty {
-                        serde_json::to_string_pretty(&constraints)?
-                    } else {
-                        serde_json::to_string(&constraints)?
-                    }
-                    .as_bytes(),
-                )
-                .with_context(|| format!("while writing to `{}`", &outfile))?;
+            write_bin(&constraints, args.ron, pretty, &outfile)?;
+        }
+        Commands::VerifyCompiled { file } => {
+            let mut cs = read_bin(args.ron, &file)?;
+            cs.verify()
+                .with_context(|| anyhow!("while verifying `{}`", file))?;
+            println!("{}", verify_summary(&file, &cs));
+        }
+        Commands::Merge {
+            inputs,
+            out,
+            allow_dups,
+            pretty,
+        } => {
+            let mut inputs = inputs.iter();
+            let first = inputs.next().unwrap();
+            let mut merged = read_bin(args.ron, first)?;
+            for input in inputs {
+                merged = merged
+                    .merge(read_bin(args.ron, input)?, allow_dups)
+                    .with_context(|| anyhow!("while merging `{}`", input))?;
+            }
+            write_bin(&merged, args.ron, pretty, &out)?;
         }
     }
 
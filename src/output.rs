@@ -0,0 +1,37 @@
+//! Replacements for the printing macros of the standard library, writing
+//! through `anstream` so that colors are stripped when the output does not
+//! support them, or when they have been disabled with `--color` or NO_COLOR.
+//!
+//! As this module is declared first, these macros shadow the standard ones in
+//! the whole crate. They are left out of tests, whose output would not be
+//! captured anymore otherwise.
+#![cfg(not(test))]
+#![allow(unused_macros)]
+
+macro_rules! print {
+    ($($arg:tt)*) => {{
+        use std::io::Write as _;
+        std::write!(anstream::stdout(), $($arg)*).expect("failed printing to stdout")
+    }};
+}
+
+macro_rules! println {
+    ($($arg:tt)*) => {{
+        use std::io::Write as _;
+        std::writeln!(anstream::stdout(), $($arg)*).expect("failed printing to stdout")
+    }};
+}
+
+macro_rules! eprint {
+    ($($arg:tt)*) => {{
+        use std::io::Write as _;
+        std::write!(anstream::stderr(), $($arg)*).expect("failed printing to stderr")
+    }};
+}
+
+macro_rules! eprintln {
+    ($($arg:tt)*) => {{
+        use std::io::Write as _;
+        std::writeln!(anstream::stderr(), $($arg)*).expect("failed printing to stderr")
+    }};
+}
@@ -1,4 +1,4 @@
-use ark_bls12_377::Fr;
+use crate::field::Fr;
 use ark_ff::{BigInteger, PrimeField};
 use itertools::Itertools;
 use num_traits::Zero;
@@ -6,7 +6,7 @@ use owo_colors::{colored::Color, OwoColorize};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    compiler::{ColumnRef, Conditioning, Expression, Magma, Node},
+    compiler::{ColumnRef, Conditioning, Expression, Magma, Node, MAIN_MODULE},
     structs::Handle,
 };
 
@@ -133,43 +133,79 @@ impl Pretty for Fr {
     }
 }
 
-impl Pretty for Node {
-    fn pretty(&self) -> String {
-        fn rec_pretty(s: &Node, depth: usize) -> String {
-            let depth = depth
-                + match s.e() {
-                    Expression::Funcall { .. } | Expression::List(_) => 1,
-                    _ => 0,
-                };
-            let c = &COLORS[depth % COLORS.len()];
-            match s.e() {
-                Expression::Const(x) => format!("{}", x).color(*c).to_string(),
-                Expression::Column { handle, .. } | Expression::ExoColumn { handle, .. } => {
-                    handle.to_string().color(*c).to_string()
-                }
-                Expression::ArrayColumn { handle, domain, .. } => {
-                    format!("{}{}", handle.as_handle().name, domain)
-                        .color(*c)
-                        .to_string()
-                }
-                Expression::List(cs) => format!("{{{}}}", format_list(cs, depth))
+/// Render a column, with its module if it lives outside of `home`, and its
+/// shift, if any, as an explicit offset; e.g. `A`, `m.A[+3]` or `A[-1]`.
+fn pretty_column(handle: &ColumnRef, shift: i16, home: Option<&str>) -> String {
+    let name = handle.map(
+        |id| format!("Col.#{}", id),
+        |h| {
+            if home.map(|m| m == h.module).unwrap_or(false) || h.module == MAIN_MODULE {
+                h.perspective
+                    .as_ref()
+                    .map(|p| format!("{}/{}", p, h.name))
+                    .unwrap_or_else(|| h.name.to_owned())
+            } else {
+                h.to_string()
+            }
+        },
+    );
+    if shift != 0 {
+        format!("{}[{:+}]", name, shift)
+    } else {
+        name
+    }
+}
+
+fn pretty_node(n: &Node, home: Option<&str>) -> String {
+    fn rec_pretty(s: &Node, depth: usize, home: Option<&str>) -> String {
+        let depth = depth
+            + match s.e() {
+                Expression::Funcall { .. } | Expression::List(_) => 1,
+                _ => 0,
+            };
+        let c = &COLORS[depth % COLORS.len()];
+        match s.e() {
+            Expression::Const(x) => format!("{}", x).color(*c).to_string(),
+            Expression::Column { handle, shift, .. }
+            | Expression::ExoColumn { handle, shift, .. } => {
+                pretty_column(handle, *shift, home).color(*c).to_string()
+            }
+            Expression::ArrayColumn { handle, domain, .. } => {
+                format!("{}{}", pretty_column(handle, 0, home), domain)
                     .color(*c)
-                    .to_string(),
-                Expression::Funcall { func, args } => {
-                    format!("({} {})", func, format_list(args, depth))
-                        .color(*c)
-                        .to_string()
-                }
-                Expression::Void => "nil".color(*c).to_string(),
+                    .to_string()
             }
+            Expression::List(cs) => format!("{{{}}}", format_list(cs, depth, home))
+                .color(*c)
+                .to_string(),
+            Expression::Funcall { func, args } => {
+                format!("({} {})", func, format_list(args, depth, home))
+                    .color(*c)
+                    .to_string()
+            }
+            Expression::Void => "nil".color(*c).to_string(),
         }
-        fn format_list(cs: &[Node], depth: usize) -> String {
-            cs.iter()
-                .map(|c| rec_pretty(c, depth))
-                .collect::<Vec<_>>()
-                .join(" ")
-        }
-        rec_pretty(self, 0)
+    }
+    fn format_list(cs: &[Node], depth: usize, home: Option<&str>) -> String {
+        cs.iter()
+            .map(|c| rec_pretty(c, depth, home))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+    rec_pretty(n, 0, home)
+}
+
+impl Node {
+    /// Pretty-print this node as seen from `module`, i.e. only prefixing the
+    /// columns of the other modules with their module name.
+    pub fn pretty_in(&self, module: &str) -> String {
+        pretty_node(self, Some(module))
+    }
+}
+
+impl Pretty for Node {
+    fn pretty(&self) -> String {
+        pretty_node(self, None)
     }
     fn pretty_with_base(&self, _base: Base) -> String {
         self.pretty()
@@ -0,0 +1,76 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressFinish, ProgressStyle};
+use log::{Log, Metadata, Record};
+
+lazy_static::lazy_static! {
+    /// All the progress bars are drawn on stderr, which is silent if it is
+    /// not a terminal
+    static ref BARS: MultiProgress = MultiProgress::with_draw_target(ProgressDrawTarget::stderr());
+}
+
+/// Create a progress bar for a task processing `len` items, that is hidden
+/// unless `enabled` and cleared once dropped. Its counter is atomic, and can
+/// thus be incremented from parallel iterators.
+pub(crate) fn bar(enabled: bool, len: usize, message: &'static str) -> ProgressBar {
+    if enabled {
+        BARS.add(
+            ProgressBar::new(len as u64)
+                .with_style(
+                    ProgressStyle::with_template(
+                        "{msg} [{elapsed_precise}] {wide_bar} {pos}/{len} (ETA {eta})",
+                    )
+                    .unwrap(),
+                )
+                .with_message(message)
+                .with_finish(ProgressFinish::AndClear),
+        )
+    } else {
+        ProgressBar::hidden()
+    }
+}
+
+/// A logger hiding the progress bars while it writes, so that they do not
+/// interleave with the log output. It may also strip the colors of the
+/// messages, which are otherwise written as is by the inner logger.
+pub struct SuspendingLogger<L: Log> {
+    inner: L,
+    strip_colors: bool,
+}
+impl<L: Log> SuspendingLogger<L> {
+    pub fn new(inner: L, strip_colors: bool) -> Self {
+        SuspendingLogger {
+            inner,
+            strip_colors,
+        }
+    }
+}
+impl<L: Log> Log for SuspendingLogger<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            if self.strip_colors {
+                let message = anstream::adapter::strip_str(&record.args().to_string()).to_string();
+                BARS.suspend(|| {
+                    self.inner.log(
+                        &Record::builder()
+                            .args(format_args!("{}", message))
+                            .level(record.level())
+                            .target(record.target())
+                            .module_path(record.module_path())
+                            .file(record.file())
+                            .line(record.line())
+                            .build(),
+                    )
+                })
+            } else {
+                BARS.suspend(|| self.inner.log(record))
+            }
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,54 @@
+use serde_json::{json, Map, Value};
+
+use crate::compiler::{ColumnRef, ConstraintSet, Kind};
+
+/// Whether the column `r` of `cs` must be provided by the trace, i.e. is an
+/// atomic column that is not filled by any computation
+fn is_atomic(cs: &ConstraintSet, r: &ColumnRef, kind: &Kind<()>) -> bool {
+    matches!(kind, Kind::Commitment) && cs.computations.computation_for(r).is_none()
+}
+
+/// Build the JSON schema of the traces accepted by `cs`, as reported by the
+/// `schema` command: each module is an object mapping its atomic columns,
+/// that are all required, to arrays of values, while its computed columns
+/// must not be provided.
+pub(crate) fn schema(cs: &ConstraintSet) -> Value {
+    let mut modules = Map::new();
+    for module in cs.columns.modules() {
+        let mut columns = Map::new();
+        let mut required = vec![];
+        for (r, c) in cs.columns.iter_module(&module) {
+            let column = if is_atomic(cs, &r, &c.kind) {
+                required.push(c.handle.name.clone());
+                json!({
+                    "description": "required",
+                    "type": "array",
+                    "items": { "type": ["integer", "string"] },
+                    "x-magma": c.t.to_string(),
+                })
+            } else {
+                json!({
+                    "description": "computed; do not provide",
+                    "not": {},
+                })
+            };
+            columns.insert(c.handle.name.clone(), column);
+        }
+        required.sort();
+        modules.insert(
+            module,
+            json!({
+                "type": "object",
+                "properties": columns,
+                "required": required,
+            }),
+        );
+    }
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "corset trace",
+        "type": "object",
+        "properties": modules,
+    })
+}
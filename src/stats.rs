@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use crate::field::Fr;
+use ark_ff::{BigInteger, PrimeField};
+use itertools::Itertools;
+use num_bigint::{BigInt, Sign};
+use num_traits::Zero;
+use serde::Serialize;
+
+use crate::compiler::ConstraintSet;
+
+/// Summary statistics of the values of a column in a computed trace, as
+/// reported by the `stats` command
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub(crate) struct ColumnStats {
+    pub module: String,
+    pub name: String,
+    /// the number of rows of the column, padding included
+    pub len: usize,
+    pub distinct: usize,
+    pub zeros: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<String>,
+    /// the most frequent values, and how many times they appear
+    pub top: Vec<(String, usize)>,
+}
+
+fn to_bi(x: &Fr) -> BigInt {
+    BigInt::from_bytes_be(Sign::Plus, &x.into_bigint().to_bytes_be())
+}
+
+/// Compute the statistics of the column `module.name` from its `values`,
+/// keeping its `top` most frequent values
+pub(crate) fn column_stats(module: &str, name: &str, values: &[Fr], top: usize) -> ColumnStats {
+    let mut histogram = HashMap::<Fr, usize>::new();
+    for x in values {
+        *histogram.entry(*x).or_default() += 1;
+    }
+    let by_value = histogram
+        .iter()
+        .map(|(x, count)| (to_bi(x), *count))
+        .sorted()
+        .collect::<Vec<_>>();
+
+    ColumnStats {
+        module: module.to_owned(),
+        name: name.to_owned(),
+        len: values.len(),
+        distinct: histogram.len(),
+        zeros: histogram.get(&Fr::zero()).copied().unwrap_or(0),
+        min: by_value.first().map(|(x, _)| x.to_string()),
+        max: by_value.last().map(|(x, _)| x.to_string()),
+        // ties are broken by value, so that the output is stable
+        top: by_value
+            .iter()
+            .sorted_by_key(|(_, count)| std::cmp::Reverse(*count))
+            .take(top)
+            .map(|(x, count)| (x.to_string(), *count))
+            .collect(),
+    }
+}
+
+/// Compute the statistics of all the filled columns of `cs`, ordered by
+/// module and name
+pub(crate) fn stats(cs: &ConstraintSet, top: usize) -> Vec<ColumnStats> {
+    cs.columns
+        .iter_cols()
+        .filter_map(|c| {
+            cs.column_values(&c.handle)
+                .map(|values| column_stats(&c.handle.module, &c.handle.name, &values, top))
+        })
+        .sorted_by(|a, b| (&a.module, &a.name).cmp(&(&b.module, &b.name)))
+        .collect()
+}
+
+/// Render `stats` as one line per column, grouped by module and followed by
+/// the most frequent values
+pub(crate) fn render(stats: &[ColumnStats]) -> String {
+    stats
+        .iter()
+        .group_by(|s| &s.module)
+        .into_iter()
+        .map(|(module, ss)| {
+            format!(
+                "{}:\n{}",
+                module,
+                ss.map(|s| {
+                    format!(
+                        "  {}.{} : {} rows, {} distinct, {} zeros{}{}",
+                        s.module,
+                        s.name,
+                        s.len,
+                        s.distinct,
+                        s.zeros,
+                        s.min
+                            .as_ref()
+                            .zip(s.max.as_ref())
+                            .map(|(min, max)| format!(", in [{}; {}]", min, max))
+                            .unwrap_or_default(),
+                        if s.top.is_empty() {
+                            String::new()
+                        } else {
+                            format!(
+                                "\n      top: {}",
+                                s.top
+                                    .iter()
+                                    .map(|(x, count)| format!("{} ×{}", x, count))
+                                    .join(", ")
+                            )
+                        }
+                    )
+                })
+                .join("\n")
+            )
+        })
+        .join("\n")
+}
@@ -7,7 +7,7 @@
 (defpurefun (if-not-zero cond then else) (if (force-bool cond) then else))
 
 (defpurefun ((force-bool :@bool :force) x) x)
-(defpurefun ((is-binary :@loob :force) e0) (* e0 (- 1 e0)))
+(defpurefun ((is-binary :@loob :force) e0) (* e0 (- 1 (as-integer e0))))
 
 (defpurefun ((force-bin :binary :force) x) x)
 
@@ -38,9 +38,10 @@
 (defpurefun ((neq! :binary@loob :force) x y) (not (~ (eq! x y))))
 (defunalias = eq!)
 
-(defpurefun ((eq :binary@bool :force) (x :binary) (y :binary)) (^ (- x y) 2))
+(defpurefun ((eq :binary@bool :force) (x :binary) (y :binary)) (^ (- (as-integer x) (as-integer y)) 2))
 (defpurefun ((eq :binary@bool :force) x y) (- 1 (~ (eq! x y))))
 (defpurefun ((neq :binary@bool :force) x y) (eq! x y))
+(defunalias not-eq neq)
 
 
 ;; Variadic versions of and/or
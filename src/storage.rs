@@ -0,0 +1,177 @@
+use crate::field::Fr;
+use anyhow::*;
+use ark_ff::{BigInteger, PrimeField};
+use memmap2::MmapMut;
+use num_bigint::{BigInt, Sign};
+use std::{
+    fs::OpenOptions,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::column::Value;
+
+/// Where the values of a vector-backed register live
+pub trait ColumnStorage: std::fmt::Debug + Send + Sync {
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn get(&self, i: usize) -> Option<Value>;
+    fn set(&mut self, i: usize, x: Value) -> Result<()>;
+    /// Whether the values are stored outside of the main memory
+    fn is_mapped(&self) -> bool {
+        false
+    }
+    /// Apply `f` to all the stored values
+    fn update(&mut self, f: &mut dyn FnMut(&mut Value) -> Result<()>) -> Result<()> {
+        for i in 0..self.len() {
+            let mut x = self.get(i).unwrap();
+            f(&mut x)?;
+            self.set(i, x)?;
+        }
+        Ok(())
+    }
+}
+
+impl ColumnStorage for Vec<Value> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+    fn get(&self, i: usize) -> Option<Value> {
+        self.as_slice().get(i).cloned()
+    }
+    fn set(&mut self, i: usize, x: Value) -> Result<()> {
+        self[i] = x;
+        Ok(())
+    }
+    fn update(&mut self, f: &mut dyn FnMut(&mut Value) -> Result<()>) -> Result<()> {
+        self.iter_mut().try_for_each(f)
+    }
+}
+
+/// Where and from which length registers shall be spilled to memory-mapped
+/// files rather than held in memory.
+#[derive(Debug, Clone)]
+pub struct MmapSettings {
+    /// the directory where the backing files are created
+    pub dir: PathBuf,
+    /// the minimal length of the spilled registers
+    pub threshold: usize,
+}
+impl MmapSettings {
+    /// By default, only registers over 64k rows are spilled
+    pub const DEFAULT_THRESHOLD: usize = 1 << 16;
+
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        MmapSettings {
+            dir: dir.as_ref().to_owned(),
+            threshold: Self::DEFAULT_THRESHOLD,
+        }
+    }
+
+    pub fn threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+}
+
+/// Each value is stored on a tag byte, followed by its 256-bits little-endian
+/// magnitude.
+const CELL_SIZE: usize = 33;
+const TAG_NATIVE: u8 = 0;
+const TAG_POSITIVE: u8 = 1;
+const TAG_NEGATIVE: u8 = 2;
+
+static FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A register stored in a memory-mapped file, that is removed once the
+/// register is dropped.
+pub struct MmapStorage {
+    mmap: MmapMut,
+    len: usize,
+    path: PathBuf,
+}
+impl std::fmt::Debug for MmapStorage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "mmap({}, {} values)", self.path.display(), self.len)
+    }
+}
+impl MmapStorage {
+    pub fn from_storage(settings: &MmapSettings, values: &dyn ColumnStorage) -> Result<Self> {
+        let path = settings.dir.join(format!(
+            "corset-{}-{}.col",
+            std::process::id(),
+            FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .with_context(|| anyhow!("creating {}", path.display()))?;
+        file.set_len((values.len().max(1) * CELL_SIZE) as u64)?;
+        let mut r = MmapStorage {
+            // SAFETY: the file has just been created for the exclusive use of
+            // this storage
+            mmap: unsafe { MmapMut::map_mut(&file) }
+                .with_context(|| anyhow!("mapping {}", path.display()))?,
+            len: values.len(),
+            path,
+        };
+        for i in 0..values.len() {
+            r.set(i, values.get(i).unwrap())?;
+        }
+        Ok(r)
+    }
+}
+impl Drop for MmapStorage {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+impl ColumnStorage for MmapStorage {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, i: usize) -> Option<Value> {
+        if i >= self.len {
+            return None;
+        }
+        let cell = &self.mmap[i * CELL_SIZE..(i + 1) * CELL_SIZE];
+        Some(match cell[0] {
+            TAG_NATIVE => Value::Native(Fr::from_le_bytes_mod_order(&cell[1..])),
+            TAG_POSITIVE => Value::BigInt(BigInt::from_bytes_le(Sign::Plus, &cell[1..])),
+            TAG_NEGATIVE => Value::BigInt(BigInt::from_bytes_le(Sign::Minus, &cell[1..])),
+            _ => unreachable!(),
+        })
+    }
+
+    fn set(&mut self, i: usize, x: Value) -> Result<()> {
+        let (tag, bytes) = match &x {
+            Value::Native(f) => (TAG_NATIVE, f.into_bigint().to_bytes_le()),
+            Value::BigInt(i) => (
+                if i.sign() == Sign::Minus {
+                    TAG_NEGATIVE
+                } else {
+                    TAG_POSITIVE
+                },
+                i.magnitude().to_bytes_le(),
+            ),
+            Value::ExoNative(_) => bail!("can not map {}", x),
+        };
+        if bytes.len() > CELL_SIZE - 1 {
+            bail!("{} does not fit in 256 bits", x)
+        }
+        let cell = &mut self.mmap[i * CELL_SIZE..(i + 1) * CELL_SIZE];
+        cell.fill(0);
+        cell[0] = tag;
+        cell[1..1 + bytes.len()].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    fn is_mapped(&self) -> bool {
+        true
+    }
+}
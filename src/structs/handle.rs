@@ -191,6 +191,29 @@ impl std::fmt::Display for Handle {
 }
 
 impl Handle {
+    /// The name of this handle in the traces, where the module and the name
+    /// are joined by `separator`
+    pub fn to_trace_key(&self, separator: &str) -> String {
+        let name = match self.perspective.as_ref() {
+            Some(p) => format!("{}/{}", p, self.name),
+            None => self.name.clone(),
+        };
+        if self.module == MAIN_MODULE {
+            name
+        } else {
+            format!("{}{}{}", self.module, separator, name)
+        }
+    }
+
+    /// Parse a column name found in a trace, where the module and the name are
+    /// joined by `separator`; names without module belong to the prelude
+    pub fn from_trace_key(key: &str, separator: &str) -> Handle {
+        match key.split_once(separator) {
+            Some((module, name)) => Handle::new(module, name),
+            None => Handle::new(MAIN_MODULE, key),
+        }
+    }
+
     pub fn to_serialized_string(&self) -> String {
         match &self.perspective {
             None => format!("{}.{}", self.module, self.name),
@@ -5,3 +5,6 @@ pub use handle::Handle;
 const ARRAY_SEPARATOR: &str = "_";
 pub const PERSPECTIVE_SEPARATOR: char = '/';
 const MODULE_SEPARATOR: &str = "__";
+/// The default separator between the module and the name of a column in
+/// traces
+pub const DEFAULT_TRACE_MODULE_SEPARATOR: &str = ".";
@@ -1,4 +1,7 @@
-use crate::{transformer::ExpansionLevel, ConstraintSetBuilder};
+use crate::{
+    transformer::{AutoConstraint, ExpansionLevel},
+    ConstraintSetBuilder,
+};
 use anyhow::*;
 
 fn make(name: &str, source: &str) -> Result<()> {
@@ -154,6 +157,53 @@ fn array_len() {
     );
 }
 
+#[test]
+fn constant_lists() {
+    must_run(
+        "nth over a constant list",
+        "(defconst MASK [1 0 1 1]) (defcolumns A) (defconstraint test () (vanishes! (- A (nth MASK 2) [MASK 3])))",
+    );
+    must_run(
+        "len of a constant list",
+        "(defconst TABLE [3 0x10 -2]) (defcolumns A) (defconstraint test () (eq! A (len TABLE)))",
+    );
+    must_fail(
+        "out of bounds",
+        "(defconst MASK [1 0 1 1]) (defcolumns A) (defconstraint test () (eq! A (nth MASK 4)))",
+    );
+    must_fail(
+        "not a list",
+        "(defconst MASK 1) (defcolumns A) (defconstraint test () (eq! A (nth MASK 0)))",
+    );
+
+    assert_eq!(
+        constraint_text(
+            "(defconst MASK [1 0 1 1]) (defcolumns A) (defconstraint c () (vanishes! (- A [MASK 2])))"
+        ),
+        "(- A 1)"
+    );
+}
+
+#[test]
+fn negative_indices() {
+    assert_eq!(
+        constraint_text("(defcolumns A (B :array [2:4])) (defconstraint c () (vanishes! (- A (nth B -1) [B -3])))"),
+        "(- A B_4 B_2)"
+    );
+    assert_eq!(
+        constraint_text("(defconst MASK [1 0 1 3]) (defcolumns A) (defconstraint c () (vanishes! (- A [MASK -1])))"),
+        "(- A 3)"
+    );
+    must_fail(
+        "out of bounds from the end",
+        "(defcolumns A (B :array [2:4])) (defconstraint c () (vanishes! (- A [B -4])))",
+    );
+    must_fail(
+        "out of bounds from the end of a list",
+        "(defconst MASK [1 0 1 3]) (defcolumns A) (defconstraint c () (vanishes! (- A [MASK -5])))",
+    );
+}
+
 #[test]
 fn global_scope() {
     must_run(
@@ -246,3 +296,2263 @@ fn complex_for() {
 //     //     "(module foobar) (defcolumns A B (C :bool) (D :i32)) (defconstraint pipo () (if (eq! A D) C D))",
 //     // );
 // }
+
+#[test]
+fn defpadding() {
+    must_run(
+        "defpadding ok",
+        "(defcolumns A B) (defpadding A 1) (defconstraint test () (vanishes! (- A B)))",
+    );
+    must_fail(
+        "defpadding: unknown column",
+        "(defcolumns A B) (defpadding C 1)",
+    );
+    must_fail(
+        "defpadding: not a column",
+        "(defconst C 3) (defcolumns A B) (defpadding C 1)",
+    );
+    must_fail(
+        "defpadding: padding already set",
+        "(defcolumns (A :padding 2) B) (defpadding A 1)",
+    );
+}
+
+#[test]
+fn defpadding_value() -> Result<()> {
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source("(module m) (defcolumns A B) (defpadding A 255)")?;
+    r.expand_to(ExpansionLevel::top());
+    let cs = r.into_constraint_set()?;
+
+    let a = cs
+        .columns
+        .column(&crate::structs::Handle::new("m", "A").into())?;
+    assert_eq!(a.padding_value, Some(crate::column::Value::from(255usize)));
+    let b = cs
+        .columns
+        .column(&crate::structs::Handle::new("m", "B").into())?;
+    assert!(b.padding_value.is_none());
+    Ok(())
+}
+
+#[test]
+fn literal_out_of_field() {
+    // the modulus of the scalar field
+    let modulus = "8444461749428370424248824938781546531375899335154063827935233455917409239041";
+    must_run("largest literal", "(defcolumns A) (defconstraint big () (vanishes! (- A 8444461749428370424248824938781546531375899335154063827935233455917409239040)))");
+    must_fail(
+        "literal too large",
+        &format!("(defcolumns A) (defconstraint big () (vanishes! (- A {modulus})))"),
+    );
+    must_fail(
+        "negative literal too large",
+        &format!("(defcolumns A) (defconstraint big () (vanishes! (+ A -{modulus})))"),
+    );
+    must_fail(
+        "constant too large",
+        &format!(
+            "(defconst BIG {modulus}) (defcolumns A) (defconstraint big () (vanishes! (- A BIG)))"
+        ),
+    );
+    must_fail(
+        "computed constant too large",
+        &format!(
+            "(defconst BIG (* 2 {modulus})) (defcolumns A) (defconstraint big () (vanishes! (- A BIG)))"
+        ),
+    );
+    // powers are raised within the field
+    must_run(
+        "large power",
+        "(defconst BIG (^ 2 300)) (defcolumns A) (defconstraint big () (vanishes! (- A BIG)))",
+    );
+}
+
+#[test]
+fn spilling_from_shifts() -> Result<()> {
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(module m) (defcolumns A B) (defconstraint past () (vanishes! (- A (shift B -3))))",
+    )?;
+    r.expand_to(ExpansionLevel::top());
+    let cs = r.into_constraint_set()?;
+    assert!(cs.spilling_of("m").unwrap() >= 3);
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(module m) (defcolumns A B) (defconstraint future () (vanishes! (- A (shift B 5))))",
+    )?;
+    r.expand_to(ExpansionLevel::top());
+    let cs = r.into_constraint_set()?;
+    assert!(cs.spilling_of("m").unwrap() >= 5);
+    Ok(())
+}
+
+#[test]
+fn defun_return_type() {
+    must_run(
+        "return type after name",
+        "(defcolumns (A :binary) (B :binary)) (defun (f :binary a b) (* a b)) (defconstraint c () (vanishes! (f A B)))",
+    );
+    must_run(
+        "return type on name",
+        "(defcolumns (A :binary) (B :binary)) (defun ((f :binary) a b) (* a b)) (defconstraint c () (vanishes! (f A B)))",
+    );
+    must_fail(
+        "mismatching return type",
+        "(defcolumns A B) (defun (f :binary a b) (+ a b)) (defconstraint c () (vanishes! (f A B)))",
+    );
+    must_run(
+        "forced return type",
+        "(defcolumns A B) (defun (f :binary :force a b) (+ a b)) (defconstraint c () (vanishes! (f A B)))",
+    );
+    must_fail(
+        "duplicated return type",
+        "(defcolumns A B) (defun ((f :binary) :byte a b) (+ a b)) (defconstraint c () (vanishes! (f A B)))",
+    );
+}
+
+/// Compile `source` and return the textual form of the vanishing constraint `c`
+fn constraint_text(source: &str) -> String {
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(source).unwrap();
+    let cs = r.into_constraint_set().unwrap();
+    cs.constraints
+        .iter()
+        .find_map(|c| match c {
+            crate::compiler::Constraint::Vanishes { handle, expr, .. } if handle.name == "c" => {
+                Some(expr.to_string())
+            }
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn begin_normalization() {
+    let prelude = "(defcolumns A B)";
+    for (begin, expected) in [
+        ("(begin (begin) A)", "A"),
+        ("(begin A (begin) (begin B (begin)))", "{A B}"),
+        ("(begin (begin A) (begin (begin B)))", "{A B}"),
+    ] {
+        assert_eq!(
+            constraint_text(&format!("{prelude} (defconstraint c () {begin})")),
+            expected,
+            "{begin}"
+        );
+    }
+
+    // a constraint reduced to nothing is dropped altogether
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(&format!(
+        "{prelude} (defconstraint c () (begin (begin (begin)) (begin)))"
+    ))
+    .unwrap();
+    let cs = r.into_constraint_set().unwrap();
+    assert!(!cs.constraints.iter().any(|c| matches!(
+        c,
+        crate::compiler::Constraint::Vanishes { handle, .. } if handle.name == "c"
+    )));
+}
+
+#[test]
+fn shift_ranges() {
+    let prelude = "(defcolumns A B)";
+    let explicit = constraint_text(&format!(
+        "{prelude} (defconstraint c () (begin (shift A -1) A (shift A 1)))"
+    ));
+    assert_eq!(explicit, "{A₋₁ A A₊₁}");
+    for shifts in ["[-1:1]", "{-1 0 1}"] {
+        assert_eq!(
+            constraint_text(&format!(
+                "{prelude} (defconstraint c () (shift A {shifts}))"
+            )),
+            explicit,
+            "{shifts}"
+        );
+    }
+
+    must_fail(
+        "shifted constant",
+        &format!("{prelude} (defconstraint c () (shift 3 [-1:1]))"),
+    );
+}
+
+#[test]
+fn partial_application() {
+    let prelude = "(defcolumns A B C) (defpurefun (add3 x y z) (+ x (* 2 y) (* 3 z)))";
+    let direct = constraint_text(&format!(
+        "{prelude} (defconstraint c () (vanishes! (add3 A B C)))"
+    ));
+    for curried in ["((add3 A B) C)", "((add3 A) B C)", "(((add3 A) B) C)"] {
+        assert_eq!(
+            constraint_text(&format!(
+                "{prelude} (defconstraint c () (vanishes! {curried}))"
+            )),
+            direct,
+            "{curried}"
+        );
+    }
+
+    must_fail(
+        "fully applied head",
+        &format!("{prelude} (defconstraint c () (vanishes! ((add3 A B C) A)))"),
+    );
+    must_fail(
+        "too many arguments overall",
+        &format!("{prelude} (defconstraint c () (vanishes! ((add3 A B) C A)))"),
+    );
+    must_fail(
+        "too few arguments overall",
+        &format!("{prelude} (defconstraint c () (vanishes! ((add3 A) B)))"),
+    );
+    must_fail(
+        "partial builtin",
+        "(defcolumns A B) (defconstraint c () (vanishes! ((+ A) B)))",
+    );
+}
+
+/// Compile `source` and return the expression computing the column `name`
+fn computed_expression(source: &str, name: &str) -> crate::compiler::Node {
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(source).unwrap();
+    let cs = r.into_constraint_set().unwrap();
+    let (column, _) = cs
+        .columns
+        .iter()
+        .find(|(_, c)| c.handle.name == name)
+        .unwrap();
+    match cs.computations.computation_for(&column) {
+        Some(crate::column::Computation::Composite { exp, .. }) => exp.clone(),
+        _ => panic!("{} is not a computed expression", name),
+    }
+}
+
+#[test]
+fn constant_folding() {
+    let source = "(defcolumns A
+                    (B :comp (+ 2 3))
+                    (C :comp (* 4 (^ 2 8)))
+                    (D :comp (- 3 2))
+                    (E :comp (* A (+ 2 3))))";
+    for (column, value) in [("B", 5), ("C", 1024), ("D", 1)] {
+        let folded = computed_expression(source, column);
+        assert!(
+            matches!(folded.e(), crate::compiler::Expression::Const(v) if *v == crate::column::Value::from(value)),
+            "{column} should fold to {value}, found {folded}"
+        );
+    }
+    assert!(computed_expression(source, "D").t().is_binary());
+
+    let e = computed_expression(source, "E");
+    assert!(matches!(
+        e.e(),
+        crate::compiler::Expression::Funcall {
+            func: crate::compiler::Intrinsic::Mul,
+            ..
+        }
+    ));
+    assert_eq!(e.to_string(), "(* A 5)");
+}
+
+#[test]
+fn matrix_columns() {
+    must_run(
+        "4x8 matrix",
+        "(defcolumns (m [0:3] [0:7]) A)
+         (defconstraint nth () (vanishes! (- A (nth (nth m 2) 5))))
+         (defconstraint indexed () (vanishes! (- A (nth [m 3] 7))))
+         (defconstraint atomic () (vanishes! (- A m_0_0)))
+         (defconstraint rows () (vanishes! (- A (len m) (len [m 0]))))",
+    );
+    must_run(
+        "matrix with :array",
+        "(defcolumns (m :array [0:3] [0:7]) A) (defconstraint nth () (vanishes! (- A (nth [m 1] 1))))",
+    );
+    must_fail(
+        "out of bounds row",
+        "(defcolumns (m [0:3] [0:7]) A) (defconstraint nth () (vanishes! (- A (nth [m 4] 1))))",
+    );
+    must_fail(
+        "out of bounds column",
+        "(defcolumns (m [0:3] [0:7]) A) (defconstraint nth () (vanishes! (- A (nth [m 1] 8))))",
+    );
+    must_fail(
+        "indexing an atomic column",
+        "(defcolumns (m [0:3] [0:7]) A) (defconstraint nth () (vanishes! (- A (nth (nth [m 1] 1) 1))))",
+    );
+}
+
+#[test]
+fn watch_debounce() {
+    use crate::watch::{run_on_changes, WatchEvent};
+    use std::time::Duration;
+
+    // a burst of changes triggers exactly one run
+    let (tx, rx) = std::sync::mpsc::channel();
+    for _ in 0..3 {
+        tx.send(WatchEvent::Changed).unwrap();
+    }
+    drop(tx);
+    let mut runs = 0;
+    assert_eq!(
+        run_on_changes(&rx, Duration::from_millis(50), || runs += 1),
+        1
+    );
+    assert_eq!(runs, 1);
+
+    // a stop request is honored before running
+    let (tx, rx) = std::sync::mpsc::channel();
+    tx.send(WatchEvent::Changed).unwrap();
+    tx.send(WatchEvent::Stop).unwrap();
+    assert_eq!(run_on_changes(&rx, Duration::from_millis(50), || {}), 0);
+
+    // separated changes trigger one run each
+    let (tx, rx) = std::sync::mpsc::channel();
+    let sender = std::thread::spawn(move || {
+        tx.send(WatchEvent::Changed).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        tx.send(WatchEvent::Changed).unwrap();
+    });
+    assert_eq!(run_on_changes(&rx, Duration::from_millis(50), || {}), 2);
+    sender.join().unwrap();
+}
+
+#[test]
+fn trace_diff() {
+    use crate::compiler::generator::TraceRadix;
+    use crate::diff::{diff, read_columns_str, ColumnDiff};
+    let read_columns_str = |trace: &[u8]| read_columns_str(trace, TraceRadix::Legacy);
+
+    let trace = read_columns_str(br#"{"m": {"A": [1, 2, 3], "B": ["4", "5", "6"]}}"#).unwrap();
+    assert!(diff(&trace, &trace).is_empty());
+
+    // values are compared as field elements, whatever their representation
+    let computed = read_columns_str(
+        br#"{"columns": {"m.A": {"values": ["0x01", "0x02", "0x03"]}, "m.B": {"values": [4, 5, 6]}}}"#,
+    )
+    .unwrap();
+    assert!(diff(&trace, &computed).is_empty());
+    // computed traces hold `0x0` followed by the decimal value
+    assert!(diff(
+        &read_columns_str(br#"{"m": {"A": [10, 0]}}"#).unwrap(),
+        &read_columns_str(br#"{"columns": {"m.A": {"values": ["0x010", "0x00"]}}}"#).unwrap()
+    )
+    .is_empty());
+    // whatever the radix they are written in, as long as it is known
+    for radix in [
+        TraceRadix::Decimal,
+        TraceRadix::Hex,
+        TraceRadix::Canonical,
+        TraceRadix::Legacy,
+    ] {
+        let values = [0, 10, 17, 255]
+            .map(|x| format!(r#""{}""#, radix.render(&crate::column::Value::from(x))))
+            .join(", ");
+        let computed = crate::diff::read_columns_str(
+            format!(r#"{{"columns": {{"m.A": {{"values": [{values}]}}}}}}"#).as_bytes(),
+            radix,
+        )
+        .unwrap();
+        assert!(
+            diff(
+                &read_columns_str(br#"{"m": {"A": [0, 10, 17, 255]}}"#).unwrap(),
+                &computed
+            )
+            .is_empty(),
+            "{:?}",
+            radix
+        );
+    }
+
+    let perturbed =
+        read_columns_str(br#"{"m": {"A": [1, 7, 8], "C": [0]}, "n": {"Trace": {"D": [1]}}}"#)
+            .unwrap();
+    let d = diff(&trace, &perturbed);
+    assert_eq!(
+        d.get("m.A"),
+        Some(&ColumnDiff::Differs {
+            first_row: 1,
+            left: Some("2".into()),
+            right: Some("7".into()),
+            count: 2
+        })
+    );
+    assert_eq!(d.get("m.B"), Some(&ColumnDiff::OnlyLeft));
+    assert_eq!(d.get("m.C"), Some(&ColumnDiff::OnlyRight));
+    assert_eq!(d.get("n.D"), Some(&ColumnDiff::OnlyRight));
+    assert_eq!(
+        crate::diff::render(&d).lines().next(),
+        Some("m.A differs on 2 rows, first at row 1: 2 vs. 7")
+    );
+}
+
+#[test]
+fn pretty_expressions() {
+    use crate::compiler::{Intrinsic, Node};
+    use crate::pretty::Pretty;
+    use crate::structs::Handle;
+
+    fn plain(s: String) -> String {
+        regex_lite::Regex::new("\x1b\\[[0-9;]*m")
+            .unwrap()
+            .replace_all(&s, "")
+            .into_owned()
+    }
+    let column = |module: &str, name: &str, shift: i16| {
+        Node::column()
+            .handle(Handle::new(module, name))
+            .shift(shift)
+            .build()
+    };
+
+    let e = Intrinsic::Sub
+        .call(&[
+            column("m", "A", 3),
+            column("m", "B", -1),
+            column("n", "C", 0),
+        ])
+        .unwrap();
+    assert_eq!(plain(e.pretty()), "(- m.A[+3] m.B[-1] n.C)");
+    assert_eq!(plain(e.pretty_in("m")), "(- A[+3] B[-1] n.C)");
+    assert_eq!(plain(e.pretty_in("n")), "(- m.A[+3] m.B[-1] C)");
+
+    // nested shifts are merged in the rendered offset
+    let source = "(module m) (defcolumns A B) (defconstraint c () (vanishes! (- (shift (shift A 1) 2) (shift B -2))))";
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(source).unwrap();
+    let cs = r.into_constraint_set().unwrap();
+    let rendered = cs
+        .constraints
+        .iter()
+        .find_map(|c| match c {
+            crate::compiler::Constraint::Vanishes { handle, expr, .. } if handle.name == "c" => {
+                Some(plain(expr.pretty_in(&handle.module)))
+            }
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(rendered, "(- A[+3] B[-2])");
+}
+
+#[test]
+fn common_subexpressions() {
+    use crate::{column::Computation, compiler::Constraint};
+
+    let source = "(defcolumns A B C D)
+                  (defconstraint c1 () (vanishes! (* (- A (shift B 1)) C)))
+                  (defconstraint c2 () (vanishes! (* (- A (shift B 1)) D)))
+                  (defconstraint c3 () (vanishes! (+ (- A (shift B 1)) (- A B))))";
+    let build = |cse| {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source(source).unwrap();
+        r.cse(cse);
+        r.into_constraint_set().unwrap()
+    };
+
+    let hoisted = |cs: &crate::compiler::ConstraintSet| {
+        cs.columns
+            .iter()
+            .filter(|(_, c)| c.handle.name.starts_with("C/CSE"))
+            .filter_map(|(r, _)| match cs.computations.computation_for(&r) {
+                Some(Computation::Composite { exp, .. }) => Some(exp.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    assert!(hoisted(&build(None)).is_empty());
+
+    let cs = build(Some(2));
+    // (- A B) only appears once, and the shift sets the other one apart
+    let exps = hoisted(&cs);
+    assert_eq!(exps.len(), 1);
+    assert!(exps[0].contains('A') && exps[0].contains('B'));
+    for c in cs.constraints.iter() {
+        if let Constraint::Vanishes { handle, expr, .. } = c {
+            if handle.name.starts_with('c') {
+                assert!(expr.to_string().contains("C/CSE"), "{}", expr);
+            }
+        }
+    }
+
+    // a higher threshold leaves the constraints untouched
+    assert!(hoisted(&build(Some(4))).is_empty());
+
+    // (* (- A B) C) is mostly nested in (+ (* (- A B) C) D), so it is not
+    // hoisted; this leaves enough outermost occurrences of (- A B) to hoist it
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(defcolumns A B C D)
+         (defconstraint c1 () (vanishes! (* (+ (* (- A B) C) D) A)))
+         (defconstraint c2 () (vanishes! (* (+ (* (- A B) C) D) B)))
+         (defconstraint c3 () (vanishes! (* (+ (* (- A B) C) D) C)))
+         (defconstraint c4 () (vanishes! (* (* (- A B) C) D)))
+         (defconstraint c5 () (vanishes! (* (- A B) (- A B))))",
+    )
+    .unwrap();
+    r.cse(Some(3));
+    let exps = hoisted(&r.into_constraint_set().unwrap());
+    assert_eq!(exps.len(), 2, "{:?}", exps);
+    assert!(exps.iter().any(|e| !e.contains('C') && !e.contains('D')));
+
+    for (trace, ok) in [
+        (
+            r#"{"<prelude>": {"A": [0, 0], "B": [0, 0], "C": [0, 0], "D": [0, 0]}}"#,
+            true,
+        ),
+        (
+            r#"{"<prelude>": {"A": [2, 0], "B": [0, 0], "C": [1, 0], "D": [0, 0]}}"#,
+            false,
+        ),
+    ] {
+        for cse in [None, Some(2)] {
+            let mut cs = build(cse);
+            crate::compute::compute_trace_str(trace.as_bytes(), &mut cs, false).unwrap();
+            assert_eq!(
+                crate::check::check(&cs, &None, &[], crate::check::DebugSettings::new()).is_ok(),
+                ok
+            );
+        }
+    }
+}
+
+#[test]
+fn explain_composite() {
+    let source = "(defcolumns A B (C :comp (+ A (* 2 B))) (D :comp (- C (shift A -1))))";
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(source).unwrap();
+    r.expand_to(ExpansionLevel::top());
+    let mut cs = r.into_constraint_set().unwrap();
+    crate::compute::compute_trace_str(
+        r#"{"<prelude>": {"A": [1, 2, 3], "B": [10, 20, 30]}}"#.as_bytes(),
+        &mut cs,
+        false,
+    )
+    .unwrap();
+
+    let plain = |s: String| {
+        regex_lite::Regex::new("\x1b\\[[0-9;]*m")
+            .unwrap()
+            .replace_all(&s, "")
+            .into_owned()
+    };
+    // row 0 is the padding row, so row 2 reads A = 2 and B = 20
+    let explanation = plain(crate::explain::explain(&cs, "D", 2).unwrap());
+    let lines = explanation.lines().map(str::trim).collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![
+            "D[2] = 41",
+            "= (- C A[-1])",
+            "= (- 42 1)",
+            "= 41",
+            "C[2] = 42",
+            "= (+ A (* 2 B))",
+            "= (+ 2 (* 2 20))",
+            "= 42",
+        ]
+    );
+    assert_eq!(
+        cs.column_value_at(&crate::structs::Handle::new("<prelude>", "D"), 2),
+        Some(crate::field::Fr::from(41u64))
+    );
+
+    assert!(crate::explain::explain(&cs, "E", 0).is_err());
+    assert!(crate::explain::explain(&cs, "D", 4).is_err());
+}
+
+#[test]
+fn column_stats() {
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source("(defcolumns A B)").unwrap();
+    r.expand_to(ExpansionLevel::top());
+    let mut cs = r.into_constraint_set().unwrap();
+    crate::compute::compute_trace_str(
+        r#"{"<prelude>": {"A": [0, 3, 3, 7, 3], "B": [1, 1, 1, 1, 1]}}"#.as_bytes(),
+        &mut cs,
+        false,
+    )
+    .unwrap();
+
+    let stats = crate::stats::stats(&cs, 2);
+    assert_eq!(
+        stats.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(),
+        vec!["A", "B"]
+    );
+    // row 0 is the padding row
+    let a = &stats[0];
+    assert_eq!((a.len, a.zeros, a.distinct), (6, 2, 3));
+    assert_eq!((a.min.as_deref(), a.max.as_deref()), (Some("0"), Some("7")));
+    assert_eq!(a.top, vec![("3".to_string(), 3), ("0".to_string(), 2)]);
+    let b = &stats[1];
+    assert_eq!((b.zeros, b.distinct), (1, 2));
+}
+
+#[test]
+fn progress_reporting() {
+    let source = "(defcolumns A B (C :comp (* A B)))
+                  (definterleaved I (A B))
+                  (defconstraint small () (vanishes! (- C 6)))
+                  (defconstraint sum () (vanishes! (- A B)))";
+    let trace = r#"{"<prelude>": {"A": [2, 3, 1], "B": [3, 2, 1]}}"#;
+    let run = |progress| {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source(source).unwrap();
+        r.expand_to(ExpansionLevel::top());
+        r.progress(progress);
+        let mut cs = r.into_constraint_set().unwrap();
+        crate::compute::compute_trace_str(trace.as_bytes(), &mut cs, false).unwrap();
+        let values = cs
+            .columns
+            .iter()
+            .map(|(r, c)| {
+                (
+                    c.handle.to_string(),
+                    (0..cs.columns.len(&r).unwrap_or(0) as isize)
+                        .map(|i| cs.columns.get(&r, i, false))
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<std::collections::BTreeMap<_, _>>();
+        let checked = crate::check::check(
+            &cs,
+            &None,
+            &[],
+            crate::check::DebugSettings::new().progress(progress),
+        )
+        .map_err(|e| e.to_string());
+        (values, checked)
+    };
+
+    let quiet = run(false);
+    let reported = run(true);
+    assert!(quiet.1.is_err());
+    assert_eq!(quiet, reported);
+}
+
+#[test]
+fn dependency_graph() {
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(module a) (defcolumns X Y (Z :comp (+ X (* 2 Y)))) (defconstraint c () (vanishes! (- Z 3)))
+         (module b) (defcolumns W) (deflookup l (W) (a.Z))",
+    )
+    .unwrap();
+    let dot = crate::exporters::dot::to_dot(&r.into_constraint_set().unwrap());
+
+    assert!(dot.starts_with("digraph"));
+    // the composite column is derived from its sources...
+    assert!(dot.contains(r#""a.X" -> "a.Z";"#), "{}", dot);
+    assert!(dot.contains(r#""a.Y" -> "a.Z";"#), "{}", dot);
+    // ...and referenced by the constraints, possibly across modules
+    assert!(dot.contains(r#""a.Z" -> "constraint:a.c";"#), "{}", dot);
+    assert!(
+        dot.contains(r#""a.Z" -> "constraint:b.l" [style=dashed];"#),
+        "{}",
+        dot
+    );
+    assert!(dot.contains(r#""a.Z" [label="a.Z", style=filled, fillcolor=lightblue];"#));
+    assert!(dot.contains(r#""a.X" [label="a.X", style=filled, fillcolor=white];"#));
+}
+
+#[test]
+fn sorting_directions() {
+    let sorted = |source: &str, trace: &str, columns: &[&str]| {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source(source).unwrap();
+        r.expand_to(ExpansionLevel::top());
+        r.auto_constraints(AutoConstraint::all());
+        let mut cs = r.into_constraint_set().unwrap();
+        crate::compute::compute_trace_str(trace.as_bytes(), &mut cs, false).unwrap();
+        let values = columns
+            .iter()
+            .map(|c| {
+                let r = crate::structs::Handle::new("<prelude>", c).into();
+                (0..cs.columns.len(&r).unwrap() as isize)
+                    .map(|i| cs.columns.get(&r, i, false).unwrap().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        (
+            values,
+            crate::check::check(&cs, &None, &[], crate::check::DebugSettings::new()).is_ok(),
+        )
+    };
+
+    for (source, trace, expected) in [
+        // the padding row sorts as a 0
+        (
+            "(defcolumns A) (defpermutation (X) (-A))",
+            r#"{"<prelude>": {"A": [3, 1, 2]}}"#,
+            vec![vec!["3", "2", "1", "0"]],
+        ),
+        // the padding row sorts among the actual rows
+        (
+            "(defcolumns A B) (defpermutation (X Y) (+A -B))",
+            r#"{"<prelude>": {"A": [0, 2, 0, 2], "B": [5, 6, 7, 8]}}"#,
+            vec![vec!["0", "0", "0", "2", "2"], vec!["7", "5", "0", "8", "6"]],
+        ),
+        (
+            "(defcolumns A B) (defpermutation (X Y) (-A +B))",
+            r#"{"<prelude>": {"A": [1, 2, 1, 2], "B": [5, 6, 7, 8]}}"#,
+            vec![vec!["2", "2", "1", "1", "0"], vec!["6", "8", "5", "7", "0"]],
+        ),
+    ] {
+        let (values, ok) = sorted(source, trace, &["X", "Y"][..expected.len()]);
+        assert_eq!(values, expected);
+        assert!(ok, "{}", source);
+    }
+    for source in [
+        "(defcolumns A B) (defpermutation (X Y) (+A -B))",
+        // the list and glued notations are equivalent
+        "(defcolumns A B) (defpermutation (X Y) ((↓ A) (↑ B)))",
+    ] {
+        let (values, ok) = sorted(
+            source,
+            r#"{"<prelude>": {"A": [1, 2, 1, 2], "B": [5, 6, 7, 8]}}"#,
+            &["X", "Y"],
+        );
+        assert_eq!(
+            values,
+            vec![vec!["0", "1", "1", "2", "2"], vec!["0", "7", "5", "8", "6"]]
+        );
+        assert!(ok);
+    }
+}
+
+#[test]
+fn inline_tests() {
+    let load = |source: &str| {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source(source)?;
+        let tests = crate::deftest::collect(&r.to_ast()?);
+        r.expand_to(ExpansionLevel::top());
+        r.auto_constraints(AutoConstraint::all());
+        Ok((r.into_constraint_set()?, tests))
+    };
+
+    let (cs, tests) = load(include_str!("../tests/old/deftest.lisp")).unwrap();
+    assert_eq!(tests.len(), 2);
+    let found = tests
+        .iter()
+        .map(|t| crate::deftest::run(&cs, t).unwrap())
+        .collect::<Vec<_>>();
+    assert!(found[0].is_empty());
+    assert_eq!(found[1].iter().collect::<Vec<_>>(), vec!["equal"]);
+    assert!(crate::deftest::run_all(&cs, &tests[..1]).is_ok());
+    assert!(crate::deftest::run_all(&cs, &tests).is_err());
+
+    // tests are bound to the module they are declared in
+    let (cs, tests) = load(
+        "(module m) (defcolumns A) (defconstraint zero () (vanishes! A))
+         (deftest t1 ((A 0 1)) (zero))",
+    )
+    .unwrap();
+    assert_eq!(tests[0].module, "m");
+    assert!(crate::deftest::run_all(&cs, &tests).is_ok());
+
+    // only literal values for input columns are accepted
+    for source in [
+        "(defcolumns A) (defpermutation (B) (+A)) (deftest t ((B 1)) ())",
+        "(defcolumns A) (deftest t ((C 1)) ())",
+        "(defcolumns A) (deftest t ((A (+ 1 2))) ())",
+        "(defcolumns A) (deftest t ((A 1)) () ())",
+    ] {
+        assert!(load(source).is_err(), "{}", source);
+    }
+}
+
+#[test]
+fn constant_conditions() {
+    use crate::compiler::{Constraint, Expression};
+
+    // the columns a constraint reduces to once its ifs are expanded, if any
+    let expanded = |body: &str| {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source(&format!("(defcolumns A B C) (defconstraint c () {})", body))
+            .unwrap();
+        r.expand_to(ExpansionLevel::ExpandsIfs);
+        let cs = r.into_constraint_set().unwrap();
+        cs.constraints.iter().find_map(|c| match c {
+            Constraint::Vanishes { expr, .. } => Some(match expr.e() {
+                Expression::Column { handle, .. } => vec![cs.handle(handle).name.clone()],
+                _ => {
+                    let mut deps = expr
+                        .dependencies()
+                        .iter()
+                        .map(|h| cs.handle(h).name.clone())
+                        .collect::<Vec<_>>();
+                    deps.sort();
+                    deps
+                }
+            }),
+            _ => None,
+        })
+    };
+
+    assert_eq!(expanded("(if-zero 0 A B)"), Some(vec!["A".to_string()]));
+    assert_eq!(expanded("(if-zero 3 A B)"), Some(vec!["B".to_string()]));
+    assert_eq!(expanded("(if-not-zero 5 A B)"), Some(vec!["A".to_string()]));
+    assert_eq!(expanded("(if-not-zero 0 A B)"), Some(vec!["B".to_string()]));
+    assert_eq!(expanded("(if-zero 0 A)"), Some(vec!["A".to_string()]));
+    assert_eq!(expanded("(if-not-zero 5 A)"), Some(vec!["A".to_string()]));
+    // without else branch, an untaken if constrains nothing
+    assert_eq!(expanded("(if-zero 3 A)"), None);
+    assert_eq!(expanded("(if-not-zero 0 A)"), None);
+    assert_eq!(expanded("(+ B (if-zero 3 A))"), None);
+    assert_eq!(
+        expanded("(begin (if-not-zero 0 A) B)"),
+        Some(vec!["B".to_string()])
+    );
+    // the else branch is kept if the other one is dead
+    assert_eq!(
+        expanded("(if-zero C (if-zero 3 A) B)"),
+        Some(vec!["B".to_string(), "C".to_string()])
+    );
+}
+
+#[test]
+fn permutation_checks() {
+    use crate::check::{check, CheckFailure, DebugSettings};
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source("(defcolumns A B) (defpermutation (X Y) (+A -B))")
+        .unwrap();
+    r.expand_to(ExpansionLevel::top());
+    r.auto_constraints(AutoConstraint::all());
+    let mut cs = r.into_constraint_set().unwrap();
+    crate::compute::compute_trace_str(
+        r#"{"<prelude>": {"A": [3, 1, 2, 1], "B": [5, 6, 7, 8]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+
+    let only = Some(vec!["A_B_intrld_X_Y".to_string()]);
+    let settings = DebugSettings::new().check_permutations(true);
+    assert!(check(&cs, &only, &[], settings).is_ok());
+
+    // corrupt X, which is only noticed by the permutation constraint if
+    // permutations are checked
+    let x: crate::compiler::ColumnRef = crate::structs::Handle::new("<prelude>", "X").into();
+    let mut values = (0..cs.columns.len(&x).unwrap() as isize)
+        .map(|i| cs.columns.get(&x, i, false).unwrap())
+        .collect::<Vec<_>>();
+    values[2] = crate::column::Value::from(9);
+    let spilling = cs.spilling_for_column(&x).unwrap();
+    cs.columns.reset_column(&x);
+    cs.columns.set_column_value(&x, values, spilling).unwrap();
+
+    assert!(check(&cs, &only, &[], DebugSettings::new()).is_ok());
+    let failure = check(&cs, &only, &[], settings).unwrap_err();
+    let failure = failure.downcast_ref::<CheckFailure>().unwrap();
+    assert_eq!(failure.failed.len(), 1);
+    assert_eq!(failure.failed[0].name, "A_B_intrld_X_Y");
+    assert_eq!(failure.failed[0].row, Some(2));
+}
+
+#[test]
+fn color_choice() {
+    use clap::Parser;
+    use std::io::Write;
+
+    let render = |color: &str| {
+        let args = crate::Args::parse_from(["corset", "--color", color, "test", "(defcolumns A)"]);
+        crate::set_color_choice(&args.color);
+        let failure = crate::check::CheckFailure {
+            failed: vec![crate::check::FailedConstraint {
+                name: "eq".into(),
+                row: Some(2),
+            }],
+        };
+        let mut out = anstream::AutoStream::auto(Vec::new());
+        write!(out, "{}", failure).unwrap();
+        String::from_utf8(out.into_inner()).unwrap()
+    };
+
+    assert!(render("always").contains('\x1b'));
+    assert_eq!(render("never"), "constraints failed: eq");
+    // in-memory outputs are not terminals
+    assert_eq!(render("auto"), "constraints failed: eq");
+}
+
+#[test]
+fn cross_module_interleaving() {
+    let interleave = |trace: &str| {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source(
+            "(module a) (defcolumns A) (module b) (defcolumns B)
+             (module c) (definterleaved I (a.A b.B))",
+        )
+        .unwrap();
+        r.expand_to(ExpansionLevel::top());
+        let mut cs = r.into_constraint_set().unwrap();
+        crate::compute::compute_trace_str(trace.as_bytes(), &mut cs, true)?;
+        let i = crate::structs::Handle::new("c", "I").into();
+        Ok((0..cs.columns.len(&i).unwrap() as isize)
+            .map(|k| cs.columns.get(&i, k, false).unwrap().to_string())
+            .collect::<Vec<_>>())
+    };
+
+    assert_eq!(
+        interleave(r#"{"a": {"A": [1, 2]}, "b": {"B": [3, 4]}}"#).unwrap(),
+        vec!["0", "0", "1", "3", "2", "4"]
+    );
+    // columns of different lengths can not be interleaved
+    let err = interleave(r#"{"a": {"A": [1, 2]}, "b": {"B": [3, 4, 5]}}"#).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<crate::errors::RuntimeError>(),
+        Some(crate::errors::RuntimeError::NotComputed(h)) if h.name == "I"
+    ));
+}
+
+#[test]
+fn same_module_interleaving() {
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source("(module m) (defcolumns A B) (definterleaved I (A B))")
+        .unwrap();
+    r.expand_to(ExpansionLevel::top());
+    let mut cs = r.into_constraint_set().unwrap();
+    crate::compute::compute_trace_str(
+        r#"{"m": {"A": [1, 2], "B": [3, 4]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+
+    // the interleaving of a module with itself is not spilled
+    let i = crate::structs::Handle::new("m", "I").into();
+    assert_eq!(
+        (0..cs.columns.len(&i).unwrap() as isize)
+            .map(|k| cs.columns.get(&i, k, false).unwrap().to_string())
+            .collect::<Vec<_>>(),
+        vec!["0", "0", "1", "3", "2", "4"]
+    );
+}
+
+#[test]
+fn trace_span_padding() {
+    use crate::check::{check, trace_span, CheckFailure, DebugSettings};
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source("(defcolumns A (B :byte)) (defconstraint c () (vanishes! (- B A 1)))")
+        .unwrap();
+    r.expand_to(ExpansionLevel::top());
+    let mut cs = r.into_constraint_set().unwrap();
+    crate::compute::compute_trace_str(
+        r#"{"<prelude>": {"A": [1, 2, 3], "B": [2, 3, 4]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+
+    // the constraint only fails on the padding row
+    let failure = check(&cs, &None, &[], DebugSettings::new()).unwrap_err();
+    let failure = failure.downcast_ref::<CheckFailure>().unwrap();
+    assert_eq!(failure.failed[0].row, Some(0));
+
+    let handles = ["A", "B"]
+        .map(|c| crate::structs::Handle::new("<prelude>", c).into())
+        .to_vec();
+    let report = trace_span(&cs, &handles, 0, false, DebugSettings::new());
+    assert_eq!(
+        anstream::adapter::strip_str(&report).to_string(),
+        concat!(
+            "       0      1    2    \n",
+            " A  𝔽  [0x0]  0x1  0x2  \n",
+            " B  𝟠  [0x0]  0x2  0x3  \n",
+            "[x]: padding\n",
+            "\n"
+        )
+    );
+}
+
+#[test]
+fn stdin_source() {
+    use std::io::Write;
+
+    // stdin can not be swapped within a process, so the test runs itself
+    // again with the program piped in
+    const PIPED: &str = "CORSET_TEST_STDIN";
+    if std::env::var(PIPED).is_ok() {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source("-").unwrap();
+        r.expand_to(ExpansionLevel::top());
+        match r.into_constraint_set() {
+            Result::Ok(cs) => println!("compiled {} columns", cs.columns.all().len()),
+            Err(e) => println!("{:?}", e),
+        }
+        return;
+    }
+
+    let run = |program: &str| {
+        let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["tests::stdin_source", "--exact", "--nocapture"])
+            .env(PIPED, "1")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(program.as_bytes())
+            .unwrap();
+        let out = child.wait_with_output().unwrap();
+        assert!(out.status.success());
+        String::from_utf8_lossy(&out.stdout).to_string()
+    };
+
+    assert!(
+        run("(defcolumns A B) (defconstraint c () (vanishes! (- A B)))")
+            .contains("compiled 2 columns")
+    );
+    // errors point to the piped source
+    assert!(run("(defcolumns A) (defconstraint c () (vanishes! (- A B)))").contains("<stdin>"));
+}
+
+#[test]
+fn sized_integers() {
+    use crate::compiler::Magma;
+
+    let import = |trace: &str| {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source("(defcolumns (A :u8) (B :u16))").unwrap();
+        r.expand_to(ExpansionLevel::top());
+        let mut cs = r.into_constraint_set().unwrap();
+        crate::compute::compute_trace_str(trace.as_bytes(), &mut cs, true)?;
+        Ok(cs
+            .columns
+            .column(&crate::structs::Handle::new("<prelude>", "A").into())?
+            .t)
+    };
+
+    assert_eq!(
+        import(r#"{"<prelude>": {"A": [0, 255], "B": [0, 256]}}"#).unwrap(),
+        Magma::integer(8)
+    );
+    // 256 does not fit in a byte
+    let err = import(r#"{"<prelude>": {"A": [0, 256], "B": [0, 256]}}"#).unwrap_err();
+    assert!(err.chain().any(|e| matches!(
+        e.downcast_ref::<crate::errors::RuntimeError>(),
+        Some(crate::errors::RuntimeError::InvalidValue(_, x)) if *x == crate::column::Value::from(256)
+    )));
+
+    let u8 = Magma::try_from(":u8").unwrap();
+    let u16 = Magma::try_from(":u16").unwrap();
+    assert_eq!(u16, Magma::try_from(":i16").unwrap());
+    assert!(u8 < u16 && u16 < Magma::try_from(":u64").unwrap());
+    assert!(u16 < Magma::native());
+    assert_eq!(u8.maxed(&u16).unwrap(), u16);
+    assert_eq!(u16.maxed(&Magma::native()).unwrap(), Magma::native());
+}
+
+#[test]
+fn automatic_ranges() {
+    use crate::compiler::Constraint;
+
+    let ranges = |auto_range: bool| {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source("(defcolumns (A :u8) (B :byte) (C :u16@prove) D)")
+            .unwrap();
+        r.expand_to(ExpansionLevel::top());
+        r.auto_constraints(AutoConstraint::all());
+        let mut cs = r.into_constraint_set().unwrap();
+        // only applied when exporting
+        if auto_range {
+            crate::transformer::enforce_magma_ranges(&mut cs);
+        }
+        cs.constraints
+            .iter()
+            .filter_map(|c| match c {
+                Constraint::InRange { handle, max, .. } => Some((handle.name.clone(), max.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // proven columns are already range-constrained by nhood
+    assert_eq!(
+        ranges(true),
+        vec![
+            ("C-arbitrary".to_string(), crate::column::Value::from(65536)),
+            ("A-range".to_string(), crate::column::Value::from(256)),
+        ]
+    );
+    assert_eq!(
+        ranges(false),
+        vec![("C-arbitrary".to_string(), crate::column::Value::from(65536))]
+    );
+}
+
+#[test]
+fn comparisons_lowering() {
+    use crate::compiler::Constraint;
+
+    let constraints = |source: &str| -> Result<Vec<(String, Option<crate::column::Value>)>> {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source(source)?;
+        r.expand_to(ExpansionLevel::top());
+        let mut cs = r.into_constraint_set()?;
+        // comparisons are only lowered in native mode, which is global to the
+        // tests; lower them explicitly instead
+        cs.expand_comparisons()?;
+        Ok(cs
+            .constraints
+            .iter()
+            .filter_map(|c| match c {
+                Constraint::Vanishes { handle, .. } => Some((handle.name.clone(), None)),
+                Constraint::InRange { handle, max, .. } => {
+                    Some((handle.name.clone(), Some(max.clone())))
+                }
+                _ => None,
+            })
+            .collect())
+    };
+
+    // the bound is either explicit, or the width of the widest operand
+    for source in [
+        "(defcolumns A B C) (defconstraint c () (if (>= A B 16) (vanishes! C)))",
+        "(defcolumns (A :u16) (B :u8) C) (defconstraint c () (if (>= A B) (vanishes! C)))",
+    ] {
+        assert_eq!(
+            constraints(source).unwrap(),
+            vec![
+                ("c".to_string(), None),
+                ("C/LT[(< A B 16)]-is-binary".to_string(), None),
+                ("C/LT-DELTA[(< A B 16)]-definition".to_string(), None),
+                (
+                    "C/LT-DELTA[(< A B 16)]-range".to_string(),
+                    Some(crate::column::Value::from(65536))
+                ),
+            ],
+            "{source}"
+        );
+    }
+
+    // constants are bounded by their value
+    assert!(constraints(
+        "(defcolumns (A :u4) C) (defconstraint c () (if (< A 100) (vanishes! C)))"
+    )
+    .unwrap()
+    .contains(&(
+        "C/LT-DELTA[(< A 100 7)]-range".to_string(),
+        Some(crate::column::Value::from(128))
+    )));
+
+    must_fail(
+        "unbounded comparison",
+        "(defcolumns A B C) (defconstraint c () (if (< A B) (vanishes! C)))",
+    );
+    must_fail(
+        "invalid bound",
+        "(defcolumns A B C) (defconstraint c () (if (< A B 0) (vanishes! C)))",
+    );
+    // wider bounds would let the prover pick the outcome of the comparison
+    let bounded = |w: usize| {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source(&format!(
+            "(defcolumns A B C) (defconstraint c () (if (< A B {w}) (vanishes! C)))"
+        ))
+        .unwrap();
+        r.into_constraint_set()
+    };
+    assert!(bounded(crate::constants::MAX_COMPARISON_WIDTH).is_ok());
+    assert!(bounded(crate::constants::MAX_COMPARISON_WIDTH + 1).is_err());
+}
+
+#[test]
+fn trace_schema() {
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(module m)
+         (defcolumns (A :byte) (B :comp (* 2 A)))
+         (definterleaved I (A B))",
+    )
+    .unwrap();
+    r.expand_to(ExpansionLevel::top());
+    let schema = crate::schema::schema(&r.into_constraint_set().unwrap());
+
+    let m = &schema["properties"]["m"];
+    assert_eq!(m["required"], serde_json::json!(["A"]));
+    let columns = &m["properties"];
+    assert_eq!(columns["A"]["description"], "required");
+    assert_eq!(columns["A"]["type"], "array");
+    for computed in ["B", "I"] {
+        assert_eq!(
+            columns[computed]["description"], "computed; do not provide",
+            "{computed}"
+        );
+        assert!(columns[computed]["not"].is_object());
+    }
+}
+
+#[test]
+fn list_columns() {
+    use crate::columns::{list, render, ColumnListing};
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(defcolumns A (B :byte) (C :array [1:2]))
+         (definterleaved I (A B))
+         (module m) (defcolumns X)",
+    )
+    .unwrap();
+    r.expand_to(ExpansionLevel::top());
+    let columns = list(&r.into_constraint_set().unwrap());
+
+    assert_eq!(
+        columns.iter().find(|c| c.name == "I"),
+        Some(&ColumnListing {
+            module: "<prelude>".into(),
+            name: "I".into(),
+            kind: "Interleaved".into(),
+            magma: "𝔽".into(),
+            sources: vec!["A".into(), "B".into()],
+            doc: None,
+        })
+    );
+    // arrays are listed element-wise
+    assert!(columns
+        .iter()
+        .any(|c| c.name == "C_2" && c.kind == "Commitment"));
+    assert_eq!(
+        render(&columns).lines().rev().take(2).collect::<Vec<_>>(),
+        vec!["  m.X : Commitment : 𝔽", "m:"]
+    );
+}
+
+#[test]
+fn large_exponents() {
+    let build = |source: &str| {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source(source).unwrap();
+        r.into_constraint_set().unwrap()
+    };
+
+    // 3^1000000 mod p
+    const POWER: &str =
+        "5666941835808367301307346236719299261965294193840494640426801756796216767284";
+    // both field elements and integers are raised within the field
+    use std::str::FromStr;
+    assert_eq!(
+        crate::column::Value::Native(crate::field::Fr::from(3u64)).pow(1000000),
+        crate::column::Value::Native(crate::field::Fr::from_str(POWER).unwrap())
+    );
+    assert_eq!(
+        crate::column::Value::BigInt(num_bigint::BigInt::from(3)).pow(1000000),
+        crate::column::Value::BigInt(num_bigint::BigInt::from_str(POWER).unwrap())
+    );
+    assert_eq!(
+        crate::column::Value::BigInt(num_bigint::BigInt::from(-3)).pow(3),
+        crate::column::Value::BigInt(num_bigint::BigInt::from(-27))
+    );
+
+    for source in [
+        "(defcolumns A B) (defconstraint c () (vanishes! (- B (^ A 1000000))))",
+        // constant powers are folded at compile time
+        "(defcolumns A B) (defconstraint c () (vanishes! (- B (* A (^ 3 999999)))))",
+    ] {
+        for (b, ok) in [(POWER, true), ("1", false)] {
+            let mut cs = build(source);
+            crate::compute::compute_trace_str(
+                format!(r#"{{"<prelude>": {{"A": [3], "B": ["{b}"]}}}}"#).as_bytes(),
+                &mut cs,
+                false,
+            )
+            .unwrap();
+            assert_eq!(
+                crate::check::check(&cs, &None, &[], crate::check::DebugSettings::new()).is_ok(),
+                ok,
+                "{source} with B = {b}"
+            );
+        }
+    }
+
+    // x^0 and x^1 are folded away
+    let cs = build("(defcolumns A B) (defconstraint c () (vanishes! (* (^ A 0) (^ B 1) A)))");
+    match &cs.constraints[0] {
+        crate::compiler::Constraint::Vanishes { expr, .. } => {
+            assert_eq!(expr.to_string(), "(* 1 B A)")
+        }
+        _ => unreachable!(),
+    }
+
+    must_fail(
+        "negative exponent",
+        "(defcolumns A) (defconstraint c () (vanishes! (^ A -1)))",
+    );
+    must_fail(
+        "non-constant exponent",
+        "(defcolumns A B) (defconstraint c () (vanishes! (^ A B)))",
+    );
+}
+
+#[test]
+fn check_loop_cursor() {
+    use crate::cursor::Cursor;
+
+    let dir = std::env::temp_dir().join(format!("corset-cursor-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("cursor.json");
+
+    // a fresh cursor starts after `since`
+    let mut cursor = Cursor::load(&path, 10).unwrap();
+    assert_eq!(cursor, Cursor::since(10));
+    assert!(cursor.is_done(10) && !cursor.is_done(11));
+
+    // blocks past a gap are remembered until it is filled
+    cursor.advance(11);
+    cursor.advance(13);
+    cursor.advance(14);
+    assert_eq!(cursor.watermark, 11);
+    assert!(!cursor.is_done(12) && cursor.is_done(14));
+    cursor.save(&path).unwrap();
+
+    // a restarted loop resumes from the persisted state, ignoring `since`
+    let mut cursor = Cursor::load(&path, 0).unwrap();
+    assert_eq!(cursor.watermark, 11);
+    assert_eq!(
+        cursor.done.iter().cloned().collect::<Vec<_>>(),
+        vec![13, 14]
+    );
+
+    // a late block fills the gap
+    cursor.advance(12);
+    cursor.advance(9);
+    assert_eq!(cursor, Cursor::since(14));
+    cursor.save(&path).unwrap();
+    assert_eq!(Cursor::load(&path, 0).unwrap(), Cursor::since(14));
+
+    std::fs::write(&path, "garbage").unwrap();
+    assert!(Cursor::load(&path, 0).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn duplicated_constraints() {
+    let build = || {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source(
+            "(defcolumns A B C)
+             (defconstraint first () (vanishes! (* (+ A B) C)))
+             (defconstraint second () (vanishes! (* C (+ B A))))
+             (defconstraint third () (vanishes! (- A B)))
+             (defconstraint fourth () (vanishes! (- B A)))",
+        )
+        .unwrap();
+        r.into_constraint_set().unwrap()
+    };
+
+    let cs = build();
+    assert!(cs.constraints[0] == cs.constraints[1]);
+    // subtraction is not commutative
+    assert!(cs.constraints[2] != cs.constraints[3]);
+
+    let mut cs = build();
+    assert_eq!(
+        crate::transformer::dedup_constraints(&mut cs, false),
+        vec![("second".to_string(), "first".to_string())]
+    );
+    assert_eq!(cs.constraints.len(), 4);
+
+    crate::transformer::dedup_constraints(&mut cs, true);
+    assert_eq!(
+        cs.constraints.iter().map(|c| c.name()).collect::<Vec<_>>(),
+        vec!["first", "third", "fourth"]
+    );
+}
+
+#[test]
+fn array_reductions() {
+    let prelude = "(defcolumns X (LIMBS :array [4]))";
+    assert_eq!(
+        constraint_text(&format!(
+            "{prelude} (defconstraint c () (vanishes! (- X (sum LIMBS))))"
+        )),
+        "(- X (+ LIMBS_1 LIMBS_2 LIMBS_3 LIMBS_4))"
+    );
+    assert_eq!(
+        constraint_text(&format!(
+            "{prelude} (defconstraint c () (vanishes! (prod LIMBS)))"
+        )),
+        "(* LIMBS_1 LIMBS_2 LIMBS_3 LIMBS_4)"
+    );
+
+    must_run(
+        "constant lists",
+        "(defconst W [1 2 3]) (defcolumns X) (defconstraint c () (vanishes! (- X (sum W) (prod W))))",
+    );
+    must_fail(
+        "not an array",
+        "(defcolumns X Y) (defconstraint c () (vanishes! (sum X)))",
+    );
+}
+
+#[test]
+fn go_manifest() {
+    use crate::exporters::zkgeth::{manifest, ManifestColumn};
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source("(defcolumns A B (C :comp (+ A (* 2 B)))) (module m) (defcolumns X)")
+        .unwrap();
+    r.expand_to(ExpansionLevel::top());
+    let columns = manifest(&r.into_constraint_set().unwrap());
+
+    assert_eq!(
+        columns.iter().find(|c| c.name == "C"),
+        Some(&ManifestColumn {
+            name: "C".into(),
+            module: "<prelude>".into(),
+            kind: "Expression".into(),
+            computed: true,
+            dependencies: vec!["A".into(), "B".into()],
+        })
+    );
+    assert!(columns
+        .iter()
+        .any(|c| c.module == "m" && c.name == "X" && !c.computed && c.dependencies.is_empty()));
+}
+
+#[test]
+fn export_boundaries() {
+    use crate::exporters::{boundaries, Boundary};
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(module m) (defcolumns A B)
+         (defconstraint past () (vanishes! (- A (shift B -1))))
+         (defconstraint local () (vanishes! (- A B)))",
+    )
+    .unwrap();
+    r.expand_to(ExpansionLevel::top());
+    let cs = r.into_constraint_set().unwrap();
+
+    assert_eq!(
+        boundaries(&cs),
+        vec![Boundary {
+            constraint: "past".into(),
+            module: "m".into(),
+            shifts: vec![-1],
+            spilling: 1,
+        }]
+    );
+}
+
+#[test]
+fn constraint_tags() {
+    let build = || {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source(
+            "(defcolumns A B)
+             (defconstraint cheap (:tags fast) (vanishes! A))
+             (defconstraint costly (:guard A :tags expensive wip) (vanishes! B))
+             (defconstraint untagged () (vanishes! (- A B)))",
+        )
+        .unwrap();
+        r.into_constraint_set().unwrap()
+    };
+    assert_eq!(
+        build()
+            .constraints
+            .iter()
+            .find(|c| c.name() == "costly")
+            .unwrap()
+            .tags(),
+        ["expensive", "wip"]
+    );
+
+    // only `untagged` fails on this trace
+    let checks = |only: Option<&[&str]>, skip: &[&str]| {
+        let mut cs = build();
+        crate::compute::compute_trace_str(
+            r#"{"<prelude>": {"A": [0, 0], "B": [0, 1]}}"#.as_bytes(),
+            &mut cs,
+            false,
+        )
+        .unwrap();
+        crate::check::check(
+            &cs,
+            &only.map(|o| o.iter().map(|s| s.to_string()).collect()),
+            &skip.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+            crate::check::DebugSettings::new(),
+        )
+        .is_ok()
+    };
+    assert!(!checks(None, &[]));
+    assert!(checks(Some(&["@fast", "@wip"]), &[]));
+    assert!(!checks(Some(&["@fast", "untagged"]), &[]));
+    assert!(!checks(None, &["@wip"]));
+    assert!(checks(None, &["@wip", "untagged"]));
+
+    must_fail(
+        "missing tags",
+        "(defcolumns A) (defconstraint c (:tags) (vanishes! A))",
+    );
+    must_fail(
+        "invalid tag",
+        "(defcolumns A) (defconstraint c (:tags 3) (vanishes! A))",
+    );
+}
+
+#[test]
+fn deterministic_output() {
+    let write = || {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source(
+            "(module gamma) (defcolumns G H) (defpermutation (S) ((+ G)))
+             (module alpha) (defcolumns A B) (definterleaved I (A B))
+             (module beta) (defcolumns X (Y :comp (* 2 X)))",
+        )
+        .unwrap();
+        r.expand_to(ExpansionLevel::top());
+        let mut cs = r.into_constraint_set().unwrap();
+        crate::compute::compute_trace_str(
+            r#"{"gamma": {"G": [3, 1, 2], "H": [0, 0, 0]},
+                "alpha": {"A": [1, 2], "B": [3, 4]},
+                "beta": {"X": [5, 6, 7]}}"#
+                .as_bytes(),
+            &mut cs,
+            false,
+        )
+        .unwrap();
+        let mut out = Vec::new();
+        cs.write(&mut out, crate::compiler::generator::TraceRadix::Hex)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    };
+
+    let first = write();
+    let modules = ["\"alpha.", "\"beta.", "\"gamma."]
+        .map(|m| first.find(m).unwrap_or_else(|| panic!("{} missing", m)));
+    assert!(modules.windows(2).all(|w| w[0] < w[1]));
+    for _ in 0..5 {
+        assert_eq!(write(), first);
+    }
+}
+
+#[test]
+fn declaration_order() {
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(defcolumns ZETA ALPHA (MID :comp (+ ZETA ALPHA)) BETA)
+         (module m) (defcolumns Y X)",
+    )
+    .unwrap();
+    r.expand_to(ExpansionLevel::top());
+    let mut cs = r.into_constraint_set().unwrap();
+
+    let names = crate::columns::list(&cs)
+        .into_iter()
+        .map(|c| c.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["ZETA", "ALPHA", "MID", "BETA", "Y", "X"]);
+
+    crate::compute::compute_trace_str(
+        r#"{"<prelude>": {"ZETA": [1], "ALPHA": [2], "BETA": [3]},
+            "m": {"X": [4], "Y": [5]}}"#
+            .as_bytes(),
+        &mut cs,
+        false,
+    )
+    .unwrap();
+    let mut out = Vec::new();
+    cs.write(&mut out, crate::compiler::generator::TraceRadix::Hex)
+        .unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let columns = ["ZETA", "ALPHA", "MID", "BETA"].map(|c| {
+        out.find(&format!("\"{}\"", c))
+            .unwrap_or_else(|| panic!("{} missing", c))
+    });
+    assert!(columns.windows(2).all(|w| w[0] < w[1]));
+}
+
+const RERUN_ARGS: &str = "CORSET_TEST_RUN_ARGS";
+
+/// The arguments of `crate::run`, when the test is executed again by [`rerun`]
+fn rerun_args() -> Option<crate::Args> {
+    use clap::Parser;
+
+    std::env::var(RERUN_ARGS)
+        .ok()
+        .map(|args| crate::Args::parse_from(args.split('\n')))
+}
+
+/// `run` installs a global logger, and its exit code and standard output are
+/// only observable from another process, so the tests invoking it run
+/// themselves again, with the arguments passed through the environment
+fn rerun(test: &str, args: &[&str]) -> std::process::Output {
+    std::process::Command::new(std::env::current_exe().unwrap())
+        .args([test, "--exact", "--nocapture"])
+        .env(RERUN_ARGS, args.join("\n"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn compute_dry_run() {
+    if let Some(args) = rerun_args() {
+        match crate::run(args) {
+            Result::Ok(()) => println!("dry run ok"),
+            Err(e) => println!("dry run failed: {:?}", e),
+        }
+        return;
+    }
+
+    let dir = std::env::temp_dir().join(format!("corset-dry-run-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("source.lisp");
+    std::fs::write(
+        &source,
+        "(module m) (defcolumns (A :u8) (B :comp (* 2 A))) (module n) (defcolumns X)",
+    )
+    .unwrap();
+    let dry_run = |trace: &str| {
+        let tracefile = dir.join("trace.json");
+        std::fs::write(&tracefile, trace).unwrap();
+        let out = rerun(
+            "tests::compute_dry_run",
+            &[
+                "corset",
+                "compute",
+                "--dry-run",
+                "-T",
+                tracefile.to_str().unwrap(),
+                source.to_str().unwrap(),
+            ],
+        );
+        assert!(out.status.success());
+        String::from_utf8_lossy(&out.stdout).to_string()
+    };
+
+    let out = dry_run(r#"{"m": {"A": [1, 2, 3]}, "n": {"X": [1]}}"#);
+    assert!(out.contains("dry run ok"));
+    assert!(out.contains("m: 4 rows") && out.contains("n: 2 rows"));
+    // 300 does not fit in a byte
+    let out = dry_run(r#"{"m": {"A": [1, 2, 300]}, "n": {"X": [1]}}"#);
+    assert!(out.contains("dry run failed") && out.contains("while reading columns"));
+    // nothing but the inputs has been written
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn compute_no_pad() {
+    if let Some(args) = rerun_args() {
+        if let Err(e) = crate::run(args) {
+            println!("compute failed: {:?}", e);
+        }
+        return;
+    }
+
+    let dir = std::env::temp_dir().join(format!("corset-no-pad-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("source.lisp");
+    std::fs::write(
+        &source,
+        "(module m) (defcolumns A (B :comp (* 2 A))) (module n) (defcolumns X)",
+    )
+    .unwrap();
+    let tracefile = dir.join("trace.json");
+    std::fs::write(&tracefile, r#"{"m": {"A": [1, 2, 3]}, "n": {"X": [1]}}"#).unwrap();
+    let lengths = |no_pad: bool| {
+        let outfile = dir.join("out.json");
+        let mut args = vec!["corset", "compute"];
+        if no_pad {
+            args.push("--no-pad");
+        }
+        args.extend([
+            "-T",
+            tracefile.to_str().unwrap(),
+            "-o",
+            outfile.to_str().unwrap(),
+            source.to_str().unwrap(),
+        ]);
+        let out = rerun("tests::compute_no_pad", &args);
+        assert!(
+            !String::from_utf8_lossy(&out.stdout).contains("compute failed"),
+            "{}",
+            String::from_utf8_lossy(&out.stdout)
+        );
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&outfile).unwrap()).unwrap();
+        ["m.A", "m.B", "n.X"].map(|c| written["columns"][c]["values"].as_array().unwrap().len())
+    };
+
+    // the written columns are exactly as long as the input ones
+    assert_eq!(lengths(true), [3, 3, 1]);
+    assert_eq!(lengths(false), [4, 4, 2]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn compute_pad_append() {
+    if let Some(args) = rerun_args() {
+        if let Err(e) = crate::run(args) {
+            println!("compute failed: {:?}", e);
+        }
+        return;
+    }
+
+    let dir = std::env::temp_dir().join(format!("corset-pad-append-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("source.lisp");
+    std::fs::write(&source, "(module m) (defcolumns A (B :comp (* 2 A)))").unwrap();
+    let tracefile = dir.join("trace.json");
+    std::fs::write(&tracefile, r#"{"m": {"A": [1, 2, 3]}}"#).unwrap();
+    let column = |pad: &str| {
+        let outfile = dir.join("out.json");
+        let args = [
+            "corset",
+            "compute",
+            "--pad",
+            pad,
+            "--module-len",
+            "m=5",
+            "--trace-format",
+            "decimal",
+            "-T",
+            tracefile.to_str().unwrap(),
+            "-o",
+            outfile.to_str().unwrap(),
+            source.to_str().unwrap(),
+        ];
+        let out = rerun("tests::compute_pad_append", &args);
+        assert!(
+            !String::from_utf8_lossy(&out.stdout).contains("compute failed"),
+            "{}",
+            String::from_utf8_lossy(&out.stdout)
+        );
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&outfile).unwrap()).unwrap();
+        let b = &written["columns"]["m.B"];
+        (
+            b["values"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|x| x.as_str().unwrap().to_owned())
+                .collect::<Vec<_>>(),
+            b["padding_strategy"]["action"].as_str().unwrap().to_owned(),
+        )
+    };
+
+    // past the padding row, the values of the trace come first
+    let (values, action) = column("append");
+    assert_eq!(values, ["0", "2", "4", "6", "0"]);
+    assert_eq!(action, "append");
+    let (values, action) = column("prepend");
+    assert_eq!(values, ["0", "0", "2", "4", "6"]);
+    assert_eq!(action, "prepend");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn folding_diagnostics() {
+    if let Some(args) = rerun_args() {
+        crate::run(args).unwrap();
+        return;
+    }
+
+    let source = std::env::temp_dir().join(format!("corset-folds-{}.lisp", std::process::id()));
+    std::fs::write(
+        &source,
+        "(defcolumns A B)
+         (defconstraint five () (vanishes! (- A (+ 2 3))))
+         (defconstraint branch () (if-zero 0 (vanishes! A) (vanishes! B)))",
+    )
+    .unwrap();
+    let out = rerun(
+        "tests::folding_diagnostics",
+        &["corset", "-vvv", "-eeee", "debug", source.to_str().unwrap()],
+    );
+    std::fs::remove_file(&source).unwrap();
+    assert!(out.status.success());
+
+    let err = anstream::adapter::strip_str(&String::from_utf8_lossy(&out.stderr)).to_string();
+    assert!(err.contains("five: folded (+ 2 3) into 5"), "{}", err);
+    assert!(
+        err.contains("branch: folded (if-zero 0 A B) into A"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn exit_codes() {
+    if let Some(args) = rerun_args() {
+        std::process::exit(match crate::run(args) {
+            Result::Ok(()) => crate::exit_code::SUCCESS,
+            Err(e) => crate::exit_code(&e),
+        } as i32);
+    }
+
+    let dir = std::env::temp_dir().join(format!("corset-exit-codes-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let write = |name: &str, content: &str| {
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path.to_str().unwrap().to_owned()
+    };
+    let valid = write(
+        "valid.lisp",
+        "(defcolumns A B) (defconstraint c () (- A B))",
+    );
+    let invalid = write(
+        "invalid.lisp",
+        "(defcolumns A B) (defconstraint c () (- A C))",
+    );
+    let holds = write("holds.json", r#"{"<prelude>": {"A": [1, 2], "B": [1, 2]}}"#);
+    let fails = write("fails.json", r#"{"<prelude>": {"A": [1, 2], "B": [1, 3]}}"#);
+    let garbled = write("garbled.json", r#"{"<prelude>": {"A": [1, 2"#);
+    let missing = dir.join("missing.json").to_str().unwrap().to_owned();
+
+    for (source, trace, expected) in [
+        (&valid, &holds, crate::exit_code::SUCCESS),
+        (&valid, &fails, crate::exit_code::VIOLATION),
+        (&invalid, &holds, crate::exit_code::COMPILATION),
+        (&valid, &garbled, crate::exit_code::TRACE),
+        (&valid, &missing, crate::exit_code::TRACE),
+    ] {
+        let status = rerun(
+            "tests::exit_codes",
+            &["corset", "check", "--quiet-success", "-T", trace, source],
+        )
+        .status;
+        assert_eq!(status.code(), Some(expected as i32), "{source} on {trace}");
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn verify_compiled() {
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source("(defcolumns A B (C :comp (* A B))) (defconstraint c () (- A B))")
+        .unwrap();
+    let cs = r.into_constraint_set().unwrap();
+
+    let dir = std::env::temp_dir().join(format!("corset-verify-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let valid = dir.join("valid.bin");
+    let valid = valid.to_str().unwrap();
+    crate::write_bin(&cs, false, false, valid).unwrap();
+    let mut cs = crate::read_bin(false, valid).unwrap();
+    assert!(cs.verify().is_ok());
+    assert!(crate::verify_summary(valid, &cs).ends_with(
+        "1 modules, 3 columns (1 computed), 2 constraints, 1 computations, 0 constants"
+    ));
+
+    // B is renamed in the constraint, but not in the column set
+    let dangling = dir.join("dangling.bin");
+    let dangling = dangling.to_str().unwrap();
+    let serialized = std::fs::read_to_string(valid).unwrap();
+    assert!(serialized.contains("\"<prelude>.B#1\""));
+    std::fs::write(
+        dangling,
+        serialized.replace("\"<prelude>.B#1\"", "\"<prelude>.Z#1\""),
+    )
+    .unwrap();
+    let err = crate::read_bin(false, dangling)
+        .unwrap()
+        .verify()
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("dangling") && err.contains("Z"), "{err}");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn compile_to_stdout() {
+    if let Some(args) = rerun_args() {
+        // keep the compiled set on its own line, apart from the test harness
+        println!();
+        if let Err(e) = crate::run(args) {
+            println!("compile failed: {:?}", e);
+        }
+        println!();
+        return;
+    }
+
+    let source = std::env::temp_dir().join(format!("corset-stdout-{}.lisp", std::process::id()));
+    std::fs::write(&source, "(defcolumns A B) (defconstraint c () (- A B))").unwrap();
+    let out = rerun(
+        "tests::compile_to_stdout",
+        &[
+            "corset",
+            "--ron",
+            "compile",
+            "-o",
+            "-",
+            source.to_str().unwrap(),
+        ],
+    );
+    std::fs::remove_file(&source).unwrap();
+    assert!(out.status.success());
+
+    let out = String::from_utf8_lossy(&out.stdout);
+    let ron = out
+        .lines()
+        .find(|l| l.starts_with('('))
+        .expect("no RON on stdout");
+    let cs: crate::compiler::ConstraintSet = ron::from_str(ron).unwrap();
+    assert_eq!(cs.columns.all().len(), 2);
+    assert_eq!(cs.constraints.len(), 1);
+}
+
+#[test]
+fn doc_comments() {
+    use crate::structs::Handle;
+
+    const SOURCE: &str = ";;;;;;;;;;;;;;;;;;;
+;; a section banner
+;;;;;;;;;;;;;;;;;;;
+(module m)
+(defcolumns
+  ;; the stamp of the
+  ;; current_row
+  STAMP
+  A ;; not a doc
+  ; a plain comment
+  B)
+
+;; STAMP never decreases
+(defconstraint stamp () (vanishes! (* (- (next STAMP) STAMP) A)))
+
+;; doubles x
+(defun (double x) (* 2 x))
+(defconstraint other () (vanishes! (double B)))";
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(SOURCE).unwrap();
+    let asts = r
+        .to_ast()
+        .unwrap()
+        .into_iter()
+        .map(|x| x.1)
+        .collect::<Vec<_>>();
+    let cs = r.into_constraint_set().unwrap();
+
+    let doc = |docs: &std::collections::HashMap<Handle, String>, name: &str| {
+        docs.get(&Handle::new("m", name)).cloned()
+    };
+    assert_eq!(
+        doc(&cs.docs.columns, "STAMP").as_deref(),
+        Some("the stamp of the\ncurrent_row")
+    );
+    assert_eq!(doc(&cs.docs.columns, "A"), None);
+    assert_eq!(doc(&cs.docs.columns, "B"), None);
+    assert_eq!(
+        doc(&cs.docs.constraints, "stamp").as_deref(),
+        Some("STAMP never decreases")
+    );
+    assert_eq!(doc(&cs.docs.constraints, "other"), None);
+    assert_eq!(
+        doc(&cs.docs.functions, "double").as_deref(),
+        Some("doubles x")
+    );
+    assert!(crate::columns::render(&crate::columns::list(&cs))
+        .contains("  m.STAMP : Commitment : 𝔽\n      ;; the stamp of the\n      ;; current_row"));
+
+    let out = std::env::temp_dir().join(format!("corset-docs-{}.tex", std::process::id()));
+    crate::exporters::latex::LatexExporter {
+        constraints_filename: Some(out.to_str().unwrap().to_owned()),
+        render_columns: true,
+        render_constraints: true,
+        ..Default::default()
+    }
+    .render(&asts)
+    .unwrap();
+    let latex = std::fs::read_to_string(&out).unwrap();
+    std::fs::remove_file(&out).unwrap();
+    assert!(latex.contains("\\item[\\col{STAMP}] the stamp of the current\\_row"));
+    assert!(latex.contains("\\tcc{ STAMP never decreases }"));
+    assert!(!latex.contains("banner"));
+}
+
+#[test]
+fn latex_sections() {
+    use crate::exporters::latex::LatexExporter;
+    use itertools::Itertools;
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(defcolumns
+  ;; the stamp
+  STAMP)
+;; STAMP never decreases
+(defconstraint stamp () (vanishes! (- (next STAMP) STAMP)))",
+    )
+    .unwrap();
+    let asts = r
+        .to_ast()
+        .unwrap()
+        .into_iter()
+        .map(|x| x.1)
+        .collect::<Vec<_>>();
+    let out = |name: &str| {
+        std::env::temp_dir()
+            .join(format!("corset-latex-{}-{}.tex", name, std::process::id()))
+            .to_str()
+            .unwrap()
+            .to_owned()
+    };
+    let render = |exporter: LatexExporter| {
+        exporter.render(&asts).unwrap();
+        [&exporter.constraints_filename, &exporter.columns_filename]
+            .into_iter()
+            .flatten()
+            .unique()
+            .map(|f| {
+                let latex = std::fs::read_to_string(f).unwrap();
+                std::fs::remove_file(f).unwrap();
+                latex
+            })
+            .collect::<Vec<_>>()
+    };
+    let columns = "\\item[\\col{STAMP}] the stamp";
+    let constraints = "\\tcc{ STAMP never decreases }";
+
+    // --constraints-only
+    let latex = render(LatexExporter {
+        constraints_filename: Some(out("constraints-only")),
+        render_columns: false,
+        render_constraints: true,
+        ..Default::default()
+    });
+    assert_eq!(latex.len(), 1);
+    assert!(latex[0].contains(constraints));
+    assert!(!latex[0].contains(columns));
+    assert!(!latex[0].contains("\\section"));
+
+    // --columns-only
+    let latex = render(LatexExporter {
+        constraints_filename: Some(out("columns-only")),
+        render_columns: true,
+        render_constraints: false,
+        ..Default::default()
+    });
+    assert!(latex[0].contains(columns));
+    assert!(!latex[0].contains(constraints));
+
+    // --out
+    let latex = render(LatexExporter {
+        constraints_filename: Some(out("merged")),
+        columns_filename: Some(out("merged")),
+        render_columns: true,
+        render_constraints: true,
+    });
+    assert_eq!(latex.len(), 1);
+    assert!(latex[0].contains("\\section{Columns}"));
+    assert!(latex[0].contains("\\section{Constraints}"));
+
+    // --constraints-file and --columns-file
+    let latex = render(LatexExporter {
+        constraints_filename: Some(out("constraints")),
+        columns_filename: Some(out("columns")),
+        render_columns: true,
+        render_constraints: true,
+    });
+    assert!(latex[0].contains(constraints) && !latex[0].contains(columns));
+    assert!(latex[1].contains(columns) && !latex[1].contains(constraints));
+}
+
+#[test]
+fn negative_trace_values() {
+    use crate::column::Value;
+    use crate::field::Fr;
+
+    let import = |trace: &str| {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source("(defcolumns A (B :binary))").unwrap();
+        r.expand_to(ExpansionLevel::top());
+        let mut cs = r.into_constraint_set().unwrap();
+        crate::compute::compute_trace_str(trace.as_bytes(), &mut cs, true)?;
+        let a = crate::structs::Handle::new("<prelude>", "A").into();
+        Ok((0..cs.columns.len(&a).unwrap() as isize)
+            .map(|k| cs.columns.get(&a, k, false).unwrap().to_fr().unwrap())
+            .collect::<Vec<_>>())
+    };
+
+    // -x is the field element p - x
+    assert_eq!(
+        Value::try_from("-1").unwrap().to_fr(),
+        Some(-Fr::from(1u64))
+    );
+    assert_eq!(
+        Value::try_from("-1").unwrap().into_native().to_bi(),
+        crate::column::field_modulus() - 1
+    );
+    // signed values, as numbers or strings, are mapped into the field
+    assert_eq!(
+        import(r#"{"<prelude>": {"A": ["-1", -2, "-0x10", "-0", 3], "B": [0, 1, 1, 0, 0]}}"#)
+            .unwrap(),
+        vec![
+            Fr::from(0u64),
+            -Fr::from(1u64),
+            -Fr::from(2u64),
+            -Fr::from(16u64),
+            Fr::from(0u64),
+            Fr::from(3u64)
+        ]
+    );
+    // -1 is not a boolean
+    let err = import(r#"{"<prelude>": {"A": [0], "B": ["-1"]}}"#).unwrap_err();
+    assert!(err.chain().any(|e| matches!(
+        e.downcast_ref::<crate::errors::RuntimeError>(),
+        Some(crate::errors::RuntimeError::InvalidValue("binary", _))
+    )));
+}
+
+#[test]
+fn threads_auto() {
+    use clap::Parser;
+
+    let threads = |t: &str| {
+        crate::Args::parse_from(["corset", "--threads", t, "test", "(defcolumns A)"]).threads
+    };
+    assert!(threads("auto") > 0);
+    assert_eq!(threads("0"), threads("auto"));
+    assert_eq!(threads("3"), 3);
+    assert!(crate::Args::try_parse_from(["corset", "--threads", "many", "test", "x"]).is_err());
+}
+
+#[test]
+fn computations_match_columns() {
+    use crate::column::{Column, ColumnSet, Computation};
+    use crate::compiler::{ColumnRef, ComputationTable, ConstraintSet, Kind, Node};
+    use crate::errors::compiler::Error;
+    use crate::structs::Handle;
+
+    let column = |name: &str, kind: Kind<()>| {
+        Column::builder()
+            .handle(Handle::new("m", name))
+            .kind(kind)
+            .build()
+    };
+    let make = |columns: ColumnSet, computations: ComputationTable| {
+        ConstraintSet::new(
+            columns,
+            vec![],
+            vec![],
+            Default::default(),
+            computations,
+            Default::default(),
+        )
+    };
+
+    // a computed column that no computation fills
+    let mut columns = ColumnSet::default();
+    columns
+        .insert_column_and_register(column("P", Kind::Computed))
+        .unwrap();
+    let err = make(columns, Default::default()).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::MissingComputation(h)) if h.contains('P')
+    ));
+
+    // a computation filling a column that has not been declared
+    let mut columns = ColumnSet::default();
+    columns
+        .insert_column_and_register(column("A", Kind::Commitment))
+        .unwrap();
+    let mut computations = ComputationTable::default();
+    let target: ColumnRef = Handle::new("m", "B").into();
+    computations
+        .insert(
+            &target,
+            Computation::Composite {
+                target: target.clone(),
+                exp: Node::from_isize(1),
+            },
+        )
+        .unwrap();
+    let err = make(columns, computations).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::UndeclaredTarget(_, h)) if h.contains('B')
+    ));
+}
+
+#[test]
+fn signed_failure_values() {
+    use crate::column::{field_modulus, Value};
+    use crate::pretty::{Base, Pretty};
+
+    let p_minus_1 = Value::BigInt(field_modulus() - 1);
+    for x in [p_minus_1.clone(), p_minus_1.clone().into_native()] {
+        assert_eq!(x.pretty_signed(Base::Dec), "-1");
+        // only the numeric bases are affected
+        assert_eq!(x.pretty_signed(Base::Bool), x.pretty_with_base(Base::Bool));
+    }
+    assert_eq!(
+        p_minus_1.pretty_with_base(Base::Dec),
+        (field_modulus() - 1u32).to_string()
+    );
+    // values below half the modulus are left as they are
+    assert_eq!(Value::from(3usize).pretty_signed(Base::Dec), "3");
+}
+
+#[test]
+fn conditional_over_begin() {
+    use crate::compiler::{Constraint, Expression};
+    use itertools::Itertools;
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(defcolumns C A B)
+         (defconstraint c () (if-not-zero C (begin (vanishes! A) (vanishes! B))))",
+    )
+    .unwrap();
+    r.expand_to(ExpansionLevel::top());
+    let cs = r.into_constraint_set().unwrap();
+    let expr = cs
+        .constraints
+        .iter()
+        .find_map(|c| match c {
+            Constraint::Vanishes { handle, expr, .. } if handle.name == "c" => Some(expr),
+            _ => None,
+        })
+        .unwrap();
+
+    // the conditional is distributed over the branches of the begin, each of
+    // them becoming a guarded scalar constraint
+    let Expression::List(guarded) = expr.e() else {
+        panic!("{:?} is not a list", expr)
+    };
+    assert_eq!(guarded.len(), 2);
+    for (e, column) in guarded.iter().zip(["A", "B"]) {
+        assert!(!e.is_list(), "{:?}", e);
+        assert_eq!(
+            e.dependencies()
+                .iter()
+                .map(|c| cs.handle(c).name.clone())
+                .sorted()
+                .collect::<Vec<_>>(),
+            vec![column.to_owned(), "C".to_owned()]
+        );
+    }
+}
+
+#[test]
+fn failure_columns() {
+    let columns = |cs: &[&str]| cs.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        crate::failures_insert(
+            "failures",
+            &columns(&["block_id", "constraint_name", "row"])
+        )
+        .unwrap(),
+        "INSERT INTO failures (block_id, constraint_name, row) VALUES ($1, $2, $3)"
+    );
+    assert_eq!(
+        crate::failures_insert("audit.failed", &columns(&["block", "name", "line"])).unwrap(),
+        "INSERT INTO audit.failed (block, name, line) VALUES ($1, $2, $3)"
+    );
+    assert!(crate::failures_insert("failures", &columns(&["block", "name"])).is_err());
+    assert!(crate::parse_sql_column("row_1").is_ok());
+    assert!(crate::parse_sql_column("row; DROP TABLE blocks").is_err());
+    assert!(crate::parse_sql_column("audit.row").is_err());
+}
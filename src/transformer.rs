@@ -1,21 +1,37 @@
+mod comparisons;
 mod concretize;
+mod cse;
+mod dedup;
 mod ifs;
 mod inverses;
 mod nhood;
+mod ranges;
 mod selectors;
 mod sort;
+mod spilling;
 mod splatter;
 mod statics;
 
 use anyhow::*;
 use log::*;
 
+use comparisons::expand_comparisons;
 pub use concretize::concretize;
+// This is only used by the corset binary
+#[allow(unused_imports)]
+pub use cse::cse;
+// This is only used by the corset binary
+#[allow(unused_imports)]
+pub use dedup::dedup_constraints;
 use ifs::expand_ifs;
 use inverses::expand_invs;
 use nhood::validate_nhood;
+// This is only used by the corset binary
+#[allow(unused_imports)]
+pub use ranges::enforce_magma_ranges;
 use selectors::expand_constraints;
 use sort::sorts;
+use spilling::spillings;
 use splatter::splatter;
 pub use statics::precompute;
 
@@ -100,7 +116,10 @@ impl ExpansionLevel {
                 ExpansionLevel::ExpandsIfs => expand_ifs(cs),
                 ExpansionLevel::Splatter => splatter(cs),
                 ExpansionLevel::ColumnizeExpressions => expand_constraints(cs)?,
-                ExpansionLevel::ExpandInvs => expand_invs(cs)?,
+                ExpansionLevel::ExpandInvs => {
+                    expand_comparisons(cs)?;
+                    expand_invs(cs)?
+                }
             }
             cs.transformations |= *self as u32;
         }
@@ -130,6 +149,7 @@ pub(crate) fn expand_to(
     }
 
     cs.convert_refs_to_ids()?;
+    spillings(cs);
     cs.validate()
 }
 
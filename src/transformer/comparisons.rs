@@ -0,0 +1,182 @@
+use std::collections::HashSet;
+
+use anyhow::*;
+use num_traits::ToPrimitive;
+
+use crate::{
+    column::{Column, Computation},
+    compiler::{
+        ColumnRef, Conditioning, Constraint, ConstraintSet, Expression, Intrinsic, Kind, Magma,
+        Node, RawMagma,
+    },
+    structs::Handle,
+};
+
+use super::expression_to_name;
+
+fn lt_magma() -> Magma {
+    Magma::binary().with_conditioning(Conditioning::Boolean)
+}
+
+impl Node {
+    /// Replace all the comparisons in this expression by a computed column
+    /// holding their result, and collect them so that the constraints
+    /// proving these columns can be generated.
+    fn do_lower_comparisons(
+        &mut self,
+        get_module: &dyn Fn(&HashSet<ColumnRef>) -> String,
+        comparisons: &mut Vec<(Handle, Node)>,
+    ) {
+        match self.e_mut() {
+            Expression::List(es) => {
+                for e in es.iter_mut() {
+                    e.do_lower_comparisons(get_module, comparisons);
+                }
+            }
+            Expression::Funcall { args, .. } => {
+                for e in args.iter_mut() {
+                    e.do_lower_comparisons(get_module, comparisons);
+                }
+            }
+            _ => {}
+        }
+
+        if let Expression::Funcall {
+            func: Intrinsic::Lt,
+            args,
+        } = self.e()
+        {
+            if let (Result::Ok(x), Result::Ok(y)) = (args[0].pure_eval(), args[1].pure_eval()) {
+                // Replace by the result if it is known at compile time
+                *self = Node::from_isize((x < y) as isize).with_type(self.t());
+            } else {
+                let module = get_module(&self.dependencies());
+                let lt = Handle::new(module, expression_to_name(self, "LT"));
+                comparisons.push((lt.clone(), self.clone()));
+                *self = Node::column()
+                    .handle(lt)
+                    .kind(Kind::Computed)
+                    .t(lt_magma())
+                    .build();
+            }
+        }
+    }
+}
+
+impl ConstraintSet {
+    /// Lower `x < y`, where `x` and `y` fit in `w` bits, into a computed binary
+    /// column `LT`, proven by the range of
+    /// `δ = LT × (y - x - 1) + (1 - LT) × (x - y)`: if `LT` does not hold
+    /// the result of the comparison, `δ` is negative and wraps around the
+    /// field, beyond 2^w.
+    pub fn expand_comparisons(&mut self) -> Result<()> {
+        let mut comparisons = vec![];
+
+        let get_module = |rs: &HashSet<ColumnRef>| self.columns.module_for(rs.iter()).unwrap();
+        for i in 0..self.constraints.len() {
+            if let Constraint::Vanishes { expr: e, .. } = self.constraints.get_mut(i).unwrap() {
+                e.do_lower_comparisons(&get_module, &mut comparisons);
+            }
+        }
+
+        for (lt_handle, comparison) in comparisons.into_iter() {
+            // The same comparison may occur several times
+            if self.columns.by_handle(&lt_handle).is_ok() {
+                continue;
+            }
+            let Expression::Funcall { args, .. } = comparison.e() else {
+                unreachable!()
+            };
+            let (x, y) = (&args[0], &args[1]);
+            let width = args[2].pure_eval()?.to_usize().unwrap();
+
+            let lt_id = self.columns.insert_column_and_register(
+                Column::builder()
+                    .handle(lt_handle.clone())
+                    .kind(Kind::Computed)
+                    .t(lt_magma())
+                    .build(),
+            )?;
+            self.computations.insert(
+                &lt_id,
+                Computation::Composite {
+                    target: lt_id.clone(),
+                    exp: comparison.clone(),
+                },
+            )?;
+            let lt = Node::column()
+                .handle(lt_id.clone())
+                .kind(Kind::Computed)
+                .t(lt_magma())
+                .build();
+
+            let delta_handle = Handle::new(
+                &lt_handle.module,
+                expression_to_name(&comparison, "LT-DELTA"),
+            );
+            let delta_exp = Intrinsic::Add.call(&[
+                Intrinsic::Mul.call(&[
+                    lt.clone(),
+                    Intrinsic::Sub.call(&[y.clone(), x.clone(), Node::one()])?,
+                ])?,
+                Intrinsic::Mul.call(&[
+                    Intrinsic::Sub.call(&[Node::one(), lt.clone()])?,
+                    Intrinsic::Sub.call(&[x.clone(), y.clone()])?,
+                ])?,
+            ])?;
+            let delta_id = self.columns.insert_column_and_register(
+                Column::builder()
+                    .handle(delta_handle.clone())
+                    .kind(Kind::Computed)
+                    .build(),
+            )?;
+            self.computations.insert(
+                &delta_id,
+                Computation::Composite {
+                    target: delta_id.clone(),
+                    exp: delta_exp.clone(),
+                },
+            )?;
+            let delta = Node::column()
+                .handle(delta_id.clone())
+                .kind(Kind::Computed)
+                .t(Magma::native())
+                .build();
+
+            self.constraints.push(Constraint::Vanishes {
+                handle: Handle::new(&lt_handle.module, format!("{}-is-binary", lt_handle.name)),
+                domain: None,
+                tags: vec![],
+                expr: Box::new(
+                    Intrinsic::Mul.call(&[lt.clone(), Intrinsic::Sub.call(&[Node::one(), lt])?])?,
+                ),
+            });
+            self.constraints.push(Constraint::Vanishes {
+                handle: Handle::new(
+                    &delta_handle.module,
+                    format!("{}-definition", delta_handle.name),
+                ),
+                domain: None,
+                tags: vec![],
+                expr: Box::new(Intrinsic::Sub.call(&[delta.clone(), delta_exp])?),
+            });
+            self.constraints.push(Constraint::InRange {
+                handle: Handle::new(&delta_handle.module, format!("{}-range", delta_handle.name)),
+                exp: delta,
+                max: RawMagma::Integer(width).upper_bound().clone(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Lower every comparison into computed columns and the constraints proving
+/// them; see [`ConstraintSet::expand_comparisons`].
+pub fn expand_comparisons(cs: &mut ConstraintSet) -> Result<()> {
+    if *crate::IS_NATIVE.read().unwrap() {
+        cs.expand_comparisons()
+    } else {
+        Ok(())
+    }
+}
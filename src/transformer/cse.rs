@@ -0,0 +1,273 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    hash::{Hash, Hasher},
+};
+
+use anyhow::*;
+use log::*;
+
+use crate::{
+    column::{Column, Computation},
+    compiler::{Constraint, ConstraintSet, Expression, Intrinsic, Kind, Node},
+    structs::Handle,
+};
+
+use super::{expression_to_name, validate_computation};
+
+/// A node hashed and compared by its structure alone, i.e. its intrinsics,
+/// the order of their arguments, its columns and their shifts, and its
+/// constants; types and debug information are disregarded.
+struct Structural<'a>(&'a Node);
+
+fn hash_node<H: Hasher>(n: &Node, state: &mut H) {
+    std::mem::discriminant(n.e()).hash(state);
+    match n.e() {
+        Expression::Funcall { func, args } => {
+            func.hash(state);
+            args.len().hash(state);
+            for a in args.iter() {
+                hash_node(a, state);
+            }
+        }
+        Expression::Const(x) => x.to_bi().hash(state),
+        Expression::Column { handle, shift, .. } | Expression::ExoColumn { handle, shift, .. } => {
+            handle.hash(state);
+            shift.hash(state);
+        }
+        Expression::ArrayColumn { handle, .. } => handle.hash(state),
+        Expression::List(xs) => {
+            xs.len().hash(state);
+            for x in xs.iter() {
+                hash_node(x, state);
+            }
+        }
+        Expression::Void => {}
+    }
+}
+
+fn same_node(a: &Node, b: &Node) -> bool {
+    match (a.e(), b.e()) {
+        (
+            Expression::Funcall { func: f1, args: a1 },
+            Expression::Funcall { func: f2, args: a2 },
+        ) => {
+            f1 == f2
+                && a1.len() == a2.len()
+                && a1.iter().zip(a2.iter()).all(|(x, y)| same_node(x, y))
+        }
+        (Expression::Const(x), Expression::Const(y)) => x.to_bi() == y.to_bi(),
+        (
+            Expression::Column {
+                handle: h1,
+                shift: s1,
+                ..
+            },
+            Expression::Column {
+                handle: h2,
+                shift: s2,
+                ..
+            },
+        )
+        | (
+            Expression::ExoColumn {
+                handle: h1,
+                shift: s1,
+                ..
+            },
+            Expression::ExoColumn {
+                handle: h2,
+                shift: s2,
+                ..
+            },
+        ) => h1 == h2 && s1 == s2,
+        (
+            Expression::ArrayColumn { handle: h1, .. },
+            Expression::ArrayColumn { handle: h2, .. },
+        ) => h1 == h2,
+        (Expression::List(xs), Expression::List(ys)) => {
+            xs.len() == ys.len() && xs.iter().zip(ys.iter()).all(|(x, y)| same_node(x, y))
+        }
+        (Expression::Void, Expression::Void) => true,
+        _ => false,
+    }
+}
+
+impl Hash for Structural<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_node(self.0, state)
+    }
+}
+impl PartialEq for Structural<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        same_node(self.0, other.0)
+    }
+}
+impl Eq for Structural<'_> {}
+
+/// Whether `n` may be replaced by a computed column
+fn is_hoistable(n: &Node) -> bool {
+    match n.e() {
+        Expression::Funcall { func, args } => {
+            !matches!(func, Intrinsic::Begin)
+                && !n.dependencies().is_empty()
+                && args.iter().all(|a| {
+                    !a.is_exocolumn()
+                        && (!matches!(a.e(), Expression::Funcall { .. }) || is_hoistable(a))
+                })
+        }
+        _ => false,
+    }
+}
+
+fn count_subexpressions<'a>(n: &'a Node, counts: &mut HashMap<Structural<'a>, usize>) {
+    match n.e() {
+        Expression::Funcall { args: xs, .. } | Expression::List(xs) => {
+            if is_hoistable(n) {
+                *counts.entry(Structural(n)).or_default() += 1;
+            }
+            for x in xs.iter() {
+                count_subexpressions(x, counts);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Count the occurrences of the `candidates` in `n`, disregarding the ones
+/// nested in another candidate.
+fn count_outermost<'a>(
+    n: &'a Node,
+    candidates: &HashSet<Structural<'a>>,
+    counts: &mut HashMap<Structural<'a>, usize>,
+) {
+    if candidates.contains(&Structural(n)) {
+        *counts.entry(Structural(n)).or_default() += 1;
+        return;
+    }
+    match n.e() {
+        Expression::Funcall { args: xs, .. } | Expression::List(xs) => {
+            for x in xs.iter() {
+                count_outermost(x, candidates, counts);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replace the hoisted subexpressions of `n` by references to the columns
+/// computing them.
+fn hoist(n: &mut Node, hoisted: &HashMap<Structural, Node>) -> usize {
+    if let Some(column) = hoisted.get(&Structural(n)) {
+        *n = column.clone();
+        return 1;
+    }
+    match n.e_mut() {
+        Expression::Funcall { args: xs, .. } | Expression::List(xs) => {
+            xs.iter_mut().map(|x| hoist(x, hoisted)).sum()
+        }
+        _ => 0,
+    }
+}
+
+/// Compute the subexpressions appearing at least `min_occurrences` times in
+/// the vanishing constraints once, in dedicated computed columns, and
+/// replace them by these columns in the constraints.
+pub fn cse(cs: &mut ConstraintSet, min_occurrences: usize) -> Result<()> {
+    let exprs = cs
+        .constraints
+        .iter()
+        .filter_map(|c| match c {
+            Constraint::Vanishes { expr, .. } => Some(expr.as_ref().clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut counts = HashMap::new();
+    for e in exprs.iter() {
+        count_subexpressions(e, &mut counts);
+    }
+
+    // Only the outermost occurrences are replaced, so the occurrences nested
+    // in another candidate do not count. Dropping a candidate that is then
+    // not repeated enough exposes the candidates nested in it, whose count
+    // may thus grow; hence, only the largest failing candidates are dropped
+    // at each iteration, until all the remaining ones are repeated enough.
+    let mut candidates = counts
+        .into_iter()
+        .filter(|(e, count)| {
+            *count >= min_occurrences && cs.columns.module_for(e.0.dependencies()).is_some()
+        })
+        .map(|(e, _)| e)
+        .collect::<HashSet<_>>();
+    loop {
+        let mut counts = HashMap::new();
+        for e in exprs.iter() {
+            count_outermost(e, &candidates, &mut counts);
+        }
+        let failing = |e: &Structural| counts.get(e).copied().unwrap_or(0) < min_occurrences;
+        let Some(largest) = candidates
+            .iter()
+            .filter(|e| failing(e))
+            .map(|e| e.0.size())
+            .max()
+        else {
+            break;
+        };
+        candidates.retain(|e| !failing(e) || e.0.size() < largest);
+    }
+
+    let mut new_cs_exps = Vec::new();
+    let mut hoisted = HashMap::new();
+    for (name, e) in candidates
+        .into_iter()
+        .map(|e| (expression_to_name(e.0, "CSE"), e))
+        .collect::<BTreeMap<_, _>>()
+    {
+        let module = cs.columns.module_for(e.0.dependencies()).unwrap();
+        let handle = Handle::new(module, name);
+        if let Some(id) = cs.columns.maybe_insert_column_and_register(
+            Column::builder()
+                .handle(handle.clone())
+                .kind(Kind::Computed)
+                .t(e.0.t().m())
+                .build(),
+        ) {
+            cs.computations.insert(
+                &id,
+                Computation::Composite {
+                    target: id.clone(),
+                    exp: e.0.clone(),
+                },
+            )?;
+            validate_computation(&mut new_cs_exps, e.0, &handle);
+        }
+        let column = Node::column()
+            .handle(handle)
+            .kind(Kind::Computed)
+            .t(e.0.t().m())
+            .build();
+        hoisted.insert(e, column);
+    }
+
+    let mut replaced = 0;
+    for c in cs.constraints.iter_mut() {
+        if let Constraint::Vanishes { expr, .. } = c {
+            replaced += hoist(expr, &hoisted);
+        }
+    }
+    info!(
+        "{} repeated subexpressions hoisted into computed columns, replacing {} occurrences",
+        hoisted.len(),
+        replaced
+    );
+
+    if !new_cs_exps.is_empty() {
+        cs.insert_constraint(Constraint::Vanishes {
+            handle: Handle::new("RESERVED", "CSE_CONSTRAINTS"),
+            domain: None,
+            tags: vec![],
+            expr: Box::new(Expression::List(new_cs_exps).into()),
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+
+use log::*;
+use owo_colors::OwoColorize;
+
+use crate::compiler::{Constraint, ConstraintSet};
+
+/// Find the constraints structurally identical to a previous one, and warn
+/// about them; if `remove` is set, only the first of these is kept.
+///
+/// Return the names of the duplicated constraints, with the name of the
+/// constraint they duplicate.
+pub fn dedup_constraints(cs: &mut ConstraintSet, remove: bool) -> Vec<(String, String)> {
+    let mut seen: HashMap<&Constraint, String> = HashMap::new();
+    let mut duplicates = vec![];
+    let mut keep = vec![];
+    for c in cs.constraints.iter() {
+        let name = c.name();
+        if let Some(original) = seen.get(c) {
+            warn!(
+                "{} duplicates {}",
+                name.bold().yellow(),
+                original.bold().white()
+            );
+            duplicates.push((name, original.to_owned()));
+            keep.push(!remove);
+        } else {
+            seen.insert(c, name);
+            keep.push(true);
+        }
+    }
+
+    if remove {
+        let mut keep = keep.into_iter();
+        cs.constraints.retain(|_| keep.next().unwrap());
+    }
+    duplicates
+}
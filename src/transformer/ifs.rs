@@ -2,19 +2,22 @@ use anyhow::Result;
 use num_traits::Zero;
 
 use crate::compiler::{Constraint, ConstraintSet, Expression, Intrinsic, Node};
+use crate::pretty::Pretty;
+use crate::structs::Handle;
+use log::*;
 
 use super::flatten_list;
 
 /// Lower an expression by eliminating if conditionals.  The simplest
 /// example is something like this:
 ///
-/// ```
+/// ```lisp
 /// (if (vanishes! A) B C)
 /// ```
 ///
 /// Which is translated into a list of two lowered constraints:
 ///
-/// ```
+/// ```text
 /// {
 ///  (1 - NORM(A)) * B
 ///  A * C
@@ -78,7 +81,8 @@ fn extract_condition(node: &Node) -> Option<Node> {
                 | Intrinsic::VectorAdd
                 | Intrinsic::VectorSub
                 | Intrinsic::VectorMul
-                | Intrinsic::Exp => {
+                | Intrinsic::Exp
+                | Intrinsic::Lt => {
                     let mut r = None;
                     // Extract condition for each term
                     for n in args {
@@ -147,7 +151,8 @@ fn extract_body(node: &Node) -> Node {
                 | Intrinsic::Mul
                 | Intrinsic::VectorAdd
                 | Intrinsic::VectorSub
-                | Intrinsic::VectorMul => {
+                | Intrinsic::VectorMul
+                | Intrinsic::Lt => {
                     let mut bodies = Vec::new();
                     // Extract bodies from each term
                     for n in args {
@@ -229,6 +234,84 @@ fn is_not_zero(node: Option<&Node>) -> bool {
     }
 }
 
+/// Replace the conditionals whose condition is a constant by the branch they
+/// take, so that their dead branches are eliminated before being lowered.
+/// For instance, `(if-zero 0 A B)` becomes `A`, and `(if-not-zero 5 A B)`
+/// becomes `A` as well.
+///
+/// Returns `None` if the expression is dead, _i.e._ if it is a conditional
+/// without else branch whose condition does not hold, as in `(if-zero 3 A)`.
+/// Such an expression does not constrain anything, and neither does any
+/// computation it is part of; but it is only removed from the lists
+/// containing it.
+///
+/// The eliminations are reported at the debug level, attributed to the
+/// constraint `handle`.
+fn fold_constant_ifs(node: &Node, handle: &Handle) -> Option<Node> {
+    match node.e() {
+        Expression::List(es) => {
+            let es = es
+                .iter()
+                .filter_map(|e| fold_constant_ifs(e, handle))
+                .collect::<Vec<_>>();
+            if es.is_empty() {
+                None
+            } else {
+                Some(Expression::List(es).into())
+            }
+        }
+        Expression::Funcall { func, args } => match func {
+            Intrinsic::IfZero | Intrinsic::IfNotZero => {
+                let cond = fold_constant_ifs(&args[0], handle)?;
+                let then = fold_constant_ifs(&args[1], handle);
+                let otherwise = args
+                    .get(2)
+                    .and_then(|otherwise| fold_constant_ifs(otherwise, handle));
+                if let Ok(c) = cond.pure_eval() {
+                    let taken = if c.is_zero() == matches!(func, Intrinsic::IfZero) {
+                        then
+                    } else {
+                        otherwise
+                    };
+                    match taken.as_ref() {
+                        Some(taken) => {
+                            debug!("{}: folded {} into {}", handle.pretty(), node, taken)
+                        }
+                        None => debug!("{}: eliminated {}", handle.pretty(), node),
+                    }
+                    taken
+                } else {
+                    match (then, otherwise) {
+                        (None, None) => None,
+                        (Some(then), None) => Some(func.unchecked_call(&[cond, then]).unwrap()),
+                        // only the else branch is left, so the condition is flipped
+                        (None, Some(otherwise)) => Some(
+                            if matches!(func, Intrinsic::IfZero) {
+                                Intrinsic::IfNotZero
+                            } else {
+                                Intrinsic::IfZero
+                            }
+                            .unchecked_call(&[cond, otherwise])
+                            .unwrap(),
+                        ),
+                        (Some(then), Some(otherwise)) => {
+                            Some(func.unchecked_call(&[cond, then, otherwise]).unwrap())
+                        }
+                    }
+                }
+            }
+            _ => {
+                let args = args
+                    .iter()
+                    .map(|a| fold_constant_ifs(a, handle))
+                    .collect::<Option<Vec<_>>>()?;
+                Some(func.unchecked_call(&args).unwrap())
+            }
+        },
+        _ => Some(node.clone()),
+    }
+}
+
 /// Pull `lists` out of nested positions and into top-most
 /// positions.  Specifically, something like this:
 ///
@@ -346,7 +429,7 @@ fn raise_binary(lhs: &Node, rhs: &Node, f: &Intrinsic, out: &mut Vec<Node>) {
 ///
 /// Would be compiled as follows:
 ///
-/// ```
+/// ```text
 /// (1 - NORM(A)) * B
 /// ```
 ///
@@ -355,8 +438,18 @@ fn raise_binary(lhs: &Node, rhs: &Node, f: &Intrinsic, out: &mut Vec<Node>) {
 ///
 /// **NOTE:** When the `if` condition is a constant expression, then
 /// it is evaluated at compile time and the entire `if` expression is
-/// eliminated.
+/// replaced by the branch it takes (see `fold_constant_ifs`).
 pub fn expand_ifs(cs: &mut ConstraintSet) {
+    // Eliminate dead branches, and the constraints consisting only of them
+    cs.constraints.retain_mut(|c| {
+        if let Constraint::Vanishes { handle, expr, .. } = c {
+            match fold_constant_ifs(expr, handle) {
+                Some(nexpr) => **expr = nexpr,
+                None => return false,
+            }
+        }
+        true
+    });
     // Raise lists
     for c in cs.constraints.iter_mut() {
         if let Constraint::Vanishes { expr, .. } = c {
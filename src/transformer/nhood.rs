@@ -1,8 +1,10 @@
 use crate::{
-    compiler::{ColumnRef, Constraint, ConstraintSet, Intrinsic, Kind, Node, RawMagma},
+    compiler::{ColumnRef, Constraint, ConstraintSet, Expression, Intrinsic, Kind, Node, RawMagma},
+    pretty::Pretty,
     structs::Handle,
 };
 use anyhow::{bail, Result};
+use log::*;
 use owo_colors::OwoColorize;
 
 fn process_binarity(column_ref: ColumnRef, cs: &mut ConstraintSet) {
@@ -11,6 +13,7 @@ fn process_binarity(column_ref: ColumnRef, cs: &mut ConstraintSet) {
     cs.insert_constraint(Constraint::Vanishes {
         handle: Handle::new(handle.module.clone(), format!("{}-binarity", handle.name)),
         domain: None,
+        tags: vec![],
         expr: Box::new(
             Intrinsic::Mul
                 .call(&[
@@ -37,6 +40,33 @@ fn process_arbitrary(column_ref: ColumnRef, bits: usize, cs: &mut ConstraintSet)
     })
 }
 
+/// Warn about range constraints on a single column that are looser than the
+/// declared magma of this column, as they hint at a mismatch between the two.
+fn check_ranges_against_magmas(cs: &ConstraintSet) {
+    for c in cs.constraints.iter() {
+        if let Constraint::InRange { handle, exp, max } = c {
+            if let Expression::Column { handle: column, .. } = exp.e() {
+                let magma = cs.columns.column(column).unwrap().t;
+                if matches!(
+                    magma.rm(),
+                    RawMagma::Any | RawMagma::Native | RawMagma::None
+                ) {
+                    continue;
+                }
+                if max.to_bi() > magma.rm().upper_bound().to_bi() {
+                    warn!(
+                        "{}: range {} is larger than {} ({}) allows",
+                        handle.pretty(),
+                        max.pretty().yellow(),
+                        cs.handle(column).pretty(),
+                        magma
+                    );
+                }
+            }
+        }
+    }
+}
+
 /// Responsible for enforcing type constraints on any user-defined
 /// column marked with `@prove`.  For `binary@prove` columns, this
 /// requires adding a single constraint to enforce binariry.  For
@@ -48,6 +78,7 @@ pub fn validate_nhood(cs: &mut ConstraintSet) -> Result<()> {
     // want to modify the constraint set).
     let mut cols = Vec::new();
     //
+    check_ranges_against_magmas(cs);
     for (h, c) in cs.columns.iter() {
         // only atomic columns (i.e. filled from traces) are of interest here
         if c.kind == Kind::Commitment && c.must_prove {
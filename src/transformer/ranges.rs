@@ -0,0 +1,30 @@
+use crate::{
+    compiler::{Constraint, ConstraintSet, Kind, Node, RawMagma},
+    structs::Handle,
+};
+
+/// Enforce the width of the columns declared with a sized integer type, e.g.
+/// `:u16`, with a range constraint on each of them, so that it is actually
+/// proven. Columns marked with `@prove` are already taken care of by the
+/// neighbourhood constraints.
+pub fn enforce_magma_ranges(cs: &mut ConstraintSet) {
+    let cols = cs
+        .columns
+        .iter()
+        .filter_map(|(h, c)| match c.t.rm() {
+            RawMagma::Integer(bits) if c.kind == Kind::Commitment && !c.must_prove => {
+                Some((h, bits))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (h, bits) in cols {
+        let handle = cs.handle(&h);
+        cs.insert_constraint(Constraint::InRange {
+            handle: Handle::new(handle.module.clone(), format!("{}-range", handle.name)),
+            max: RawMagma::Integer(bits).upper_bound().clone(),
+            exp: Node::column().handle(h).build(),
+        })
+    }
+}
@@ -118,6 +118,7 @@ pub fn expand_constraints(cs: &mut ConstraintSet) -> Result<()> {
         cs.insert_constraint(Constraint::Vanishes {
             handle: Handle::new("RESERVED", "EXPANSION_CONSTRAINTS"),
             domain: None,
+            tags: vec![],
             expr: Box::new(Expression::List(new_cs_exps).into()),
         });
     }
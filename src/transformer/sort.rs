@@ -89,11 +89,30 @@ fn create_sort_constraint(
             )
         })
         .collect::<Result<Vec<_>>>()?;
+    // With a descending key, the all-zero padding may not sort before the
+    // actual rows; the ordering is then only enforced from the first row that
+    // is not all zeros onwards.
+    let started = if signs.iter().any(|s| !s) {
+        let size = cs.length_multiplier(&froms[0]);
+        Some(
+            cs.columns.insert_column_and_register(
+                Column::builder()
+                    .handle(Handle::new(&module, format!("__SRT__Started_{suffix}")))
+                    .kind(Kind::Computed)
+                    .t(Magma::binary())
+                    .intrinsic_size_factor(size)
+                    .build(),
+            )?,
+        )
+    } else {
+        None
+    };
 
     // Create the binarity constraints
     cs.insert_constraint(Constraint::Vanishes {
         handle: Handle::new(&module, format!("{}-is-binary", cs.handle(&eq).name)),
         domain: None,
+        tags: vec![],
         expr: Box::new(Intrinsic::Mul.call(&[
             Node::column().handle(eq.clone()).t(Magma::binary()).build(),
             Intrinsic::Sub.call(&[
@@ -106,6 +125,7 @@ fn create_sort_constraint(
         cs.insert_constraint(Constraint::Vanishes {
             handle: Handle::new(&module, format!("{}-is-binary", cs.handle(at).name)),
             domain: None,
+            tags: vec![],
             expr: Box::new(Intrinsic::Mul.call(&[
                 Node::column().handle(at.clone()).t(Magma::binary()).build(),
                 Intrinsic::Sub.call(&[
@@ -120,6 +140,7 @@ fn create_sort_constraint(
     cs.insert_constraint(Constraint::Vanishes {
         handle: Handle::new(&module, format!("{}-decomposition", cs.handle(&delta).name)),
         domain: None,
+        tags: vec![],
         expr: Box::new(
             Intrinsic::Sub.call(&[
                 Node::column()
@@ -154,6 +175,62 @@ fn create_sort_constraint(
         })
     }
 
+    // Create the start constraints
+    // 1 on the row where the ordering starts, 0 elsewhere
+    let starts_here = if let Some(started) = started.as_ref() {
+        let started_now = Node::column()
+            .handle(started.clone())
+            .t(Magma::binary())
+            .build();
+        let started_before = Node::column()
+            .handle(started.clone())
+            .t(Magma::binary())
+            .shift(-1)
+            .build();
+        cs.insert_constraint(Constraint::Vanishes {
+            handle: Handle::new(&module, format!("{}-is-binary", cs.handle(started).name)),
+            domain: None,
+            tags: vec![],
+            expr: Box::new(Intrinsic::Mul.call(&[
+                started_now.clone(),
+                Intrinsic::Sub.call(&[Node::from_isize(1), started_now.clone()])?,
+            ])?),
+        });
+        // once started, the ordering is never escaped again
+        cs.insert_constraint(Constraint::Vanishes {
+            handle: Handle::new(&module, format!("{}-increasing", cs.handle(started).name)),
+            domain: None,
+            tags: vec![],
+            expr: Box::new(Intrinsic::Mul.call(&[
+                started_before.clone(),
+                Intrinsic::Sub.call(&[Node::from_isize(1), started_now.clone()])?,
+            ])?),
+        });
+        let starts_here = Intrinsic::Sub.call(&[started_now, started_before])?;
+        // the ordering may only start after an all-zero row
+        for (i, s) in sorted.iter().take(signs.len()).enumerate() {
+            let sorted_t = cs.columns.column(s)?.t;
+            cs.insert_constraint(Constraint::Vanishes {
+                handle: Handle::new(&module, format!("{}-after-{i}", cs.handle(started).name)),
+                domain: None,
+                tags: vec![],
+                expr: Box::new(
+                    Intrinsic::Mul.call(&[
+                        starts_here.clone(),
+                        Node::column()
+                            .handle(s.clone())
+                            .t(sorted_t)
+                            .shift(-1)
+                            .build(),
+                    ])?,
+                ),
+            });
+        }
+        Some(starts_here)
+    } else {
+        None
+    };
+
     // // Create the @ constraints
     for (i, at) in ats.iter().enumerate() {
         // ∑_k=0^i-1 @_k = 0...
@@ -180,6 +257,7 @@ fn create_sort_constraint(
         cs.insert_constraint(Constraint::Vanishes {
             handle: Handle::new(&module, format!("{at}-0")),
             domain: None,
+            tags: vec![],
             expr: Box::new(
                 Intrinsic::Mul.call(&[
                     // ∑_k=0^i-1 @_k = 0...
@@ -204,6 +282,7 @@ fn create_sort_constraint(
         cs.insert_constraint(Constraint::Vanishes {
             handle: Handle::new(&module, format!("{at}-1")),
             domain: None,
+            tags: vec![],
             expr: Box::new(Intrinsic::Mul.call(&[
                 // ∑_k=0^i-1 @_k = 0...
                 sum_ats.clone(),
@@ -230,6 +309,7 @@ fn create_sort_constraint(
     cs.insert_constraint(Constraint::Vanishes {
         handle: Handle::new(&module, format!("Eq_@_{suffix}")),
         domain: None,
+        tags: vec![],
         expr: Box::new(
             Intrinsic::Sub.call(&[
                 Node::from_isize(1),
@@ -250,52 +330,60 @@ fn create_sort_constraint(
     cs.insert_constraint(Constraint::Vanishes {
         handle: Handle::new(&module, format!("__SRT__Eq_i_{suffix}")),
         domain: None,
+        tags: vec![],
         expr: Box::new(
-            Intrinsic::Mul.call(&[
-                // Eq = 0
-                Intrinsic::Sub.call(&[
-                    Node::from_isize(1),
-                    Node::column().handle(eq.clone()).t(Magma::binary()).build(),
-                ])?,
-                // Δ = ∑ ε_i × @_i × δSorted_i
-                Intrinsic::Sub.call(&[
-                    Node::column()
-                        .handle(delta.clone())
-                        .t(Magma::native())
-                        .build(),
-                    Intrinsic::Add.call(
-                        (0..signs.len())
-                            .map(|l| {
-                                let sorted_l_t = cs.columns.column(&sorted[l])?.t;
-                                let tgt_diff = Intrinsic::Sub.call(&[
-                                    Node::column()
-                                        .handle(sorted[l].clone())
-                                        .t(sorted_l_t)
-                                        .build(),
-                                    Node::column()
-                                        .handle(sorted[l].clone())
-                                        .t(sorted_l_t)
-                                        .shift(-1)
-                                        .build(),
-                                ])?;
-                                Intrinsic::Mul.call(&[
-                                    if !signs[l] {
-                                        Node::from_isize(-1)
-                                    } else {
-                                        Node::from_isize(1)
-                                    },
-                                    Node::column()
-                                        .handle(ats[l].clone())
-                                        .t(Magma::binary())
-                                        .build(),
-                                    tgt_diff,
-                                ])
-                            })
-                            .collect::<Result<Vec<_>>>()?
-                            .as_ref(),
-                    )?,
-                ])?,
-            ])?,
+            Intrinsic::Mul.call(
+                &starts_here
+                    .map(|starts_here| Intrinsic::Sub.call(&[Node::from_isize(1), starts_here]))
+                    .transpose()?
+                    .into_iter()
+                    .chain([
+                        // Eq = 0
+                        Intrinsic::Sub.call(&[
+                            Node::from_isize(1),
+                            Node::column().handle(eq.clone()).t(Magma::binary()).build(),
+                        ])?,
+                        // Δ = ∑ ε_i × @_i × δSorted_i
+                        Intrinsic::Sub.call(&[
+                            Node::column()
+                                .handle(delta.clone())
+                                .t(Magma::native())
+                                .build(),
+                            Intrinsic::Add.call(
+                                (0..signs.len())
+                                    .map(|l| {
+                                        let sorted_l_t = cs.columns.column(&sorted[l])?.t;
+                                        let tgt_diff = Intrinsic::Sub.call(&[
+                                            Node::column()
+                                                .handle(sorted[l].clone())
+                                                .t(sorted_l_t)
+                                                .build(),
+                                            Node::column()
+                                                .handle(sorted[l].clone())
+                                                .t(sorted_l_t)
+                                                .shift(-1)
+                                                .build(),
+                                        ])?;
+                                        Intrinsic::Mul.call(&[
+                                            if !signs[l] {
+                                                Node::from_isize(-1)
+                                            } else {
+                                                Node::from_isize(1)
+                                            },
+                                            Node::column()
+                                                .handle(ats[l].clone())
+                                                .t(Magma::binary())
+                                                .build(),
+                                            tgt_diff,
+                                        ])
+                                    })
+                                    .collect::<Result<Vec<_>>>()?
+                                    .as_ref(),
+                            )?,
+                        ])?,
+                    ])
+                    .collect::<Vec<_>>(),
+            )?,
         ),
     });
 
@@ -305,6 +393,7 @@ fn create_sort_constraint(
             .into_iter()
             .chain(ats.iter().cloned())
             .chain(delta_bytes.iter().cloned())
+            .chain(started.iter().cloned())
             .collect::<Vec<_>>()
             .as_slice(),
         Computation::SortingConstraints {
@@ -312,6 +401,7 @@ fn create_sort_constraint(
             eq,
             delta,
             delta_bytes,
+            started,
             signs: signs.to_vec(),
             froms: froms.to_vec(),
             sorted: sorted.to_vec(),
@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+
+use crate::compiler::{Constraint, ConstraintSet, Expression};
+
+impl ConstraintSet {
+    /// Walk all the vanishing constraints and, for each module, return the
+    /// largest distance a shifted column of this module is read at from the
+    /// current row.
+    fn constraints_spilling(&self) -> HashMap<String, isize> {
+        let mut r = HashMap::new();
+        for c in self.constraints.iter() {
+            if let Constraint::Vanishes { expr, .. } = c {
+                for leaf in expr.leaves() {
+                    if let Expression::Column { handle, shift, .. }
+                    | Expression::ExoColumn { handle, shift, .. } = leaf.e()
+                    {
+                        if let Result::Ok(column) = self.columns.column(handle) {
+                            let spilling = r.entry(column.handle.module.clone()).or_insert(0);
+                            *spilling = (*spilling).max((*shift as isize).abs());
+                        }
+                    }
+                }
+            }
+        }
+        r
+    }
+}
+
+/// Ensure that the spilling of every module covers the shifts applied to its
+/// columns by the vanishing constraints, which may live in another module or
+/// have been created by the previous transformations.
+pub fn spillings(cs: &mut ConstraintSet) {
+    for (module, spilling) in cs.constraints_spilling() {
+        let current = cs.columns.spilling.entry(module).or_insert(0);
+        *current = (*current).max(spilling);
+    }
+}
@@ -7,6 +7,38 @@ use std::io::Read;
 
 use crate::{column::Value, compiler::Magma, pretty::Pretty, structs::Handle};
 
+/// Parallel iterators when rayon is available; otherwise, their sequential
+/// counterparts, so that the library still works single-threaded.
+pub(crate) mod parallel {
+    #[cfg(feature = "rayon")]
+    pub(crate) use rayon::prelude::*;
+
+    #[cfg(not(feature = "rayon"))]
+    pub(crate) trait IntoParallelIterator: IntoIterator + Sized {
+        fn into_par_iter(self) -> Self::IntoIter {
+            self.into_iter()
+        }
+    }
+    #[cfg(not(feature = "rayon"))]
+    impl<T: IntoIterator> IntoParallelIterator for T {}
+
+    #[cfg(not(feature = "rayon"))]
+    pub(crate) trait IntoParallelRefIterator<'a> {
+        type Iter: Iterator;
+        fn par_iter(&'a self) -> Self::Iter;
+    }
+    #[cfg(not(feature = "rayon"))]
+    impl<'a, T: 'a + ?Sized> IntoParallelRefIterator<'a> for T
+    where
+        &'a T: IntoIterator,
+    {
+        type Iter = <&'a T as IntoIterator>::IntoIter;
+        fn par_iter(&'a self) -> Self::Iter {
+            self.into_iter()
+        }
+    }
+}
+
 pub fn is_file_empty(f: &str) -> Result<bool> {
     std::fs::metadata(f)
         .with_context(|| anyhow!("unable to read metadata of `{}`", f))
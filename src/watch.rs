@@ -0,0 +1,103 @@
+use anyhow::*;
+use log::*;
+use notify::{RecursiveMode, Watcher};
+use owo_colors::OwoColorize;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+/// How long to wait for the sources to settle after a change before
+/// rerunning, so that rapid successive saves only trigger a single run
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+pub(crate) enum WatchEvent {
+    /// One of the watched files changed
+    Changed,
+    /// The user asked to stop watching
+    Stop,
+}
+
+/// Call `run` once for every burst of [`WatchEvent::Changed`] received on
+/// `events`, i.e. once no other change happened during `debounce`; return the
+/// number of runs once a [`WatchEvent::Stop`] is received or all the senders
+/// are gone.
+pub(crate) fn run_on_changes<F: FnMut()>(
+    events: &Receiver<WatchEvent>,
+    debounce: Duration,
+    mut run: F,
+) -> usize {
+    let mut runs = 0;
+    while let Result::Ok(WatchEvent::Changed) = events.recv() {
+        loop {
+            match events.recv_timeout(debounce) {
+                Result::Ok(WatchEvent::Changed) => continue,
+                Result::Ok(WatchEvent::Stop) => return runs,
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        run();
+        runs += 1;
+    }
+    runs
+}
+
+fn report(r: Result<()>) {
+    match r {
+        Result::Ok(_) => println!("{}", "✓ SUCCESS".green().bold()),
+        Err(e) => {
+            error!("{:?}", e);
+            println!("{}", "✗ FAILED".red().bold());
+        }
+    }
+    info!("waiting for changes...");
+}
+
+/// Run `f` a first time, then again every time one of the given `files`
+/// changes, until the user hits Ctrl-C.
+pub(crate) fn watch<F: FnMut() -> Result<()>>(files: &[String], mut f: F) -> Result<()> {
+    let files = files
+        .iter()
+        .map(|f| {
+            Path::new(f)
+                .canonicalize()
+                .with_context(|| anyhow!("watching {}", f))
+        })
+        .collect::<Result<HashSet<PathBuf>>>()?;
+
+    let (tx, rx) = channel();
+    let stop_tx = tx.clone();
+    ctrlc::set_handler(move || {
+        let _ = stop_tx.send(WatchEvent::Stop);
+    })
+    .with_context(|| "while setting the Ctrl-C handler")?;
+
+    // Editors frequently save by replacing the file, so the directories are
+    // watched rather than the files themselves.
+    let watched = files.clone();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Result::Ok(event) = event {
+            if (event.kind.is_modify() || event.kind.is_create())
+                && event.paths.iter().any(|p| watched.contains(p))
+            {
+                let _ = tx.send(WatchEvent::Changed);
+            }
+        }
+    })?;
+    for dir in files
+        .iter()
+        .filter_map(|f| f.parent())
+        .collect::<HashSet<_>>()
+    {
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| anyhow!("watching {}", dir.display()))?;
+    }
+
+    report(f());
+    run_on_changes(&rx, DEBOUNCE, || report(f()));
+    info!("stopped watching");
+    Ok(())
+}
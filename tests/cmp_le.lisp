@@ -0,0 +1,6 @@
+(defcolumns (X :u2) (Y :u2) Z)
+(definrange X 4)
+(definrange Y 4)
+
+(defconstraint test1 ()
+  (if (<= X Y) (vanishes! Z)))
@@ -0,0 +1,2 @@
+(defcolumns X)
+(definrange X 2)
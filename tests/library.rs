@@ -0,0 +1,1982 @@
+use corset::column::{PaddingStrategy, ValueBacking};
+use corset::compiler::{ColumnRef, Constraint};
+use corset::field::Fr;
+use corset::storage::MmapSettings;
+use corset::structs::Handle;
+use corset::{
+    append_trace_str, check, compile, compute_trace_str, update_trace_str, CompileSettings,
+    ConstraintSet, DebugSettings,
+};
+
+fn compile_str(source: &str) -> ConstraintSet {
+    compile(&[("test", source)], &CompileSettings::default()).unwrap()
+}
+
+fn accepts(source: &str, trace: &str) -> bool {
+    let mut cs = compile_str(source);
+    compute_trace_str(trace.as_bytes(), &mut cs, true).unwrap();
+    check(&cs, &None, &[], DebugSettings::new()).is_ok()
+}
+
+#[test]
+fn compile_and_check() {
+    let source = "(defcolumns A B) (defconstraint eq () (vanishes! (- A B)))";
+
+    assert!(accepts(
+        source,
+        r#"{"<prelude>": {"A": [1, 2, 3], "B": [1, 2, 3]}}"#
+    ));
+    assert!(!accepts(
+        source,
+        r#"{"<prelude>": {"A": [1, 2, 3], "B": [1, 5, 3]}}"#
+    ));
+}
+
+#[test]
+fn field_choice() {
+    // the modulus of the scalar field of BLS12-377, that is smaller than the
+    // one of BLS12-381
+    let p377 = num_bigint::BigInt::parse_bytes(
+        b"8444461749428370424248824938781546531375899335154063827935233455917409239041",
+        10,
+    )
+    .unwrap();
+    let source = "(defcolumns A) (defconstraint c () (vanishes! (* A (+ A 1))))";
+    let trace = |a: num_bigint::BigInt| format!(r#"{{"<prelude>": {{"A": [{}]}}}}"#, a);
+
+    // p377 - 1 is -1 in BLS12-377, but not in BLS12-381
+    assert!(!accepts(source, &trace(num_bigint::BigInt::from(1))));
+    assert_eq!(
+        accepts(source, &trace(p377 - 1)),
+        cfg!(not(feature = "bls12-381"))
+    );
+}
+
+#[test]
+fn invalid_source() {
+    assert!(compile(
+        &[("test", "(defconstraint eq () (vanishes! (- A B)))")],
+        &CompileSettings::default()
+    )
+    .is_err());
+}
+
+#[test]
+fn read_column_values() {
+    let mut cs = compile_str(
+        "(defcolumns A B (C :comp (+ A B))) (defconstraint eq () (vanishes! (- C (+ A B))))",
+    );
+    compute_trace_str(
+        r#"{"<prelude>": {"A": [1, 2, 3], "B": [10, 20, 30]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+
+    let a = Handle::new("<prelude>", "A");
+    let c = Handle::new("<prelude>", "C");
+    // imported traces start with a padding row
+    assert_eq!(
+        cs.column_values(&a),
+        Some(vec![
+            Fr::from(0u64),
+            Fr::from(1u64),
+            Fr::from(2u64),
+            Fr::from(3u64)
+        ])
+    );
+    assert_eq!(
+        cs.column_values(&c),
+        Some(vec![
+            Fr::from(0u64),
+            Fr::from(11u64),
+            Fr::from(22u64),
+            Fr::from(33u64)
+        ])
+    );
+    assert_eq!(cs.column_value_at(&c, 2), Some(Fr::from(22u64)));
+    // the spilling is filled with padding
+    assert_eq!(cs.column_value_at(&c, -1), Some(Fr::from(0u64)));
+    assert_eq!(cs.column_values(&Handle::new("<prelude>", "D")), None);
+
+    let computed = cs
+        .computed_columns()
+        .map(|(h, _)| h.clone())
+        .collect::<Vec<_>>();
+    assert!(computed.contains(&c));
+    assert!(!computed.contains(&a));
+}
+
+#[test]
+fn recompute_dirty_columns() {
+    let mut cs = compile_str(
+        "(defcolumns A B X (C :comp (+ A B)) (D :comp (* X 2)))
+         (defconstraint c () (vanishes! (- C (+ A B))))
+         (defconstraint d () (vanishes! (- D (* X 2))))",
+    );
+    compute_trace_str(
+        r#"{"<prelude>": {"A": [1, 2], "B": [10, 20], "X": [3, 4]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+
+    let a = Handle::new("<prelude>", "A");
+    let c = Handle::new("<prelude>", "C");
+    let d = Handle::new("<prelude>", "D");
+    let d_backing = cs.columns.backing(&d.clone().into()).unwrap() as *const _;
+
+    let a_ref: ColumnRef = a.clone().into();
+    let spilling = cs.spilling_for_column(&a_ref).unwrap();
+    cs.columns.reset_column(&a_ref);
+    cs.columns
+        .set_column_value(&a_ref, vec![0.into(), 5.into(), 6.into()], spilling)
+        .unwrap();
+    cs.recompute_dirty(&[a]).unwrap();
+
+    assert_eq!(
+        cs.column_values(&c),
+        Some(vec![Fr::from(0u64), Fr::from(15u64), Fr::from(26u64)])
+    );
+    // D does not depend on A, and must not have been recomputed
+    assert!(std::ptr::eq(
+        cs.columns.backing(&d.clone().into()).unwrap(),
+        d_backing
+    ));
+    assert_eq!(
+        cs.column_values(&d),
+        Some(vec![Fr::from(0u64), Fr::from(6u64), Fr::from(8u64)])
+    );
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_ok());
+}
+
+#[test]
+fn update_trace() {
+    let mut cs = compile_str(
+        "(defcolumns A B X (C :comp (+ A B)) (D :comp (* X 2)))
+         (defconstraint c () (vanishes! (- C (+ A B))))
+         (defconstraint d () (vanishes! (- D (* X 2))))",
+    );
+    compute_trace_str(
+        r#"{"<prelude>": {"A": [1, 2], "B": [10, 20], "X": [3, 4]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+
+    let c = Handle::new("<prelude>", "C");
+    let d = Handle::new("<prelude>", "D");
+    let d_backing = cs.columns.backing(&d.clone().into()).unwrap() as *const _;
+
+    update_trace_str(
+        r#"{"<prelude>": {"A": [5, 6], "B": [10, 20], "X": [3, 4]}}"#.as_bytes(),
+        &mut cs,
+    )
+    .unwrap();
+    assert_eq!(
+        cs.column_values(&c),
+        Some(vec![Fr::from(0u64), Fr::from(15u64), Fr::from(26u64)])
+    );
+    // X did not change, so neither did D
+    assert!(std::ptr::eq(
+        cs.columns.backing(&d.clone().into()).unwrap(),
+        d_backing
+    ));
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_ok());
+
+    // a longer trace is computed anew
+    update_trace_str(
+        r#"{"<prelude>": {"A": [5, 6, 7], "B": [10, 20, 30], "X": [3, 4, 5]}}"#.as_bytes(),
+        &mut cs,
+    )
+    .unwrap();
+    assert_eq!(
+        cs.column_values(&d),
+        Some(vec![
+            Fr::from(0u64),
+            Fr::from(6u64),
+            Fr::from(8u64),
+            Fr::from(10u64)
+        ])
+    );
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_ok());
+}
+
+#[test]
+fn sampled_check() {
+    let mut cs = compile_str("(defcolumns A B) (defconstraint eq () (vanishes! (- A B)))");
+    let a = (0..20)
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    // B differs from A on the 8th line, i.e. row 8 once the padding row is
+    // accounted for
+    let b = (0..20)
+        .map(|i| {
+            if i == 7 {
+                "100".to_string()
+            } else {
+                i.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    compute_trace_str(
+        format!(r#"{{"<prelude>": {{"A": [{a}], "B": [{b}]}}}}"#).as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+    let nrows = cs
+        .column_values(&Handle::new("<prelude>", "A"))
+        .unwrap()
+        .len();
+
+    let hitting = (0..)
+        .find(|seed| corset::check::sample_rows(nrows, 3, *seed).contains(&8))
+        .unwrap();
+    assert!(check(
+        &cs,
+        &None,
+        &[],
+        DebugSettings::new().and_sample(Some(3)).seed(hitting)
+    )
+    .is_err());
+
+    let missing = (0..)
+        .find(|seed| !corset::check::sample_rows(nrows, 3, *seed).contains(&8))
+        .unwrap();
+    assert!(check(
+        &cs,
+        &None,
+        &[],
+        DebugSettings::new().and_sample(Some(3)).seed(missing)
+    )
+    .is_ok());
+}
+
+#[test]
+fn appended_traces() {
+    let source = "(defcolumns A B) (defconstraint inc () (vanishes! (* B (- (next A) A 1))))";
+    let block = |a: &str, b: &str| format!(r#"{{"<prelude>": {{"A": [{a}], "B": [{b}]}}}}"#);
+    let appended = |blocks: &[(&str, &str)]| {
+        let mut cs = compile_str(source);
+        for (a, b) in blocks {
+            append_trace_str(block(a, b).as_bytes(), &mut cs).unwrap();
+        }
+        cs
+    };
+    let full = |a: &str, b: &str| {
+        let mut cs = compile_str(source);
+        compute_trace_str(block(a, b).as_bytes(), &mut cs, true).unwrap();
+        cs
+    };
+    let a = Handle::new("<prelude>", "A");
+
+    // the appended rows follow the padding row and the rows of the first block
+    let cs = appended(&[("1, 2, 3", "1, 1, 1"), ("4, 5", "1, 0")]);
+    assert_eq!(cs.columns.appended_from.get("<prelude>"), Some(&4));
+    assert_eq!(
+        cs.column_values(&a),
+        full("1, 2, 3, 4, 5", "1, 1, 1, 1, 0").column_values(&a)
+    );
+    assert!(check(&cs, &None, &[], DebugSettings::new().appended_only(true)).is_ok());
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_ok());
+
+    // the last row of the first block only fails once its next row is appended
+    let cs = appended(&[("1, 2, 3", "1, 1, 1"), ("5, 6", "1, 0")]);
+    assert!(check(&cs, &None, &[], DebugSettings::new().appended_only(true)).is_err());
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_err());
+    assert!(check(
+        &full("1, 2, 3, 5, 6", "1, 1, 1, 1, 0"),
+        &None,
+        &[],
+        DebugSettings::new()
+    )
+    .is_err());
+
+    // failures in the former rows are not reported again
+    let cs = appended(&[("1, 5, 6", "1, 1, 0"), ("1, 2", "1, 0")]);
+    assert!(check(&cs, &None, &[], DebugSettings::new().appended_only(true)).is_ok());
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_err());
+}
+
+#[test]
+fn appended_traces_padded_after() {
+    let source = "(defcolumns A B) (defconstraint inc () (vanishes! (* B (- (next A) A 1))))";
+    let block = |a: &str, b: &str| format!(r#"{{"<prelude>": {{"A": [{a}], "B": [{b}]}}}}"#);
+    let mut cs = compile_str(source);
+    cs.columns.padding_strategy = PaddingStrategy::Append;
+    cs.columns.min_len.insert("<prelude>".into(), 6);
+    append_trace_str(block("1, 2, 3", "1, 1, 1").as_bytes(), &mut cs).unwrap();
+    append_trace_str(block("4, 5", "1, 0").as_bytes(), &mut cs).unwrap();
+
+    // the appended rows replace the trailing padding rows of the first block
+    let a = Handle::new("<prelude>", "A");
+    assert_eq!(cs.columns.appended_from.get("<prelude>"), Some(&4));
+    assert_eq!(
+        cs.column_values(&a).unwrap(),
+        [0, 1, 2, 3, 4, 5, 0, 0, 0].map(Fr::from)
+    );
+    assert!(check(&cs, &None, &[], DebugSettings::new().appended_only(true)).is_ok());
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_ok());
+}
+
+#[test]
+fn appended_traces_across_modules() {
+    let mut cs = compile_str(
+        "(module a) (defcolumns A) (module b) (defcolumns B)
+         (module c) (definterleaved I (a.A b.B))",
+    );
+    let block = |a: &str, b: &str| format!(r#"{{"a": {{"A": [{a}]}}, "b": {{"B": [{b}]}}}}"#);
+    append_trace_str(block("1, 2", "3, 4").as_bytes(), &mut cs).unwrap();
+    append_trace_str(block("5", "6").as_bytes(), &mut cs).unwrap();
+
+    // columns computed from the appended modules are computed again
+    let i = Handle::new("c", "I");
+    let mut full = compile_str(
+        "(module a) (defcolumns A) (module b) (defcolumns B)
+         (module c) (definterleaved I (a.A b.B))",
+    );
+    compute_trace_str(block("1, 2, 5", "3, 4, 6").as_bytes(), &mut full, true).unwrap();
+    assert_eq!(cs.column_values(&i), full.column_values(&i));
+}
+
+fn compile_modules() -> ConstraintSet {
+    compile_str(
+        "(module a) (defcolumns A B) (defconstraint eq () (vanishes! (- A B)))
+         (module b) (defcolumns X (Y :comp (* 2 X))) (defconstraint zero () (vanishes! X))
+         (module c) (defcolumns P) (deflookup c-in-b (b.Y) (c.P))",
+    )
+}
+
+const MODULES_TRACE: &str =
+    r#"{"a": {"A": [1, 2], "B": [1, 2]}, "b": {"X": [3, 4]}, "c": {"P": [6, 8]}}"#;
+
+#[test]
+fn only_modules() {
+    let mut cs = compile_modules();
+    cs.restrict_to_modules(&Some(vec!["a".to_string()]), &[]);
+    compute_trace_str(MODULES_TRACE.as_bytes(), &mut cs, true).unwrap();
+
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_ok());
+    // columns of filtered out modules are not even imported
+    assert_eq!(cs.column_values(&Handle::new("b", "X")), None);
+    assert_eq!(cs.column_values(&Handle::new("b", "Y")), None);
+}
+
+#[test]
+fn skip_modules() {
+    let mut cs = compile_modules();
+    cs.restrict_to_modules(&None, &["b".to_string()]);
+    compute_trace_str(MODULES_TRACE.as_bytes(), &mut cs, true).unwrap();
+
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_ok());
+    // b.Y is required by the lookup in c, and must thus still be computed
+    assert_eq!(
+        cs.column_values(&Handle::new("b", "Y")),
+        Some(vec![Fr::from(0u64), Fr::from(6u64), Fr::from(8u64)])
+    );
+
+    let mut cs = compile_modules();
+    cs.restrict_to_modules(&None, &["a".to_string()]);
+    compute_trace_str(MODULES_TRACE.as_bytes(), &mut cs, true).unwrap();
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_err());
+}
+
+#[test]
+fn modules_and_constraints_filters() {
+    let mut cs = compile_modules();
+    cs.restrict_to_modules(&None, &["a".to_string()]);
+    compute_trace_str(MODULES_TRACE.as_bytes(), &mut cs, true).unwrap();
+
+    assert!(check(&cs, &None, &["b.zero".to_string()], DebugSettings::new()).is_ok());
+}
+
+#[test]
+fn merge_constraint_sets() {
+    let a = compile_str("(module a) (defcolumns A B) (defconstraint eq () (vanishes! (- A B)))");
+    let b = compile_str(
+        "(module b) (defcolumns X (Y :comp (* 2 X))) (defconstraint zero () (vanishes! X))",
+    );
+    let mut cs = a.merge(b, false).unwrap();
+    compute_trace_str(
+        r#"{"a": {"A": [1, 2], "B": [1, 2]}, "b": {"X": [0, 0]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_ok());
+    assert_eq!(
+        cs.column_values(&Handle::new("b", "Y")),
+        Some(vec![Fr::from(0u64); 3])
+    );
+
+    let mut cs =
+        compile_str("(module a) (defcolumns A B) (defconstraint eq () (vanishes! (- A B)))")
+            .merge(
+                compile_str("(module b) (defcolumns X) (defconstraint zero () (vanishes! X))"),
+                false,
+            )
+            .unwrap();
+    compute_trace_str(
+        r#"{"a": {"A": [1, 2], "B": [1, 2]}, "b": {"X": [3, 4]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+    assert!(check(&cs, &None, &["b.zero".to_string()], DebugSettings::new()).is_ok());
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_err());
+}
+
+#[test]
+fn merge_collisions() {
+    let source = "(module a) (defcolumns A B) (defconstraint eq () (vanishes! (- A B)))";
+    let err = compile_str(source)
+        .merge(compile_str(source), false)
+        .unwrap_err();
+    assert!(format!("{:?}", err).contains("a.A"));
+    assert!(compile_str(source).merge(compile_str(source), true).is_ok());
+}
+
+#[test]
+fn forced_module_len() {
+    let source = "(module m) (defcolumns A (B :comp (* 2 A)))";
+
+    let mut cs = compile_str(source);
+    cs.force_module_len("m", 8).unwrap();
+    compute_trace_str(r#"{"m": {"A": [1, 2]}}"#.as_bytes(), &mut cs, true).unwrap();
+    assert_eq!(cs.effective_len_for("m"), Some(8));
+    let mut expected = vec![Fr::from(0u64); 6];
+    expected.extend([Fr::from(2u64), Fr::from(4u64)]);
+    assert_eq!(cs.column_values(&Handle::new("m", "B")), Some(expected));
+
+    // the trace may be as long as the forced length...
+    let mut cs = compile_str(source);
+    cs.force_module_len("m", 2).unwrap();
+    compute_trace_str(r#"{"m": {"A": [1, 2]}}"#.as_bytes(), &mut cs, true).unwrap();
+    assert_eq!(
+        cs.column_values(&Handle::new("m", "B")),
+        Some(vec![Fr::from(0u64), Fr::from(2u64), Fr::from(4u64)])
+    );
+
+    // ...but not longer
+    let mut cs = compile_str(source);
+    cs.force_module_len("m", 2).unwrap();
+    assert!(compute_trace_str(r#"{"m": {"A": [1, 2, 3]}}"#.as_bytes(), &mut cs, true).is_err());
+
+    assert!(compile_str(source).force_module_len("nope", 8).is_err());
+}
+
+#[test]
+fn len_of_forced_modules() {
+    let source = "(module m) (defcolumns A) (defconstraint c (:guard A) (vanishes! (- A (len A))))";
+    let compile_with = |module_lens: &[(&str, usize)]| {
+        compile(
+            &[("test", source)],
+            &CompileSettings {
+                module_lens: module_lens
+                    .iter()
+                    .map(|(m, l)| (m.to_string(), *l))
+                    .collect(),
+                ..Default::default()
+            },
+        )
+    };
+
+    let mut cs = compile_with(&[("m", 8)]).unwrap();
+    assert_eq!(cs.columns.forced_len.get("m"), Some(&8));
+    compute_trace_str(r#"{"m": {"A": [8, 0, 8]}}"#.as_bytes(), &mut cs, true).unwrap();
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_ok());
+
+    let mut cs = compile_with(&[("m", 4)]).unwrap();
+    compute_trace_str(r#"{"m": {"A": [8, 0, 8]}}"#.as_bytes(), &mut cs, true).unwrap();
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_err());
+
+    // the length of a module is otherwise only known from the trace
+    assert!(compile_with(&[]).is_err());
+}
+
+#[test]
+fn is_not_zero() {
+    let source = "(defcolumns A B) (defconstraint nz () (vanishes! (- B (!=0? A))))";
+
+    // the inverse of A backing the normalization must be proven
+    let cs = compile_str(source);
+    assert!(cs
+        .constraints
+        .iter()
+        .any(|c| matches!(c, Constraint::Normalization { .. })));
+
+    assert!(accepts(
+        source,
+        r#"{"<prelude>": {"A": [0, 3, -1], "B": [0, 1, 1]}}"#
+    ));
+    assert!(!accepts(
+        source,
+        r#"{"<prelude>": {"A": [0, 3, -1], "B": [0, 3, 1]}}"#
+    ));
+    assert!(!accepts(
+        source,
+        r#"{"<prelude>": {"A": [0, 3, -1], "B": [1, 1, 1]}}"#
+    ));
+}
+
+#[test]
+fn inline_interleaving() {
+    let source = "(module m) (defcolumns A B) (definterleaved C (A B))
+                  (defconstraint same () (vanishes! (- C (interleave A B))))";
+
+    let mut cs = compile_str(source);
+    compute_trace_str(
+        r#"{"m": {"A": [1, 2], "B": [3, 4]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+    let inline = cs
+        .columns
+        .iter_cols()
+        .find(|c| c.handle.name.starts_with("interleaved-"))
+        .map(|c| c.handle.clone())
+        .unwrap();
+    assert_eq!(inline.module, "m");
+    assert_eq!(
+        cs.column_values(&inline),
+        cs.column_values(&Handle::new("m", "C"))
+    );
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_ok());
+
+    // the same sources yield the same column
+    let cs = compile_str(
+        "(defcolumns A B) (defconstraint twice () (vanishes! (- (interleave A B) (interleave A B))))",
+    );
+    assert_eq!(
+        cs.columns
+            .iter_cols()
+            .filter(|c| c.handle.name.starts_with("interleaved-"))
+            .count(),
+        1
+    );
+
+    for invalid in [
+        "(module m) (defcolumns A) (module n) (defcolumns B) (deflookup l ((interleave m.A n.B)) (n.B))",
+        "(defcolumns A B) (definterleaved C (A B)) (defconstraint c () (vanishes! (interleave A C)))",
+        "(defcolumns A B) (defconstraint c () (vanishes! (interleave A (shift B 1))))",
+    ] {
+        assert!(compile(&[("test", invalid)], &CompileSettings::default()).is_err());
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+#[test]
+fn split_traces() {
+    use corset::compute_traces;
+
+    let dir = std::env::temp_dir().join(format!("corset-split-traces-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let write = |name: &str, trace: &str| {
+        let path = dir.join(name);
+        std::fs::write(&path, trace).unwrap();
+        path.to_str().unwrap().to_string()
+    };
+    let a = write("a.json", r#"{"a": {"A": [1, 2], "B": [1, 2]}}"#);
+    let bc = write("bc.json", r#"{"b": {"X": [3, 4]}, "c": {"P": [6, 8]}}"#);
+
+    let mut cs = compile_modules();
+    compute_traces(&[&a, &bc], &mut cs, true).unwrap();
+    assert_eq!(
+        cs.column_values(&Handle::new("a", "A")),
+        Some(vec![Fr::from(0u64), Fr::from(1u64), Fr::from(2u64)])
+    );
+    // computations only happen once the whole trace is available
+    assert_eq!(
+        cs.column_values(&Handle::new("b", "Y")),
+        Some(vec![Fr::from(0u64), Fr::from(6u64), Fr::from(8u64)])
+    );
+    assert!(check(&cs, &None, &["b.zero".to_string()], DebugSettings::new()).is_ok());
+
+    // a column can not be provided by several files
+    let mut cs = compile_modules();
+    let err = compute_traces(&[&a, &bc, &a], &mut cs, true).unwrap_err();
+    assert!(format!("{:?}", err).contains("is defined by several traces"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn lookup_tuples() {
+    let source =
+        "(module p) (defcolumns A B) (module c) (defcolumns X Y) (deflookup l (p.A p.B) (c.X c.Y))";
+    let failures = |trace: &str| {
+        let mut cs = compile_str(source);
+        compute_trace_str(trace.as_bytes(), &mut cs, true).unwrap();
+        check(&cs, &None, &[], DebugSettings::new())
+            .err()
+            .map(|e| e.downcast::<corset::check::CheckFailure>().unwrap().failed)
+    };
+
+    assert_eq!(
+        failures(r#"{"p": {"A": [1, 3], "B": [2, 0]}, "c": {"X": [3, 1, 3], "Y": [0, 2, 0]}}"#),
+        None
+    );
+    // (0, 2) is not a parent row, though 2×0 + 3×2 = 2×3 + 3×0
+    assert_eq!(
+        failures(r#"{"p": {"A": [1, 3], "B": [2, 0]}, "c": {"X": [1, 0], "Y": [2, 2]}}"#),
+        Some(vec![corset::check::FailedConstraint {
+            name: "c.l".into(),
+            row: Some(2),
+        }])
+    );
+}
+
+#[test]
+fn memoized_checks() {
+    // the expressions of `shared` share (* (- A B) (- C (shift A -1)))
+    const SOURCE: &str = "(defcolumns A B C)
+         (defconstraint shared ()
+           (begin (vanishes! (* (- A B) (- C (shift A -1))))
+                  (vanishes! (* (- A B) (- C (shift A -1)) (+ B 1)))))
+         (defconstraint other () (vanishes! (* (- A B) (- C (shift A -1)) (- C B))))";
+    let failures = |cs: &ConstraintSet, memoize: bool| {
+        check(cs, &None, &[], DebugSettings::new().memoize(memoize))
+            .err()
+            .map(|err| {
+                err.downcast_ref::<corset::check::CheckFailure>()
+                    .unwrap()
+                    .failed
+                    .clone()
+            })
+    };
+
+    for (trace, failing) in [
+        (
+            r#"{"<prelude>": {"A": [1, 2, 3, 4], "B": [1, 2, 3, 4], "C": [5, 6, 7, 8]}}"#,
+            0,
+        ),
+        (
+            r#"{"<prelude>": {"A": [1, 2, 3, 4], "B": [1, 7, 3, 9], "C": [0, 1, 2, 2]}}"#,
+            2,
+        ),
+        (
+            r#"{"<prelude>": {"A": [1, 2, 3, 4], "B": [1, 7, 3, 9], "C": [0, 7, 5, 3]}}"#,
+            1,
+        ),
+    ] {
+        let mut cs = compile_str(SOURCE);
+        compute_trace_str(trace.as_bytes(), &mut cs, true).unwrap();
+        let memoized = failures(&cs, true);
+        assert_eq!(memoized.as_ref().map(Vec::len).unwrap_or(0), failing);
+        assert_eq!(memoized, failures(&cs, false));
+    }
+}
+
+#[test]
+fn structured_failures() {
+    let mut cs = compile_str(
+        "(defcolumns A B) (defconstraint eq () (vanishes! (- A B))) (defconstraint ok () (vanishes! (- A A)))",
+    );
+    compute_trace_str(
+        r#"{"<prelude>": {"A": [1, 2, 3], "B": [1, 5, 3]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+
+    let err = check(&cs, &None, &[], DebugSettings::new()).unwrap_err();
+    let failure = err.downcast_ref::<corset::check::CheckFailure>().unwrap();
+    // row 0 is the padding row
+    assert_eq!(
+        failure.failed,
+        vec![corset::check::FailedConstraint {
+            name: "eq".into(),
+            row: Some(2),
+        }]
+    );
+
+    let json = serde_json::to_string(failure).unwrap();
+    let back: corset::check::CheckFailure = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.failed, failure.failed);
+}
+
+#[test]
+fn max_failures() {
+    const CHILD: &str = "CORSET_TEST_MAX_FAILURES";
+
+    // the reported rows go to stderr, that is only captured from another process
+    if let Ok(cap) = std::env::var(CHILD) {
+        let mut cs = compile_str("(defcolumns A B) (defconstraint eq () (vanishes! (- A B)))");
+        let column = |offset: usize| {
+            (1..=50)
+                .map(|i| (i + offset).to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let trace = format!(
+            r#"{{"<prelude>": {{"A": [{}], "B": [{}]}}}}"#,
+            column(0),
+            column(1)
+        );
+        compute_trace_str(trace.as_bytes(), &mut cs, true).unwrap();
+        let mut settings = DebugSettings::new().continue_on_error(true);
+        if let Ok(cap) = cap.parse() {
+            settings = settings.max_failures(cap);
+        }
+        // failing rows are only reported, whatever their number
+        check(&cs, &None, &[], settings).unwrap();
+        return;
+    }
+
+    let stderr = |cap: &str| {
+        let out = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["max_failures", "--exact", "--nocapture"])
+            .env(CHILD, cap)
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+        String::from_utf8_lossy(&out.stderr).to_string()
+    };
+    let capped = stderr("3");
+    assert_eq!(capped.matches("failed:").count(), 3);
+    assert!(capped.contains("failed on 50 rows (showing first 3)"));
+    let by_default = stderr("");
+    assert_eq!(by_default.matches("failed:").count(), 10);
+    assert!(by_default.contains("failed on 50 rows (showing first 10)"));
+}
+
+#[test]
+fn random_probe() {
+    let mut cs = compile_str(
+        "(defcolumns A B)
+         (defconstraint taut () (vanishes! (- (* A (shift B 1)) (* (shift B 1) A))))
+         (defconstraint coinc () (vanishes! (- A B)))",
+    );
+    compute_trace_str(
+        r#"{"<prelude>": {"A": [1, 2, 3], "B": [1, 2, 3]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_ok());
+
+    let suspicious = corset::check::random_probe(&cs, &None, &[], 4, 0)
+        .into_iter()
+        .map(|h| h.name)
+        .collect::<Vec<_>>();
+    assert_eq!(suspicious, vec!["coinc".to_string()]);
+}
+
+#[test]
+fn timeout() {
+    use corset::check::{run_with_timeout, TimedOut};
+    use std::time::Duration;
+
+    // a slow computation is abandoned once the deadline is reached
+    let r = run_with_timeout(Some(Duration::from_millis(50)), || {
+        std::thread::sleep(Duration::from_secs(5));
+        42
+    });
+    assert!(r.unwrap_err().is::<TimedOut>());
+
+    // a complete compute & check pass fitting in the deadline goes through
+    let r = run_with_timeout(Some(Duration::from_secs(60)), || {
+        accepts(
+            "(defcolumns A B) (defconstraint eq () (vanishes! (- A B)))",
+            r#"{"<prelude>": {"A": [1, 2, 3], "B": [1, 2, 3]}}"#,
+        )
+    });
+    assert!(r.unwrap());
+
+    // no timeout means waiting indefinitely
+    assert_eq!(run_with_timeout(None, || 42).unwrap(), 42);
+}
+
+#[test]
+fn literal_out_of_field() {
+    let err = compile(
+        &[(
+            "test",
+            "(defcolumns A)\n(defconstraint big () (vanishes! (- A (^ 2 300) 115792089237316195423570985008687907853269984665640564039457584007913129639936)))",
+        )],
+        &CompileSettings::default(),
+    )
+    .unwrap_err();
+    let msg = format!("{:?}", err);
+    assert!(msg.contains("does not fit in the field"), "{}", msg);
+    assert!(msg.contains(
+        "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+    ));
+}
+
+fn compile_error(source: &str) -> anyhow::Error {
+    compile(&[("test", source)], &CompileSettings::default()).unwrap_err()
+}
+
+#[test]
+fn typed_compile_errors() {
+    use corset::errors::{parser::SourceLocation, CompileError};
+
+    let err = compile_error("(defcolumns A)\n(defconstraint c () (vanishes! (- A B)))");
+    assert!(matches!(
+        err.downcast_ref::<CompileError>(),
+        Some(CompileError::UnknownSymbol(s, _, _)) if s == "B"
+    ));
+    let location = err.downcast_ref::<SourceLocation>().unwrap();
+    assert_eq!(location.line, 2);
+    assert!(location.src.contains('B'));
+
+    let err = compile_error("(defcolumns A) (defconstraint c () (vanishes! (shift A)))");
+    assert!(matches!(
+        err.downcast_ref::<CompileError>(),
+        Some(CompileError::ArityMismatch(_, 1))
+    ));
+
+    let err = compile_error("(defcolumns A) (defconstraint c () (vanishes! (len A)))");
+    assert!(matches!(
+        err.downcast_ref::<CompileError>(),
+        Some(CompileError::TypeMismatch(f, _, _)) if f == "len"
+    ));
+
+    let err = compile_error("(defcolumns A A)");
+    assert!(matches!(
+        err.downcast_ref::<CompileError>(),
+        Some(CompileError::SymbolAlreadyExists(s, _)) if s == "A"
+    ));
+
+    let err = compile_error(&format!(
+        "(defcolumns A) (defconstraint c () (vanishes! (- A {})))",
+        corset::column::field_modulus()
+    ));
+    assert!(matches!(
+        err.downcast_ref::<CompileError>(),
+        Some(CompileError::OutOfRange(_))
+    ));
+}
+
+#[test]
+fn derived_constants() {
+    use corset::errors::CompileError;
+
+    let source =
+        "(defconst A 10 B (* A 2)) (defcolumns X Y) (defconstraint c () (vanishes! (- Y (* B X))))";
+    assert!(accepts(
+        source,
+        r#"{"<prelude>": {"X": [1, 2], "Y": [20, 40]}}"#
+    ));
+    assert!(!accepts(
+        source,
+        r#"{"<prelude>": {"X": [1, 2], "Y": [10, 20]}}"#
+    ));
+
+    let err = compile_error("(defcolumns X) (defconst B (* X 2))");
+    assert!(matches!(
+        err.downcast_ref::<CompileError>(),
+        Some(CompileError::ColumnInConstant(c, x)) if c == "B" && x == "X"
+    ));
+    let err = compile_error("(module m) (defcolumns X) (module n) (defconst B (+ 1 m.X))");
+    assert!(matches!(
+        err.downcast_ref::<CompileError>(),
+        Some(CompileError::ColumnInConstant(c, x)) if c == "B" && x == "m.X"
+    ));
+}
+
+#[test]
+fn unused_definitions() {
+    let deny_unused = |source: &str| {
+        compile(
+            &[("test", source)],
+            &CompileSettings {
+                deny_unused: true,
+                ..Default::default()
+            },
+        )
+    };
+    let plain = |err: anyhow::Error| {
+        regex_lite::Regex::new("\x1b\\[[0-9;]*m")
+            .unwrap()
+            .replace_all(&err.to_string(), "")
+            .into_owned()
+    };
+
+    let err =
+        plain(deny_unused("(defcolumns A B) (defconstraint c () (vanishes! A))").unwrap_err());
+    assert!(err.contains("B is never used"), "{}", err);
+    assert!(!err.contains("A is never used"), "{}", err);
+
+    let err = plain(
+        deny_unused(
+            "(defcolumns A)
+             (defun (double x) (* 2 x))
+             (defun (triple x) (* 3 x))
+             (defconstraint c () (vanishes! (double A)))",
+        )
+        .unwrap_err(),
+    );
+    assert!(err.contains("function triple is never called"), "{}", err);
+    assert!(!err.contains("double"), "{}", err);
+
+    // neither the used definitions nor the unused functions of the standard
+    // library are reported
+    assert!(deny_unused(
+        "(module m)
+         (defcolumns A B)
+         (defpurefun (double x) (* 2 x))
+         (defconstraint c () (vanishes! (- (double A) B)))"
+    )
+    .is_ok());
+}
+
+#[test]
+fn strict_types() {
+    use corset::errors::CompileError;
+
+    let strict = |source: &str| {
+        compile(
+            &[("test", source)],
+            &CompileSettings {
+                strict_types: true,
+                ..Default::default()
+            },
+        )
+    };
+    let widening =
+        "(defcolumns (A :binary) (B :binary) C) (defconstraint c () (vanishes! (- C (+ A B))))";
+    let cast = "(defcolumns (A :binary) (B :binary) C)
+                (defconstraint c () (vanishes! (- C (+ (as-integer A) (as-integer B)))))";
+
+    let err = strict(widening).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<CompileError>(),
+        Some(CompileError::ImplicitWidening(..))
+    ));
+    assert!(strict(cast).is_ok());
+    // widening is only rejected in strict mode
+    compile_str(widening);
+    assert!(strict("(defcolumns (A :binary)) (defconstraint c () (vanishes! (- 1 A)))").is_err());
+    // forcing the return type of a function is an explicit cast...
+    assert!(strict("(defcolumns (A :binary)) (defconstraint c () (vanishes! (not A)))").is_ok());
+    // ...of its result only, not of its whole body
+    assert!(strict(
+        "(defpurefun ((f :binary :force) (x :binary)) (* 2 (- 1 x)))
+         (defcolumns (A :binary)) (defconstraint c () (vanishes! (f A)))"
+    )
+    .is_err());
+    assert!(strict(
+        "(defpurefun ((f :binary :force) (x :binary)) (- 1 (as-integer x)))
+         (defcolumns (A :binary)) (defconstraint c () (vanishes! (f A)))"
+    )
+    .is_ok());
+}
+
+#[test]
+fn casts() {
+    // as-integer only changes the type
+    let source =
+        "(defcolumns (A :binary) B) (defconstraint c () (vanishes! (- B (* (as-integer A) 2))))";
+    assert!(accepts(
+        source,
+        r#"{"<prelude>": {"A": [1, 0], "B": [2, 0]}}"#
+    ));
+    assert!(!accepts(
+        source,
+        r#"{"<prelude>": {"A": [1, 0], "B": [1, 1]}}"#
+    ));
+
+    // as-bool makes a condition out of A, and constrains it to 0 or 1
+    let source = "(defcolumns A B) (defconstraint c () (if (as-bool A) (vanishes! B)))";
+    assert!(compile_str(source)
+        .constraints
+        .iter()
+        .any(|c| c.name() == "#as-bool-A"));
+    assert!(accepts(
+        source,
+        r#"{"<prelude>": {"A": [0, 1, 0, 1], "B": [5, 0, 7, 0]}}"#
+    ));
+    assert!(!accepts(
+        source,
+        r#"{"<prelude>": {"A": [0, 1, 0, 1], "B": [5, 3, 7, 0]}}"#
+    ));
+    assert!(!accepts(
+        source,
+        r#"{"<prelude>": {"A": [0, 2, 0, 1], "B": [5, 0, 7, 0]}}"#
+    ));
+
+    // unless told not to, in which case it is only verified when checking
+    let unchecked = "(defcolumns A B) (defconstraint c () (if (as-bool A 0) (vanishes! B)))";
+    let cs = compile_str(unchecked);
+    assert!(!cs.constraints.iter().any(|c| c.name() == "#as-bool-A"));
+    assert!(cs.assertions.iter().any(|c| c.name() == "#as-bool-A"));
+    assert!(accepts(
+        unchecked,
+        r#"{"<prelude>": {"A": [0, 1, 0, 1], "B": [5, 0, 7, 0]}}"#
+    ));
+    assert!(!accepts(
+        unchecked,
+        r#"{"<prelude>": {"A": [0, 2, 0, 1], "B": [5, 0, 7, 0]}}"#
+    ));
+
+    // the cast only holds where it is made
+    let guarded = "(defcolumns (S :binary@bool) A B)
+                   (defconstraint c () (if S (if (as-bool A) (vanishes! B))))";
+    assert!(accepts(
+        guarded,
+        r#"{"<prelude>": {"S": [0, 1, 1], "A": [2, 1, 0], "B": [0, 0, 7]}}"#
+    ));
+    assert!(!accepts(
+        guarded,
+        r#"{"<prelude>": {"S": [0, 1, 1], "A": [2, 1, 2], "B": [0, 0, 7]}}"#
+    ));
+    let guarded = "(defcolumns (S :binary@bool) A B)
+                   (defconstraint c (:guard S) (if (as-bool A) (vanishes! B)))";
+    assert!(accepts(
+        guarded,
+        r#"{"<prelude>": {"S": [0, 1, 1], "A": [2, 1, 0], "B": [0, 0, 7]}}"#
+    ));
+    let restricted =
+        "(defcolumns A B) (defconstraint c (:domain {2}) (if (as-bool A) (vanishes! B)))";
+    assert!(accepts(
+        restricted,
+        r#"{"<prelude>": {"A": [0, 1, 2], "B": [0, 0, 7]}}"#
+    ));
+    assert!(!accepts(
+        restricted,
+        r#"{"<prelude>": {"A": [0, 2, 0], "B": [0, 0, 7]}}"#
+    ));
+
+    // the same cast is only constrained once, and the reserved names of the
+    // casts can not clash with user-defined constraints
+    let cs = compile_str(
+        "(defcolumns A B)
+         (defconstraint c () (if (as-bool A) (vanishes! B)))
+         (defconstraint as-bool-A () (if (as-bool A) (vanishes! (- B 1))))",
+    );
+    assert_eq!(
+        cs.constraints
+            .iter()
+            .filter(|c| c.name().contains("as-bool-A"))
+            .count(),
+        2
+    );
+
+    // constants are checked at compile time
+    compile_str("(defcolumns A) (defconstraint c () (vanishes! (* A (as-bool 1))))");
+    compile_error("(defcolumns A) (defconstraint c () (vanishes! (* A (as-bool 2))))");
+}
+
+#[test]
+fn rich_compile_errors() {
+    use corset::errors::parser::{render_rich, SourceLocation};
+
+    let err =
+        compile_error("(defcolumns A (B :array [2]))\n(defconstraint c ()\n  (vanishes! (+ A B)))");
+    // the outermost location knows where the error stems from
+    let origin = err.downcast_ref::<SourceLocation>().unwrap().origin();
+    assert_eq!((origin.line, origin.column), (3, 14));
+
+    let rendered = anstream::adapter::strip_str(&render_rich(&err).unwrap()).to_string();
+    let lines = rendered.lines().collect::<Vec<_>>();
+    assert!(lines[0].starts_with("error: + expects"), "{}", rendered);
+    assert_eq!(lines[1], " --> test:3:14");
+    assert_eq!(lines[3], "3 |   (vanishes! (+ A B)))");
+    assert_eq!(lines[4], "  |              ^^^^^^^");
+
+    // nothing to point at without a location
+    assert!(render_rich(&anyhow::anyhow!("no location")).is_none());
+}
+
+#[test]
+fn matrix_columns() {
+    let source =
+        "(defcolumns (m [0:1] [0:2]) A) (defconstraint c () (vanishes! (- A (nth [m 1] 2))))";
+    assert!(accepts(
+        source,
+        r#"{"<prelude>": {"A": [1, 2], "m_0_0": [0, 0], "m_0_1": [0, 0], "m_0_2": [3, 4],
+                          "m_1_0": [0, 0], "m_1_1": [0, 0], "m_1_2": [1, 2]}}"#
+    ));
+    assert!(!accepts(
+        source,
+        r#"{"<prelude>": {"A": [1, 2], "m_0_0": [0, 0], "m_0_1": [0, 0], "m_0_2": [1, 2],
+                          "m_1_0": [0, 0], "m_1_1": [0, 0], "m_1_2": [3, 4]}}"#
+    ));
+}
+
+#[test]
+fn max_degree() {
+    let cs = compile_str(
+        "(defcolumns A B C)
+         (defconstraint cubic () (vanishes! (- (* A B C) (^ A 2))))
+         (defconstraint linear () (vanishes! (- A B)))",
+    );
+    assert!(cs.check_max_degree(3).is_ok());
+
+    let err = cs.check_max_degree(2).unwrap_err().to_string();
+    assert!(err.contains("cubic"));
+    assert!(err.contains("degree 3"));
+    assert!(!err.contains("linear"));
+}
+
+#[test]
+fn let_columns() {
+    let source = "(defcolumns A B)
+                  (defconstraint cube () (let-column W (* A A) (vanishes! (- (* W A) B))))";
+    let cs = compile_str(source);
+    let witness = |cs: &ConstraintSet| {
+        cs.columns
+            .iter()
+            .map(|(_, c)| c.handle.clone())
+            .find(|h| h.name.starts_with("__LET__W"))
+            .unwrap()
+    };
+
+    // the body refers to the witness column, lowering its degree
+    let cube = cs
+        .constraints
+        .iter()
+        .find_map(|c| match c {
+            Constraint::Vanishes { handle, expr, .. } if handle.name == "cube" => Some(expr),
+            _ => None,
+        })
+        .unwrap();
+    assert!(cube
+        .dependencies()
+        .iter()
+        .any(|c| cs.columns.column(c).unwrap().handle == witness(&cs)));
+    assert_eq!(cube.degree(), 2);
+
+    let mut cs = compile_str(source);
+    compute_trace_str(
+        r#"{"<prelude>": {"A": [2, 3], "B": [8, 27]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+    assert_eq!(
+        cs.column_values(&witness(&cs))
+            .unwrap()
+            .iter()
+            .skip(1)
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>(),
+        ["4", "9"]
+    );
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_ok());
+
+    assert!(!accepts(
+        source,
+        r#"{"<prelude>": {"A": [2, 3], "B": [8, 28]}}"#
+    ));
+
+    // the name is only bound in the body, and may thus be reused
+    let source = "(defcolumns A B)
+                  (defconstraint c1 () (let-column W (* A A) (vanishes! (- W B))))
+                  (defconstraint c2 () (let-column W (* B B) (vanishes! (- W A))))";
+    let cs = compile_str(source);
+    assert_eq!(
+        cs.columns
+            .iter()
+            .filter(|(_, c)| c.handle.name.starts_with("__LET__W"))
+            .count(),
+        2
+    );
+    assert!(accepts(
+        source,
+        r#"{"<prelude>": {"A": [1, 0], "B": [1, 0]}}"#
+    ));
+    assert!(!accepts(source, r#"{"<prelude>": {"A": [2], "B": [4]}}"#));
+    assert!(compile(
+        &[(
+            "test",
+            "(defcolumns A) (defconstraint c () (let-column W (* A A) (vanishes! W)))
+             (defconstraint d () (vanishes! W))"
+        )],
+        &CompileSettings::default(),
+    )
+    .is_err());
+}
+
+#[test]
+fn node_degrees() {
+    let cs = compile_str(
+        "(defcolumns A B C)
+         (defconstraint linear () (vanishes! (- A (* 3 B))))
+         (defconstraint shifted () (vanishes! (* A B (next C))))
+         (defconstraint negated () (vanishes! (* A (- B))))
+         (defconstraint powered () (vanishes! (^ (* A B) 2)))
+         (defconstraint summed () (vanishes! (+ (^ A 3) (* B C) 1)))
+         (defconstraint normalized () (vanishes! (* A (~ B))))",
+    );
+    let degrees = cs
+        .constraints
+        .iter()
+        .filter_map(|c| match c {
+            Constraint::Vanishes { handle, expr, .. } => {
+                Some((handle.name.as_str(), expr.degree()))
+            }
+            _ => None,
+        })
+        .collect::<std::collections::HashMap<_, _>>();
+
+    assert_eq!(degrees["linear"], 1);
+    assert_eq!(degrees["shifted"], 3);
+    assert_eq!(degrees["negated"], 2);
+    assert_eq!(degrees["powered"], 4);
+    assert_eq!(degrees["summed"], 3);
+    // ~B stands for B × inv(B), the inverse being a fresh column
+    assert_eq!(degrees["normalized"], 3);
+}
+
+#[test]
+fn negative_constants() {
+    // a negative constant -x stands for the field element p - x
+    let source = "(defcolumns A B) (defconstraint c () (vanishes! (- B (* A (- 1 2)))))";
+    let minus = |x: u32| (corset::column::field_modulus() - x).to_string();
+    assert!(accepts(
+        source,
+        &format!(
+            r#"{{"<prelude>": {{"A": [1, 2], "B": [{}, {}]}}}}"#,
+            minus(1),
+            minus(2)
+        )
+    ));
+    assert!(!accepts(
+        source,
+        &format!(
+            r#"{{"<prelude>": {{"A": [1, 2], "B": [{}, 2]}}}}"#,
+            minus(1)
+        )
+    ));
+}
+
+#[test]
+fn alias_chains() {
+    use corset::errors::CompileError;
+
+    // cycles are reported when defined, even if never used
+    let err = compile_error("(defcolumns A) (defunalias f g) (defunalias g f)");
+    let cycle = match err.downcast_ref::<CompileError>() {
+        Some(CompileError::CircularDefinition(chain)) => chain.to_owned(),
+        _ => panic!("expected a circular definition, found {:?}", err),
+    };
+    assert_eq!(cycle, "f → g → f");
+
+    let source = "(defcolumns A B)
+                  (defalias A1 A) (defalias A2 A1) (defalias A3 A2) (defalias A4 A3)
+                  (defunalias plus +) (defunalias add plus) (defunalias total add)
+                  (defconstraint c () (vanishes! (- (total A4 A) B)))";
+    assert!(accepts(
+        source,
+        r#"{"<prelude>": {"A": [1, 2, 3], "B": [2, 4, 6]}}"#
+    ));
+    assert!(!accepts(
+        source,
+        r#"{"<prelude>": {"A": [1, 2, 3], "B": [2, 4, 5]}}"#
+    ));
+}
+
+#[test]
+fn boundary_domains() {
+    let last = "(defcolumns A) (defconstraint last (:domain {-1}) (vanishes! (- A 7)))";
+    assert!(accepts(last, r#"{"<prelude>": {"A": [1, 2, 7]}}"#));
+    // only the final row is constrained
+    assert!(!accepts(last, r#"{"<prelude>": {"A": [7, 7, 3]}}"#));
+
+    // row 0 is the padding row
+    let first = "(defcolumns A) (defconstraint first (:domain {0}) (vanishes! A))";
+    assert!(accepts(first, r#"{"<prelude>": {"A": [1, 2, 3]}}"#));
+
+    let mut cs = compile_str(last);
+    compute_trace_str(
+        r#"{"<prelude>": {"A": [1, 2, 3]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+    let err = check(&cs, &None, &[], DebugSettings::new()).unwrap_err();
+    let failure = err.downcast_ref::<corset::check::CheckFailure>().unwrap();
+    // failures are reported on the resolved row
+    assert_eq!(
+        failure.failed,
+        vec![corset::check::FailedConstraint {
+            name: "last".into(),
+            row: Some(3),
+        }]
+    );
+}
+
+// the expected renderings are the ones of the BLS12-377 field
+#[cfg(not(feature = "bls12-381"))]
+#[test]
+fn trace_radixes() {
+    use corset::column::Value;
+    use corset::compiler::generator::TraceRadix;
+
+    let minus_one = -Fr::from(1u64);
+    let hex_minus_one = "12ab655e9a2ca55660b44d1e5c37b00159aa76fed00000010a11800000000000";
+    let cases = [
+        (
+            Fr::from(0u64),
+            ["0", "0x0", &format!("0x{}", "0".repeat(64)), "0x00"],
+        ),
+        (
+            Fr::from(255u64),
+            ["255", "0xff", &format!("0x{}ff", "0".repeat(62)), "0x0255"],
+        ),
+        (
+            minus_one,
+            [
+                "8444461749428370424248824938781546531375899335154063827935233455917409239040",
+                &format!("0x{}", hex_minus_one),
+                &format!("0x{}", hex_minus_one),
+                "0x08444461749428370424248824938781546531375899335154063827935233455917409239040",
+            ],
+        ),
+    ];
+
+    let a = Handle::new("<prelude>", "A");
+    for (x, expected) in cases {
+        for (radix, expected) in [
+            TraceRadix::Decimal,
+            TraceRadix::Hex,
+            TraceRadix::Canonical,
+            TraceRadix::Legacy,
+        ]
+        .into_iter()
+        .zip(expected)
+        {
+            let rendered = radix.render(&Value::from(x));
+            assert_eq!(rendered, expected, "{:?}", radix);
+
+            // the legacy format can not be told apart from hexadecimal
+            if radix != TraceRadix::Legacy {
+                let mut cs = compile_str("(defcolumns A)");
+                compute_trace_str(
+                    format!(r#"{{"<prelude>": {{"A": ["{}"]}}}}"#, rendered).as_bytes(),
+                    &mut cs,
+                    true,
+                )
+                .unwrap();
+                assert_eq!(cs.column_value_at(&a, 1), Some(x));
+            }
+        }
+    }
+}
+
+#[test]
+fn malformed_trace_values() {
+    for x in ["0xzz", "12a", "-0x"] {
+        let mut cs = compile_str("(defcolumns A)");
+        assert!(compute_trace_str(
+            format!(r#"{{"<prelude>": {{"A": ["{}"]}}}}"#, x).as_bytes(),
+            &mut cs,
+            true,
+        )
+        .is_err());
+    }
+}
+
+#[test]
+fn trace_module_separators() {
+    use corset::compiler::generator::TraceRadix;
+    use corset::structs::DEFAULT_TRACE_MODULE_SEPARATOR;
+
+    for separator in [DEFAULT_TRACE_MODULE_SEPARATOR, "___"] {
+        let mut cs = compile_str("(defcolumns A) (module m) (defcolumns B)");
+        cs.columns.module_separator = Some(separator.to_owned());
+        compute_trace_str(
+            r#"{"<prelude>": {"A": [1]}, "m": {"B": [2]}}"#.as_bytes(),
+            &mut cs,
+            true,
+        )
+        .unwrap();
+        let mut out = Vec::new();
+        cs.write(&mut out, TraceRadix::Decimal).unwrap();
+
+        let written: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let keys = written["columns"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        assert!(keys.contains(&format!("m{}B", separator)), "{:?}", keys);
+        for k in keys {
+            assert_eq!(
+                Handle::from_trace_key(&k, separator).to_trace_key(separator),
+                k
+            );
+        }
+        assert_eq!(
+            Handle::from_trace_key(&format!("m{}B", separator), separator),
+            Handle::new("m", "B")
+        );
+        assert_eq!(
+            Handle::from_trace_key("A", separator),
+            Handle::new("<prelude>", "A")
+        );
+    }
+}
+
+#[test]
+fn shift_modes() {
+    use corset::compiler::ShiftMode;
+
+    let source = "(defcolumns A B) (defconstraint c () (vanishes! (* (shift A -2) B)))";
+    let trace = r#"{"<prelude>": {"A": [1, 2, 3], "B": [1, 0, 0]}}"#;
+    let check_with = |spilling: Option<isize>, mode: ShiftMode| {
+        let mut cs = compile_str(source);
+        if let Some(spilling) = spilling {
+            cs.columns.spilling.insert("<prelude>".into(), spilling);
+        }
+        compute_trace_str(trace.as_bytes(), &mut cs, true).unwrap();
+        check(&cs, &None, &[], DebugSettings::new().shift_mode(mode))
+    };
+
+    // reading into the padding is fine
+    assert!(check_with(None, ShiftMode::Error).is_ok());
+    assert!(check_with(None, ShiftMode::Zero).is_ok());
+    // with a too short spilling, rows 0 and 1 read uninitialized rows
+    assert!(check_with(Some(0), ShiftMode::Zero).is_ok());
+    let err = check_with(Some(0), ShiftMode::Error).unwrap_err();
+    let failure = err.downcast_ref::<corset::check::CheckFailure>().unwrap();
+    assert_eq!(
+        failure.failed,
+        vec![corset::check::FailedConstraint {
+            name: "c".into(),
+            row: Some(0),
+        }]
+    );
+    // which is the default
+    let mut cs = compile_str(source);
+    cs.columns.spilling.insert("<prelude>".into(), 0);
+    compute_trace_str(trace.as_bytes(), &mut cs, true).unwrap();
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_err());
+}
+
+#[test]
+fn shift_modes_past_the_end() {
+    use corset::compiler::ShiftMode;
+
+    let check_with = |mode: ShiftMode| {
+        let mut cs =
+            compile_str("(defcolumns A B) (defconstraint c () (vanishes! (* (shift A 1) B)))");
+        compute_trace_str(
+            r#"{"<prelude>": {"A": [1, 2, 0], "B": [0, 0, 1]}}"#.as_bytes(),
+            &mut cs,
+            true,
+        )
+        .unwrap();
+        check(&cs, &None, &[], DebugSettings::new().shift_mode(mode))
+    };
+
+    // reading past the end from the last row is legitimate
+    assert!(check_with(ShiftMode::Zero).is_ok());
+    assert!(check_with(ShiftMode::Error).is_ok());
+}
+
+const MMAP_SOURCE: &str = "(defcolumns A B (C :comp (* A B)) (D :comp (+ A C)))
+                           (defconstraint c () (vanishes! (- D (+ A (* A B)))))
+                           (defconstraint b () (vanishes! (* B (- B 1))))";
+
+fn mmap_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("corset-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn compute_with(trace: &str, mmap: Option<&std::path::Path>) -> ConstraintSet {
+    let mut cs = compile_str(MMAP_SOURCE);
+    cs.columns.mmap = mmap.map(|dir| MmapSettings::new(dir).threshold(1));
+    compute_trace_str(trace.as_bytes(), &mut cs, true).unwrap();
+    cs
+}
+
+#[test]
+fn mmap_columns() {
+    let dir = mmap_dir("mmap-columns");
+    let handles = ["A", "B", "C", "D"].map(|c| Handle::new("<prelude>", c));
+
+    for trace in [
+        r#"{"<prelude>": {"A": [1, 2, 3, 4], "B": [0, 1, 1, 0]}}"#,
+        r#"{"<prelude>": {"A": [1, 2, 3, 4], "B": [0, 1, 2, 0]}}"#,
+    ] {
+        let in_memory = compute_with(trace, None);
+        let mapped = compute_with(trace, Some(&dir));
+
+        for h in handles.iter() {
+            let r: ColumnRef = h.clone().into();
+            // expression-backed registers have nothing to spill
+            let in_memory_backing = in_memory.columns.backing(&r).unwrap();
+            assert!(!in_memory_backing.is_mapped());
+            assert_eq!(
+                mapped.columns.backing(&r).unwrap().is_mapped(),
+                matches!(in_memory_backing, ValueBacking::Vector { .. })
+            );
+            assert_eq!(in_memory.column_values(h), mapped.column_values(h));
+        }
+        assert_eq!(
+            check(&in_memory, &None, &[], DebugSettings::new()).is_ok(),
+            check(&mapped, &None, &[], DebugSettings::new()).is_ok()
+        );
+    }
+
+    // the backing files go away with their registers
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    std::fs::remove_dir(&dir).unwrap();
+}
+
+/// Peak and current anonymous resident memory of this process, in kB
+fn rss() -> (usize, usize) {
+    let status = std::fs::read_to_string("/proc/self/status").unwrap();
+    let field = |name: &str| {
+        status
+            .lines()
+            .find_map(|l| l.strip_prefix(name))
+            .and_then(|l| l.split_whitespace().next())
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(0)
+    };
+    (field("VmHWM:"), field("RssAnon:"))
+}
+
+/// Compare the memory used to compute and check a large trace with and
+/// without memory-mapped columns; as the peak RSS can only grow, each mode
+/// runs in its own process.
+///
+/// Run with `cargo test --release --test library mmap_rss -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn mmap_rss() {
+    const MODE: &str = "CORSET_MMAP_BENCH";
+    const ROWS: usize = 1 << 18;
+
+    if let Result::Ok(mode) = std::env::var(MODE) {
+        let dir = mmap_dir("mmap-rss");
+        let a = (0..ROWS)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let b = (0..ROWS)
+            .map(|i| (i % 2).to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let trace = format!(r#"{{"<prelude>": {{"A": [{}], "B": [{}]}}}}"#, a, b);
+        let cs = compute_with(&trace, (mode == "mmap").then_some(dir.as_path()));
+        assert!(check(&cs, &None, &[], DebugSettings::new()).is_ok());
+        let (peak, anon) = rss();
+        println!("{}: peak RSS {} kB, anonymous RSS {} kB", mode, peak, anon);
+        drop(cs);
+        std::fs::remove_dir(&dir).unwrap();
+        return;
+    }
+
+    for mode in ["memory", "mmap"] {
+        let out = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["mmap_rss", "--exact", "--ignored", "--nocapture"])
+            .env(MODE, mode)
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        let tag = format!("{}: ", mode);
+        if let Some(l) = stdout.lines().find_map(|l| l.find(&tag).map(|i| &l[i..])) {
+            println!("{}", l);
+        }
+    }
+}
+
+#[test]
+fn readable_type_errors() {
+    let rendered = |source: &str| {
+        anstream::adapter::strip_str(&format!("{:#}", compile_error(source))).to_string()
+    };
+
+    // mismatching arguments are spelled out rather than shown symbolically
+    let err = rendered("(defcolumns A (B :array [2]))\n(defconstraint c () (vanishes! (+ A B)))");
+    assert!(
+        err.contains("+ expects (any value, any value), found (field column, field array column)"),
+        "{}",
+        err
+    );
+
+    let err = rendered("(defcolumns (A :byte))\n(defconstraint c () (vanishes! (^ A A)))");
+    assert!(err.contains("found (byte column, byte column)"), "{}", err);
+}
+
+#[test]
+fn grouped_column_types() {
+    use corset::compiler::{Conditioning, Magma};
+
+    let cs = compile_str("(defcolumns :boolean A B C (D :byte) E :u16 F G)");
+    let magma = |name: &str| {
+        cs.columns
+            .by_handle(&Handle::new("<prelude>", name))
+            .unwrap()
+            .t
+    };
+    let boolean = Magma::binary().with_conditioning(Conditioning::Boolean);
+    for name in ["A", "B", "C", "E"] {
+        assert_eq!(magma(name), boolean, "{}", name);
+    }
+    assert_eq!(magma("D"), Magma::byte());
+    assert_eq!(magma("F"), Magma::integer(16));
+    assert_eq!(magma("G"), Magma::integer(16));
+
+    // a grouped type must apply to some columns
+    compile_error("(defcolumns A :byte)");
+    // and is only allowed in defcolumns
+    compile_error("(defcolumns A) (defconstraint c :byte () (vanishes! A))");
+}
+
+#[test]
+fn custom_trace_reader() {
+    use corset::column::Value;
+    use corset::import::{import_column, TraceReader};
+
+    /// Serves the same columns, whatever the trace file
+    struct InMemory(Vec<(Handle, Vec<u64>)>);
+    impl TraceReader for InMemory {
+        fn name(&self) -> &str {
+            "in-memory"
+        }
+
+        fn accepts(&self, _tracefile: &str) -> bool {
+            true
+        }
+
+        fn read_bytes(
+            &self,
+            _trace: &[u8],
+            cs: &mut ConstraintSet,
+            keep_raw: bool,
+        ) -> anyhow::Result<()> {
+            for (handle, xs) in self.0.iter() {
+                import_column(
+                    cs,
+                    handle,
+                    xs.iter().map(|x| Value::from(*x)).collect(),
+                    keep_raw,
+                )?;
+            }
+            Ok(())
+        }
+
+        fn read_file(
+            &self,
+            _tracefile: &str,
+            cs: &mut ConstraintSet,
+            keep_raw: bool,
+        ) -> anyhow::Result<()> {
+            self.read_bytes(&[], cs, keep_raw)
+        }
+    }
+
+    let mut cs = compile_str(
+        "(defcolumns A B (C :comp (+ A B))) (defconstraint eq () (vanishes! (- C (+ A B))))",
+    );
+    let reader = InMemory(vec![
+        (Handle::new("<prelude>", "A"), vec![1, 2, 3]),
+        (Handle::new("<prelude>", "B"), vec![10, 20, 30]),
+    ]);
+    corset::compute_trace_with("nowhere", &mut cs, &reader, true).unwrap();
+
+    let c = Handle::new("<prelude>", "C");
+    assert_eq!(
+        cs.column_values(&c),
+        Some(vec![
+            Fr::from(0u64),
+            Fr::from(11u64),
+            Fr::from(22u64),
+            Fr::from(33u64)
+        ])
+    );
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_ok());
+
+    // imported columns are validated against their type
+    let mut cs = compile_str("(defcolumns (A :byte))");
+    let reader = InMemory(vec![(Handle::new("<prelude>", "A"), vec![256])]);
+    assert!(corset::compute_trace_with("nowhere", &mut cs, &reader, true).is_err());
+}
+
+#[test]
+fn constant_boolean_columns() {
+    let mut cs = compile_str(
+        "(defcolumns (A :bool) (B :bool) X) (defconstraint c () (vanishes! (* A B X)))",
+    );
+    compute_trace_str(
+        r#"{"<prelude>": {"A": [0, 0, 0], "B": [0, 1, 0], "X": [5, 6, 7]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+
+    // the padding row does not count, and X is not boolean
+    let constants = corset::check::constant_boolean_columns(&cs);
+    assert_eq!(constants.len(), 1);
+    assert_eq!(constants[0].0, Handle::new("<prelude>", "A"));
+
+    let settings = DebugSettings::new().pedantic(true);
+    assert!(check(&cs, &None, &[], settings).is_ok());
+    let err = check(&cs, &None, &[], settings.deny_warnings(true)).unwrap_err();
+    assert!(
+        err.to_string().contains("constant boolean columns"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn multi_index_for() {
+    use corset::compiler::Expression;
+
+    let source = "(defcolumns (A :array [2]) (B :array [3]))
+                  (defconstraint pairs () (for (i j) ([2] [3]) (vanishes! (- [A i] [B j]))))";
+    let cs = compile_str(source);
+    let pairs = cs
+        .constraints
+        .iter()
+        .find_map(|c| match c {
+            Constraint::Vanishes { handle, expr, .. } if handle.name == "pairs" => Some(expr),
+            _ => None,
+        })
+        .unwrap();
+    // one body instance per element of {1 2} × {1 2 3}
+    assert!(matches!(pairs.e(), Expression::List(es) if es.len() == 6));
+
+    // indices and ranges must go in pairs
+    compile_error(
+        "(defcolumns (A :array [2])) (defconstraint c () (for (i j) ([2]) (vanishes! [A i])))",
+    );
+}
+
+#[test]
+fn format_version() {
+    use corset::compiler::generator::FORMAT_VERSION;
+
+    let cs = compile_str("(defcolumns A B) (defconstraint eq () (vanishes! (- A B)))");
+    let current = format!("format_version:{}", FORMAT_VERSION);
+
+    let ron = ron::to_string(&cs).unwrap();
+    assert!(ConstraintSet::from_ron(&ron).is_ok());
+    let stale = ron.replacen(
+        &current,
+        &format!("format_version:{}", FORMAT_VERSION + 1),
+        1,
+    );
+    let err = ConstraintSet::from_ron(&stale).unwrap_err().to_string();
+    assert_eq!(
+        err,
+        format!(
+            "compiled with Corset format v{}, this build expects v{}; recompile",
+            FORMAT_VERSION + 1,
+            FORMAT_VERSION
+        )
+    );
+
+    // constraint sets serialized before versioning are v0
+    let json = serde_json::to_string(&cs).unwrap();
+    assert!(ConstraintSet::from_json(&json).is_ok());
+    let stale = json.replacen(&format!("\"{},", current.replace(':', "\":")), "", 1);
+    let err = ConstraintSet::from_json(&stale).unwrap_err().to_string();
+    assert!(err.starts_with("compiled with Corset format v0"), "{}", err);
+
+    // constraint sets compiled for another field are rejected
+    let json = serde_json::to_string(&cs).unwrap();
+    let other = if corset::field::FIELD == "bls12-377" {
+        "bls12-381"
+    } else {
+        "bls12-377"
+    };
+    let foreign = json.replacen(
+        &format!("\"field\":\"{}\"", corset::field::FIELD),
+        &format!("\"field\":\"{}\"", other),
+        1,
+    );
+    let err = ConstraintSet::from_json(&foreign).unwrap_err().to_string();
+    assert_eq!(
+        err,
+        format!(
+            "compiled for the {} field, this build uses {}; recompile",
+            other,
+            corset::field::FIELD
+        )
+    );
+}
+
+#[test]
+fn lookup_table() {
+    let table = std::env::temp_dir().join(format!("corset-table-{}.csv", std::process::id()));
+    std::fs::write(&table, "OP,RESULT\n1,2\n2,4\n0x3,6\n").unwrap();
+    let source = format!(
+        "(defcolumns OP RESULT) (deflookup double (OP RESULT) (file {:?}))",
+        table.display().to_string()
+    );
+
+    let mut cs = compile_str(&source);
+    compute_trace_str(
+        r#"{"<prelude>": {"OP": [1, 3, 2], "RESULT": [2, 6, 4]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_ok());
+
+    // (2, 5) is not a row of the table
+    let mut cs = compile_str(&source);
+    compute_trace_str(
+        r#"{"<prelude>": {"OP": [1, 2], "RESULT": [2, 5]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+    let err = check(&cs, &None, &[], DebugSettings::new()).unwrap_err();
+    assert!(err.to_string().contains("double"), "{}", err);
+
+    // the first row is a header, and may not be omitted
+    std::fs::write(&table, "1,2\n2,4\n").unwrap();
+    let mut cs = compile_str(&source);
+    assert!(compute_trace_str(
+        r#"{"<prelude>": {"OP": [2], "RESULT": [4]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .is_err());
+
+    std::fs::remove_file(&table).unwrap();
+}
+
+#[test]
+fn lookup_table_path() {
+    // a relative table path is resolved against the directory of the source
+    let dir = std::env::temp_dir().join(format!("corset-tables-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("double.csv"), "OP,RESULT\n1,2\n2,4\n").unwrap();
+    let source = "(defcolumns OP RESULT) (deflookup double (OP RESULT) (file \"double.csv\"))";
+    let lisp = dir.join("double.lisp");
+    std::fs::write(&lisp, source).unwrap();
+
+    let mut cs = compile(
+        &[(lisp.display().to_string(), source)],
+        &CompileSettings::default(),
+    )
+    .unwrap();
+    compute_trace_str(
+        r#"{"<prelude>": {"OP": [2, 1], "RESULT": [4, 2]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+    assert!(check(&cs, &None, &[], DebugSettings::new()).is_ok());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn constant_shift_offsets() {
+    let cs = compile_str(
+        "(defconst OFFSET 2) (defcolumns A B)
+         (defconstraint c () (vanishes! (- (shift A (+ 1 OFFSET)) (shift B (- 1 OFFSET)))))",
+    );
+    let c = cs
+        .constraints
+        .iter()
+        .find_map(|c| match c {
+            Constraint::Vanishes { handle, expr, .. } if handle.name == "c" => Some(expr),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(c.future_spill(), 3);
+    assert_eq!(c.past_spill(), -1);
+
+    // the offset must fold to a constant small enough for a shift
+    compile_error(
+        "(defconst OFFSET 2) (defcolumns A) (defconstraint c () (vanishes! (shift A (+ OFFSET 40000))))",
+    );
+}
+
+#[test]
+fn compile_summary() {
+    use corset::compiler::Summary;
+
+    let cs = compile_str(
+        "(defcolumns A B (C :comp (* A B)))
+         (module m) (defcolumns D)
+         (defun (double x) (* 2 x))
+         (defconstraint c () (vanishes! (- (double A) B)))
+         (deflookup l (A) (m.D))
+         (definrange A 10)",
+    );
+    assert_eq!(
+        cs.summary(),
+        Summary {
+            modules: 2,
+            atomic_columns: 3,
+            computed_columns: 1,
+            // c, and the constraint proving C
+            vanishes: 2,
+            lookups: 1,
+            permutations: 0,
+            ranges: 1,
+            normalizations: 0,
+            functions: 1,
+        }
+    );
+}
+
+#[test]
+fn decompress_payloads() {
+    use corset::import::decompress_trace;
+    use std::io::Write;
+
+    let trace = r#"{"<prelude>": {"A": [1, 2], "B": [1, 2]}}"#;
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(trace.as_bytes()).unwrap();
+    let gzipped = gz.finish().unwrap();
+
+    assert_eq!(
+        decompress_trace(trace.as_bytes()).unwrap(),
+        trace.as_bytes()
+    );
+    assert_eq!(decompress_trace(&gzipped).unwrap(), trace.as_bytes());
+
+    // a corrupted payload still carrying the gzip header is an error
+    assert!(decompress_trace(&gzipped[..gzipped.len() / 2]).is_err());
+}
+
+#[test]
+fn unevaluable_ranges() {
+    let check_range = |source: &str| {
+        let mut cs = compile_str(source);
+        compute_trace_str(r#"{"<prelude>": {"A": [1, 2]}}"#.as_bytes(), &mut cs, true).unwrap();
+        check(&cs, &None, &[], DebugSettings::new())
+    };
+
+    assert!(check_range("(defcolumns A) (definrange A 10)").is_ok());
+    // the last row has no next one to range-check
+    let err = check_range("(defcolumns A) (definrange (shift A 1) 10)").unwrap_err();
+    let failure = err.downcast_ref::<corset::check::CheckFailure>().unwrap();
+    assert_eq!(failure.failed.len(), 1);
+    assert_eq!(failure.failed[0].row, Some(2));
+}
+
+#[test]
+fn padding_values() {
+    let mut cs = compile_str("(defcolumns A B) (defpadding A 7) (defpadding B -1)");
+    cs.columns.min_len.insert("<prelude>".into(), 4);
+    compute_trace_str(
+        r#"{"<prelude>": {"A": [1, 2], "B": [1, 2]}}"#.as_bytes(),
+        &mut cs,
+        true,
+    )
+    .unwrap();
+
+    // the rows padding the module to its minimal length hold the declared values
+    let minus_one = Fr::from(0) - Fr::from(1);
+    assert_eq!(
+        cs.column_values(&Handle::new("<prelude>", "A")).unwrap(),
+        [7, 0, 1, 2].map(Fr::from)
+    );
+    assert_eq!(
+        cs.column_values(&Handle::new("<prelude>", "B")).unwrap(),
+        [minus_one, Fr::from(0), Fr::from(1), Fr::from(2)]
+    );
+}
@@ -275,6 +275,41 @@ static MODELS: &[Model] = &[
         cols: &["ST", "X", "Y"],
         oracle: Some(issue219_d_oracle),
     },
+    Model {
+        name: "inrange_1",
+        cols: &["X"],
+        oracle: Some(inrange_1_oracle),
+    },
+    Model {
+        name: "inrange_2",
+        cols: &["X"],
+        oracle: Some(inrange_2_oracle),
+    },
+    Model {
+        name: "cmp_lt",
+        cols: &["X", "Y", "Z"],
+        oracle: Some(cmp_lt_oracle),
+    },
+    Model {
+        name: "cmp_le",
+        cols: &["X", "Y", "Z"],
+        oracle: Some(cmp_le_oracle),
+    },
+    Model {
+        name: "cmp_gt",
+        cols: &["X", "Y", "Z"],
+        oracle: Some(cmp_gt_oracle),
+    },
+    Model {
+        name: "cmp_ge",
+        cols: &["X", "Y", "Z"],
+        oracle: Some(cmp_ge_oracle),
+    },
+    Model {
+        name: "notzero",
+        cols: &["A", "B", "C"],
+        oracle: Some(notzero_oracle),
+    },
 ];
 
 // ===================================================================
@@ -413,3 +448,85 @@ fn issue219_d_oracle(tr: &Trace) -> bool {
     }
     true
 }
+
+// ===================================================================
+// InRange
+// ===================================================================
+
+#[allow(non_snake_case)]
+fn inrange_1_oracle(tr: &Trace) -> bool {
+    let X = tr.col("X");
+
+    for k in 0..tr.height() {
+        // -1 wraps around the field, and is thus out of range
+        if X[k] != 0 && X[k] != 1 {
+            return false;
+        }
+    }
+    true
+}
+
+#[allow(non_snake_case)]
+fn inrange_2_oracle(tr: &Trace) -> bool {
+    let X = tr.col("X");
+
+    for k in 0..tr.height() {
+        if X[k] != -1 && X[k] != 0 {
+            return false;
+        }
+    }
+    true
+}
+
+// ===================================================================
+// Comparisons
+// ===================================================================
+
+#[allow(non_snake_case)]
+fn cmp_oracle(tr: &Trace, cmp: fn(isize, isize) -> bool) -> bool {
+    let (X, Y, Z) = (tr.col("X"), tr.col("Y"), tr.col("Z"));
+
+    for k in 0..tr.height() {
+        // -1 wraps around the field, and is thus not a valid :u2
+        if X[k] < 0 || Y[k] < 0 {
+            return false;
+        }
+        if cmp(X[k], Y[k]) && Z[k] != 0 {
+            return false;
+        }
+    }
+    true
+}
+
+fn cmp_lt_oracle(tr: &Trace) -> bool {
+    cmp_oracle(tr, |x, y| x < y)
+}
+
+fn cmp_le_oracle(tr: &Trace) -> bool {
+    cmp_oracle(tr, |x, y| x <= y)
+}
+
+fn cmp_gt_oracle(tr: &Trace) -> bool {
+    cmp_oracle(tr, |x, y| x > y)
+}
+
+fn cmp_ge_oracle(tr: &Trace) -> bool {
+    cmp_oracle(tr, |x, y| x >= y)
+}
+
+// ===================================================================
+// NotZero
+// ===================================================================
+
+#[allow(non_snake_case)]
+fn notzero_oracle(tr: &Trace) -> bool {
+    let (A, B, C) = (tr.col("A"), tr.col("B"), tr.col("C"));
+
+    for k in 0..tr.height() {
+        let nz = isize::from(A[k] != 0);
+        if B[k] != nz || (nz == 1 && C[k] != 0) {
+            return false;
+        }
+    }
+    true
+}
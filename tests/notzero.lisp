@@ -0,0 +1,7 @@
+(defcolumns A B C)
+
+(defconstraint is-not-zero ()
+  (vanishes! (- B (!=0? A))))
+
+(defconstraint guarded ()
+  (if (!=0? A) (vanishes! C)))
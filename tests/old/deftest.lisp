@@ -0,0 +1,16 @@
+(defcolumns A B (C :binary))
+
+(defconstraint equal () (vanishes! (- A B)))
+
+(defconstraint c-is-bool () (vanishes! (* C (- 1 C))))
+
+;; A and B are equal on every row, so no constraint fails
+(deftest equal-rows
+  ((A 1 2 3) (B 1 2 3) (C 0 1 0))
+  ())
+
+;; A and B differ on the last row; this test expects the wrong failures and
+;; is thus reported as failed
+(deftest differing-rows
+  ((A 1 2 3) (B 1 2 4))
+  (c-is-bool))